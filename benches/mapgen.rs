@@ -0,0 +1,88 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rltk::RandomNumberGenerator;
+use rogue::map::{Map, TileType, MAP_HEIGHT, MAP_WIDTH};
+use rogue::map_builder::{build_constraints, named_builder, solve_once, MapBuilder, WaveformCollapseBuilder, BUILDER_NAMES};
+
+/// Every level's built from the same seed, so runs can be compared
+const SEED : u64 = 1;
+
+/// Deep enough that depth-scaled builders do their share of the work
+const DEPTH : i32 = 5;
+
+fn builder (name: &str) -> Box<dyn MapBuilder> {
+	named_builder(name, DEPTH).expect("There's no builder by that name")
+}
+
+// Builders
+// =========================================================================
+
+/// Each builder on its own, as asked for with --builder
+fn builders (c: &mut Criterion) {
+	let mut group = c.benchmark_group("builders");
+	group.sample_size(20);
+
+	for name in BUILDER_NAMES.iter() {
+		group.bench_function(*name, |b| b.iter_batched(
+			|| (builder(name), RandomNumberGenerator::seeded(SEED)),
+			|(mut builder, mut rng)| builder.build(&mut rng),
+			BatchSize::SmallInput,
+		));
+	}
+
+	group.finish();
+}
+
+/// Each builder again, run through Waveform Collapse after as a third of
+/// levels are. This is where the retries show up.
+fn derived (c: &mut Criterion) {
+	let mut group = c.benchmark_group("waveform_collapse");
+	group.sample_size(10);
+
+	for name in BUILDER_NAMES.iter() {
+		group.bench_function(*name, |b| b.iter_batched(
+			|| (WaveformCollapseBuilder::derived_map(DEPTH, builder(name)), RandomNumberGenerator::seeded(SEED)),
+			|(mut builder, mut rng)| builder.build(&mut rng),
+			BatchSize::SmallInput,
+		));
+	}
+
+	group.finish();
+}
+
+// Waveform Collapse
+// =========================================================================
+
+/// Waveform Collapse's two halves apart, from a cave map
+fn waveform_stages (c: &mut Criterion) {
+	let source = {
+		let mut source = builder("cellular_automata");
+		source.build(&mut RandomNumberGenerator::seeded(SEED));
+		source.take_map()
+	};
+	let constraints = build_constraints(&source);
+	let blank = || Map::new(MAP_WIDTH as i32, MAP_HEIGHT as i32, DEPTH, Some(TileType::Wall));
+
+	let mut group = c.benchmark_group("waveform_stages");
+	group.sample_size(20);
+
+	group.bench_function("constraints", |b| b.iter(|| build_constraints(&source)));
+
+	// One go, whether or not it comes out whole
+	group.bench_function("solve_once", |b| b.iter_batched(
+		|| (blank(), RandomNumberGenerator::seeded(SEED)),
+		|(mut map, mut rng)| solve_once(&constraints, &mut map, &mut rng, |_| {}),
+		BatchSize::SmallInput,
+	));
+
+	// As many goes as it takes, like the builder
+	group.bench_function("solve", |b| b.iter_batched(
+		|| (blank(), RandomNumberGenerator::seeded(SEED)),
+		|(mut map, mut rng)| while !solve_once(&constraints, &mut map, &mut rng, |_| {}) {},
+		BatchSize::SmallInput,
+	));
+
+	group.finish();
+}
+
+criterion_group!(benches, builders, derived, waveform_stages);
+criterion_main!(benches);
@@ -0,0 +1,184 @@
+use rltk::{DistanceAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use crate::{Ability, AreaOfEffect, Awareness, Blink, BossPhases, CombatStats, EntityMoved, Faction, InflictsDamage, InflictsStatus, MyTurn, Name, Perception, PhaseAbility, Position, ProvidesHealing, Ranged, Viewshed, WantsToUseItem};
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+use crate::ai::{hunting_player, sees, step_to};
+
+/// Monsters below this percent health will heal or blink away if they can
+const HURT_BELOW : i32 = 50;
+
+/// Blinking monsters try to land at least this far from the player
+const BLINK_CLEARANCE : f32 = 4.;
+
+/// Lets monsters use their special abilities once they've cooled down:
+/// healing when hurt, blinking away when cornered, and anything ranged at
+/// the player from a distance
+pub struct AbilityAI {}
+
+impl<'a> System<'a> for AbilityAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		ReadExpect<'a, Point>,
+		WriteExpect<'a, GameLog>,
+		WriteExpect<'a, RandomNumberGenerator>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, Ability>,
+		ReadStorage<'a, Blink>,
+		ReadStorage<'a, ProvidesHealing>,
+		ReadStorage<'a, InflictsDamage>,
+		ReadStorage<'a, InflictsStatus>,
+		ReadStorage<'a, Ranged>,
+		ReadStorage<'a, AreaOfEffect>,
+		ReadStorage<'a, CombatStats>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, Faction>,
+		ReadStorage<'a, Awareness>,
+		WriteStorage<'a, WantsToUseItem>,
+		WriteStorage<'a, EntityMoved>,
+		ReadStorage<'a, PhaseAbility>,
+		ReadStorage<'a, BossPhases>,
+		ReadStorage<'a, Perception>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map,
+			mut spatial,
+			player_pos,
+			mut log,
+			mut rng,
+			entities,
+			mut turns,
+			mut abilities,
+			blinks,
+			healing,
+			inflicts_damage,
+			inflicts_status,
+			ranged,
+			aoe,
+			combat_stats,
+			mut positions,
+			mut viewsheds,
+			names,
+			factions,
+			awareness,
+			mut wants_use,
+			mut entity_moved,
+			phase_abilities,
+			bosses,
+			perception,
+		) = data;
+
+		// Cool down the abilities of everything getting a turn
+		for ability in (&mut abilities).join() {
+			if turns.get(ability.owner).is_some() && ability.cooldown_left > 0 {
+				ability.cooldown_left -= 1;
+			}
+		}
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, _turn, pos, viewshed, stats) in (&entities, &turns, &mut positions, &mut viewsheds, &combat_stats).join() {
+			let ready : Vec<Entity> = (&entities, &abilities).join()
+				.filter(|(_, a)| a.owner == entity && a.cooldown_left < 1)
+				.map(|(e, _)| e)
+				.filter(|e| phase_abilities.get(*e).map_or(true, |p| {
+					bosses.get(entity).map_or(false, |boss| boss.current >= p.phase)
+				}))
+				.collect();
+			if ready.is_empty() { continue; }
+
+			let hurt = stats.hp * 100 / i32::max(1, stats.max_hp) < HURT_BELOW;
+			let sees_player = hunting_player(entity, &factions, &awareness)
+				&& sees(&map, pos, viewshed, perception.get(entity), &player_pos);
+			let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+
+			let mut used : Option<Entity> = None;
+
+			// Patch itself up
+			if hurt {
+				if let Some(heal) = ready.iter().find(|a| healing.get(**a).is_some()) {
+					wants_use.insert(entity, WantsToUseItem { item: *heal, target: None })
+						.or_warn("Healing fizzled");
+					used = Some(*heal);
+				}
+			}
+
+			// Get out of reach
+			if used.is_none() && hurt && sees_player && distance < 1.5 {
+				if let Some((blink, range)) = ready.iter().find_map(|a| blinks.get(*a).map(|b| (a, b.range as f32))) {
+					if let Some(dest) = blink_destination(&map, &spatial, pos, viewshed, *player_pos, range, &mut rng) {
+						step_to(&map, &mut spatial, entity, pos, viewshed, dest);
+						entity_moved.insert(entity, EntityMoved {})
+							.or_warn("Blinked into the void");
+
+						if map.is_visible(dest) {
+							if let Some(name) = names.get(entity) {
+								log.combat(format!("{} blinks away!", &name.name));
+							}
+						}
+
+						used = Some(*blink);
+					}
+				}
+			}
+
+			// Something nasty at range, keeping itself out of the blast
+			if used.is_none() && sees_player && distance >= 1.5 {
+				let attack = ready.iter().find(|a| {
+					ranged.get(**a).map_or(false, |r| distance <= r.range as f32)
+						&& (inflicts_damage.get(**a).is_some() || inflicts_status.get(**a).is_some())
+						&& aoe.get(**a).map_or(true, |blast| distance > blast.radius as f32)
+				});
+				if let Some(attack) = attack {
+					wants_use.insert(entity, WantsToUseItem { item: *attack, target: Some(*player_pos) })
+						.or_warn("Ability misfired");
+					used = Some(*attack);
+				}
+			}
+
+			if let Some(ability) = used {
+				if let Some(ability) = abilities.get_mut(ability) {
+					ability.cooldown_left = ability.cooldown;
+				}
+				turn_done.push(entity);
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+	}
+}
+
+/// A random free tile in sight and in range, well away from the threat
+fn blink_destination (
+	map: &Map,
+	spatial: &SpatialIndex,
+	pos: &Position,
+	viewshed: &Viewshed,
+	threat: Point,
+	range: f32,
+	rng: &mut RandomNumberGenerator,
+) -> Option<usize> {
+	let here = Point::new(pos.x, pos.y);
+
+	let candidates : Vec<usize> = viewshed.visible_tiles.iter()
+		.filter(|tile| {
+			DistanceAlg::Pythagoras.distance2d(here, **tile) <= range
+				&& DistanceAlg::Pythagoras.distance2d(threat, **tile) >= BLINK_CLEARANCE
+		})
+		.map(|tile| map.xy_idx(tile.x, tile.y))
+		.filter(|idx| !spatial.is_blocked(*idx))
+		.collect();
+
+	if candidates.is_empty() { return None; }
+	rng.random_slice_entry(&candidates).copied()
+}
@@ -0,0 +1,134 @@
+use rltk::Point;
+use specs::prelude::*;
+use crate::{CanOpenDoors, Door, EntityMoved, MyTurn, PackMember, Position, Swarm, Viewshed, WantsToApproach, WantsToOpenDoor};
+use crate::ai::{closed_door_at, next_step, step_to, PlayerFlowMap};
+use crate::debug_log::OrWarn;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// Takes a step along the path towards whatever's in WantsToApproach, going
+/// by the shared flow map when that's the player and falling back on A* when
+/// the flow map's no help. Swarming monsters path straight through each
+/// other, swapping places with whichever swarm mate is in the way.
+pub struct ApproachAI {}
+
+impl<'a> System<'a> for ApproachAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, WantsToApproach>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		WriteStorage<'a, EntityMoved>,
+		ReadStorage<'a, CanOpenDoors>,
+		ReadStorage<'a, Door>,
+		WriteStorage<'a, WantsToOpenDoor>,
+		ReadStorage<'a, Swarm>,
+		ReadStorage<'a, PackMember>,
+		ReadExpect<'a, Point>,
+		WriteExpect<'a, PlayerFlowMap>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, mut spatial, entities, mut turns, mut wants_approach, mut positions,
+			mut viewsheds, mut entity_moved, door_openers, doors, mut wants_open,
+			swarms, pack_members, player_pos, mut flow,
+		) = data;
+
+		let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+		let movers : Vec<(Entity, usize)> = (&entities, &turns, &wants_approach).join()
+			.map(|(entity, _, approach)| (entity, approach.idx as usize))
+			.collect();
+
+		// Where every swarm member is, kept up to date as they shuffle about
+		let mut swarm_tiles : Vec<(Entity, i32, usize)> = (&entities, &swarms, &pack_members, &positions).join()
+			.map(|(entity, _, member, pos)| (entity, member.pack_id, map.xy_idx(pos.x, pos.y)))
+			.collect();
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, destination) in movers {
+			turn_done.push(entity);
+
+			let (pos, viewshed) = match (positions.get_mut(entity), viewsheds.get_mut(entity)) {
+				(Some(pos), Some(viewshed)) => (pos, viewshed),
+				_ => continue,
+			};
+			let start = map.xy_idx(pos.x, pos.y);
+
+			let swarm_id = swarms.get(entity)
+				.and(pack_members.get(entity))
+				.map(|member| member.pack_id);
+			let mates : Vec<(Entity, usize)> = swarm_tiles.iter()
+				.filter(|(mate, id, _)| *mate != entity && Some(*id) == swarm_id)
+				.map(|(mate, _, idx)| (*mate, *idx))
+				.collect();
+
+			// Whatever's being approached blocks its own tile, which would
+			// otherwise leave no path to it
+			let was_blocked = spatial.is_blocked(destination);
+			spatial.set_blocked(destination, false);
+			for (_, idx) in mates.iter() { spatial.set_blocked(*idx, false); }
+
+			let can_open_doors = door_openers.get(entity).is_some();
+
+			// Something that can't open doors has to path round them itself
+			let flow_step =
+				if destination == player_idx { flow.step_towards_player(&map, &spatial, start) }
+				else { None }
+				.filter(|step| can_open_doors || !spatial.is_closed_door(*step));
+
+			let step = match flow_step {
+				Some(step) => Some(step),
+				None if flow.spend_path() => next_step(&map, &spatial, start, destination, can_open_doors),
+				None => None,
+			};
+
+			for (_, idx) in mates.iter() { spatial.set_blocked(*idx, true); }
+			spatial.set_blocked(destination, was_blocked);
+
+			let step = match step {
+				None => continue,
+				Some(step) => step,
+			};
+
+			if let Some(door) = closed_door_at(&spatial, &doors, step) {
+				wants_open.insert(entity, WantsToOpenDoor { door })
+					.or_warn("Door handle came off");
+				continue;
+			}
+
+			let in_the_way = mates.iter().find(|(_, idx)| *idx == step).map(|(mate, _)| *mate);
+
+			step_to(&map, &mut spatial, entity, pos, viewshed, step);
+			entity_moved.insert(entity, EntityMoved {})
+				.or_warn("Failed to use numerous legs");
+			for tile in swarm_tiles.iter_mut().filter(|t| t.0 == entity) { tile.2 = step; }
+
+			// Squeeze past, swapping places
+			if let Some(mate) = in_the_way {
+				spatial.move_entity(mate, step, start);
+				if let Some(mate_pos) = positions.get_mut(mate) {
+					mate_pos.x = start as i32 % map.width;
+					mate_pos.y = start as i32 / map.width;
+				}
+				if let Some(mate_viewshed) = viewsheds.get_mut(mate) {
+					mate_viewshed.dirty = true;
+				}
+				entity_moved.insert(mate, EntityMoved {})
+					.or_warn("Swarm mate wouldn't budge");
+				for tile in swarm_tiles.iter_mut().filter(|t| t.0 == mate) { tile.2 = start; }
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+
+		wants_approach.clear();
+	}
+}
@@ -0,0 +1,108 @@
+use rltk::{DistanceAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use crate::{Awareness, AwarenessState, DefenseBonus, Equipped, MyTurn, Name, Perception, Position, Viewshed};
+use crate::ai::perceives;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+
+/// Sleeping monsters can only notice the player within this many tiles
+const WAKE_DISTANCE : f32 = 4.;
+
+/// The player's stealth before light and armour are taken into account
+const BASE_STEALTH : i32 = 10;
+
+/// Decides whether monsters that aren't already hunting the player notice
+/// them, rolling against the player's stealth. Sleeping monsters lose their
+/// turn either way.
+pub struct AwarenessAI {}
+
+impl<'a> System<'a> for AwarenessAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, Point>,
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, GameLog>,
+		WriteExpect<'a, RandomNumberGenerator>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, Awareness>,
+		ReadStorage<'a, Position>,
+		ReadStorage<'a, Viewshed>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, DefenseBonus>,
+		ReadStorage<'a, Equipped>,
+		ReadStorage<'a, Perception>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, player_pos, player_entity, mut log, mut rng, entities,
+			mut turns, mut awareness, positions, viewsheds, names,
+			defense_bonuses, equipped, perception,
+		) = data;
+
+		let stealth = player_stealth(&map, *player_pos, *player_entity, &defense_bonuses, &equipped);
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, _turn, awareness, pos, viewshed) in (&entities, &turns, &mut awareness, &positions, &viewsheds).join() {
+			if awareness.state == AwarenessState::Hunting { continue; }
+
+			let asleep = awareness.state == AwarenessState::Asleep;
+
+			// Sleeping or waking up, either way that's the turn gone
+			if asleep { turn_done.push(entity); }
+
+			if !perceives(&map, pos, viewshed, perception.get(entity), &player_pos) { continue; }
+
+			let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+			if asleep && distance >= WAKE_DISTANCE { continue; }
+
+			// Closer is easier to spot, and being on edge helps
+			let bonus = match awareness.state {
+				AwarenessState::Asleep => -5,
+				AwarenessState::Alert => 5,
+				_ => 0,
+			};
+			let notice = rng.roll_dice(1, 20) + bonus + i32::max(0, 8 - distance as i32);
+			if notice <= stealth { continue; }
+
+			awareness.state = if asleep { AwarenessState::Alert } else { AwarenessState::Hunting };
+
+			if map.is_visible(map.xy_idx(pos.x, pos.y)) {
+				if let Some(name) = names.get(entity) {
+					log.combat(format!(
+						"{} {}",
+						&name.name,
+						if asleep { "wakes up!" } else { "notices you!" },
+					));
+				}
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+	}
+}
+
+/// How hard the player is to spot: shadows help, clanking armour doesn't
+fn player_stealth (
+	map: &Map,
+	player_pos: Point,
+	player_entity: Entity,
+	defense_bonuses: &ReadStorage<DefenseBonus>,
+	equipped: &ReadStorage<Equipped>,
+) -> i32 {
+	let mut stealth = BASE_STEALTH;
+
+	if !map.is_lit(map.xy_idx(player_pos.x, player_pos.y)) { stealth += 4; }
+
+	for (defense_bonus, equipped_by) in (defense_bonuses, equipped).join() {
+		if equipped_by.owner == player_entity {
+			stealth -= defense_bonus.defense;
+		}
+	}
+
+	stealth
+}
@@ -0,0 +1,101 @@
+use specs::prelude::*;
+use crate::{BossPhases, CombatStats, MyTurn, Name, Position, StatusEffectKind, StatusEffects};
+use crate::gamelog::GameLog;
+use crate::map::Map;
+
+/// Haste from a boss phase lasts for the rest of the fight
+const PHASE_HASTE : i32 = 1000;
+
+// Builder
+// =========================================================================
+
+struct SummonRequest {
+	name: String,
+	x: i32,
+	y: i32,
+}
+
+/// Bosses calling for help ask for their adds here, and the spawner brings
+/// them in once the systems have run
+#[derive(Default)]
+pub struct SummonBuilder {
+	requests : Vec<SummonRequest>,
+}
+
+impl SummonBuilder {
+	pub fn new() -> SummonBuilder {
+		SummonBuilder { requests: Vec::new() }
+	}
+
+	pub fn request (&mut self, name: &str, x: i32, y: i32) {
+		self.requests.push(SummonRequest { name: name.to_string(), x, y });
+	}
+
+	/// Hands over everything asked for so far, as (name, x, y)
+	pub fn take (&mut self) -> Vec<(String, i32, i32)> {
+		self.requests.drain(..).map(|r| (r.name, r.x, r.y)).collect()
+	}
+}
+
+// System
+// =========================================================================
+
+/// Moves bosses on to their next phase as their health drops past each
+/// threshold: calling in adds, getting angrier and faster, and unlocking
+/// abilities. Changing phase takes the boss's turn.
+pub struct BossAI {}
+
+impl<'a> System<'a> for BossAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, GameLog>,
+		WriteExpect<'a, SummonBuilder>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, BossPhases>,
+		WriteStorage<'a, CombatStats>,
+		WriteStorage<'a, StatusEffects>,
+		ReadStorage<'a, Position>,
+		ReadStorage<'a, Name>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, mut log, mut summons, entities, mut turns, mut bosses,
+			mut combat_stats, mut statuses, positions, names,
+		) = data;
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, _turn, boss, stats, pos) in (&entities, &turns, &mut bosses, &mut combat_stats, &positions).join() {
+			let health = stats.hp * 100 / i32::max(1, stats.max_hp);
+
+			while let Some(phase) = boss.phases.get(boss.current) {
+				if health >= phase.below { break; }
+				boss.current += 1;
+
+				if map.is_visible(map.xy_idx(pos.x, pos.y)) {
+					if let Some(name) = names.get(entity) {
+						log.dialogue(format!("{} {}", &name.name, &phase.shout));
+					}
+				}
+
+				for name in phase.summons.iter() {
+					summons.request(name, pos.x, pos.y);
+				}
+
+				stats.power += phase.enrage;
+
+				if phase.haste {
+					StatusEffects::add(&mut statuses, entity, StatusEffectKind::Hasted, PHASE_HASTE);
+				}
+
+				turn_done.push(entity);
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+	}
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use rltk::{DistanceAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use crate::{Ally, Awareness, AwarenessState, CanOpenDoors, Door, EntityMoved, Idle, IdleBehaviour, Memory, Morale, MyTurn, PackMember, Patrol, Position, Swarm, Viewshed, WantsToOpenDoor};
+use crate::ai::{closed_door_at, next_step, step_to, PlayerFlowMap};
+use crate::debug_log::OrWarn;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// Wandering monsters won't stray further than this from home
+const WANDER_RADIUS : f32 = 6.;
+
+/// Allies catch up with the player once they fall this far behind
+const FOLLOW_DISTANCE : f32 = 2.5;
+
+/// Swarm members drift back towards the rest once they stray this far from
+/// the middle of the swarm
+const SWARM_SPREAD : f32 = 2.5;
+
+/// Whatever's still got its turn has nothing in sight to deal with, so it
+/// investigates where it last saw the player, or failing that idles about.
+/// Allies just tag along after the player.
+pub struct DefaultMoveAI {}
+
+impl<'a> System<'a> for DefaultMoveAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		WriteExpect<'a, RandomNumberGenerator>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		WriteStorage<'a, Memory>,
+		ReadStorage<'a, Idle>,
+		ReadStorage<'a, Morale>,
+		WriteStorage<'a, EntityMoved>,
+		ReadStorage<'a, CanOpenDoors>,
+		ReadStorage<'a, Door>,
+		WriteStorage<'a, WantsToOpenDoor>,
+		WriteStorage<'a, Awareness>,
+		ReadExpect<'a, Point>,
+		ReadStorage<'a, Ally>,
+		WriteStorage<'a, Patrol>,
+		ReadStorage<'a, Swarm>,
+		ReadStorage<'a, PackMember>,
+		WriteExpect<'a, PlayerFlowMap>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, mut spatial, mut rng, entities, mut turns, mut positions, mut viewsheds,
+			mut memories, idle, morale, mut entity_moved, door_openers, doors,
+			mut wants_open, mut awareness, player_pos, allies, mut patrols,
+			swarms, pack_members, mut flow,
+		) = data;
+
+		// The middle of each swarm
+		let mut swarm_centres : HashMap<i32, (i32, i32, i32)> = HashMap::new();
+		for (_swarm, member, pos) in (&swarms, &pack_members, &positions).join() {
+			let centre = swarm_centres.entry(member.pack_id).or_insert((0, 0, 0));
+			centre.0 += pos.x;
+			centre.1 += pos.y;
+			centre.2 += 1;
+		}
+
+		for (entity, _turn, pos, viewshed) in (&entities, &turns, &mut positions, &mut viewsheds).join() {
+			// Licking its wounds
+			if morale.get(entity).map_or(false, |m| m.fleeing) { continue; }
+
+			let mut destination : Option<usize> = None;
+			let mut searching = false;
+
+			if allies.get(entity).is_some() {
+				let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+				if distance > FOLLOW_DISTANCE {
+					destination = Some(map.xy_idx(player_pos.x, player_pos.y));
+				}
+			} else if let Some(memory) = memories.get_mut(entity) {
+				destination = investigate(memory, &map, &spatial, pos, &mut rng);
+				searching = memory.last_seen.is_some();
+
+				// Lost the trail, but still on edge
+				if !searching {
+					if let Some(awareness) = awareness.get_mut(entity) {
+						if awareness.state == AwarenessState::Hunting {
+							awareness.state = AwarenessState::Alert;
+						}
+					}
+				}
+			}
+
+			// Nothing to investigate, so go about its rounds or idle about
+			if destination.is_none() && !searching && allies.get(entity).is_none() {
+				let swarm_centre = swarms.get(entity)
+					.and(pack_members.get(entity))
+					.and_then(|member| swarm_centres.get(&member.pack_id))
+					.map(|(x, y, count)| Point::new(x / count, y / count));
+
+				if let Some(patrol) = patrols.get_mut(entity) {
+					destination = patrol_destination(patrol, &map, pos);
+				} else if let Some(centre) = swarm_centre.filter(|centre| {
+					DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *centre) > SWARM_SPREAD
+				}) {
+					destination = Some(map.xy_idx(centre.x, centre.y));
+				} else if let Some(idle) = idle.get(entity) {
+					destination = idle_destination(idle, &map, &spatial, pos, &mut rng);
+				}
+			}
+
+			// Out of pathfinding for this turn, so potter about next turn instead
+			let destination = destination.filter(|_| flow.spend_path());
+
+			let step = destination.and_then(|destination| next_step(
+				&map,
+				&spatial,
+				map.xy_idx(pos.x, pos.y),
+				destination,
+				door_openers.get(entity).is_some(),
+			));
+
+			// Can't get to this waypoint from here, so try the next one
+			if step.is_none() && destination.is_some() && !searching {
+				if let Some(patrol) = patrols.get_mut(entity) {
+					patrol.next = (patrol.next + 1) % patrol.waypoints.len();
+				}
+			}
+
+			if let Some(step) = step {
+				if let Some(door) = closed_door_at(&spatial, &doors, step) {
+					wants_open.insert(entity, WantsToOpenDoor { door })
+						.or_warn("Door handle came off");
+				} else {
+					step_to(&map, &mut spatial, entity, pos, viewshed, step);
+					entity_moved.insert(entity, EntityMoved {})
+						.or_warn("Failed to use ambling legs");
+				}
+			}
+		}
+
+		turns.clear();
+	}
+}
+
+/// Heads for where the player was last seen, then pokes around nearby for a
+/// few turns before giving up
+fn investigate (memory: &mut Memory, map: &Map, spatial: &SpatialIndex, pos: &Position, rng: &mut RandomNumberGenerator) -> Option<usize> {
+	let (x, y) = memory.last_seen?;
+
+	let distance = DistanceAlg::Pythagoras.distance2d(
+		Point::new(pos.x, pos.y),
+		Point::new(x, y),
+	);
+	if distance >= 1.5 { return Some(map.xy_idx(x, y)); }
+
+	memory.search_turns -= 1;
+	if memory.search_turns < 1 {
+		memory.last_seen = None;
+		return None;
+	}
+
+	let x = pos.x + rng.range(-1, 2);
+	let y = pos.y + rng.range(-1, 2);
+	if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { return None; }
+
+	let idx = map.xy_idx(x, y);
+	if spatial.is_blocked(idx) { None } else { Some(idx) }
+}
+
+/// The waypoint a guard's heading for, moving on to the next once it's there
+fn patrol_destination (patrol: &mut Patrol, map: &Map, pos: &Position) -> Option<usize> {
+	if patrol.waypoints.is_empty() { return None; }
+
+	if patrol.waypoints[patrol.next] == (pos.x, pos.y) {
+		patrol.next = (patrol.next + 1) % patrol.waypoints.len();
+	}
+
+	let (x, y) = patrol.waypoints[patrol.next];
+	Some(map.xy_idx(x, y))
+}
+
+/// Picks where an idle monster would like to go next, if anywhere
+fn idle_destination (idle: &Idle, map: &Map, spatial: &SpatialIndex, pos: &Position, rng: &mut RandomNumberGenerator) -> Option<usize> {
+	let (home_x, home_y) = idle.home;
+
+	match idle.behaviour {
+		IdleBehaviour::Guard => {
+			if pos.x == home_x && pos.y == home_y { None }
+			else { Some(map.xy_idx(home_x, home_y)) }
+		}
+		IdleBehaviour::Wander => {
+			// Strayed too far chasing something, head back
+			let from_home = DistanceAlg::Pythagoras.distance2d(
+				Point::new(pos.x, pos.y),
+				Point::new(home_x, home_y),
+			);
+			if from_home > WANDER_RADIUS { return Some(map.xy_idx(home_x, home_y)); }
+
+			// Amble, don't march
+			if rng.roll_dice(1, 3) != 1 { return None; }
+
+			let x = pos.x + rng.range(-1, 2);
+			let y = pos.y + rng.range(-1, 2);
+			if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { return None; }
+
+			let idx = map.xy_idx(x, y);
+			if spatial.is_blocked(idx) { return None; }
+
+			let from_home = DistanceAlg::Pythagoras.distance2d(
+				Point::new(x, y),
+				Point::new(home_x, home_y),
+			);
+			if from_home > WANDER_RADIUS { return None; }
+
+			Some(idx)
+		}
+	}
+}
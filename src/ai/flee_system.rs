@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use rltk::{DijkstraMap, Point};
+use specs::prelude::*;
+use crate::{CombatStats, EntityMoved, MyTurn, Position, Viewshed, WantsToFlee, WantsToMelee};
+use crate::ai::{step_to, PlayerFlowMap};
+use crate::debug_log::OrWarn;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How many flee maps are kept before they're all thrown out
+const FLEE_MAPS_KEPT : usize = 32;
+
+/// Runs away from everything in WantsToFlee, lashing out at whatever's
+/// adjacent if there's nowhere left to run. Running from the player follows
+/// the flow map backwards, so however many are fleeing it costs nothing
+/// extra. Running from anything else follows a flee map over the floor plan,
+/// kept for as long as the system's around (until the level changes), so
+/// something fleeing the same dangers turn after turn only costs the once.
+#[derive(Default)]
+pub struct FleeAI {
+	flee_maps : HashMap<Vec<usize>, Vec<f32>>,
+}
+
+impl FleeAI {
+	/// The flee map away from `dangers`, worked out if it's not been already
+	fn flee_map (&mut self, map: &Map, dangers: &[usize]) -> &Vec<f32> {
+		if !self.flee_maps.contains_key(dangers) && self.flee_maps.len() >= FLEE_MAPS_KEPT {
+			self.flee_maps.clear();
+		}
+
+		self.flee_maps.entry(dangers.to_vec()).or_insert_with(|| {
+			DijkstraMap::new(map.width, map.height, dangers, map, 200.).map
+		})
+	}
+
+	/// How many flee maps are being kept
+	#[cfg(test)]
+	pub fn flee_maps_kept (&self) -> usize { self.flee_maps.len() }
+}
+
+impl<'a> System<'a> for FleeAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		WriteStorage<'a, WantsToFlee>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		ReadStorage<'a, CombatStats>,
+		WriteStorage<'a, WantsToMelee>,
+		WriteStorage<'a, EntityMoved>,
+		ReadExpect<'a, PlayerFlowMap>,
+		ReadExpect<'a, Point>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, mut spatial, entities, mut turns, mut wants_flee, mut positions,
+			mut viewsheds, combat_stats, mut wants_melee, mut entity_moved,
+			flow, player_pos,
+		) = data;
+
+		let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, _turn, flee, pos, viewshed) in (&entities, &turns, &wants_flee, &mut positions, &mut viewsheds).join() {
+			turn_done.push(entity);
+
+			let idx = map.xy_idx(pos.x, pos.y);
+			let exit =
+				if flee.indices.contains(&player_idx) { flow.step_away_from_player(&map, &spatial, idx) }
+				else {
+					let mut dangers = flee.indices.clone();
+					dangers.sort_unstable();
+					dangers.dedup();
+					let flee_map = self.flee_map(&map, &dangers);
+
+					// Further from the dangers than here, and not stood on
+					map.terrain_exits(idx).iter()
+						.map(|(exit, _)| *exit)
+						.filter(|exit| !spatial.is_blocked(*exit) && flee_map[*exit] > flee_map[idx])
+						.max_by(|a, b| flee_map[*a].total_cmp(&flee_map[*b]))
+				};
+
+			if let Some(exit) = exit {
+				step_to(&map, &mut spatial, entity, pos, viewshed, exit);
+				entity_moved.insert(entity, EntityMoved {})
+					.or_warn("Failed to use cowardly legs");
+				continue;
+			}
+
+			// Cornered, nowhere left to run so fight back
+			let cornered_by = flee.indices.iter()
+				.filter(|danger| {
+					let (x, y) = (**danger as i32 % map.width, **danger as i32 / map.width);
+					(x - pos.x).abs() <= 1 && (y - pos.y).abs() <= 1
+				})
+				.flat_map(|danger| spatial.content(*danger))
+				.find(|e| combat_stats.get(*e).is_some());
+
+			if let Some(target) = cornered_by {
+				wants_melee.insert(entity, WantsToMelee { target })
+					.or_warn("Unable to lash out");
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+
+		wants_flee.clear();
+	}
+}
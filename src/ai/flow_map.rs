@@ -0,0 +1,85 @@
+use rltk::DijkstraMap;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How many A* searches the AI gets through each monster turn. Anything after
+/// that has to make do with the flow map, or wait its turn.
+const PATH_BUDGET : i32 = 24;
+
+/// How far out from the player the flow map reaches
+const FLOW_DEPTH : f32 = 200.;
+
+/// A Dijkstra map over the floor plan leading to the player, shared by
+/// everything approaching them, and followed backwards by everything running
+/// away from them. It's only worked out again when the player
+/// moves, so a crowd of monsters costs no more than one of them.
+pub struct PlayerFlowMap {
+	distances  : Vec<f32>,
+	player_idx : Option<usize>,
+	paths_left : i32,
+}
+
+impl Default for PlayerFlowMap {
+	fn default () -> PlayerFlowMap { PlayerFlowMap::new() }
+}
+
+impl PlayerFlowMap {
+	pub fn new() -> PlayerFlowMap {
+		PlayerFlowMap {
+			distances: Vec::new(),
+			player_idx: None,
+			paths_left: PATH_BUDGET,
+		}
+	}
+
+	/// Readies the flow map for a new monster turn, working it out again if
+	/// the player has moved since the last one
+	pub fn update (&mut self, map: &Map, player_idx: usize) {
+		self.paths_left = PATH_BUDGET;
+
+		if self.player_idx == Some(player_idx) { return; }
+		self.player_idx = Some(player_idx);
+
+		let flow = DijkstraMap::new(
+			map.width, map.height,
+			&[player_idx], map,
+			FLOW_DEPTH,
+		);
+		self.distances = flow.map;
+	}
+
+	/// Forgets the flow map, for when the level it was worked out on is gone
+	pub fn invalidate (&mut self) {
+		self.player_idx = None;
+		self.distances.clear();
+	}
+
+	/// The free tile next to `idx` that gets closest to the player, if any of
+	/// them are closer than where it is now
+	pub fn step_towards_player (&self, map: &Map, spatial: &SpatialIndex, idx: usize) -> Option<usize> {
+		let here = *self.distances.get(idx)?;
+
+		map.terrain_exits(idx).iter()
+			.map(|(exit, _)| *exit)
+			.filter(|exit| !spatial.is_blocked(*exit) && self.distances[*exit] < here)
+			.min_by(|a, b| self.distances[*a].total_cmp(&self.distances[*b]))
+	}
+
+	/// The free tile next to `idx` that gets furthest from the player, if any
+	/// of them are further than where it is now
+	pub fn step_away_from_player (&self, map: &Map, spatial: &SpatialIndex, idx: usize) -> Option<usize> {
+		let here = *self.distances.get(idx)?;
+
+		map.terrain_exits(idx).iter()
+			.map(|(exit, _)| *exit)
+			.filter(|exit| !spatial.is_blocked(*exit) && self.distances[*exit] > here)
+			.max_by(|a, b| self.distances[*a].total_cmp(&self.distances[*b]))
+	}
+
+	/// Uses up one of this turn's A* searches, if there are any left
+	pub fn spend_path (&mut self) -> bool {
+		if self.paths_left < 1 { return false; }
+		self.paths_left -= 1;
+		true
+	}
+}
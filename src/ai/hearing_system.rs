@@ -0,0 +1,143 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use specs::prelude::*;
+use crate::{Awareness, AwarenessState, Memory, Name, Position};
+use crate::ai::visible_system::SEARCH_TURNS;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How many extra tiles of a sound's reach a closed door soaks up
+const DOOR_MUFFLE : i32 = 3;
+
+// Builder
+// =========================================================================
+
+struct NoiseRequest {
+	x: i32,
+	y: i32,
+	radius: i32,
+}
+
+/// Anything loud asks for a noise here, and the HearingAI lets every monster
+/// within earshot know about it on its next run
+#[derive(Default)]
+pub struct NoiseBuilder {
+	requests : Vec<NoiseRequest>,
+}
+
+impl NoiseBuilder {
+	pub fn new() -> NoiseBuilder {
+		NoiseBuilder { requests: Vec::new() }
+	}
+
+	pub fn request (&mut self, x: i32, y: i32, radius: i32) {
+		self.requests.push(NoiseRequest { x, y, radius });
+	}
+}
+
+// System
+// =========================================================================
+
+/// Wakes up or draws in monsters that can hear a noise, even round corners.
+/// Sound travels along the ground rather than through walls, and closed
+/// doors muffle it.
+pub struct HearingAI {}
+
+impl<'a> System<'a> for HearingAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, SpatialIndex>,
+		WriteExpect<'a, NoiseBuilder>,
+		WriteExpect<'a, GameLog>,
+		Entities<'a>,
+		ReadStorage<'a, Position>,
+		WriteStorage<'a, Awareness>,
+		WriteStorage<'a, Memory>,
+		ReadStorage<'a, Name>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, spatial, mut noise, mut log, entities, positions, mut awareness,
+			mut memories, names,
+		) = data;
+
+		for request in noise.requests.iter() {
+			let heard = earshot(&map, &spatial, request);
+
+			for (entity, pos, awareness) in (&entities, &positions, &mut awareness).join() {
+				let idx = map.xy_idx(pos.x, pos.y);
+				if !heard[idx] { continue; }
+
+				// Already after the player, a bit of noise won't distract it
+				if awareness.state == AwarenessState::Hunting { continue; }
+
+				if awareness.state == AwarenessState::Asleep && map.is_visible(idx) {
+					if let Some(name) = names.get(entity) {
+						log.combat(format!("{} wakes up!", &name.name));
+					}
+				}
+				awareness.state = AwarenessState::Alert;
+
+				// Go and see what all the fuss is about
+				if let Some(memory) = memories.get_mut(entity) {
+					memory.last_seen = Some((request.x, request.y));
+					memory.search_turns = SEARCH_TURNS;
+				}
+			}
+		}
+
+		noise.requests.clear();
+	}
+}
+
+/// Whether a noise `radius` loud at `x`, `y` carries as far as `idx`, for
+/// telling the player about whatever they'd have heard
+pub fn within_earshot (map: &Map, spatial: &SpatialIndex, x: i32, y: i32, radius: i32, idx: usize) -> bool {
+	earshot(map, spatial, &NoiseRequest { x, y, radius })[idx]
+}
+
+/// Every tile the noise carries to
+fn earshot (map: &Map, spatial: &SpatialIndex, request: &NoiseRequest) -> Vec<bool> {
+	let mut heard = vec![false; map.tiles.len()];
+	let mut cost = vec![i32::MAX; map.tiles.len()];
+	let mut open : BinaryHeap<Reverse<(i32, usize)>> = BinaryHeap::new();
+
+	if request.x < 0 || request.x > map.width - 1 || request.y < 0 || request.y > map.height - 1 {
+		return heard;
+	}
+
+	let start = map.xy_idx(request.x, request.y);
+	cost[start] = 0;
+	open.push(Reverse((0, start)));
+
+	while let Some(Reverse((so_far, idx))) = open.pop() {
+		if so_far > cost[idx] { continue; }
+		heard[idx] = true;
+
+		let x = idx as i32 % map.width;
+		let y = idx as i32 / map.width;
+
+		for dy in -1 ..= 1 {
+			for dx in -1 ..= 1 {
+				if dx == 0 && dy == 0 { continue; }
+
+				let (nx, ny) = (x + dx, y + dy);
+				if nx < 0 || nx > map.width - 1 || ny < 0 || ny > map.height - 1 { continue; }
+				if map.is_void_or_wall(nx, ny) { continue; }
+
+				let next = map.xy_idx(nx, ny);
+				let step = if spatial.is_closed_door(next) { 1 + DOOR_MUFFLE } else { 1 };
+				let next_cost = so_far + step;
+
+				if next_cost <= request.radius && next_cost < cost[next] {
+					cost[next] = next_cost;
+					open.push(Reverse((next_cost, next)));
+				}
+			}
+		}
+	}
+
+	heard
+}
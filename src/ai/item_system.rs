@@ -0,0 +1,184 @@
+use specs::prelude::*;
+use rltk::{a_star_search, DistanceAlg, Point};
+use crate::{AreaOfEffect, Awareness, CombatStats, DefenseBonus, EntityMoved, Equippable, Equipped, Faction, InBackpack, InflictsDamage, InflictsStatus, Item, MeleePowerBonus, Monster, MyTurn, Perception, Position, ProvidesHealing, Ranged, Viewshed, WantsToPickupItem, WantsToUseItem};
+use crate::debug_log::OrWarn;
+use crate::map::Map;
+use crate::spatial::{LiveMap, SpatialIndex};
+use crate::ai::{hunting_player, sees, step_to};
+
+/// Monsters below this percent health will drink a potion if they have one
+const HEAL_BELOW : i32 = 50;
+
+/// Lets monsters pick up, equip, and use the items they come across, using the
+/// same intents as the player
+pub struct ItemAI {}
+
+impl<'a> System<'a> for ItemAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		ReadExpect<'a, Point>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		ReadStorage<'a, Monster>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		ReadStorage<'a, CombatStats>,
+		ReadStorage<'a, Item>,
+		ReadStorage<'a, InBackpack>,
+		ReadStorage<'a, Equippable>,
+		ReadStorage<'a, Equipped>,
+		ReadStorage<'a, MeleePowerBonus>,
+		ReadStorage<'a, DefenseBonus>,
+		ReadStorage<'a, ProvidesHealing>,
+		ReadStorage<'a, InflictsDamage>,
+		ReadStorage<'a, InflictsStatus>,
+		ReadStorage<'a, Ranged>,
+		ReadStorage<'a, AreaOfEffect>,
+		WriteStorage<'a, WantsToUseItem>,
+		WriteStorage<'a, WantsToPickupItem>,
+		WriteStorage<'a, EntityMoved>,
+		ReadStorage<'a, Faction>,
+		ReadStorage<'a, Awareness>,
+		ReadStorage<'a, Perception>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map,
+			mut spatial,
+			player_pos,
+			entities,
+			mut turns,
+			monsters,
+			mut positions,
+			mut viewsheds,
+			combat_stats,
+			items,
+			backpack,
+			equippable,
+			equipped,
+			power_bonuses,
+			defense_bonuses,
+			healing,
+			inflicts_damage,
+			inflicts_status,
+			ranged,
+			aoe,
+			mut wants_use,
+			mut wants_pickup,
+			mut entity_moved,
+			factions,
+			awareness,
+			perception,
+		) = data;
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		let bonus = |item: Entity| -> i32 {
+			power_bonuses.get(item).map_or(0, |b| b.power)
+				+ defense_bonuses.get(item).map_or(0, |b| b.defense)
+		};
+
+		for (entity, _turn, _monster, pos, viewshed, stats) in (&entities, &turns, &monsters, &mut positions, &mut viewsheds, &combat_stats).join() {
+			let carried : Vec<Entity> = (&entities, &backpack).join()
+				.filter(|(_, pack)| pack.owner == entity)
+				.map(|(item, _)| item)
+				.collect();
+
+			// How much better this item is than whatever's in its slot now
+			let upgrade = |item: Entity| -> i32 {
+				match equippable.get(item) {
+					None => 0,
+					Some(can_equip) => {
+						let current = (&entities, &equipped).join()
+							.filter(|(_, e)| e.owner == entity && e.slot == can_equip.slot)
+							.map(|(e, _)| bonus(e))
+							.max()
+							.unwrap_or(0);
+						bonus(item) - current
+					}
+				}
+			};
+
+			let is_throwable = |item: Entity| -> bool {
+				ranged.get(item).is_some()
+					&& (inflicts_damage.get(item).is_some() || inflicts_status.get(item).is_some())
+			};
+
+			let is_wanted = |item: Entity| -> bool {
+				healing.get(item).is_some() || is_throwable(item) || upgrade(item) > 0
+			};
+
+			let distance = DistanceAlg::Pythagoras.distance2d(
+				Point::new(pos.x, pos.y),
+				*player_pos,
+			);
+			let can_see_player = hunting_player(entity, &factions, &awareness)
+				&& sees(&map, pos, viewshed, perception.get(entity), &player_pos);
+
+			// Drink a potion when hurt
+			if stats.hp * 100 / i32::max(1, stats.max_hp) < HEAL_BELOW {
+				if let Some(potion) = carried.iter().find(|i| healing.get(**i).is_some()) {
+					wants_use.insert(entity, WantsToUseItem { item: *potion, target: None })
+						.or_warn("Potion cork stuck");
+					turn_done.push(entity);
+					continue;
+				}
+			}
+
+			// Throw something nasty at the player from a distance
+			if can_see_player && distance >= 1.5 {
+				let throwable = carried.iter().find(|i| {
+					is_throwable(**i)
+						&& ranged.get(**i).map_or(false, |r| distance <= r.range as f32)
+						&& aoe.get(**i).map_or(true, |a| distance > a.radius as f32)
+				});
+				if let Some(throwable) = throwable {
+					wants_use.insert(entity, WantsToUseItem { item: *throwable, target: Some(*player_pos) })
+						.or_warn("Butterfingers");
+					turn_done.push(entity);
+					continue;
+				}
+			}
+
+			// Equip anything better than what's worn
+			if let Some(gear) = carried.iter().find(|i| upgrade(**i) > 0) {
+				wants_use.insert(entity, WantsToUseItem { item: *gear, target: None })
+					.or_warn("Stuck in a sleeve");
+				turn_done.push(entity);
+				continue;
+			}
+
+			// Pick up anything useful underfoot
+			let idx = map.xy_idx(pos.x, pos.y);
+			let underfoot = spatial.content(idx).find(|e| items.get(*e).is_some() && is_wanted(*e));
+			if let Some(item) = underfoot {
+				wants_pickup.insert(entity, WantsToPickupItem { collected_by: entity, items: vec![item] })
+					.or_warn("Dropped it");
+				turn_done.push(entity);
+				continue;
+			}
+
+			// Go and fetch anything useful in sight, when there's nothing better to do
+			if can_see_player { continue; }
+
+			let fetch = viewshed.visible_tiles.iter()
+				.map(|tile| map.xy_idx(tile.x, tile.y))
+				.find(|tile_idx| spatial.content(*tile_idx).any(|e| items.get(e).is_some() && is_wanted(e)));
+			if let Some(fetch) = fetch {
+				let path = a_star_search(idx as i32, fetch as i32, &LiveMap { map: &map, spatial: &spatial, can_open_doors: false });
+				if path.success && path.steps.len() > 1 {
+					step_to(&map, &mut spatial, entity, pos, viewshed, path.steps[1]);
+					entity_moved.insert(entity, EntityMoved {})
+						.or_warn("Failed to use greedy legs");
+					turn_done.push(entity);
+				}
+			}
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+	}
+}
@@ -0,0 +1,103 @@
+mod flow_map;
+mod turn_system;
+mod hearing_system;
+mod awareness_system;
+mod boss_system;
+mod quip_system;
+mod item_system;
+mod ability_system;
+mod visible_system;
+mod flee_system;
+mod approach_system;
+mod default_move_system;
+
+use rltk::{a_star_search, DistanceAlg, Point};
+use specs::prelude::*;
+use crate::{Awareness, AwarenessState, Door, Faction, FactionKind, Perception, Position, Reaction, Viewshed};
+use crate::map::Map;
+use crate::spatial::{LiveMap, SpatialIndex};
+pub use crate::ai::{
+	flow_map::PlayerFlowMap,
+	turn_system::TurnSystem,
+	hearing_system::{HearingAI, NoiseBuilder, within_earshot},
+	awareness_system::AwarenessAI,
+	boss_system::{BossAI, SummonBuilder},
+	quip_system::QuipSystem,
+	item_system::ItemAI,
+	ability_system::AbilityAI,
+	visible_system::VisibleAI,
+	flee_system::FleeAI,
+	approach_system::ApproachAI,
+	default_move_system::DefaultMoveAI,
+};
+
+// The AI runs as a chain of small systems, in the order above. TurnSystem
+// hands out MyTurn to everything that can act, then each system in turn picks
+// off the entities it has something to do with, taking away their MyTurn (or
+// handing them an intent like WantsToApproach for a later system to act on).
+// Monsters that haven't noticed the player act as if they weren't there.
+// Anything left at the end just potters about. Behaviours are mixed per
+// monster by which components it's spawned with.
+
+/// How close something has to be to make it out in the dark, without dark
+/// vision
+const DARK_SIGHT : f32 = 3.;
+
+/// Whether the entity can see whatever's at `target`: in line of sight, in
+/// range, and either lit or close enough. Anything without a Perception goes
+/// by its Viewshed alone.
+pub fn sees (map: &Map, pos: &Position, viewshed: &Viewshed, perception: Option<&Perception>, target: &Point) -> bool {
+	if !viewshed.visible_tiles.contains(target) { return false; }
+
+	let perception = match perception {
+		None => return true,
+		Some(perception) => perception,
+	};
+
+	let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *target);
+	distance <= perception.vision as f32
+		&& (perception.dark_vision || distance <= DARK_SIGHT || map.is_lit(map.xy_idx(target.x, target.y)))
+}
+
+/// Whether the entity can see or smell whatever's at `target`
+pub fn perceives (map: &Map, pos: &Position, viewshed: &Viewshed, perception: Option<&Perception>, target: &Point) -> bool {
+	let smell = perception.map_or(0, |p| p.smell) as f32;
+	DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *target) <= smell
+		|| sees(map, pos, viewshed, perception, target)
+}
+
+/// Moves the entity onto the given map index, keeping the spatial index in
+/// sync
+pub fn step_to (map: &Map, spatial: &mut SpatialIndex, entity: Entity, pos: &mut Position, viewshed: &mut Viewshed, dest_idx: usize) {
+	let idx = map.xy_idx(pos.x, pos.y);
+	spatial.move_entity(entity, idx, dest_idx);
+
+	pos.x = dest_idx as i32 % map.width;
+	pos.y = dest_idx as i32 / map.width;
+
+	viewshed.dirty = true;
+}
+
+/// The next step along the path from start to dest, if there is one, going
+/// through closed doors if the entity knows how to open them. This is a full
+/// A* search, so check PlayerFlowMap::spend_path first.
+pub fn next_step (map: &Map, spatial: &SpatialIndex, start: usize, dest: usize, can_open_doors: bool) -> Option<usize> {
+	let path = a_star_search(start, dest, &LiveMap { map, spatial, can_open_doors });
+
+	if path.success && path.steps.len() > 1 { Some(path.steps[1]) }
+	else { None }
+}
+
+/// The closed door standing on the given map index, if there is one
+pub fn closed_door_at (spatial: &SpatialIndex, doors: &ReadStorage<Door>, idx: usize) -> Option<Entity> {
+	if !spatial.is_closed_door(idx) { return None; }
+
+	spatial.content(idx).find(|e| doors.get(*e).map_or(false, |door| !door.open))
+}
+
+/// Whether the entity is out to get the player right now: its faction wants
+/// the player dead, and it's noticed them
+pub fn hunting_player (entity: Entity, factions: &ReadStorage<Faction>, awareness: &ReadStorage<Awareness>) -> bool {
+	factions.get(entity).map_or(true, |f| f.kind.reaction_to(FactionKind::Player) == Reaction::Attack)
+		&& awareness.get(entity).map_or(true, |a| a.state == AwarenessState::Hunting)
+}
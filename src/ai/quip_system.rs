@@ -0,0 +1,44 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+use crate::{MyTurn, Name, Perception, Position, Quips, Viewshed};
+use crate::ai::sees;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+
+/// Gives monsters the player can see, and that can see the player, the odd
+/// chance to pipe up. Talking is free, so this doesn't use up their turn.
+pub struct QuipSystem {}
+
+impl<'a> System<'a> for QuipSystem {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, Point>,
+		WriteExpect<'a, GameLog>,
+		WriteExpect<'a, RandomNumberGenerator>,
+		Entities<'a>,
+		ReadStorage<'a, MyTurn>,
+		ReadStorage<'a, Quips>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, Position>,
+		ReadStorage<'a, Viewshed>,
+		ReadStorage<'a, Perception>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, player_pos, mut log, mut rng, entities, turns, quips, names, positions,
+			viewsheds, perception,
+		) = data;
+
+		for (entity, _turn, quips, name, pos, viewshed) in (&entities, &turns, &quips, &names, &positions, &viewsheds).join() {
+			if quips.available.is_empty() { continue; }
+			if !map.is_visible(map.xy_idx(pos.x, pos.y)) { continue; }
+			if !sees(&map, pos, viewshed, perception.get(entity), &player_pos) { continue; }
+			if rng.roll_dice(1, 6) != 1 { continue; }
+
+			if let Some(quip) = rng.random_slice_entry(&quips.available) {
+				log.dialogue(format!("{} {}", &name.name, quip));
+			}
+		}
+	}
+}
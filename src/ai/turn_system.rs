@@ -0,0 +1,101 @@
+use rltk::Point;
+use specs::prelude::*;
+use crate::{Ally, Burden, Bystander, Monster, MyTurn, Position, StatusEffectKind, StatusEffects, TurnState};
+use crate::ai::PlayerFlowMap;
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
+use crate::map::Map;
+use crate::palette::Palette;
+use crate::particle_system::{Particle, ParticleBuilder};
+
+/// Hands out a turn to everything with an AI at the start of the monster
+/// turn, skipping anything too confused or tangled up to act. Anything hasted
+/// gets a second go in a HastedTurn straight after, and so does everyone
+/// while the player's falling behind under too much weight.
+pub struct TurnSystem {}
+
+impl TurnSystem {
+	/// Whether anything but the player is hasted, or the player's too laden
+	/// to keep up, and there needs to be a HastedTurn
+	pub fn anyone_goes_twice (ecs: &World) -> bool {
+		let monsters = ecs.read_storage::<Monster>();
+		let statuses = ecs.read_storage::<StatusEffects>();
+
+		(&monsters, &statuses).join()
+			.any(|(_, statuses)| statuses.has(StatusEffectKind::Hasted))
+			|| falls_behind(
+				ecs.read_storage::<Burden>().get(*ecs.fetch::<Entity>()),
+				ecs.fetch::<EventCounter>().turns,
+			)
+	}
+}
+
+/// An overloaded player loses the initiative every other turn, and everyone
+/// else gets to go twice
+fn falls_behind (burden: Option<&Burden>, turns: i32) -> bool {
+	burden.map_or(false, |b| b.overloaded()) && turns % 2 == 0
+}
+
+impl<'a> System<'a> for TurnSystem {
+	type SystemData = (
+		Entities<'a>,
+		ReadExpect<'a, TurnState>,
+		WriteStorage<'a, MyTurn>,
+		ReadStorage<'a, Monster>,
+		ReadStorage<'a, Bystander>,
+		ReadStorage<'a, Ally>,
+		ReadStorage<'a, Position>,
+		ReadStorage<'a, StatusEffects>,
+		WriteExpect<'a, ParticleBuilder>,
+		ReadExpect<'a, Palette>,
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, Point>,
+		WriteExpect<'a, PlayerFlowMap>,
+		ReadExpect<'a, Config>,
+		ReadStorage<'a, Burden>,
+		ReadExpect<'a, Entity>,
+		ReadExpect<'a, EventCounter>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, turn_state, mut turns, monsters, bystanders, allies,
+			positions, statuses, mut particle_builder, palette, map, player_pos,
+			mut flow, config, burdens, player_entity, counter,
+		) = data;
+
+		turns.clear();
+
+		let hasted_turn = match *turn_state {
+			TurnState::MonsterTurn => false,
+			TurnState::HastedTurn => true,
+			_ => return,
+		};
+
+		flow.update(&map, map.xy_idx(player_pos.x, player_pos.y));
+		let player_behind = falls_behind(burdens.get(*player_entity), counter.turns);
+
+		for (entity, pos) in (&entities, &positions).join() {
+			if monsters.get(entity).is_none()
+				&& bystanders.get(entity).is_none()
+				&& allies.get(entity).is_none()
+			{ continue; }
+
+			let hasted = statuses.get(entity).map_or(false, |s| s.has(StatusEffectKind::Hasted));
+			if hasted_turn && !hasted && !player_behind { continue; }
+
+			if let Some(statuses) = statuses.get(entity) {
+				if let Some(effect) = statuses.effects.iter().find(|e| e.kind.skips_turn()) {
+					particle_builder.request(
+						pos.x, pos.y,
+						Particle { fg: effect.kind.colour(), bg: palette.background, glyph: effect.kind.glyph(), lifetime: config.particles.effect_ms },
+					);
+					continue;
+				}
+			}
+
+			turns.insert(entity, MyTurn {}).or_warn("Unable to take a turn");
+		}
+	}
+}
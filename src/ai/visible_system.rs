@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use rltk::{DistanceAlg, Point};
+use specs::prelude::*;
+use crate::{Awareness, AwarenessState, Bystander, CombatStats, Faction, FactionKind, Memory, Monster, Morale, MyTurn, Name, PackMember, Perception, Position, Reaction, Viewshed, WantsToApproach, WantsToFlee, WantsToMelee};
+use crate::ai::{perceives, sees};
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How many turns a monster spends poking around where it last saw the player
+pub const SEARCH_TURNS : i32 = 5;
+
+/// Decides what to do about everything in sight: attack what's adjacent,
+/// approach or surround what's hated, and flee from what's feared
+pub struct VisibleAI {}
+
+impl<'a> System<'a> for VisibleAI {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, SpatialIndex>,
+		ReadExpect<'a, Point>,
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, GameLog>,
+		Entities<'a>,
+		WriteStorage<'a, MyTurn>,
+		ReadStorage<'a, Position>,
+		ReadStorage<'a, Viewshed>,
+		ReadStorage<'a, CombatStats>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, Faction>,
+		ReadStorage<'a, Monster>,
+		ReadStorage<'a, Bystander>,
+		WriteStorage<'a, Morale>,
+		WriteStorage<'a, Memory>,
+		ReadStorage<'a, PackMember>,
+		WriteStorage<'a, WantsToMelee>,
+		WriteStorage<'a, WantsToApproach>,
+		WriteStorage<'a, WantsToFlee>,
+		WriteStorage<'a, Awareness>,
+		ReadStorage<'a, Perception>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map,
+			spatial,
+			player_pos,
+			player_entity,
+			mut log,
+			entities,
+			mut turns,
+			positions,
+			viewsheds,
+			combat_stats,
+			names,
+			factions,
+			monsters,
+			bystanders,
+			mut morale,
+			mut memories,
+			pack_members,
+			mut wants_melee,
+			mut wants_approach,
+			mut wants_flee,
+			mut awareness,
+			perception,
+		) = data;
+
+		let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+		// Things without a faction only have it in for the player
+		let reaction = |entity: Entity, other: FactionKind| -> Reaction {
+			match factions.get(entity) {
+				None => if other == FactionKind::Player { Reaction::Attack } else { Reaction::Ignore },
+				Some(faction) => faction.kind.reaction_to(other),
+			}
+		};
+
+		// Everything that could be fought, and where
+		let mut combatants : Vec<(Entity, FactionKind, Point)> = Vec::new();
+		for (entity, faction, pos, stats) in (&entities, &factions, &positions, &combat_stats).join() {
+			if stats.hp < 1 { continue; }
+			combatants.push((entity, faction.kind, Point::new(pos.x, pos.y)));
+		}
+
+		// Remember sightings, packs share theirs with each other
+		let mut pack_sightings : HashMap<i32, (i32, i32)> = HashMap::new();
+		for (entity, memory, pos, viewshed) in (&entities, &mut memories, &positions, &viewsheds).join() {
+			if !perceives(&map, pos, viewshed, perception.get(entity), &player_pos) { continue; }
+			if reaction(entity, FactionKind::Player) != Reaction::Attack { continue; }
+			if !knows_of_player(&awareness, entity) { continue; }
+
+			memory.last_seen = Some((player_pos.x, player_pos.y));
+			memory.search_turns = SEARCH_TURNS;
+
+			if let Some(member) = pack_members.get(entity) {
+				pack_sightings.insert(member.pack_id, (player_pos.x, player_pos.y));
+			}
+		}
+		for (entity, member, memory) in (&entities, &pack_members, &mut memories).join() {
+			if let Some(sighting) = pack_sightings.get(&member.pack_id) {
+				memory.last_seen = Some(*sighting);
+				memory.search_turns = SEARCH_TURNS;
+
+				if let Some(awareness) = awareness.get_mut(entity) {
+					awareness.state = AwarenessState::Hunting;
+				}
+			}
+		}
+
+		let mut pack_positions : Vec<(i32, Entity, Point)> = Vec::new();
+		for (entity, member, pos) in (&entities, &pack_members, &positions).join() {
+			pack_positions.push((member.pack_id, entity, Point::new(pos.x, pos.y)));
+		}
+		let mut claimed_flanks : Vec<usize> = Vec::new();
+
+		let mut turn_done : Vec<Entity> = Vec::new();
+
+		for (entity, _turn, pos, viewshed) in (&entities, &turns, &positions, &viewsheds).join() {
+			let my_pos = Point::new(pos.x, pos.y);
+			let my_faction = factions.get(entity).map(|f| f.kind);
+			let aware_of_player = knows_of_player(&awareness, entity);
+			let perception = perception.get(entity);
+
+			// Morale
+			let mut is_fleeing = false;
+			if let (Some(morale), Some(stats)) = (morale.get_mut(entity), combat_stats.get(entity)) {
+				let hp_percent = stats.hp * 100 / i32::max(1, stats.max_hp);
+				let was_fleeing = morale.fleeing;
+
+				if morale.fleeing && hp_percent >= morale.rally_above {
+					morale.fleeing = false;
+				} else if !morale.fleeing && hp_percent < morale.flee_below {
+					morale.fleeing = true;
+				}
+
+				if morale.fleeing != was_fleeing && map.is_visible(map.xy_idx(pos.x, pos.y)) {
+					if let Some(name) = names.get(entity) {
+						log.combat(format!(
+							"{} {}",
+							&name.name,
+							if morale.fleeing { "turns to flee!" } else { "rallies!" },
+						));
+					}
+				}
+
+				is_fleeing = morale.fleeing;
+			}
+
+			// Size up everything in sight
+			let mut target : Option<(Entity, Point, f32)> = None;
+			let mut flee_from : Vec<usize> = Vec::new();
+			let mut sees_monster = false;
+			for (other, other_faction, other_pos) in combatants.iter() {
+				if *other == entity || !perceives(&map, pos, viewshed, perception, other_pos) { continue; }
+				if *other == *player_entity && !aware_of_player { continue; }
+
+				let other_idx = map.xy_idx(other_pos.x, other_pos.y);
+				if monsters.get(*other).is_some() { sees_monster = true; }
+
+				// Things that want us dead are worth running from when we're beaten
+				let wants_us_dead = my_faction
+					.map_or(false, |mine| other_faction.reaction_to(mine) == Reaction::Attack);
+
+				match reaction(entity, *other_faction) {
+					Reaction::Flee => flee_from.push(other_idx),
+					Reaction::Attack if !is_fleeing => {
+						let distance = DistanceAlg::Pythagoras.distance2d(my_pos, *other_pos);
+						if target.map_or(true, |t| distance < t.2) {
+							target = Some((*other, *other_pos, distance));
+						}
+					}
+					_ => if is_fleeing && wants_us_dead { flee_from.push(other_idx); }
+				}
+
+				if is_fleeing && *other == *player_entity && !flee_from.contains(&other_idx) {
+					flee_from.push(other_idx);
+				}
+			}
+
+			// Bystanders want no part of a fight
+			let sees_player = aware_of_player && sees(&map, pos, viewshed, perception, &player_pos);
+			if bystanders.get(entity).is_some() && sees_player && sees_monster {
+				for (other, _, other_pos) in combatants.iter() {
+					if *other != entity && sees(&map, pos, viewshed, perception, other_pos) {
+						flee_from.push(map.xy_idx(other_pos.x, other_pos.y));
+					}
+				}
+			}
+
+			if !flee_from.is_empty() {
+				wants_flee.insert(entity, WantsToFlee { indices: flee_from })
+					.or_warn("Unable to panic");
+				continue;
+			}
+
+			let (target, target_pos, distance) = match target {
+				None => continue,
+				Some(target) => target,
+			};
+
+			if distance < 1.5 {
+				wants_melee.insert(entity, WantsToMelee { target })
+					.or_warn("Unable to attack target!");
+				turn_done.push(entity);
+				continue;
+			}
+
+			let destination = if target != *player_entity {
+				map.xy_idx(target_pos.x, target_pos.y)
+			} else if let Some(member) = pack_members.get(entity) {
+				let pack_size = pack_positions.iter()
+					.filter(|p| p.0 == member.pack_id)
+					.count() as i32;
+				let mates_in_view = 1 + pack_positions.iter()
+					.filter(|p| p.0 == member.pack_id && p.1 != entity && viewshed.visible_tiles.contains(&p.2))
+					.count() as i32;
+
+				// Hold back until enough of the pack is here
+				if mates_in_view < i32::min(member.min_engage, pack_size) {
+					turn_done.push(entity);
+					continue;
+				}
+
+				flank_tile(&map, &spatial, *player_pos, pos, &mut claimed_flanks)
+			} else {
+				player_idx
+			};
+
+			wants_approach.insert(entity, WantsToApproach { idx: destination as i32 })
+				.or_warn("Unable to approach");
+		}
+
+		for entity in turn_done.iter() {
+			turns.remove(*entity);
+		}
+	}
+}
+
+/// Monsters that haven't noticed the player carry on as if they weren't there
+fn knows_of_player (awareness: &WriteStorage<Awareness>, entity: Entity) -> bool {
+	awareness.get(entity).map_or(true, |a| a.state == AwarenessState::Hunting)
+}
+
+/// Picks the free tile next to the target that's closest to the hunter and
+/// not already claimed by a packmate, so the pack spreads out around its prey
+fn flank_tile (map: &Map, spatial: &SpatialIndex, target: Point, hunter: &Position, claimed: &mut Vec<usize>) -> usize {
+	let mut best : Option<(usize, f32)> = None;
+
+	for dy in -1 ..= 1 {
+		for dx in -1 ..= 1 {
+			if dx == 0 && dy == 0 { continue; }
+
+			let (x, y) = (target.x + dx, target.y + dy);
+			if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { continue; }
+
+			let idx = map.xy_idx(x, y);
+			if spatial.is_blocked(idx) || claimed.contains(&idx) { continue; }
+
+			let distance = DistanceAlg::Pythagoras.distance2d(
+				Point::new(x, y),
+				Point::new(hunter.x, hunter.y),
+			);
+			if best.map_or(true, |b| distance < b.1) {
+				best = Some((idx, distance));
+			}
+		}
+	}
+
+	match best {
+		None => map.xy_idx(target.x, target.y),
+		Some((idx, _)) => {
+			claimed.push(idx);
+			idx
+		}
+	}
+}
@@ -0,0 +1,77 @@
+use specs::prelude::*;
+use crate::{Ally, Awareness, AwarenessState, Befriended, Faction, FactionKind, Name, StatusEffectKind, StatusEffects};
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+
+/// Brings charmed and tamed monsters over to the player's side, and sends
+/// them back again when the charm wears off or the player turns on them
+pub struct BefriendSystem {}
+
+impl<'a> System<'a> for BefriendSystem {
+	type SystemData = (
+		Entities<'a>,
+		WriteExpect<'a, GameLog>,
+		WriteStorage<'a, Befriended>,
+		WriteStorage<'a, Faction>,
+		WriteStorage<'a, Ally>,
+		WriteStorage<'a, StatusEffects>,
+		WriteStorage<'a, Awareness>,
+		ReadStorage<'a, Name>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, mut log, mut befriended, mut factions, mut allies,
+			mut statuses, mut awareness, names,
+		) = data;
+
+		// Newly charmed
+		let mut charmed : Vec<(Entity, FactionKind)> = Vec::new();
+		for (entity, faction, statuses, _not_friend) in (&entities, &factions, &statuses, !&befriended).join() {
+			if faction.kind == FactionKind::Player { continue; }
+			if statuses.has(StatusEffectKind::Charmed) {
+				charmed.push((entity, faction.kind));
+			}
+		}
+		for (entity, faction) in charmed {
+			befriended.insert(entity, Befriended { faction, charmed: true, broken: false })
+				.or_warn("Charm fizzled");
+		}
+
+		let mut lost : Vec<Entity> = Vec::new();
+
+		for (entity, friend, faction) in (&entities, &befriended, &mut factions).join() {
+			let wore_off = friend.charmed && statuses.get(entity)
+				.map_or(true, |s| !s.has(StatusEffectKind::Charmed));
+
+			if friend.broken || wore_off {
+				faction.kind = friend.faction;
+				allies.remove(entity);
+				lost.push(entity);
+
+				if let Some(awareness) = awareness.get_mut(entity) {
+					awareness.state = AwarenessState::Hunting;
+				}
+
+				if let Some(name) = names.get(entity) {
+					log.dialogue(
+						if friend.broken { format!("{} turns on you!", name.name) }
+						else { StatusEffectKind::Charmed.expired_message(Some(&name.name)) }
+					);
+				}
+			} else if allies.get(entity).is_none() {
+				faction.kind = FactionKind::Player;
+				allies.insert(entity, Ally {}).or_warn("Friendship rejected");
+			}
+		}
+
+		for entity in lost.iter() {
+			befriended.remove(*entity);
+
+			// Don't let a lingering charm win it straight back
+			if let Some(statuses) = statuses.get_mut(*entity) {
+				statuses.effects.retain(|e| e.kind != StatusEffectKind::Charmed);
+			}
+		}
+	}
+}
@@ -1,9 +1,14 @@
-use rltk::{Point, RGB};
+use std::ops::Deref;
+use rltk::{Point, RandomNumberGenerator, RGB};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use specs::saveload::{Marker, ConvertSaveload};
+use specs::saveload::{Marker, ConvertSaveload, SimpleMarker};
+use specs::storage::MaskedStorage;
 #[allow(deprecated)] use specs::error::NoError;
 use specs_derive::*;
+use crate::config::{HungerConfig, ThirstConfig};
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
 use crate::gamelog::GameLog;
 use crate::map::Map;
 
@@ -21,6 +26,19 @@ pub struct Player {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// Harmless critters and townsfolk, who run from fights rather than start them
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Bystander {}
+
+/// Follows the player about, fights whatever the player's fighting, and comes
+/// along to the next level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ally {}
+
+/// Chained up until the player frees them, after which they become an ally
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Prisoner {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
@@ -42,6 +60,20 @@ pub struct EntityMoved {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SingleActivation {}
 
+/// Pack members that swarm: they bunch up as they go, squeeze past each other,
+/// and bite harder the more of them are on the same target
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Swarm {}
+
+/// Can open doors, rather than having to find a way around them
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CanOpenDoors {}
+
+/// Handed out to every AI at the start of the monster turn, and taken away
+/// by whichever AI system acts on it first
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
 // Components
 // =========================================================================
 
@@ -53,6 +85,27 @@ pub struct Name {
 	pub name : String,
 }
 
+/// Looking up what to call an entity in the log, for when it might not have
+/// a name
+pub trait NameOf {
+	/// The entity's name, or "something" if it hasn't got one
+	fn name_of (&self, entity: Entity) -> &str { self.name_or(entity, "something") }
+	/// The entity's name, or `fallback` if it hasn't got one
+	fn name_or<'a> (&'a self, entity: Entity, fallback: &'a str) -> &'a str;
+}
+
+impl<'e, D : Deref<Target = MaskedStorage<Name>>> NameOf for Storage<'e, Name, D> {
+	fn name_or<'a> (&'a self, entity: Entity, fallback: &'a str) -> &'a str {
+		self.get(entity).map_or(fallback, |n| &n.name)
+	}
+}
+
+/// What the player is told about the entity when they examine it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Description {
+	pub text : String,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Position {
 	pub x : i32,
@@ -65,6 +118,8 @@ pub struct Renderable {
 	pub fg : RGB,
 	pub bg : RGB,
 	pub render_order : i32,
+	/// Drawn instead of the glyph's spot in the tileset in sprite mode
+	pub sprite : Option<usize>,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -74,11 +129,222 @@ pub struct Viewshed {
 	pub dirty : bool,
 }
 
+/// How a monster keeps track of things, as opposed to the Viewshed which is
+/// just what's in line of sight. It can only see as far as `vision`, and only
+/// up close in the dark unless it has `dark_vision`. Anything within `smell`
+/// it sniffs out, walls or no walls.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Perception {
+	pub vision      : i32,
+	pub dark_vision : bool,
+	pub smell       : i32,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ParticleLifetime {
 	pub lifetime_ms : f32,
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+	pub open : bool,
+}
+
+/// Runs scripts/`script`.rhai on whatever the item's used on or whoever
+/// sets the trap off
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ScriptedEffect {
+	pub script : String,
+}
+
+/// Makes a racket when triggered, loud enough to be heard `radius` tiles away
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EmitsNoise {
+	pub radius : i32,
+}
+
+// AI
+// -------------------------------------------------------------------------
+
+/// Monsters with morale flee from anything that wants them dead once their
+/// health drops below `flee_below` percent, rallying when healed above
+/// `rally_above` percent
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Morale {
+	pub flee_below  : i32,
+	pub rally_above : i32,
+	pub fleeing     : bool,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum FactionKind {
+	Player,
+	Orcs,
+	Goblins,
+	Predators,
+	Prey,
+	Townsfolk,
+	Vermin,
+	Undead,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Reaction {
+	Ignore,
+	Attack,
+	Flee,
+}
+
+impl FactionKind {
+	/// How members of this faction feel about members of the other
+	pub fn reaction_to (&self, other: FactionKind) -> Reaction {
+		use FactionKind::*;
+
+		if *self == other { return Reaction::Ignore; }
+
+		match (self, other) {
+			(Orcs, Player) | (Orcs, Goblins) | (Orcs, Townsfolk) => Reaction::Attack,
+			(Goblins, Player) | (Goblins, Orcs) => Reaction::Attack,
+			(Predators, Player) | (Predators, Prey) => Reaction::Attack,
+			(Vermin, Player) | (Vermin, Townsfolk) => Reaction::Attack,
+			(Undead, Player) | (Undead, Townsfolk) | (Undead, Prey) => Reaction::Attack,
+			(Player, Orcs) | (Player, Goblins) | (Player, Predators) | (Player, Vermin) | (Player, Undead) => Reaction::Attack,
+			(Prey, Predators) | (Prey, Undead) | (Townsfolk, Orcs) | (Townsfolk, Vermin) | (Townsfolk, Undead) => Reaction::Flee,
+			_ => Reaction::Ignore,
+		}
+	}
+}
+
+impl FactionKind {
+	/// Animals can be won over by throwing them a bit of food
+	pub fn is_animal (&self) -> bool {
+		*self == FactionKind::Predators || *self == FactionKind::Prey
+	}
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+	pub kind : FactionKind,
+}
+
+/// Where the player was last seen (or something was last heard), and how
+/// much longer to search there once the trail goes cold
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Memory {
+	pub last_seen    : Option<(i32, i32)>,
+	pub search_turns : i32,
+}
+
+/// Members of the same pack share sightings of the player, hold back until
+/// `min_engage` packmates are in view, and spread out to surround their prey
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PackMember {
+	pub pack_id    : i32,
+	pub min_engage : i32,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum IdleBehaviour {
+	/// Ambles about within a few tiles of home
+	Wander,
+	/// Heads back to and stands at home
+	Guard,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum AwarenessState {
+	/// Dozing, only the player creeping right up to it or a hit will wake it
+	Asleep,
+	/// Going about its business, might not spot the player in plain sight
+	Unaware,
+	/// Knows something's up, so is quicker to spot the player
+	Alert,
+	/// After the player, and sees them whenever they're in view
+	Hunting,
+}
+
+/// How switched on a monster is to the player. Anything short of hunting has
+/// to notice the player past their stealth first, and can be sneak attacked.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Awareness {
+	pub state : AwarenessState,
+}
+
+/// Won over to the player's side by a charm or a bit of food, remembering
+/// which side it was on before. It goes back if the player hurts it, or if
+/// it was only charmed, once the charm wears off.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Befriended {
+	pub faction : FactionKind,
+	pub charmed : bool,
+	pub broken  : bool,
+}
+
+/// A round of waypoints a guard walks when it's not been alerted, currently
+/// heading for `waypoints[next]`
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Patrol {
+	pub waypoints : Vec<(i32, i32)>,
+	pub next      : usize,
+}
+
+/// Something special a monster can do every `cooldown` turns. Each ability is
+/// its own entity, carrying the same effect components as an item would
+/// (healing, damage, area of effect, statuses), and is used through the
+/// ItemUseSystem just like one.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Ability {
+	pub owner         : Entity,
+	pub cooldown      : i32,
+	pub cooldown_left : i32,
+}
+
+/// An ability its owner can only use once its boss fight has reached `phase`
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PhaseAbility {
+	pub phase : usize,
+}
+
+/// One stage of a boss fight, kicking in once the boss drops below `below`
+/// percent health
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BossPhase {
+	pub below   : i32,
+	pub shout   : String,
+	pub summons : Vec<String>,
+	pub enrage  : i32,
+	pub haste   : bool,
+}
+
+/// A boss that changes tack as it's worn down. `current` is how many of the
+/// phases have kicked in so far.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct BossPhases {
+	pub phases  : Vec<BossPhase>,
+	pub current : usize,
+}
+
+/// An ability that teleports its owner up to `range` tiles away from
+/// whatever's hurting it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Blink {
+	pub range : i32,
+}
+
+/// Flavour lines a monster might come out with when the player's in sight,
+/// each finishing "The goblin ..."
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Quips {
+	pub available : Vec<String>,
+}
+
+/// What a monster gets up to when it has nothing to hunt
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Idle {
+	pub behaviour : IdleBehaviour,
+	pub home      : (i32, i32),
+}
+
 // Combat
 // -------------------------------------------------------------------------
 
@@ -97,7 +363,12 @@ pub struct InflictsDamage {
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-	pub amount : Vec<i32>,
+	pub amount    : Vec<i32>,
+	/// What dealt each hit, in the same order
+	pub causes    : Vec<String>,
+	/// Whether any of it was the player's doing, with their own hands or
+	/// with something they threw or used
+	pub by_player : bool,
 }
 
 impl SufferDamage {
@@ -105,16 +376,27 @@ impl SufferDamage {
 		store: &mut WriteStorage<SufferDamage>,
 		victim: Entity,
 		amount: i32,
+		cause: &str,
+		by_player: bool,
 	) {
 		if let Some(suffering) = store.get_mut(victim) {
 			suffering.amount.push(amount);
+			suffering.causes.push(cause.to_string());
+			suffering.by_player |= by_player;
 		} else {
-			let dmg = SufferDamage { amount: vec![amount] };
-			store.insert(victim, dmg).expect("Failed to insert damage");
+			let dmg = SufferDamage { amount: vec![amount], causes: vec![cause.to_string()], by_player };
+			store.insert(victim, dmg).or_warn("Failed to insert damage");
 		}
 	}
 }
 
+/// Flashes the health bar of whatever's just been hurt. Only lasts a moment,
+/// so it isn't saved.
+#[derive(Component, Debug, Clone)]
+pub struct DamageFlash {
+	pub ms_left : f32,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Ranged {
 	pub range : i32,
@@ -152,9 +434,202 @@ pub struct HungerClock {
 	pub duration : i32,
 }
 
+impl HungerClock {
+	/// How long is left before starving sets in
+	pub fn turns_until_starving (&self, config: &HungerConfig) -> i32 {
+		match self.state {
+			HungerState::WellFed => self.duration + 2 * config.stage_turns,
+			HungerState::Normal => self.duration + config.stage_turns,
+			HungerState::Hungry => self.duration,
+			HungerState::Starving => 0,
+		}
+	}
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct ProvidesFood {}
 
+// Thirst
+// -------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum ThirstState {
+	Quenched,
+	Normal,
+	Thirsty,
+	Parched,
+}
+
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct ThirstClock {
+	pub state    : ThirstState,
+	pub duration : i32,
+}
+
+impl ThirstClock {
+	/// How long is left before going without water starts to hurt
+	pub fn turns_until_parched (&self, config: &ThirstConfig) -> i32 {
+		match self.state {
+			ThirstState::Quenched => self.duration + 2 * config.stage_turns,
+			ThirstState::Normal => self.duration + config.stage_turns,
+			ThirstState::Thirsty => self.duration,
+			ThirstState::Parched => 0,
+		}
+	}
+}
+
+/// Holds a few drinks, and fills up again at any pool
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Waterskin {
+	pub sips     : i32,
+	pub capacity : i32,
+}
+
+/// Flashes the food or water bar when going without starts to hurt. Only
+/// lasts a moment, so it isn't saved.
+#[derive(Component, Debug, Clone, Default)]
+pub struct NeedsFlash {
+	pub food_ms  : f32,
+	pub water_ms : f32,
+}
+
+// Status Effects
+// -------------------------------------------------------------------------
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusEffectKind {
+	Confused,
+	Charmed,
+	Webbed,
+	Hasted,
+}
+
+impl StatusEffectKind {
+	pub fn name (&self) -> &'static str {
+		match self {
+			StatusEffectKind::Confused => "Confused",
+			StatusEffectKind::Charmed => "Charmed",
+			StatusEffectKind::Webbed => "Webbed",
+			StatusEffectKind::Hasted => "Hasted",
+		}
+	}
+
+	/// The kind going by `name`, as scripts ask for it
+	pub fn named (name: &str) -> Option<StatusEffectKind> {
+		match name {
+			"Confused" => Some(StatusEffectKind::Confused),
+			"Charmed" => Some(StatusEffectKind::Charmed),
+			"Webbed" => Some(StatusEffectKind::Webbed),
+			"Hasted" => Some(StatusEffectKind::Hasted),
+			_ => None,
+		}
+	}
+
+	/// Short enough to sit next to the health bar
+	pub fn abbreviation (&self) -> &'static str {
+		match self {
+			StatusEffectKind::Confused => "Cnf",
+			StatusEffectKind::Charmed => "Chm",
+			StatusEffectKind::Webbed => "Web",
+			StatusEffectKind::Hasted => "Hst",
+		}
+	}
+
+	/// Appended to "You use X on Y, ..." when an item inflicts this status
+	pub fn inflicted_message (&self) -> &'static str {
+		match self {
+			StatusEffectKind::Confused => "confusing them",
+			StatusEffectKind::Charmed => "charming them",
+			StatusEffectKind::Webbed => "tangling them in webs",
+			StatusEffectKind::Hasted => "speeding them up",
+		}
+	}
+
+	/// What's said when it wears off whoever's named, or the player if
+	/// there's no one named
+	pub fn expired_message (&self, who: Option<&str>) -> String {
+		match (self, who) {
+			(StatusEffectKind::Confused, None) => "You are no longer confused".to_string(),
+			(StatusEffectKind::Confused, Some(who)) => format!("{} is no longer confused", who),
+			(StatusEffectKind::Charmed, None) => "You are no longer charmed".to_string(),
+			(StatusEffectKind::Charmed, Some(who)) => format!("{} is no longer charmed", who),
+			(StatusEffectKind::Webbed, None) => "You break free of the web".to_string(),
+			(StatusEffectKind::Webbed, Some(who)) => format!("{} breaks free of the web", who),
+			(StatusEffectKind::Hasted, None) => "You slow down".to_string(),
+			(StatusEffectKind::Hasted, Some(who)) => format!("{} slows down", who),
+		}
+	}
+
+	pub fn glyph (&self) -> rltk::FontCharType {
+		match self {
+			StatusEffectKind::Confused => rltk::to_cp437('?'),
+			StatusEffectKind::Charmed => rltk::to_cp437('♥'),
+			StatusEffectKind::Webbed => rltk::to_cp437('#'),
+			StatusEffectKind::Hasted => rltk::to_cp437('»'),
+		}
+	}
+
+	/// Whether monsters suffering this lose their turns
+	pub fn skips_turn (&self) -> bool {
+		match self {
+			StatusEffectKind::Confused | StatusEffectKind::Webbed => true,
+			StatusEffectKind::Charmed | StatusEffectKind::Hasted => false,
+		}
+	}
+
+	pub fn colour (&self) -> RGB {
+		match self {
+			StatusEffectKind::Confused => RGB::named(rltk::BLUEVIOLET),
+			StatusEffectKind::Charmed => RGB::named(rltk::HOTPINK),
+			StatusEffectKind::Webbed => RGB::named(rltk::LIGHTGRAY),
+			StatusEffectKind::Hasted => RGB::named(rltk::GOLD),
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+	pub kind     : StatusEffectKind,
+	pub duration : i32,
+}
+
+/// All status effects currently active on an entity, ticked by the
+/// StatusEffectSystem
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StatusEffects {
+	pub effects : Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+	pub fn has (&self, kind: StatusEffectKind) -> bool {
+		self.effects.iter().any(|e| e.kind == kind)
+	}
+
+	pub fn get (&self, kind: StatusEffectKind) -> Option<&StatusEffect> {
+		self.effects.iter().find(|e| e.kind == kind)
+	}
+
+	/// Applies the status to the victim, refreshing the duration if they
+	/// already have it
+	pub fn add (
+		store: &mut WriteStorage<StatusEffects>,
+		victim: Entity,
+		kind: StatusEffectKind,
+		duration: i32,
+	) {
+		if let Some(statuses) = store.get_mut(victim) {
+			if let Some(existing) = statuses.effects.iter_mut().find(|e| e.kind == kind) {
+				existing.duration = i32::max(existing.duration, duration);
+			} else {
+				statuses.effects.push(StatusEffect { kind, duration });
+			}
+		} else {
+			let statuses = StatusEffects { effects: vec![StatusEffect { kind, duration }] };
+			store.insert(victim, statuses).or_warn("Failed to insert status effect");
+		}
+	}
+}
+
 // Intents
 // =========================================================================
 
@@ -163,15 +638,18 @@ pub struct WantsToMelee {
 	pub target : Entity,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
+// These two only ever last for the turn they're made in, and hold a list of
+// items which can't be saved, so they're left out of saves
+
+#[derive(Component, Debug, Clone)]
 pub struct WantsToPickupItem {
 	pub collected_by : Entity,
-	pub item : Entity,
+	pub items        : Vec<Entity>,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct WantsToDropItem {
-	pub item : Entity,
+	pub items : Vec<Entity>,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -180,18 +658,46 @@ pub struct WantsToUseItem {
 	pub target: Option<Point>,
 }
 
+/// Hurl an item from the backpack at a spot. Thrown the turn it's asked
+/// for, so it isn't saved.
+#[derive(Component, Debug, Clone)]
+pub struct WantsToThrowItem {
+	pub item   : Entity,
+	pub target : Point,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToRemoveItem {
 	pub item : Entity,
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToOpenDoor {
+	pub door : Entity,
+}
+
+/// Take a step along the path towards the given map index
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToApproach {
+	pub idx : i32,
+}
+
+/// Take a step away from all the given map indices
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToFlee {
+	pub indices : Vec<usize>,
+}
+
 // Items
 // =========================================================================
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum EquipmentSlot {
 	Melee,
-	Shield,
+	/// A shield or a torch, but not both. Saves from before torches call it
+	/// the shield slot.
+	#[serde(alias = "Shield")]
+	OffHand,
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -215,19 +721,175 @@ pub struct ProvidesHealing {
 	pub heal_amount : i32,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
-	pub turns : i32,
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+	pub kind     : StatusEffectKind,
+	pub duration : i32,
 }
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct MagicMapper {}
 
+/// A consumable that's good for more than one go, only used up once the
+/// last is gone
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Charges {
+	pub uses     : i32,
+	pub max_uses : i32,
+}
+
+/// Lights up the way for whoever's holding it, until the fuel runs out.
+/// Fuel's counted in turns.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LightSource {
+	pub radius : i32,
+	pub fuel   : i32,
+}
+
+/// Breaks when thrown, splashing whatever's in it over everyone nearby
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Fragile {}
+
+/// Carrying this out of the final depth is the point of the whole thing
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct GoalArtifact {}
+
+/// How heavy an item is. Anything without one weighs nothing.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+	pub kg : f32,
+}
+
+/// Everything someone's carrying or wearing weighed up against what they can
+/// manage. Worked out again every turn, so it isn't saved.
+#[derive(Component, Debug, Clone)]
+pub struct Burden {
+	pub carried  : f32,
+	pub capacity : f32,
+}
+
+impl Burden {
+	pub fn overloaded (&self) -> bool { self.carried > self.capacity }
+}
+
 // Special
 // =========================================================================
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
-	pub map : Map,
-	pub log : GameLog,
-}
\ No newline at end of file
+	pub map   : Map,
+	pub log   : GameLog,
+	pub stats : EventCounter,
+	/// Where the random stream had got to, so a loaded game carries on with
+	/// the same rolls. Older saves don't have it.
+	#[serde(default)]
+	pub rng   : Option<RandomNumberGenerator>,
+}
+// Registry
+// =========================================================================
+
+/// Every component that goes in a save, handed on to `$then` after whatever
+/// else it's given. They're written and read back in this order, so adding
+/// one anywhere but the end needs SAVE_VERSION bumping.
+macro_rules! saved_components {
+	($then:ident!($($args:tt)*)) => {
+		$then!($($args)*
+			Player,
+			Monster,
+			Bystander,
+			Ally,
+			Prisoner,
+			Swarm,
+			BlocksTile,
+			Item,
+			Consumable,
+			Name,
+			Description,
+			Position,
+			Renderable,
+			Viewshed,
+			ParticleLifetime,
+			Door,
+			EmitsNoise,
+			Hidden,
+			EntityTrigger,
+			EntityMoved,
+			SingleActivation,
+			CanOpenDoors,
+			CombatStats,
+			InflictsDamage,
+			Ranged,
+			AreaOfEffect,
+			HungerClock,
+			ProvidesFood,
+			WantsToOpenDoor,
+			WantsToUseItem,
+			WantsToRemoveItem,
+			InBackpack,
+			ProvidesHealing,
+			InflictsStatus,
+			StatusEffects,
+			Morale,
+			Faction,
+			Memory,
+			PackMember,
+			Idle,
+			Awareness,
+			Befriended,
+			Ability,
+			Blink,
+			Patrol,
+			PhaseAbility,
+			BossPhases,
+			Perception,
+			Quips,
+			SerializationHelper,
+			Equippable,
+			Equipped,
+			MeleePowerBonus,
+			DefenseBonus,
+			MagicMapper,
+			GoalArtifact,
+			ThirstClock,
+			Waterskin,
+			Weight,
+			Fragile,
+			Charges,
+			LightSource,
+			ScriptedEffect,
+		)
+	};
+}
+pub(crate) use saved_components;
+
+/// The components that are never saved: they only last the turn, or the
+/// frame, and a loaded game can do without them
+macro_rules! unsaved_components {
+	($then:ident!($($args:tt)*)) => {
+		$then!($($args)*
+			DamageFlash,
+			NeedsFlash,
+			Burden,
+			WantsToPickupItem,
+			WantsToDropItem,
+			WantsToThrowItem,
+			MyTurn,
+			WantsToApproach,
+			WantsToFlee,
+			WantsToMelee,
+			SufferDamage,
+		)
+	};
+}
+
+macro_rules! register {
+	($ecs:expr, $($type:ty),* $(,)?) => { $($ecs.register::<$type>();)* };
+}
+
+/// Registers every component with the world, saved or not. A new component
+/// only needs adding to one of the lists above.
+pub fn register_components (ecs: &mut World) {
+	ecs.register::<SimpleMarker<SerializeMe>>();
+	saved_components!(register!(ecs,));
+	unsaved_components!(register!(ecs,));
+}
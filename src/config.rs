@@ -0,0 +1,211 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+/// Where the gameplay numbers are read from, beside the settings
+pub const CONFIG_FILE : &str = "./config.toml";
+
+/// The numbers the game's balanced around, read from config.toml at startup
+/// so they can be tinkered with without a rebuild. Anything the file leaves
+/// out (or the whole lot, if there's no file) keeps its default.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+	pub spawns    : SpawnConfig,
+	pub hunger    : HungerConfig,
+	pub thirst    : ThirstConfig,
+	pub carrying  : CarryConfig,
+	pub throwing  : ThrowConfig,
+	pub light     : LightConfig,
+	pub descend   : DescendConfig,
+	pub sight     : SightConfig,
+	pub particles : ParticleConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SpawnConfig {
+	/// The most things put in each room or area, before depth adds more
+	pub max_per_area : i32,
+}
+
+impl Default for SpawnConfig {
+	fn default () -> SpawnConfig { SpawnConfig { max_per_area: 4 } }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HungerConfig {
+	/// How many turns each stage of hunger lasts, short of starving
+	pub stage_turns    : i32,
+	/// How long a meal keeps anyone well fed
+	pub well_fed_turns : i32,
+}
+
+impl Default for HungerConfig {
+	fn default () -> HungerConfig { HungerConfig { stage_turns: 200, well_fed_turns: 20 } }
+}
+
+impl HungerConfig {
+	/// The most turns that can be left before starving, just after a meal
+	pub fn most_turns (&self) -> i32 { self.well_fed_turns + 2 * self.stage_turns }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThirstConfig {
+	/// How many turns each stage of thirst lasts, short of being parched
+	pub stage_turns    : i32,
+	/// How long a drink keeps anyone's thirst off
+	pub quenched_turns : i32,
+	/// How many drinks a waterskin holds
+	pub waterskin_sips : i32,
+}
+
+impl Default for ThirstConfig {
+	fn default () -> ThirstConfig { ThirstConfig { stage_turns: 150, quenched_turns: 50, waterskin_sips: 5 } }
+}
+
+impl ThirstConfig {
+	/// The most turns that can be left before being parched, just after a
+	/// drink
+	pub fn most_turns (&self) -> i32 { self.quenched_turns + 2 * self.stage_turns }
+}
+
+/// How much anyone can carry before it slows them down, which goes up with
+/// how strong they are
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CarryConfig {
+	pub base_kg      : f32,
+	/// On top of the base, for each point of attack power
+	pub kg_per_power : f32,
+}
+
+impl Default for CarryConfig {
+	fn default () -> CarryConfig { CarryConfig { base_kg: 5., kg_per_power: 2. } }
+}
+
+impl CarryConfig {
+	pub fn capacity (&self, power: i32) -> f32 { self.base_kg + power as f32 * self.kg_per_power }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThrowConfig {
+	/// How far anything can be thrown, whatever it is
+	pub range         : i32,
+	/// How far a potion splashes when it shatters
+	pub splash_radius : i32,
+}
+
+impl Default for ThrowConfig {
+	fn default () -> ThrowConfig { ThrowConfig { range: 6, splash_radius: 1 } }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LightConfig {
+	/// The first depth with no lit rooms, where the player only sees as far
+	/// as the light they're holding
+	pub dark_from_depth : i32,
+	/// How far the player can see in the dark with nothing lit
+	pub unlit_sight     : i32,
+	pub torch_radius    : i32,
+	/// How many turns a fresh torch burns for
+	pub torch_fuel      : i32,
+}
+
+impl Default for LightConfig {
+	fn default () -> LightConfig {
+		LightConfig { dark_from_depth: 4, unlit_sight: 2, torch_radius: 6, torch_fuel: 300 }
+	}
+}
+
+impl LightConfig {
+	pub fn is_dark (&self, depth: i32) -> bool { depth >= self.dark_from_depth }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DescendConfig {
+	/// Going down the stairs tops the player up to at least this percent of
+	/// their health
+	pub heal_to_percent : i32,
+}
+
+impl Default for DescendConfig {
+	fn default () -> DescendConfig { DescendConfig { heal_to_percent: 50 } }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SightConfig {
+	pub player    : i32,
+	pub monster   : i32,
+	/// The dog, and any prisoners freed along the way
+	pub ally      : i32,
+	pub bystander : i32,
+}
+
+impl Default for SightConfig {
+	fn default () -> SightConfig { SightConfig { player: 8, monster: 8, ally: 8, bystander: 6 } }
+}
+
+/// How long each sort of particle hangs about, in milliseconds
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ParticleConfig {
+	/// Blows landing, from weapons, traps and anything thrown
+	pub hit_ms    : f32,
+	/// Healing and statuses taking hold
+	pub effect_ms : f32,
+	pub blast_ms  : f32,
+	/// The damage numbers over whatever's been hurt
+	pub damage_ms : f32,
+	pub timing    : AnimationTiming,
+	/// How much animating each turn gets, when they're timed by turns
+	pub turn_ms   : f32,
+}
+
+/// What particles and flashes count their time in
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationTiming {
+	/// As it goes by, stopping while a menu's open
+	Frames,
+	/// The same, but only `turn_ms` of it a turn, so anything still going
+	/// waits on screen for the player's next move
+	Turns,
+}
+
+impl Default for ParticleConfig {
+	fn default () -> ParticleConfig {
+		ParticleConfig {
+			hit_ms: 150.,
+			effect_ms: 250.,
+			blast_ms: 150.,
+			damage_ms: 400.,
+			timing: AnimationTiming::Frames,
+			turn_ms: 400.,
+		}
+	}
+}
+
+impl Config {
+	/// The config file as it stands, or the defaults if it's missing or
+	/// mangled
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn load () -> Config {
+		fs::read_to_string(CONFIG_FILE)
+			.ok()
+			.and_then(|data| toml::from_str(&data).ok())
+			.unwrap_or_default()
+	}
+
+	/// The browser can't read config.toml off the disk, so it's built in
+	#[cfg(target_arch = "wasm32")]
+	pub fn load () -> Config {
+		toml::from_str(include_str!("../config.toml")).unwrap_or_default()
+	}
+}
@@ -1,7 +1,18 @@
 use specs::prelude::*;
-use crate::{CombatStats, Name, Player, Position, RunState, SufferDamage};
+use crate::{LaunchOptions, Ability, Awareness, AwarenessState, CombatStats, DamageFlash, Equipped, InBackpack, Monster, Name, Player, Position, SufferDamage, TurnState};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::display::DisplaySettings;
+use crate::event_counter::EventCounter;
+use crate::palette::Palette;
+use crate::particle_system::{AnimationClock, ParticleBuilder};
+use crate::saveload_system;
 use crate::gamelog::GameLog;
 use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How long a health bar flashes for after a hit
+const FLASH_MS : f32 = 200.;
 
 pub struct DamageSystem {}
 
@@ -12,19 +23,61 @@ impl<'a> System<'a> for DamageSystem {
 		Entities<'a>,
 		ReadStorage<'a, Position>,
 		WriteExpect<'a, Map>,
+		WriteStorage<'a, Awareness>,
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, EventCounter>,
+		WriteStorage<'a, DamageFlash>,
+		WriteExpect<'a, ParticleBuilder>,
+		ReadExpect<'a, Palette>,
+		ReadExpect<'a, DisplaySettings>,
+		ReadExpect<'a, Config>,
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
-			mut stats, mut damage, entities, positions, mut map,
+			mut stats, mut damage, entities, positions, mut map, mut awareness,
+			player_entity, mut counter, mut flashes, mut particle_builder,
+			palette, display, config,
 		) = data;
 
 		for (entity, mut stats, damage) in (&entities, &mut stats, &damage).join() {
-			stats.hp -= damage.amount.iter().sum::<i32>();
+			let total = damage.amount.iter().sum::<i32>();
+			stats.hp -= total;
 			let pos = positions.get(entity);
 			if let Some(pos) = pos {
 				let idx = map.xy_idx(pos.x, pos.y);
-				map.bloodstains.insert(idx);
+				map.bloodstain(idx);
+
+				// The damage done, just above whatever took it
+				if display.damage_feedback && map.is_visible(idx) {
+					let text = total.to_string();
+					let y = if pos.y > 0 { pos.y - 1 } else { pos.y + 1 };
+					particle_builder.request_text(
+						pos.x - (text.len() as i32 - 1) / 2, y,
+						palette.damage, palette.background,
+						&text,
+						config.particles.damage_ms,
+					);
+				}
+			}
+
+			if display.damage_feedback {
+				flashes.insert(entity, DamageFlash { ms_left: FLASH_MS })
+					.or_warn("Flash went off in the wrong place");
+			}
+
+			if entity == *player_entity && stats.hp < 1 && counter.killed_by.is_none() {
+				counter.killed_by = damage.causes.last().cloned();
+			}
+
+			// Nothing gets your attention quite like being hurt, though only
+			// the player's blows give away where the player is
+			if let Some(awareness) = awareness.get_mut(entity) {
+				if damage.by_player {
+					awareness.state = AwarenessState::Hunting;
+				} else if awareness.state != AwarenessState::Hunting {
+					awareness.state = AwarenessState::Alert;
+				}
 			}
 		}
 
@@ -33,41 +86,129 @@ impl<'a> System<'a> for DamageSystem {
 }
 
 impl DamageSystem {
+	/// Runs down the health bar flashes, as real time passes
+	pub fn fade_flashes (ecs: &mut World) {
+		let step_ms = ecs.fetch::<AnimationClock>().step_ms;
+		let mut faded : Vec<Entity> = Vec::new();
+		{
+			let mut flashes = ecs.write_storage::<DamageFlash>();
+			let entities = ecs.entities();
+			for (entity, flash) in (&entities, &mut flashes).join() {
+				flash.ms_left -= step_ms;
+				if flash.ms_left < 0. { faded.push(entity); }
+			}
+		}
+
+		let mut flashes = ecs.write_storage::<DamageFlash>();
+		for entity in faded { flashes.remove(entity); }
+	}
+
 	pub fn delete_the_dead (ecs: &mut World) {
 		let mut dead : Vec<Entity> = Vec::new();
 
 		{
-			let combat_stats = ecs.read_storage::<CombatStats>();
+			let mut combat_stats = ecs.write_storage::<CombatStats>();
 			let players = ecs.read_storage::<Player>();
+			let monsters = ecs.read_storage::<Monster>();
 			let names = ecs.read_storage::<Name>();
+			let positions = ecs.read_storage::<Position>();
+			let map = ecs.fetch::<Map>();
 			let mut log = ecs.write_resource::<GameLog>();
+			let mut counter = ecs.write_resource::<EventCounter>();
 			let entities = ecs.entities();
 
-			for (entity, stats) in (&entities, &combat_stats).join() {
+			let wizard = ecs.fetch::<LaunchOptions>().wizard;
+
+			for (entity, stats) in (&entities, &mut combat_stats).join() {
 				if stats.hp < 1 {
 					let player = players.get(entity);
 					match player {
 						None => {
 							let victim_name = names.get(entity);
-							if let Some(victim_name) = victim_name {
-								log.entries.push(format!(
+							if let (Some(victim_name), Some(_)) = (victim_name, monsters.get(entity)) {
+								counter.kill(&victim_name.name);
+							}
+							let seen = positions.get(entity)
+								.map_or(false, |pos| map.is_visible(map.xy_idx(pos.x, pos.y)));
+							if let (Some(victim_name), true) = (victim_name, seen) {
+								log.combat(format!(
 									"{} is dead!",
 									&victim_name.name,
 								));
 							}
 							dead.push(entity);
 						}
+						Some(_) if wizard => {
+							stats.hp = stats.max_hp;
+							log.system("Death reconsiders, and lets you be.".to_string());
+						}
 						Some(_) => {
-							let mut runstate = ecs.write_resource::<RunState>();
-							*runstate = RunState::GameOver;
+							let mut turn_state = ecs.write_resource::<TurnState>();
+							*turn_state = TurnState::GameOver;
+
+							// No carrying on from the autosave
+							saveload_system::delete_save();
 						}
 					}
 				}
 			}
 		}
 
+		// Drop whatever the dead were carrying
+		{
+			let entities = ecs.entities();
+			let map = ecs.fetch::<Map>();
+			let mut spatial = ecs.write_resource::<SpatialIndex>();
+			let mut positions = ecs.write_storage::<Position>();
+			let mut backpack = ecs.write_storage::<InBackpack>();
+			let mut equipped = ecs.write_storage::<Equipped>();
+
+			let mut to_drop : Vec<(Entity, Position)> = Vec::new();
+			for (item, owner) in (&entities, &backpack).join() {
+				if !dead.contains(&owner.owner) { continue; }
+				if let Some(pos) = positions.get(owner.owner) {
+					to_drop.push((item, pos.clone()));
+				}
+			}
+			for (item, owner) in (&entities, &equipped).join() {
+				if !dead.contains(&owner.owner) { continue; }
+				if let Some(pos) = positions.get(owner.owner) {
+					to_drop.push((item, pos.clone()));
+				}
+			}
+
+			for (item, pos) in to_drop {
+				backpack.remove(item);
+				equipped.remove(item);
+				spatial.index_entity(item, map.xy_idx(pos.x, pos.y), false);
+				positions.insert(item, pos).or_warn("Failed to drop loot");
+			}
+		}
+
+		// Abilities die with their owners
+		{
+			let entities = ecs.entities();
+			let abilities = ecs.read_storage::<Ability>();
+			for (ability, owned) in (&entities, &abilities).join() {
+				if dead.contains(&owned.owner) { dead.push(ability); }
+			}
+		}
+
+		// Clear the bodies off the map, so nothing trips over them before the
+		// next reindex
+		{
+			let map = ecs.fetch::<Map>();
+			let mut spatial = ecs.write_resource::<SpatialIndex>();
+			let positions = ecs.read_storage::<Position>();
+			for victim in dead.iter() {
+				if let Some(pos) = positions.get(*victim) {
+					spatial.remove_entity(*victim, map.xy_idx(pos.x, pos.y));
+				}
+			}
+		}
+
 		for victim in dead {
-			ecs.delete_entity(victim).expect("Failed to delete dead");
+			ecs.delete_entity(victim).or_warn("Failed to delete dead");
 		}
 	}
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::sync::Mutex;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rltk::{ColorPair, DrawBatch, Point, Rect};
+use crate::palette::Palette;
+
+/// How many of the latest warnings are kept for the overlay
+const KEPT_RECORDS : usize = 12;
+
+/// The latest warnings and errors, newest last
+static RECENT : Mutex<VecDeque<(Level, String)>> = Mutex::new(VecDeque::new());
+
+// Logger
+// =========================================================================
+
+/// Writes everything logged to stderr, and keeps the latest warnings for the
+/// debug overlay so they can be seen without a terminal
+struct DebugLogger;
+
+impl Log for DebugLogger {
+	fn enabled (&self, metadata: &Metadata) -> bool {
+		metadata.level() <= Level::Info
+	}
+
+	fn log (&self, record: &Record) {
+		if !self.enabled(record.metadata()) { return; }
+
+		let line = format!("{}", record.args());
+		eprintln!("[{}] {}", record.level(), line);
+
+		if record.level() <= Level::Warn {
+			let mut recent = RECENT.lock().unwrap();
+			if recent.len() == KEPT_RECORDS { recent.pop_front(); }
+			recent.push_back((record.level(), line));
+		}
+	}
+
+	fn flush (&self) {}
+}
+
+static LOGGER : DebugLogger = DebugLogger;
+
+/// Sets the logger up, once, at startup. Tests go without, so logging in
+/// them goes nowhere.
+pub fn init () {
+	if log::set_logger(&LOGGER).is_ok() {
+		log::set_max_level(LevelFilter::Info);
+	}
+}
+
+/// For things going wrong that aren't worth ending the run over, like a
+/// component going on something that died earlier in the frame
+pub trait OrWarn {
+	/// Logs a warning saying `what`, and why, if it didn't work out
+	fn or_warn (self, what: &str);
+}
+
+impl<T, E : Display> OrWarn for Result<T, E> {
+	fn or_warn (self, what: &str) {
+		if let Err(e) = self {
+			log::warn!("{} ({})", what, e);
+		}
+	}
+}
+
+// Overlay
+// =========================================================================
+
+/// Whether the debug overlay is up, toggled from the map
+#[derive(Default)]
+pub struct DebugOverlay {
+	pub shown : bool,
+}
+
+/// The latest warnings in a box over the top left of the map
+pub fn draw_overlay (draw: &mut DrawBatch, palette: &Palette) {
+	let recent = RECENT.lock().unwrap();
+	let width = 50;
+
+	draw.draw_box(Rect::with_size(0, 0, width + 1, usize::max(recent.len(), 1) as i32 + 1), ColorPair::new(palette.text, palette.background));
+	draw.print_color(Point::new(2, 0), " Warnings ", ColorPair::new(palette.value, palette.background));
+	if recent.is_empty() {
+		draw.print_color(Point::new(1, 1), "All quiet", ColorPair::new(palette.text_dim, palette.background));
+	}
+
+	for (i, (level, line)) in recent.iter().enumerate() {
+		let colour = if *level == Level::Error { palette.danger } else { palette.warning };
+		let line : String = line.chars().take(width as usize).collect();
+		draw.print_color(Point::new(1, i as i32 + 1), &line, ColorPair::new(colour, palette.background));
+	}
+}
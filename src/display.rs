@@ -0,0 +1,260 @@
+use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use rltk::{ColorPair, DrawBatch, Point, Rect, RGB, Rltk, SpriteSheet};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use crate::Renderable;
+use crate::debug_log::OrWarn;
+use crate::map::Map;
+use crate::event_counter::EventCounter;
+use crate::palette::PaletteKind;
+
+/// How wide the side panel is at the least. Consoles wider than the map and
+/// panel together give the extra room to the panel.
+pub const PANEL_WIDTH : i32 = 20;
+
+/// How tall the message log under the map is at the least
+pub const LOG_HEIGHT : i32 = 7;
+
+/// The console sizes on offer, in characters
+pub const CONSOLE_SIZES : [(i32, i32); 4] = [(80, 50), (100, 50), (120, 60), (160, 90)];
+
+/// How much the 8x8 font is blown up by
+pub const FONT_SCALES : [i32; 3] = [1, 2, 3];
+
+/// The graphical tileset, laid out like the CP437 font so anything without a
+/// sprite of its own gets the picture in its glyph's place
+const TILESET : &str = "resources/example_tiles.jpg";
+
+/// How big each sprite is, both in the tileset and on the sprite console
+const SPRITE_SIZE : i32 = 16;
+
+/// The map goes on a console of its own, with the text console over the top
+/// for everything on it and the interface. In sprite mode it's the sprite
+/// console; otherwise it's a simple console that's only drawn on where the
+/// map's changed, and the text console's a sparse one over it.
+pub const MAP_CONSOLE : usize = 0;
+pub const TEXT_CONSOLE : usize = 1;
+
+// Settings
+// =========================================================================
+
+/// What's been picked in the display options, kept in the settings file. The
+/// console size, font and graphics take hold the next time the game starts.
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub struct DisplaySettings {
+	pub console_size    : usize,
+	pub font_scale      : usize,
+	#[serde(default)]
+	pub sprites         : bool,
+	#[serde(default)]
+	pub palette         : PaletteKind,
+	/// Damage numbers and health bar flashes
+	#[serde(default = "enabled")]
+	pub damage_feedback : bool,
+	#[serde(default = "enabled")]
+	pub scanlines       : bool,
+}
+
+fn enabled () -> bool { true }
+
+impl DisplaySettings {
+	pub fn defaults () -> DisplaySettings {
+		DisplaySettings {
+			console_size: 1,
+			font_scale: 1,
+			sprites: false,
+			palette: PaletteKind::Default,
+			damage_feedback: true,
+			scanlines: true,
+		}
+	}
+
+	/// The settings as they were saved, or the defaults if they've been
+	/// mangled into something that can't be shown
+	pub fn validated (self) -> DisplaySettings {
+		if self.console_size < CONSOLE_SIZES.len()
+			&& self.font_scale < FONT_SCALES.len() { self }
+		else { DisplaySettings::defaults() }
+	}
+
+	pub fn width (&self) -> i32 { CONSOLE_SIZES[self.console_size].0 }
+
+	pub fn height (&self) -> i32 { CONSOLE_SIZES[self.console_size].1 }
+
+	/// How big each character is on screen, in pixels
+	pub fn tile_size (&self) -> i32 { 8 * FONT_SCALES[self.font_scale] }
+
+	/// Whether to draw with sprites: only if they're wanted and the tileset's
+	/// there, falling back on plain CP437 otherwise
+	pub fn use_sprites (&self) -> bool {
+		self.sprites && Path::new(TILESET).exists()
+	}
+
+	/// Sets up the window, with the map console under the text
+	pub fn build_context (&self) -> rltk::BResult<Rltk> {
+		let builder =
+			if self.use_sprites() {
+				rltk::RltkBuilder::new()
+					.with_dimensions(self.width(), self.height())
+					.with_font("terminal8x8.png", 8, 8)
+					.with_sprite_sheet(sprite_sheet())
+					.with_sprite_console(self.width() * SPRITE_SIZE, self.height() * SPRITE_SIZE, 0)
+					.with_sparse_console(self.width(), self.height(), "terminal8x8.png")
+			} else {
+				rltk::RltkBuilder::simple(self.width(), self.height())?
+					.with_sparse_console(self.width(), self.height(), "terminal8x8.png")
+			};
+
+		let mut context = builder
+			.with_tile_dimensions(self.tile_size(), self.tile_size())
+			.with_title("Rogue")
+			.build()?;
+		context.set_active_console(TEXT_CONSOLE);
+		Ok(context)
+	}
+}
+
+/// The mode the window was actually built in. The settings can be changed
+/// under it, but the window stays as it is until the next start.
+pub struct ActiveDisplay {
+	pub sprites : bool,
+}
+
+// Drawing
+// =========================================================================
+
+/// Everything drawn this frame, queued up and submitted in one go at the
+/// end of it. rltk hands its batches out of a pool, wrapped up, so the
+/// wrapper's kept as it comes.
+pub struct FrameBatch (Box<dyn DerefMut<Target = DrawBatch> + Send + Sync>);
+
+impl Default for FrameBatch {
+	fn default () -> FrameBatch { FrameBatch(Box::new(DrawBatch::new())) }
+}
+
+impl Deref for FrameBatch {
+	type Target = DrawBatch;
+	fn deref (&self) -> &DrawBatch { &self.0 }
+}
+
+impl DerefMut for FrameBatch {
+	fn deref_mut (&mut self) -> &mut DrawBatch { &mut self.0 }
+}
+
+// Sprites
+// =========================================================================
+
+/// Every tile in the tileset, in CP437 order
+fn sprite_sheet () -> SpriteSheet {
+	let mut sheet = SpriteSheet::new(TILESET);
+	for index in 0 .. 256 {
+		sheet = sheet.add_sprite(Rect::with_size(
+			(index % 16) * SPRITE_SIZE,
+			(index / 16) * SPRITE_SIZE,
+			SPRITE_SIZE,
+			SPRITE_SIZE,
+		));
+	}
+	sheet
+}
+
+/// Wipes the sprites from last frame, leaving the text console active
+pub fn clear_sprites (ctx: &mut Rltk) {
+	ctx.set_active_console(MAP_CONSOLE);
+	ctx.cls();
+	ctx.set_active_console(TEXT_CONSOLE);
+}
+
+/// Draws a sprite over the given console cell, on the map console
+pub fn draw_sprite (ctx: &mut Rltk, x: i32, y: i32, z_order: i32, tint: RGB, index: usize) {
+	ctx.set_active_console(MAP_CONSOLE);
+	ctx.add_sprite(
+		Rect::with_size(x * SPRITE_SIZE, y * SPRITE_SIZE, SPRITE_SIZE, SPRITE_SIZE),
+		z_order,
+		tint.into(),
+		index,
+	);
+	ctx.set_active_console(TEXT_CONSOLE);
+}
+
+/// Draws something at the given console cell, as its sprite (or its glyph's
+/// place in the tileset) in sprite mode. Things with a sprite of their own
+/// are drawn as they are; the rest are tinted their glyph's colour.
+pub fn draw_renderable (draw: &mut DrawBatch, ctx: &mut Rltk, sprites: bool, x: i32, y: i32, render: &Renderable, bg: RGB) {
+	if !sprites {
+		draw.set(Point::new(x, y), ColorPair::new(render.fg, bg), render.glyph);
+		return;
+	}
+
+	let (index, tint) = match render.sprite {
+		Some(sprite) => (sprite, RGB::named(rltk::WHITE)),
+		None => (render.glyph as usize, render.fg),
+	};
+
+	// Lower render orders go on top
+	draw_sprite(ctx, x, y, 10 - render.render_order, tint, index);
+}
+
+// Screenshots
+// =========================================================================
+
+/// Saves the console as a PNG and what's been seen of the level as a text
+/// file beside it, named for the depth and turn, and gives back the name
+pub fn screenshot (ecs: &World, ctx: &mut Rltk) -> String {
+	let map = ecs.fetch::<Map>();
+	let name = format!("./screenshot_depth_{}_turn_{}", map.depth, ecs.fetch::<EventCounter>().turns);
+
+	ctx.screenshot(format!("{}.png", name));
+	fs::write(format!("{}.txt", name), map.revealed_ascii(*ecs.fetch::<Point>()))
+		.or_warn("Couldn't find anywhere to put the map");
+
+	name
+}
+
+// Viewport
+// =========================================================================
+
+/// The part of the map that's on screen. When the console's too small to
+/// fit the whole map beside the panel it scrolls to follow the player.
+pub struct Viewport {
+	pub x      : i32,
+	pub y      : i32,
+	pub width  : i32,
+	pub height : i32,
+}
+
+impl Viewport {
+	/// As much of the map as fits, centred on `centre` where it can be
+	pub fn new (map: &Map, ctx: &Rltk, centre: Point) -> Viewport {
+		let (console_width, console_height) = ctx.get_char_size();
+		let width = i32::min(map.width, console_width as i32 - PANEL_WIDTH);
+		let height = i32::min(map.height, console_height as i32 - LOG_HEIGHT);
+
+		Viewport {
+			x: (centre.x - width / 2).clamp(0, map.width - width),
+			y: (centre.y - height / 2).clamp(0, map.height - height),
+			width,
+			height,
+		}
+	}
+
+	/// The view around the player
+	pub fn for_player (ecs: &World, ctx: &Rltk) -> Viewport {
+		Viewport::new(&ecs.fetch::<Map>(), ctx, *ecs.fetch::<Point>())
+	}
+
+	/// Where a map tile is drawn, if it's in view
+	pub fn to_screen (&self, x: i32, y: i32) -> Option<(i32, i32)> {
+		let (sx, sy) = (x - self.x, y - self.y);
+		if sx < 0 || sx > self.width - 1 || sy < 0 || sy > self.height - 1 { return None; }
+		Some((sx, sy))
+	}
+
+	/// The map tile under a point on screen, if there is one
+	pub fn to_map (&self, sx: i32, sy: i32) -> Option<Point> {
+		if sx < 0 || sx > self.width - 1 || sy < 0 || sy > self.height - 1 { return None; }
+		Some(Point::new(sx + self.x, sy + self.y))
+	}
+}
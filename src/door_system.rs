@@ -0,0 +1,79 @@
+use specs::prelude::*;
+use crate::{BlocksTile, Door, Name, Position, Renderable, Viewshed, WantsToOpenDoor};
+use crate::ai::NoiseBuilder;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+use crate::spatial::SpatialIndex;
+
+/// How far the creak of a door opening carries
+const DOOR_NOISE : i32 = 3;
+
+pub struct DoorSystem {}
+
+impl<'a> System<'a> for DoorSystem {
+	type SystemData = (
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, GameLog>,
+		Entities<'a>,
+		WriteStorage<'a, WantsToOpenDoor>,
+		WriteStorage<'a, Door>,
+		WriteStorage<'a, BlocksTile>,
+		WriteStorage<'a, Renderable>,
+		ReadStorage<'a, Position>,
+		WriteStorage<'a, Viewshed>,
+		ReadStorage<'a, Name>,
+		WriteExpect<'a, NoiseBuilder>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			map, mut spatial, player_entity, mut log, entities, mut wants_open,
+			mut doors, mut blockers, mut renderables, positions,
+			mut viewsheds, names, mut noise,
+		) = data;
+
+		let mut opened_any = false;
+
+		for (entity, wants) in (&entities, &wants_open).join() {
+			let door = match doors.get_mut(wants.door) {
+				None => continue,
+				Some(door) => door,
+			};
+			if door.open { continue; }
+
+			door.open = true;
+			opened_any = true;
+			blockers.remove(wants.door);
+
+			if let Some(renderable) = renderables.get_mut(wants.door) {
+				renderable.glyph = rltk::to_cp437('/');
+			}
+
+			if let Some(pos) = positions.get(wants.door) {
+				let idx = map.xy_idx(pos.x, pos.y);
+				spatial.set_door(idx, false);
+				spatial.set_blocks(wants.door, idx, false);
+				noise.request(pos.x, pos.y, DOOR_NOISE);
+
+				if entity == *player_entity {
+					log.system("You open the door.".to_string());
+				} else if map.is_visible(idx) {
+					if let Some(name) = names.get(entity) {
+						log.system(format!("{} opens a door.", &name.name));
+					}
+				}
+			}
+		}
+
+		// Everyone's view might have just opened up
+		if opened_any {
+			for viewshed in (&mut viewsheds).join() {
+				viewshed.dirty = true;
+			}
+		}
+
+		wants_open.clear();
+	}
+}
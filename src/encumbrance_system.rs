@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use specs::prelude::*;
+use crate::{Burden, CombatStats, Equipped, InBackpack, Weight};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+
+/// Weighs up what everyone's carrying and wearing against how much they can
+/// manage, and lets the player know when it tips over either way
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+	type SystemData = (
+		Entities<'a>,
+		ReadStorage<'a, CombatStats>,
+		ReadStorage<'a, Weight>,
+		ReadStorage<'a, InBackpack>,
+		ReadStorage<'a, Equipped>,
+		WriteStorage<'a, Burden>,
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, GameLog>,
+		ReadExpect<'a, Config>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, combat_stats, weights, backpack, equipped,
+			mut burdens, player_entity, mut log, config,
+		) = data;
+
+		let mut carried : HashMap<Entity, f32> = HashMap::new();
+		for (weight, pack) in (&weights, &backpack).join() {
+			*carried.entry(pack.owner).or_insert(0.) += weight.kg;
+		}
+		for (weight, worn) in (&weights, &equipped).join() {
+			*carried.entry(worn.owner).or_insert(0.) += weight.kg;
+		}
+
+		for (entity, stats) in (&entities, &combat_stats).join() {
+			let burden = Burden {
+				carried: carried.get(&entity).copied().unwrap_or(0.),
+				capacity: config.carrying.capacity(stats.power),
+			};
+
+			if entity == *player_entity {
+				let was_overloaded = burdens.get(entity).map_or(false, |b| b.overloaded());
+				if burden.overloaded() && !was_overloaded {
+					log.system("You're carrying more than you can manage, and it's slowing you down".to_string());
+				} else if !burden.overloaded() && was_overloaded {
+					log.system("Your load feels manageable again".to_string());
+				}
+			}
+
+			burdens.insert(entity, burden).or_warn("Couldn't weigh someone up");
+		}
+	}
+}
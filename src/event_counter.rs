@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+/// A tally of how the run's gone, for the summary at the end of it
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct EventCounter {
+	pub turns       : i32,
+	pub deepest     : i32,
+	pub items_used  : i32,
+	/// How many of each kind of monster have died
+	pub kills       : BTreeMap<String, i32>,
+	/// How many of each kind of item the player's used up
+	#[serde(default)]
+	pub items       : BTreeMap<String, i32>,
+	/// Whatever dealt the killing blow to the player
+	pub killed_by   : Option<String>,
+	/// Time spent actually playing, not sat in menus
+	#[serde(default)]
+	pub playtime_ms : f64,
+	/// What the run's levels are all generated from
+	#[serde(default)]
+	pub seed        : u64,
+	/// Saved after every turn, with no quick loading
+	#[serde(default)]
+	pub ironman     : bool,
+}
+
+impl EventCounter {
+	pub fn kill (&mut self, name: &str) {
+		*self.kills.entry(name.to_string()).or_insert(0) += 1;
+	}
+
+	pub fn use_item (&mut self, name: &str) {
+		self.items_used += 1;
+		*self.items.entry(name.to_string()).or_insert(0) += 1;
+	}
+
+	pub fn total_kills (&self) -> i32 {
+		self.kills.values().sum()
+	}
+
+	/// What the run's worth on the high score table: mostly how deep it got,
+	/// then what it killed and how long it lasted, with a bonus for winning
+	pub fn score (&self, victory: bool) -> i32 {
+		self.deepest * 100 + self.total_kills() * 10 + self.turns / 20 + if victory { 1000 } else { 0 }
+	}
+
+	/// The playtime as hours, minutes and seconds
+	pub fn playtime (&self) -> String {
+		let seconds = (self.playtime_ms / 1000.) as i64;
+		if seconds >= 3600 {
+			format!("{}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
+		} else {
+			format!("{}m {:02}s", seconds / 60, seconds % 60)
+		}
+	}
+}
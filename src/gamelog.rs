@@ -1,6 +1,66 @@
 use serde::{Deserialize, Serialize};
 
+/// What a log entry is about, for colouring and filtering the log by
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum LogCategory {
+	Combat,
+	Loot,
+	System,
+	Dialogue,
+}
+
+impl LogCategory {
+	pub const ALL : [LogCategory; 4] = [LogCategory::Combat, LogCategory::Loot, LogCategory::System, LogCategory::Dialogue];
+
+	pub fn name (&self) -> &'static str {
+		match self {
+			LogCategory::Combat => "Combat",
+			LogCategory::Loot => "Loot",
+			LogCategory::System => "System",
+			LogCategory::Dialogue => "Dialogue",
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+	pub text     : String,
+	pub category : LogCategory,
+	/// How many times in a row it's happened
+	pub count    : i32,
+}
+
+impl LogEntry {
+	/// The entry as it's shown, with "x3" on the end for repeats
+	pub fn display (&self) -> String {
+		if self.count > 1 { format!("{} x{}", self.text, self.count) }
+		else { self.text.clone() }
+	}
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct GameLog {
-	pub entries : Vec<String>,
-}
\ No newline at end of file
+	pub entries : Vec<LogEntry>,
+}
+
+impl GameLog {
+	/// Adds an entry, or counts it again if it's the same as the last one
+	pub fn push (&mut self, category: LogCategory, text: String) {
+		if let Some(last) = self.entries.last_mut() {
+			if last.text == text && last.category == category {
+				last.count += 1;
+				return;
+			}
+		}
+
+		self.entries.push(LogEntry { text, category, count: 1 });
+	}
+
+	pub fn combat (&mut self, text: String) { self.push(LogCategory::Combat, text); }
+
+	pub fn loot (&mut self, text: String) { self.push(LogCategory::Loot, text); }
+
+	pub fn system (&mut self, text: String) { self.push(LogCategory::System, text); }
+
+	pub fn dialogue (&mut self, text: String) { self.push(LogCategory::Dialogue, text); }
+}
@@ -1,10 +1,20 @@
-use rltk::{DistanceAlg, Point, RGB, Rltk, VirtualKeyCode};
+use std::collections::BTreeMap;
+use rltk::{ColorPair, DistanceAlg, DrawBatch, Point, Rect, RGB, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use crate::{CombatStats, Equipped, Hidden, HungerClock, HungerState, InBackpack, Name, Player, Position, RunState, State, Viewshed};
-use crate::gamelog::GameLog;
-use crate::map::Map;
+use crate::{Ally, AreaOfEffect, Burden, Charges, CombatStats, Consumable, DamageFlash, DefenseBonus, Description, Equippable, EquipmentSlot, Equipped, Fragile, Hidden, HungerClock, HungerState, InBackpack, InflictsDamage, InflictsStatus, Item, LightSource, MeleePowerBonus, Monster, Name, NeedsFlash, Position, ProvidesFood, ProvidesHealing, Ranged, Renderable, State, StatusEffects, ThirstClock, ThirstState, Viewshed, Waterskin, Weight};
+use crate::display::{CONSOLE_SIZES, DisplaySettings, FONT_SCALES, FrameBatch, Viewport};
+use crate::palette::Palette;
+use crate::event_counter::EventCounter;
+use crate::gamelog::{GameLog, LogCategory};
+use crate::keybindings::{Command, COMMANDS, KeyBindings};
+use crate::config::Config;
+use crate::map::{Map, TileType};
+use crate::spatial::SpatialIndex;
 use crate::rex_assets::RexAssets;
-use crate::saveload_system::does_save_exist;
+use crate::inventory_system::blast_area;
+use crate::records::{HIGH_SCORES, most, Records};
+use crate::settings::{self, AUTOSAVE_INTERVALS, GameplaySettings};
+use crate::saveload_system::{does_save_exist, has_backup, read_save_header};
 
 // Enums
 // =========================================================================
@@ -16,17 +26,168 @@ pub enum ItemMenuResult {
 	Selected,
 }
 
+/// What a menu of things to pick from wants, with its cursor on one row
+#[derive(PartialEq, Clone)]
+pub enum ListMenuResult<T> {
+	NoResponse,
+	Cancel,
+	/// Moved the cursor to another row
+	Select { selection: usize },
+	Selected(T),
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ConfirmResult {
+	NoResponse,
+	Yes,
+	No,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ItemAction {
+	Use,
+	Equip,
+	Throw,
+	Drop,
+	Examine,
+}
+
+impl ItemAction {
+	pub fn label (&self) -> &'static str {
+		match self {
+			ItemAction::Use => "Use",
+			ItemAction::Equip => "Equip",
+			ItemAction::Throw => "Throw",
+			ItemAction::Drop => "Drop",
+			ItemAction::Examine => "Examine",
+		}
+	}
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuSelection {
 	NewGame,
+	SeededGame,
 	LoadGame,
+	ExportSave,
+	ImportSave,
+	Records,
+	HighScores,
+	KeyBindings,
+	Display,
 	Quit,
 }
 
+impl MainMenuSelection {
+	/// Every entry, top to bottom
+	pub const ALL : [MainMenuSelection; 10] = [
+		MainMenuSelection::NewGame,
+		MainMenuSelection::SeededGame,
+		MainMenuSelection::LoadGame,
+		MainMenuSelection::ExportSave,
+		MainMenuSelection::ImportSave,
+		MainMenuSelection::Records,
+		MainMenuSelection::HighScores,
+		MainMenuSelection::KeyBindings,
+		MainMenuSelection::Display,
+		MainMenuSelection::Quit,
+	];
+
+	pub fn label (&self) -> &'static str {
+		match self {
+			MainMenuSelection::NewGame => "New Game",
+			MainMenuSelection::SeededGame => "New Game (enter seed)",
+			MainMenuSelection::LoadGame => "Continue",
+			MainMenuSelection::ExportSave => "Export Save",
+			MainMenuSelection::ImportSave => "Import Save",
+			MainMenuSelection::Records => "Records",
+			MainMenuSelection::HighScores => "High Scores",
+			MainMenuSelection::KeyBindings => "Key Bindings",
+			MainMenuSelection::Display => "Display",
+			MainMenuSelection::Quit => "Quit",
+		}
+	}
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum PauseSelection {
+	Resume,
+	Options,
+	Help,
+	SaveAndQuit,
+	Abandon,
+}
+
+impl PauseSelection {
+	const ALL : [PauseSelection; 5] = [
+		PauseSelection::Resume,
+		PauseSelection::Options,
+		PauseSelection::Help,
+		PauseSelection::SaveAndQuit,
+		PauseSelection::Abandon,
+	];
+
+	fn label (&self) -> &'static str {
+		match self {
+			PauseSelection::Resume => "Resume",
+			PauseSelection::Options => "Options",
+			PauseSelection::Help => "Help",
+			PauseSelection::SaveAndQuit => "Save & Quit",
+			PauseSelection::Abandon => "Abandon Run",
+		}
+	}
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum PauseMenuResult {
+	NoResponse,
+	Resume,
+	/// Moved to another option, or to asking whether to really abandon
+	Select { selection: PauseSelection, confirming: bool },
+	Selected { selection: PauseSelection },
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuResult {
 	NoSelection { selected: MainMenuSelection },
 	Selected { selected: MainMenuSelection },
+	RestoreBackup,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SeedEntryResult {
+	NoResponse,
+	Edit { seed: Option<u64> },
+	Cancel,
+	Start { seed: u64 },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LogViewerResult {
+	NoResponse,
+	Close,
+	Scroll { offset: i32, filter: Option<LogCategory> },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ExamineResult {
+	NoResponse,
+	Close,
+	Move { x: i32, y: i32 },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum KeyBindingsMenuResult {
+	NoResponse,
+	Close,
+	Edit { selection: usize, rebinding: bool },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DisplayOptionsResult {
+	NoResponse,
+	Close,
+	Select { selection: usize },
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -38,617 +199,2073 @@ pub enum GameOverResult {
 // Main Menu
 // =========================================================================
 
-pub fn draw_main_menu (gs: &State, ctx: &mut Rltk) -> MainMenuResult {
+pub fn draw_main_menu (gs: &State, ctx: &mut Rltk, selection: MainMenuSelection) -> MainMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
 	let save_exists = does_save_exist();
-	let runstate = gs.ecs.fetch::<RunState>();
 
 	let assets = gs.ecs.fetch::<RexAssets>();
 	ctx.render_xp_sprite(&assets.menu, 0, 0);
 
-	ctx.print_color_centered(
-		15,
-		RGB::named(rltk::GOLD),
-		RGB::named(rltk::BLACK),
-		"Rogue",
-	);
+	draw.print_color_centered(
+		15,
+		"Rogue",
+		ColorPair::new(palette.title, palette.background),
+	);
+
+	// Continue and Export need a save to work with, and there's nowhere to
+	// quit to in the browser
+	let enabled = |entry: &MainMenuSelection| match entry {
+		MainMenuSelection::LoadGame | MainMenuSelection::ExportSave => save_exists,
+		MainMenuSelection::Quit => !cfg!(target_arch = "wasm32"),
+		_ => true,
+	};
+
+	for (i, entry) in MainMenuSelection::ALL.iter().enumerate() {
+		let fg =
+			if *entry == selection { palette.highlight }
+			else if enabled(entry) { palette.text }
+			else { palette.faint };
+		draw.print_color_centered(24 + i as i32 * 2, entry.label(), ColorPair::new(fg, palette.background));
+	}
+
+	let info_y = 26 + MainMenuSelection::ALL.len() as i32 * 2;
+	let can_restore = gs.load_error.is_some() && has_backup();
+	if let Some(error) = &gs.load_error {
+		draw.print_color_centered(info_y, error, ColorPair::new(palette.danger, palette.background));
+		if can_restore {
+			draw.print_color_centered(info_y + 2, "Press R to restore the backup", ColorPair::new(palette.text_dim, palette.background));
+		}
+	} else if let Some(notice) = &gs.menu_notice {
+		draw.print_color_centered(info_y, notice, ColorPair::new(palette.text_dim, palette.background));
+	} else if selection == MainMenuSelection::LoadGame {
+		if let Some(header) = read_save_header() {
+			draw.print_color_centered(
+				info_y,
+				&format!(
+					"Depth {}, turn {}, HP {}/{} - saved {}",
+					header.depth, header.turns, header.hp, header.max_hp, header.age(),
+				),
+				ColorPair::new(palette.text_dim, palette.background),
+			);
+		}
+	}
+
+	// The next entry along that can be picked, wrapping round
+	let step = |by: i32| {
+		let count = MainMenuSelection::ALL.len() as i32;
+		let mut i = MainMenuSelection::ALL.iter().position(|entry| *entry == selection).unwrap_or(0) as i32;
+		loop {
+			i = (i + by).rem_euclid(count);
+			if enabled(&MainMenuSelection::ALL[i as usize]) { return MainMenuSelection::ALL[i as usize]; }
+		}
+	};
+
+	match ctx.key {
+		None => MainMenuResult::NoSelection { selected: selection },
+		Some(key) => {
+			match key {
+				VirtualKeyCode::Escape => {
+					MainMenuResult::NoSelection {
+						selected: MainMenuSelection::Quit,
+					}
+				}
+				VirtualKeyCode::Up | VirtualKeyCode::W => {
+					MainMenuResult::NoSelection { selected: step(-1) }
+				}
+				VirtualKeyCode::Down | VirtualKeyCode::S => {
+					MainMenuResult::NoSelection { selected: step(1) }
+				}
+				VirtualKeyCode::Return => {
+					MainMenuResult::Selected { selected: selection }
+				}
+				VirtualKeyCode::R if can_restore => MainMenuResult::RestoreBackup,
+				_ => MainMenuResult::NoSelection { selected: selection },
+			}
+		}
+	}
+}
+
+// Seed Entry
+// =========================================================================
+
+/// The longest seed that can be typed, which keeps it inside a u64
+const SEED_DIGITS : usize = 19;
+
+/// Types in the seed for a new run, digit by digit. Nothing typed yet is
+/// `None`.
+pub fn enter_seed (gs: &State, ctx: &mut Rltk, seed: Option<u64>) -> SeedEntryResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let middle = middle_row(ctx);
+
+	draw.print_color_centered(middle - 4, "New Game", ColorPair::new(palette.title, palette.background));
+	draw.print_color_centered(middle - 2, "Enter a seed:", ColorPair::new(palette.text, palette.background));
+	draw.print_color_centered(
+		middle,
+		&format!("{:_<width$}", seed.map_or(String::new(), |seed| seed.to_string()), width = SEED_DIGITS),
+		ColorPair::new(palette.value, palette.background),
+	);
+	draw.print_color_centered(
+		middle + 3,
+		"ENTER to start, ESCAPE to go back",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	let digit = match ctx.key {
+		None => return SeedEntryResult::NoResponse,
+		Some(VirtualKeyCode::Escape) => return SeedEntryResult::Cancel,
+		Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::NumpadEnter) => {
+			return match seed {
+				Some(seed) => SeedEntryResult::Start { seed },
+				None => SeedEntryResult::NoResponse,
+			};
+		}
+		Some(VirtualKeyCode::Back) => {
+			return SeedEntryResult::Edit { seed: seed.filter(|seed| *seed >= 10).map(|seed| seed / 10) };
+		}
+		Some(key) => key_digit(key),
+	};
+
+	let length = seed.map_or(0, |seed| seed.to_string().len());
+	match digit {
+		Some(digit) if length < SEED_DIGITS => {
+			SeedEntryResult::Edit { seed: Some(seed.unwrap_or(0) * 10 + digit as u64) }
+		}
+		_ => SeedEntryResult::NoResponse,
+	}
+}
+
+/// The digit a key types, if it's a number key
+fn key_digit (key: VirtualKeyCode) -> Option<u32> {
+	let digit = match key {
+		VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => 0,
+		VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => 1,
+		VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => 2,
+		VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => 3,
+		VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => 4,
+		VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => 5,
+		VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => 6,
+		VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => 7,
+		VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => 8,
+		VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => 9,
+		_ => return None,
+	};
+	Some(digit)
+}
+
+// Pause Menu
+// =========================================================================
+
+/// The menu over the game when it's paused. Abandoning the run has to be
+/// confirmed, since there's no coming back from it.
+pub fn pause_menu (gs: &State, ctx: &mut Rltk, selection: PauseSelection, confirming: bool) -> PauseMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let (width, _) = screen_size(ctx);
+	let options = PauseSelection::ALL;
+	let box_height = options.len() as i32 * 2 + 3;
+	let y = middle_row(ctx) - box_height / 2;
+
+	draw.draw_box(Rect::with_size(width / 2 - 16, y, 31, box_height), ColorPair::new(palette.text, palette.background));
+	draw.print_color_centered(y, " Paused ", ColorPair::new(palette.title, palette.background));
+
+	for (i, option) in options.iter().enumerate() {
+		let fg = if *option == selection { palette.highlight } else { palette.text };
+		draw.print_color_centered(y + 2 + i as i32 * 2, option.label(), ColorPair::new(fg, palette.background));
+	}
+
+	if confirming {
+		draw.print_color_centered(
+			y + box_height,
+			" Abandon this run for good? (Y/N) ",
+			ColorPair::new(palette.danger, palette.background),
+		);
+
+		return match ctx.key {
+			Some(VirtualKeyCode::Y) => PauseMenuResult::Selected { selection },
+			Some(VirtualKeyCode::N) | Some(VirtualKeyCode::Escape) => PauseMenuResult::Select { selection, confirming: false },
+			_ => PauseMenuResult::NoResponse,
+		};
+	}
+
+	let current = options.iter().position(|o| *o == selection).unwrap_or(0);
+	match ctx.key {
+		None => PauseMenuResult::NoResponse,
+		Some(key) => match key {
+			VirtualKeyCode::Escape => PauseMenuResult::Resume,
+			VirtualKeyCode::Up | VirtualKeyCode::W => PauseMenuResult::Select {
+				selection: options[(current + options.len() - 1) % options.len()],
+				confirming: false,
+			},
+			VirtualKeyCode::Down | VirtualKeyCode::S => PauseMenuResult::Select {
+				selection: options[(current + 1) % options.len()],
+				confirming: false,
+			},
+			VirtualKeyCode::Return if selection == PauseSelection::Abandon => PauseMenuResult::Select { selection, confirming: true },
+			VirtualKeyCode::Return => PauseMenuResult::Selected { selection },
+			_ => PauseMenuResult::NoResponse,
+		},
+	}
+}
+
+// Quick Load
+// =========================================================================
+
+/// Checks before throwing away everything since the quick save
+pub fn confirm_quick_load (gs: &State, ctx: &mut Rltk) -> ConfirmResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let (width, _) = screen_size(ctx);
+	let y = middle_row(ctx) - 2;
+
+	draw.draw_box(Rect::with_size(width / 2 - 22, y, 43, 4), ColorPair::new(palette.text, palette.background));
+	draw.print_color_centered(y, " Quick Load ", ColorPair::new(palette.title, palette.background));
+	draw.print_color_centered(y + 2, "Go back to the quick save? (Y/N)", ColorPair::new(palette.text, palette.background));
+
+	match ctx.key {
+		Some(VirtualKeyCode::Y) => ConfirmResult::Yes,
+		Some(VirtualKeyCode::N) | Some(VirtualKeyCode::Escape) => ConfirmResult::No,
+		_ => ConfirmResult::NoResponse,
+	}
+}
+
+// Game Interface
+// =========================================================================
+
+/// The console's size in characters
+fn screen_size (ctx: &Rltk) -> (i32, i32) {
+	let (width, height) = ctx.get_char_size();
+	(width as i32, height as i32)
+}
+
+/// The row halfway down the screen, for centring menus on
+fn middle_row (ctx: &Rltk) -> i32 {
+	screen_size(ctx).1 / 2
+}
+
+/// Where the side panel and log go: the map viewport takes the top left, the
+/// panel fills everything to its right and the log everything below it
+struct Layout {
+	panel_x       : i32,
+	panel_width   : i32,
+	log_y         : i32,
+	log_width     : i32,
+	log_height    : i32,
+	screen_height : i32,
+}
+
+impl Layout {
+	fn new (viewport: &Viewport, ctx: &Rltk) -> Layout {
+		let (width, height) = screen_size(ctx);
+
+		Layout {
+			panel_x: viewport.width,
+			panel_width: width - viewport.width,
+			log_y: viewport.height,
+			log_width: viewport.width,
+			log_height: height - viewport.height,
+			screen_height: height,
+		}
+	}
+}
+
+pub fn draw_ui (ecs: &World, ctx: &mut Rltk) {
+	let palette = *ecs.fetch::<Palette>();
+	let mut draw = ecs.write_resource::<FrameBatch>();
+	let map = ecs.fetch::<Map>();
+	let viewport = Viewport::for_player(ecs, ctx);
+	let layout = Layout::new(&viewport, ctx);
+
+	// Borders
+	draw.draw_box(
+		Rect::with_size(layout.panel_x, 0, layout.panel_width - 1, layout.screen_height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.draw_box(
+		Rect::with_size(0, layout.log_y, layout.log_width - 1, layout.log_height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+
+	let x = layout.panel_x + 2;
+	let inner_width = layout.panel_width - 4;
+	let mut y = 2;
+
+	// Depth and Turn
+	let depth = format!("Depth: {}", map.depth);
+	draw.print_color(Point::new(x, y), &depth, ColorPair::new(palette.value, palette.background));
+	let turn = format!("Turn: {}", ecs.fetch::<EventCounter>().turns);
+	draw.print_color(Point::new(x, y + 1), &truncate(&turn, inner_width), ColorPair::new(palette.text_dim, palette.background));
+	y += 3;
+
+	// Player Health
+	let player_entity = ecs.fetch::<Entity>();
+	let combat_stats = ecs.read_storage::<CombatStats>();
+	let hunger = ecs.read_storage::<HungerClock>();
+	let thirst = ecs.read_storage::<ThirstClock>();
+	let flash = ecs.read_storage::<NeedsFlash>();
+	let flash = flash.get(*player_entity);
+	if let Some(stats) = combat_stats.get(*player_entity) {
+		let health = format!("HP: {} / {}", stats.hp, stats.max_hp);
+		draw.print_color(Point::new(x, y), &health, ColorPair::new(palette.value, palette.background));
+		draw.bar_horizontal(
+			Point::new(x, y + 1),
+			inner_width,
+			stats.hp,
+			stats.max_hp,
+			ColorPair::new(health_colour(ecs, *player_entity), palette.faint),
+		);
+		y += 2;
+	}
+
+	// Status Indicators
+	let statuses = ecs.read_storage::<StatusEffects>();
+	let mut indicators : Vec<(rltk::FontCharType, RGB, String)> = Vec::new();
+	if let Some(statuses) = statuses.get(*player_entity) {
+		for effect in statuses.effects.iter() {
+			indicators.push((
+				effect.kind.glyph(),
+				effect.kind.colour(),
+				format!("{}{}", effect.kind.abbreviation(), effect.duration),
+			));
+		}
+	}
+	if let Some(hc) = hunger.get(*player_entity) {
+		if hc.state == HungerState::WellFed {
+			indicators.push((rltk::to_cp437('%'), palette.good, format!("Fed{}", hc.duration)));
+		}
+	}
+	if let Some(tc) = thirst.get(*player_entity) {
+		if tc.state == ThirstState::Quenched {
+			indicators.push((rltk::to_cp437('~'), palette.water, format!("Wet{}", tc.duration)));
+		}
+	}
+
+	let mut indicator_x = x;
+	for (glyph, colour, label) in indicators.iter() {
+		let width = label.len() as i32 + 2;
+		if indicator_x > x && indicator_x + width > x + inner_width {
+			indicator_x = x;
+			y += 1;
+		}
+		draw.set(Point::new(indicator_x, y), ColorPair::new(*colour, palette.background), *glyph);
+		draw.print_color(Point::new(indicator_x + 1, y), label, ColorPair::new(*colour, palette.background));
+		indicator_x += width;
+	}
+	y += if indicators.is_empty() { 1 } else { 2 };
+
+	// Food clock, running down to starving
+	if let Some(hc) = hunger.get(*player_entity) {
+		let config = ecs.fetch::<Config>();
+		let (fg, msg) = match hc.state {
+			HungerState::WellFed => (palette.good, "Well Fed"),
+			HungerState::Normal => (palette.text, "Not hungry"),
+			HungerState::Hungry => (palette.warning, "Hungry"),
+			HungerState::Starving => (palette.danger, "Starving"),
+		};
+		let fg = if flash.map_or(false, |f| f.food_ms > 0.) { palette.text } else { fg };
+		draw.print_color(Point::new(x, y), &truncate(&format!("Food: {}", msg), inner_width), ColorPair::new(fg, palette.background));
+		draw.bar_horizontal(
+			Point::new(x, y + 1),
+			inner_width,
+			hc.turns_until_starving(&config.hunger),
+			config.hunger.most_turns(),
+			ColorPair::new(fg, palette.faint),
+		);
+		y += 3;
+	}
+
+	// Water clock, running down to parched
+	if let Some(tc) = thirst.get(*player_entity) {
+		let config = ecs.fetch::<Config>();
+		let (fg, msg) = match tc.state {
+			ThirstState::Quenched => (palette.water, "Quenched"),
+			ThirstState::Normal => (palette.text, "Not thirsty"),
+			ThirstState::Thirsty => (palette.warning, "Thirsty"),
+			ThirstState::Parched => (palette.danger, "Parched"),
+		};
+		let fg = if flash.map_or(false, |f| f.water_ms > 0.) { palette.text } else { fg };
+		draw.print_color(Point::new(x, y), &truncate(&format!("Water: {}", msg), inner_width), ColorPair::new(fg, palette.background));
+		draw.bar_horizontal(
+			Point::new(x, y + 1),
+			inner_width,
+			tc.turns_until_parched(&config.thirst),
+			config.thirst.most_turns(),
+			ColorPair::new(fg, palette.faint),
+		);
+		y += 3;
+	}
+
+	// Equipment
+	let entities = ecs.entities();
+	let equipped = ecs.read_storage::<Equipped>();
+	let names = ecs.read_storage::<Name>();
+	let power_bonuses = ecs.read_storage::<MeleePowerBonus>();
+	let defense_bonuses = ecs.read_storage::<DefenseBonus>();
+
+	let lights = ecs.read_storage::<LightSource>();
+
+	for (slot, label) in [(EquipmentSlot::Melee, "Weapon"), (EquipmentSlot::OffHand, "Off hand")].iter() {
+		draw.print_color(Point::new(x, y), label, ColorPair::new(palette.text, palette.background));
+		y += 1;
+
+		let item = (&entities, &equipped, &names).join()
+			.find(|(_, e, _)| e.owner == *player_entity && e.slot == *slot)
+			.map(|(item, _, name)| (name, item));
+
+		match item {
+			None => { draw.print_color(Point::new(x, y), "Nothing", ColorPair::new(palette.text_dim, palette.background)); }
+			Some((name, item)) => {
+				let summary = match (slot, lights.get(item)) {
+					(_, Some(light)) => format!("{} ({})", name.name, light.fuel),
+					(EquipmentSlot::Melee, None) => format!("{} {:+}", name.name, power_bonuses.get(item).map_or(0, |b| b.power)),
+					(EquipmentSlot::OffHand, None) => format!("{} {:+}", name.name, defense_bonuses.get(item).map_or(0, |b| b.defense)),
+				};
+				draw.print_color(Point::new(x, y), &truncate(&summary, inner_width), ColorPair::new(palette.highlight, palette.background));
+			},
+		}
+		y += 2;
+	}
+
+	// Visible Enemies, any of which can be clicked on to target it
+	draw.print_color(Point::new(x, y), "In View", ColorPair::new(palette.text, palette.background));
+	y += 1;
+	let enemies = visible_enemies(ecs, &map);
+	if enemies.is_empty() {
+		draw.print_color(Point::new(x, y), "Nothing", ColorPair::new(palette.text_dim, palette.background));
+	}
+
+	let mut targeting = ecs.fetch_mut::<Targeting>();
+	let mouse_pos = ctx.mouse_pos();
+	targeting.panel_rows.clear();
+	for (enemy, name, distance) in enemies.iter() {
+		if y > layout.screen_height - 4 { break; }
+
+		let row = Rect::with_size(x, y, inner_width, 2);
+		if ctx.left_click && row.point_in_rect(Point::new(mouse_pos.0, mouse_pos.1)) {
+			targeting.last_target = Some(*enemy);
+		}
+		targeting.panel_rows.push((row, *enemy));
+
+		let fg = if targeting.last_target == Some(*enemy) { palette.highlight } else { palette.warning };
+		draw.print_color(Point::new(x, y), &truncate(name, inner_width - 4), ColorPair::new(fg, palette.background));
+		draw.print_color(Point::new(x + inner_width - 3, y), &format!("{:>3}", *distance as i32), ColorPair::new(palette.text_dim, palette.background));
+
+		if let Some(stats) = combat_stats.get(*enemy) {
+			draw.bar_horizontal(
+				Point::new(x, y + 1),
+				inner_width,
+				stats.hp,
+				stats.max_hp,
+				ColorPair::new(health_colour(ecs, *enemy), palette.faint),
+			);
+		}
+		y += 2;
+	}
+
+	// Log
+	let log = ecs.fetch::<GameLog>();
+	for (i, entry) in log.entries.iter().rev().take((layout.log_height - 2) as usize).enumerate() {
+		draw.print_color(
+			Point::new(2, layout.log_y + 1 + i as i32),
+			&truncate(&entry.display(), layout.log_width - 4),
+			ColorPair::new(palette.log(entry.category), palette.background),
+		);
+	}
+
+	// Tooltips
+	draw_tooltips(ecs, &mut draw, ctx);
+}
+
+/// Every hostile the player can see, with its name and how far off it is,
+/// nearest first
+fn visible_enemies (ecs: &World, map: &Map) -> Vec<(Entity, String, f32)> {
+	let player_entity = ecs.fetch::<Entity>();
+	let player_pos = ecs.fetch::<Point>();
+	let spatial = ecs.fetch::<SpatialIndex>();
+	let viewsheds = ecs.read_storage::<Viewshed>();
+	let monsters = ecs.read_storage::<Monster>();
+	let allies = ecs.read_storage::<Ally>();
+	let names = ecs.read_storage::<Name>();
+
+	let viewshed = match viewsheds.get(*player_entity) {
+		Some(viewshed) => viewshed,
+		None => return Vec::new(),
+	};
+
+	let mut enemies : Vec<(Entity, String, f32)> = Vec::new();
+	for tile in viewshed.visible_tiles.iter() {
+		for entity in spatial.content(map.xy_idx(tile.x, tile.y)) {
+			if monsters.get(entity).is_none() || allies.get(entity).is_some() { continue; }
+			if let Some(name) = names.get(entity) {
+				let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *tile);
+				enemies.push((entity, name.name.to_string(), distance));
+			}
+		}
+	}
+
+	enemies.sort_by(|a, b| a.2.total_cmp(&b.2));
+	enemies
+}
+
+/// Cuts a string down to fit the given width
+fn truncate (text: &str, width: i32) -> String {
+	text.chars().take(i32::max(0, width) as usize).collect()
+}
+
+enum TooltipLine {
+	Text(RGB, String),
+	Bar(i32, i32, RGB),
+}
+
+/// What to fill a health bar with, flashing it for a moment after a hit
+fn health_colour (ecs: &World, entity: Entity) -> RGB {
+	let palette = ecs.fetch::<Palette>();
+	if ecs.read_storage::<DamageFlash>().get(entity).is_some() { palette.text }
+	else { palette.health }
+}
+
+/// Everything worth knowing about an entity at a glance
+fn tooltip_lines (ecs: &World, entity: Entity, name: &Name) -> Vec<TooltipLine> {
+	let palette = *ecs.fetch::<Palette>();
+	let combat_stats = ecs.read_storage::<CombatStats>();
+	let statuses = ecs.read_storage::<StatusEffects>();
+	let inflicts_damage = ecs.read_storage::<InflictsDamage>();
+	let healing = ecs.read_storage::<ProvidesHealing>();
+	let ranged = ecs.read_storage::<Ranged>();
+	let aoe = ecs.read_storage::<AreaOfEffect>();
+	let inflicts_status = ecs.read_storage::<InflictsStatus>();
+	let power_bonuses = ecs.read_storage::<MeleePowerBonus>();
+	let defense_bonuses = ecs.read_storage::<DefenseBonus>();
+	let food = ecs.read_storage::<ProvidesFood>();
+	let waterskins = ecs.read_storage::<Waterskin>();
+	let weights = ecs.read_storage::<Weight>();
+	let consumables = ecs.read_storage::<Consumable>();
+
+	let white = palette.text;
+	let grey = palette.text_dim;
+	let mut lines = vec![TooltipLine::Text(palette.value, name.name.to_string())];
+
+	if let Some(stats) = combat_stats.get(entity) {
+		lines.push(TooltipLine::Bar(stats.hp, stats.max_hp, health_colour(ecs, entity)));
+		lines.push(TooltipLine::Text(white, format!("HP {} / {}", stats.hp, stats.max_hp)));
+		lines.push(TooltipLine::Text(white, format!("Attack {}  Defence {}", stats.power, stats.defence)));
+	}
+
+	if let Some(statuses) = statuses.get(entity) {
+		for effect in statuses.effects.iter() {
+			lines.push(TooltipLine::Text(
+				effect.kind.colour(),
+				format!("{} ({})", effect.kind.name(), effect.duration),
+			));
+		}
+	}
+
+	if let Some(damage) = inflicts_damage.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Deals {} damage", damage.damage)));
+	}
+	if let Some(heal) = healing.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Heals {} HP", heal.heal_amount)));
+	}
+	if let Some(status) = inflicts_status.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("{} for {} turns", status.kind.name(), status.duration)));
+	}
+	if let Some(range) = ranged.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Range {}", range.range)));
+	}
+	if let Some(blast) = aoe.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Blast radius {}", blast.radius)));
+	}
+	if let Some(bonus) = power_bonuses.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Attack {:+}", bonus.power)));
+	}
+	if let Some(bonus) = defense_bonuses.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Defence {:+}", bonus.defense)));
+	}
+	if food.get(entity).is_some() {
+		lines.push(TooltipLine::Text(grey, "Edible".to_string()));
+	}
+	if let Some(skin) = waterskins.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Sips {} / {}", skin.sips, skin.capacity)));
+	}
+	if consumables.get(entity).is_some() {
+		lines.push(TooltipLine::Text(grey, "Single use".to_string()));
+	}
+	if let Some(light) = ecs.read_storage::<LightSource>().get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Lights {} tiles, {} turns left", light.radius, light.fuel)));
+	}
+	if let Some(charges) = ecs.read_storage::<Charges>().get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Uses {} / {}", charges.uses, charges.max_uses)));
+	}
+	if ecs.read_storage::<Fragile>().get(entity).is_some() {
+		lines.push(TooltipLine::Text(grey, "Shatters when thrown".to_string()));
+	}
+	if let Some(weight) = weights.get(entity) {
+		lines.push(TooltipLine::Text(grey, format!("Weighs {}kg", weight.kg)));
+	}
+
+	lines
+}
+
+/// A panel beside the mouse describing whatever's under it
+fn draw_tooltips (ecs: &World, draw: &mut DrawBatch, ctx: &mut Rltk) {
+	let palette = *ecs.fetch::<Palette>();
+	let map = ecs.fetch::<Map>();
+	let entities = ecs.entities();
+	let names = ecs.read_storage::<Name>();
+	let positions = ecs.read_storage::<Position>();
+	let hidden = ecs.read_storage::<Hidden>();
+
+	let viewport = Viewport::for_player(ecs, ctx);
+	let mouse_pos = ctx.mouse_pos();
+
+	let tile = match viewport.to_map(mouse_pos.0, mouse_pos.1) {
+		Some(tile) => tile,
+		None => return,
+	};
+	if !map.is_visible(map.xy_idx(tile.x, tile.y)) { return; }
+
+	let mut lines : Vec<TooltipLine> = Vec::new();
+	for (entity, name, position, _hidden) in (&entities, &names, &positions, !&hidden).join() {
+		if position.x != tile.x || position.y != tile.y { continue; }
+
+		if !lines.is_empty() { lines.push(TooltipLine::Text(palette.background, String::new())); }
+		lines.extend(tooltip_lines(ecs, entity, name));
+	}
+
+	if lines.is_empty() { return; }
+
+	let text_width = lines.iter()
+		.map(|line| match line {
+			TooltipLine::Text(_, text) => text.len() as i32,
+			TooltipLine::Bar(_, _, _) => 0,
+		})
+		.max()
+		.unwrap_or(0);
+	let width = i32::max(12, text_width) + 3;
+	let height = lines.len() as i32 + 1;
+
+	// Off to whichever side of the mouse has more room, and kept on the map
+	let (left_x, arrow_x, arrow) =
+		if mouse_pos.0 > viewport.width / 2 { (mouse_pos.0 - width - 2, mouse_pos.0 - 2, "->") }
+		else { (mouse_pos.0 + 3, mouse_pos.0 + 1, "<-") };
+	let top_y = i32::max(0, i32::min(mouse_pos.1 - 1, viewport.height - height - 1));
+
+	draw.draw_box(
+		Rect::with_size(left_x, top_y, width, height),
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	for (i, line) in lines.iter().enumerate() {
+		let y = top_y + 1 + i as i32;
+		match line {
+			TooltipLine::Text(colour, text) => {
+				draw.print_color(Point::new(left_x + 2, y), text, ColorPair::new(*colour, palette.background));
+			},
+			TooltipLine::Bar(value, max, colour) => {
+				draw.bar_horizontal(
+					Point::new(left_x + 2, y),
+					width - 3,
+					*value,
+					*max,
+					ColorPair::new(*colour, palette.faint),
+				);
+			},
+		}
+	}
+
+	draw.print_color(
+		Point::new(arrow_x, mouse_pos.1),
+		arrow,
+		ColorPair::new(palette.background, palette.text_dim),
+	);
+}
+
+// Inventory
+// =========================================================================
+
+/// The row a key moves a list menu's cursor to, wrapping round at either end
+fn moved_cursor (key: VirtualKeyCode, selection: usize, count: usize) -> Option<usize> {
+	if count == 0 { return None; }
+	match key {
+		VirtualKeyCode::Up => Some((selection + count - 1) % count),
+		VirtualKeyCode::Down => Some((selection + 1) % count),
+		_ => None,
+	}
+}
+
+/// The row a key picks from a list menu: its letter, or the one under the
+/// cursor for ENTER
+fn picked_row (key: VirtualKeyCode, selection: usize, count: usize) -> Option<usize> {
+	let row = match key {
+		VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => selection as i32,
+		_ => rltk::letter_to_option(key),
+	};
+	if row > -1 && row < count as i32 { Some(row as usize) } else { None }
+}
+
+/// Moves the cursor with UP and DOWN, and picks a row by its letter or with
+/// ENTER
+fn list_menu_input<T: Copy> (key: Option<VirtualKeyCode>, selection: usize, rows: &[T]) -> ListMenuResult<T> {
+	let Some(key) = key else { return ListMenuResult::NoResponse };
+	if key == VirtualKeyCode::Escape { return ListMenuResult::Cancel; }
+	if let Some(selection) = moved_cursor(key, selection, rows.len()) {
+		return ListMenuResult::Select { selection };
+	}
+
+	match picked_row(key, selection, rows.len()) {
+		Some(row) => ListMenuResult::Selected(rows[row]),
+		None => ListMenuResult::NoResponse,
+	}
+}
+
+pub fn show_inventory (gs: &mut State, ctx: &mut Rltk, selection: usize) -> ListMenuResult<Entity> {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let player_entity = gs.ecs.fetch::<Entity>();
+	let stacks = stacked_backpack(&gs.ecs, *player_entity);
+	let count = stacks.len();
+	let selection = selection.min(count.saturating_sub(1));
+
+	let mut y = middle_row(ctx) - (count / 2) as i32;
+	draw.draw_box(
+		Rect::with_size(15, y - 2, 32, (count + 3) as i32),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y - 2),
+		" Inventory ",
+		ColorPair::new(palette.title, palette.background),
+	);
+	if let Some(burden) = gs.ecs.read_storage::<Burden>().get(*player_entity) {
+		let weight = format!(" {:.1} / {:.0}kg ", burden.carried, burden.capacity);
+		let fg = if burden.overloaded() { palette.danger } else { palette.text_dim };
+		draw.print_color(Point::new(46 - weight.len() as i32, y - 2), &weight, ColorPair::new(fg, palette.background));
+	}
+	draw.print_color(
+		Point::new(18, y + count as i32 + 1),
+		" ESCAPE to cancel ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	let mut equippable : Vec<Entity> = Vec::new();
+
+	for (j, (name, items)) in stacks.iter().enumerate() {
+		let fg = if j == selection { palette.highlight } else { palette.text };
+		draw.print_color(Point::new(17, y), &format!("({})", (97 + j as u8) as char), ColorPair::new(fg, palette.background));
+
+		let label =
+			if items.len() > 1 { format!("{} x{}", name, items.len()) }
+			else { name.to_string() };
+		draw.print_color(Point::new(21, y), &label, ColorPair::new(fg, palette.background));
+		equippable.push(items[0]);
+		y += 1;
+	}
+
+	list_menu_input(ctx.key, selection, &equippable)
+}
+
+/// The items in someone's backpack, with identical ones gathered into a
+/// single stack
+fn stacked_backpack (ecs: &World, owner: Entity) -> Vec<(String, Vec<Entity>)> {
+	let entities = ecs.entities();
+	let backpack = ecs.read_storage::<InBackpack>();
+
+	let items = (&entities, &backpack).join()
+		.filter(|item| item.1.owner == owner)
+		.map(|(e, _)| e)
+		.collect();
+	stack_by_name(ecs, items)
+}
+
+/// An item's name, with how many uses are left if it has charges
+fn item_label (ecs: &World, item: Entity) -> Option<String> {
+	let names = ecs.read_storage::<Name>();
+	let name = &names.get(item)?.name;
+	match ecs.read_storage::<Charges>().get(item) {
+		Some(charges) => Some(format!("{} ({}/{})", name, charges.uses, charges.max_uses)),
+		None => Some(name.to_string()),
+	}
+}
+
+/// Gathers items with the same name, and the same charges left, into stacks,
+/// keeping them in order
+fn stack_by_name (ecs: &World, items: Vec<Entity>) -> Vec<(String, Vec<Entity>)> {
+	let mut stacks : Vec<(String, Vec<Entity>)> = Vec::new();
+	for item in items {
+		let Some(name) = item_label(ecs, item) else { continue };
+		match stacks.iter_mut().find(|(stack, _)| *stack == name) {
+			Some((_, stack)) => stack.push(item),
+			None => stacks.push((name, vec![item])),
+		}
+	}
+
+	stacks
+}
+
+// Drop & Pick Up Menus
+// =========================================================================
+
+/// The items marked so far in the drop or pick up menu
+#[derive(Default)]
+pub struct ItemSelection {
+	marked : Vec<Entity>,
+}
+
+impl ItemSelection {
+	/// Marks another item from the stack, or unmarks the lot once they're all
+	/// marked
+	fn mark_one_more (&mut self, stack: &[Entity]) {
+		match stack.iter().find(|e| !self.marked.contains(e)) {
+			Some(item) => self.marked.push(*item),
+			None => self.marked.retain(|e| !stack.contains(e)),
+		}
+	}
+
+	fn unmark_one (&mut self, stack: &[Entity]) {
+		if let Some(i) = self.marked.iter().rposition(|e| stack.contains(e)) {
+			self.marked.remove(i);
+		}
+	}
+}
+
+pub fn drop_item_menu (gs: &mut State, ctx: &mut Rltk, selection: usize) -> ListMenuResult<Vec<Entity>> {
+	let player_entity = *gs.ecs.fetch::<Entity>();
+	let stacks = stacked_backpack(&gs.ecs, player_entity);
+	multi_select_menu(gs, ctx, " Drop Which Items? ", &stacks, selection)
+}
+
+pub fn pickup_item_menu (gs: &mut State, ctx: &mut Rltk, selection: usize) -> ListMenuResult<Vec<Entity>> {
+	let stacks = {
+		let player_pos = gs.ecs.fetch::<Point>();
+		let entities = gs.ecs.entities();
+		let items = gs.ecs.read_storage::<Item>();
+		let positions = gs.ecs.read_storage::<Position>();
+
+		let underfoot = (&entities, &items, &positions).join()
+			.filter(|(_, _, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+			.map(|(e, _, _)| e)
+			.collect();
+		stack_by_name(&gs.ecs, underfoot)
+	};
+	multi_select_menu(gs, ctx, " Pick Up Which Items? ", &stacks, selection)
+}
+
+/// A list of stacks where each letter, or RIGHT on the one under the cursor,
+/// marks one more item from its stack (wrapping back round to none) and LEFT
+/// one fewer. ENTER takes everything marked, or the whole stack under the
+/// cursor when nothing is.
+fn multi_select_menu (
+	gs: &mut State,
+	ctx: &mut Rltk,
+	title: &str,
+	stacks: &[(String, Vec<Entity>)],
+	selection: usize,
+) -> ListMenuResult<Vec<Entity>> {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let mut marks = gs.ecs.write_resource::<ItemSelection>();
+	let count = stacks.len();
+	let selection = selection.min(count.saturating_sub(1));
+
+	let mut y = middle_row(ctx) - (count / 2) as i32;
+	draw.draw_box(
+		Rect::with_size(15, y - 2, 40, (count + 3) as i32),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y - 2),
+		title,
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y + count as i32 + 1),
+		" ENTER to confirm, ESCAPE to cancel ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	for (j, (name, items)) in stacks.iter().enumerate() {
+		let marked = items.iter().filter(|e| marks.marked.contains(e)).count();
+		let colour =
+			if j == selection { palette.highlight }
+			else if marked > 0 { palette.value }
+			else { palette.text };
+
+		draw.print_color(Point::new(17, y), &format!("({})", (97 + j as u8) as char), ColorPair::new(colour, palette.background));
+
+		let label =
+			if items.len() > 1 { format!("{} x{}", name, items.len()) }
+			else { name.to_string() };
+		draw.print_color(Point::new(21, y), &label, ColorPair::new(colour, palette.background));
+
+		if marked > 0 {
+			let mark =
+				if items.len() > 1 { format!("[{}/{}]", marked, items.len()) }
+				else { "[*]".to_string() };
+			draw.print_color(Point::new(48, y), &mark, ColorPair::new(colour, palette.background));
+		}
+
+		y += 1;
+	}
+
+	let Some(key) = ctx.key else { return ListMenuResult::NoResponse };
+	if let Some(selection) = moved_cursor(key, selection, count) {
+		return ListMenuResult::Select { selection };
+	}
+
+	match key {
+		VirtualKeyCode::Escape => {
+			marks.marked.clear();
+			ListMenuResult::Cancel
+		},
+		VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+			if !marks.marked.is_empty() { return ListMenuResult::Selected(marks.marked.drain(..).collect()); }
+			match stacks.get(selection) {
+				Some((_, items)) => ListMenuResult::Selected(items.clone()),
+				None => ListMenuResult::NoResponse,
+			}
+		},
+		VirtualKeyCode::Right => {
+			if let Some((_, items)) = stacks.get(selection) { marks.mark_one_more(items); }
+			ListMenuResult::NoResponse
+		},
+		VirtualKeyCode::Left => {
+			if let Some((_, items)) = stacks.get(selection) { marks.unmark_one(items); }
+			ListMenuResult::NoResponse
+		},
+		_ => {
+			if let Some(row) = picked_row(key, selection, count) { marks.mark_one_more(&stacks[row].1); }
+			ListMenuResult::NoResponse
+		},
+	}
+}
+
+// Item Actions
+// =========================================================================
+
+/// Everything that can be done with an item from the inventory
+fn item_actions (ecs: &World, item: Entity) -> Vec<ItemAction> {
+	let mut actions = Vec::new();
+
+	if ecs.read_storage::<Equippable>().get(item).is_some() { actions.push(ItemAction::Equip); }
+	else { actions.push(ItemAction::Use); }
+
+	actions.push(ItemAction::Throw);
+	actions.push(ItemAction::Drop);
+	actions.push(ItemAction::Examine);
+	actions
+}
+
+pub fn item_action_menu (gs: &mut State, ctx: &mut Rltk, item: Entity, selection: usize) -> ListMenuResult<ItemAction> {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let actions = item_actions(&gs.ecs, item);
+	let title = item_label(&gs.ecs, item).map_or("Item".to_string(), |name| format!(" {} ", name));
+	let count = actions.len();
+	let selection = selection.min(count - 1);
+
+	let mut y = middle_row(ctx) - (count / 2) as i32;
+	draw.draw_box(
+		Rect::with_size(15, y - 2, 32, (count + 3) as i32),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(Point::new(18, y - 2), &title, ColorPair::new(palette.title, palette.background));
+	draw.print_color(
+		Point::new(18, y + count as i32 + 1),
+		" ESCAPE to go back ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	for (j, action) in actions.iter().enumerate() {
+		let fg = if j == selection { palette.highlight } else { palette.text };
+		draw.print_color(
+			Point::new(17, y),
+			&format!("({}) {}", (97 + j as u8) as char, action.label()),
+			ColorPair::new(fg, palette.background),
+		);
+		y += 1;
+	}
+
+	list_menu_input(ctx.key, selection, &actions)
+}
+
+/// The item's description and properties, until a key is pressed
+pub fn examine_item (gs: &State, ctx: &mut Rltk, item: Entity) -> ItemMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let names = gs.ecs.read_storage::<Name>();
+	let descriptions = gs.ecs.read_storage::<Description>();
+
+	let mut lines : Vec<TooltipLine> = Vec::new();
+	if let Some(name) = names.get(item) {
+		lines.extend(tooltip_lines(&gs.ecs, item, name));
+	}
+	if let Some(description) = descriptions.get(item) {
+		lines.push(TooltipLine::Text(palette.background, String::new()));
+		for line in wrap_text(&description.text, 40) {
+			lines.push(TooltipLine::Text(palette.text, line));
+		}
+	}
+
+	let y = middle_row(ctx) - lines.len() as i32 / 2;
+	draw.draw_box(
+		Rect::with_size(15, y - 2, 46, lines.len() as i32 + 3),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y + lines.len() as i32 + 1),
+		" Press any key ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	for (i, line) in lines.iter().enumerate() {
+		match line {
+			TooltipLine::Text(colour, text) => {
+				draw.print_color(Point::new(18, y + i as i32), text, ColorPair::new(*colour, palette.background));
+			},
+			TooltipLine::Bar(value, max, colour) => {
+				draw.bar_horizontal(
+					Point::new(18, y + i as i32),
+					40,
+					*value,
+					*max,
+					ColorPair::new(*colour, palette.faint),
+				);
+			},
+		}
+	}
+
+	match ctx.key {
+		None => ItemMenuResult::NoResponse,
+		Some(_) => ItemMenuResult::Cancel,
+	}
+}
+
+// Remove Item
+// =========================================================================
+
+pub fn remove_item_menu (gs: &mut State, ctx: &mut Rltk, selection: usize) -> ListMenuResult<Entity> {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let player_entity = gs.ecs.fetch::<Entity>();
+	let names = gs.ecs.read_storage::<Name>();
+	let equipped = gs.ecs.read_storage::<Equipped>();
+	let entities = gs.ecs.entities();
+
+	let inventory = (&equipped, &names).join()
+		.filter(|item| item.0.owner == *player_entity);
+	let count = inventory.count();
+	let selection = selection.min(count.saturating_sub(1));
+
+	let mut y = middle_row(ctx) - (count / 2) as i32;
+	draw.draw_box(
+		Rect::with_size(15, y - 2, 31, count as i32 + 3),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y - 2),
+		" Remove which item? ",
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(18, y + count as i32 + 1),
+		" ESCAPE to cancel ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	let mut equippable : Vec<Entity> = Vec::new();
+	let inventory_items = (&entities, &equipped, &names).join()
+		.filter(|item| item.1.owner == *player_entity);
+
+	for (j, (entity, _, name)) in inventory_items.enumerate() {
+		let fg = if j == selection { palette.highlight } else { palette.text };
+		draw.print_color(Point::new(17, y), &format!("({})", (97 + j as u8) as char), ColorPair::new(fg, palette.background));
+		draw.print_color(Point::new(21, y), &name.name, ColorPair::new(fg, palette.background));
+		equippable.push(entity);
+		y += 1;
+	}
+
+	list_menu_input(ctx.key, selection, &equippable)
+}
+
+// Ranged Targeting
+// =========================================================================
+
+/// Where the keyboard targeting cursor is, and what was targeted last time so
+/// the cursor can start back on it. The cursor jumps to the mouse whenever it
+/// moves.
+#[derive(Default)]
+pub struct Targeting {
+	pub cursor      : Option<Point>,
+	pub last_target : Option<Entity>,
+	pub last_mouse  : (i32, i32),
+	/// Where each enemy in the side panel was listed last frame, for
+	/// clicking on
+	pub panel_rows  : Vec<(Rect, Entity)>,
+}
+
+/// Picks a tile within `range` to use something on, showing the area it'll
+/// hit if it has a blast `radius`
+pub fn ranged_target (gs: &mut State, ctx: &mut Rltk, range: i32, radius: Option<i32>)
+	-> (ItemMenuResult, Option<Point>)
+{
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let player_entity = gs.ecs.fetch::<Entity>();
+	let player_pos = gs.ecs.fetch::<Point>();
+	let viewsheds = gs.ecs.read_storage::<Viewshed>();
+	let mut targeting = gs.ecs.fetch_mut::<Targeting>();
+	let viewport = Viewport::for_player(&gs.ecs, ctx);
+
+	draw.print_color(
+		Point::new(5, 0),
+		" Select Target: TAB to cycle, ENTER to confirm, ESCAPE to cancel ",
+		ColorPair::new(palette.value, palette.background),
+	);
+
+	// Highlight available target cells
+	let mut available_cells = Vec::new();
+	let visible = viewsheds.get(*player_entity);
+	if let Some(visible) = visible {
+		for idx in visible.visible_tiles.iter() {
+			let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
+			if distance <= range as f32 {
+				if let Some((x, y)) = viewport.to_screen(idx.x, idx.y) {
+					draw.set_bg(Point::new(x, y), palette.target);
+				}
+				available_cells.push(idx);
+			}
+		}
+	} else {
+		return (ItemMenuResult::Cancel, None);
+	}
+
+	// Everything worth shooting at, nearest first
+	let mut targets : Vec<(Entity, Point, f32)> = Vec::new();
+	{
+		let entities = gs.ecs.entities();
+		let positions = gs.ecs.read_storage::<Position>();
+		let combat_stats = gs.ecs.read_storage::<CombatStats>();
+		let allies = gs.ecs.read_storage::<Ally>();
+
+		for (entity, pos, _stats, _ally) in (&entities, &positions, &combat_stats, !&allies).join() {
+			if entity == *player_entity { continue; }
+
+			let point = Point::new(pos.x, pos.y);
+			if !available_cells.contains(&&point) { continue; }
+
+			targets.push((entity, point, DistanceAlg::Pythagoras.distance2d(*player_pos, point)));
+		}
+	}
+	targets.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+	// Start on whatever was targeted last time, if it's still about
+	let mut cursor = match targeting.cursor {
+		Some(cursor) => cursor,
+		None => {
+			let last = targeting.last_target
+				.and_then(|last| targets.iter().find(|t| t.0 == last));
+			last.or(targets.first()).map_or(*player_pos, |t| t.1)
+		},
+	};
+
+	let mouse_pos = ctx.mouse_pos();
+	let mouse_tile = viewport.to_map(mouse_pos.0, mouse_pos.1);
+	if targeting.cursor.is_some() && mouse_pos != targeting.last_mouse {
+		if let Some(tile) = mouse_tile { cursor = tile; }
+	}
+	targeting.last_mouse = mouse_pos;
+
+	// Show what the blast would catch
+	let cursor_valid = available_cells.contains(&&cursor);
+	if let (Some(radius), true) = (radius, cursor_valid) {
+		for tile in blast_area(&gs.ecs.fetch::<Map>(), &gs.ecs.fetch::<SpatialIndex>(), cursor, radius) {
+			if let Some((x, y)) = viewport.to_screen(tile.x, tile.y) {
+				draw.set_bg(Point::new(x, y), palette.blast);
+			}
+		}
+	}
+
+	// Draw keyboard cursor
+	if let Some((x, y)) = viewport.to_screen(cursor.x, cursor.y) {
+		draw.set_bg(
+			Point::new(x, y),
+			if cursor_valid { palette.highlight } else { palette.danger },
+		);
+	}
+
+	let nudged = |dx: i32, dy: i32| Point::new(cursor.x + dx, cursor.y + dy);
+	let mut next_cursor = cursor;
+	let mut result = (ItemMenuResult::NoResponse, None);
+
+	if let Some(key) = ctx.key {
+		let command = gs.ecs.fetch::<KeyBindings>().command_for(key);
+		match key {
+			VirtualKeyCode::Escape => result = (ItemMenuResult::Cancel, None),
+			VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+				if cursor_valid { result = (ItemMenuResult::Selected, Some(cursor)); }
+			},
+			VirtualKeyCode::Tab => {
+				if !targets.is_empty() {
+					let current = targets.iter().position(|t| t.1 == cursor);
+					let next = current.map_or(0, |i| (i + 1) % targets.len());
+					next_cursor = targets[next].1;
+				}
+			},
+			_ => if let Some(Command::Move { dx, dy }) = command {
+				next_cursor = nudged(dx, dy);
+			},
+		}
+	}
+
+	// Draw mouse cursor
+	let mut valid_target = false;
+
+	for idx in available_cells.iter() {
+		if Some(**idx) == mouse_tile {
+			valid_target = true;
+		}
+	}
+
+	// Enemies listed in the side panel can be clicked on too
+	let panel_target = targeting.panel_rows.iter()
+		.find(|(row, _)| row.point_in_rect(Point::new(mouse_pos.0, mouse_pos.1)))
+		.and_then(|(_, enemy)| targets.iter().find(|t| t.0 == *enemy));
 
-	if let RunState::MainMenu { menu_selection: selection } = *runstate {
-		ctx.print_color_centered(
-			24,
-			if selection == MainMenuSelection::NewGame
-				{ RGB::named(rltk::CYAN) } else
-				{ RGB::named(rltk::WHITE) },
-			RGB::named(rltk::BLACK),
-			"New Game",
-		);
-		ctx.print_color_centered(
-			26,
-			if selection == MainMenuSelection::LoadGame
-				{ RGB::named(rltk::CYAN) } else
-				{ RGB::named(if save_exists { rltk::WHITE } else { rltk::DARK_GRAY } ) },
-			RGB::named(rltk::BLACK),
-			"Continue",
+	if valid_target {
+		draw.set_bg(
+			Point::new(mouse_pos.0, mouse_pos.1),
+			palette.highlight,
 		);
-		ctx.print_color_centered(
-			28,
-			if selection == MainMenuSelection::Quit
-				{ RGB::named(rltk::CYAN) } else
-				{ RGB::named(rltk::WHITE) },
-			RGB::named(rltk::BLACK),
-			"Quit",
+
+		if ctx.left_click {
+			result = (ItemMenuResult::Selected, mouse_tile);
+		}
+	} else if let Some(target) = panel_target {
+		if ctx.left_click {
+			result = (ItemMenuResult::Selected, Some(target.1));
+		}
+	} else {
+		draw.set_bg(
+			Point::new(mouse_pos.0, mouse_pos.1),
+			palette.danger,
 		);
 
-		match ctx.key {
-			None => return MainMenuResult::NoSelection { selected: selection },
-			Some(key) => {
-				match key {
-					VirtualKeyCode::Escape => {
-						return MainMenuResult::NoSelection {
-							selected: MainMenuSelection::Quit,
-						};
-					}
-					VirtualKeyCode::Up | VirtualKeyCode::W => {
-						let mut new_selection;
-						match selection {
-							MainMenuSelection::NewGame => new_selection = MainMenuSelection::Quit,
-							MainMenuSelection::LoadGame => new_selection = MainMenuSelection::NewGame,
-							MainMenuSelection::Quit => new_selection = MainMenuSelection::LoadGame,
-						}
-						if new_selection == MainMenuSelection::LoadGame && !save_exists {
-							new_selection = MainMenuSelection::NewGame;
-						}
-						return MainMenuResult::NoSelection { selected: new_selection };
-					}
-					VirtualKeyCode::Down | VirtualKeyCode::S => {
-						let mut new_selection;
-						match selection {
-							MainMenuSelection::NewGame => new_selection = MainMenuSelection::LoadGame,
-							MainMenuSelection::LoadGame => new_selection = MainMenuSelection::Quit,
-							MainMenuSelection::Quit => new_selection = MainMenuSelection::NewGame,
-						}
-						if new_selection == MainMenuSelection::LoadGame && !save_exists {
-							new_selection = MainMenuSelection::Quit;
-						}
-						return MainMenuResult::NoSelection { selected: new_selection };
-					}
-					VirtualKeyCode::Return => {
-						return MainMenuResult::Selected { selected: selection };
-					}
-					_ => return MainMenuResult::NoSelection { selected: selection },
+		if ctx.left_click {
+			result = (ItemMenuResult::Cancel, None);
+		}
+	}
+
+	match result.0 {
+		ItemMenuResult::NoResponse => targeting.cursor = Some(next_cursor),
+		ItemMenuResult::Cancel => targeting.cursor = None,
+		ItemMenuResult::Selected => {
+			targeting.cursor = None;
+			if let Some(target) = result.1 {
+				if let Some(hit) = targets.iter().find(|t| t.1 == target) {
+					targeting.last_target = Some(hit.0);
 				}
 			}
-		}
+		},
 	}
 
-	return MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame };
+	result
 }
 
-// Game Interface
+// Examine
 // =========================================================================
 
-pub fn draw_ui (ecs: &World, ctx: &mut Rltk) {
-	// Border
-	ctx.draw_box(
-		0, 43,
-		79, 6,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
+/// Moves a cursor about the map with the movement keys, describing whatever's
+/// under it: what's there now if it's in view, or what was last seen there
+pub fn examine (gs: &State, ctx: &mut Rltk, x: i32, y: i32) -> ExamineResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let map = gs.ecs.fetch::<Map>();
+
+	draw.print_color(
+		Point::new(5, 0),
+		" Examine: move with the movement keys, ESCAPE to finish ",
+		ColorPair::new(palette.value, palette.background),
 	);
-
-	// Depth
-	let map = ecs.fetch::<Map>();
-	let depth = format!(" Depth: {} ", map.depth);
-	ctx.print_color(
-		4, 43,
-		RGB::named(rltk::YELLOW),
-		RGB::named(rltk::BLACK),
-		&depth,
+	let viewport = Viewport::for_player(&gs.ecs, ctx);
+	let (cursor_x, cursor_y) = viewport.to_screen(x, y).unwrap_or((0, 0));
+	draw.set_bg(Point::new(cursor_x, cursor_y), palette.highlight);
+
+	let lines = describe_tile(&gs.ecs, &map, x, y);
+	let panel_x = if cursor_x > viewport.width / 2 { 1 } else { viewport.width - 33 };
+	let panel_y = if cursor_y > viewport.height / 2 { 2 } else { viewport.height - 2 - lines.len() as i32 };
+	draw.draw_box(
+		Rect::with_size(panel_x, panel_y, 31, lines.len() as i32 + 1),
+		ColorPair::new(palette.text, palette.background),
 	);
-
-	// Player Health
-	let combat_stats = ecs.read_storage::<CombatStats>();
-	let players = ecs.read_storage::<Player>();
-	let hunger = ecs.read_storage::<HungerClock>();
-	for (_player, stats, hc) in (&players, &combat_stats, &hunger).join() {
-		let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
-		ctx.print_color(
-			17, 43,
-			RGB::named(rltk::YELLOW),
-			RGB::named(rltk::BLACK),
-			&health,
+	for (i, (colour, line)) in lines.iter().enumerate() {
+		draw.print_color(
+			Point::new(panel_x + 2, panel_y + 1 + i as i32),
+			line,
+			ColorPair::new(*colour, palette.background),
 		);
+	}
 
-		ctx.draw_bar_horizontal(
-			34, 43, 28,
-			stats.hp, stats.max_hp,
-			RGB::named(rltk::RED),
-			RGB::named(rltk::DARK_GRAY),
-		);
+	let moved = |dx: i32, dy: i32| ExamineResult::Move {
+		x: (x + dx).clamp(viewport.x, viewport.x + viewport.width - 1),
+		y: (y + dy).clamp(viewport.y, viewport.y + viewport.height - 1),
+	};
 
-		let mut fg = RGB::new();
-		let mut msg = "";
+	match ctx.key {
+		None => ExamineResult::NoResponse,
+		Some(VirtualKeyCode::Escape) => ExamineResult::Close,
+		Some(key) => match gs.ecs.fetch::<KeyBindings>().command_for(key) {
+			Some(Command::Examine) => ExamineResult::Close,
+			Some(Command::Move { dx, dy }) => moved(dx, dy),
+			_ => ExamineResult::NoResponse,
+		},
+	}
+}
 
-		match hc.state {
-			HungerState::WellFed => {
-				fg = RGB::named(rltk::LAWN_GREEN);
-				msg = " Well Fed ";
-			}
-			HungerState::Normal => {}
-			HungerState::Hungry => {
-				fg = RGB::named(rltk::ORANGE);
-				msg = " Hungry ";
-			}
-			HungerState::Starving => {
-				fg = RGB::named(rltk::RED3);
-				msg = " Starving ";
-			}
-		}
+/// The lines of the examine panel for the given tile
+fn describe_tile (ecs: &World, map: &Map, x: i32, y: i32) -> Vec<(RGB, String)> {
+	let palette = *ecs.fetch::<Palette>();
+	let idx = map.xy_idx(x, y);
+	let mut lines : Vec<(RGB, String)> = Vec::new();
 
-		if msg != "" {
-			ctx.print_color(
-				66, 43,
-				fg, RGB::named(rltk::BLACK),
-				msg
-			);
-		}
+	if !map.is_revealed(idx) {
+		lines.push((palette.text_dim, "Unexplored".to_string()));
+		return lines;
 	}
 
-	// Log
-	let log = ecs.fetch::<GameLog>();
-	let mut y = 44;
-	for s in log.entries.iter().rev() {
-		if y < 49 { ctx.print(2, y, s) }
-		y += 1;
+	if !map.is_visible(idx) {
+		lines.push((palette.text_dim, "You remember:".to_string()));
+		lines.push((palette.text, tile_name(map.tiles[idx]).to_string()));
+		for name in map.remembered.get(&idx).iter().flat_map(|names| names.iter()) {
+			lines.push((palette.text, name.to_string()));
+		}
+		return lines;
 	}
 
-	// Tooltips
-	draw_tooltips(ecs, ctx);
-}
-
-fn draw_tooltips (ecs: &World, ctx: &mut Rltk) {
-	let map = ecs.fetch::<Map>();
 	let names = ecs.read_storage::<Name>();
 	let positions = ecs.read_storage::<Position>();
 	let hidden = ecs.read_storage::<Hidden>();
+	let descriptions = ecs.read_storage::<Description>();
+	let combat_stats = ecs.read_storage::<CombatStats>();
+	let entities = ecs.entities();
 
-	let mouse_pos = ctx.mouse_pos();
-
-	if mouse_pos.0 >= map.width || mouse_pos.1 >= map.width { return; }
+	for (entity, name, pos, _hidden) in (&entities, &names, &positions, !&hidden).join() {
+		if pos.x != x || pos.y != y { continue; }
 
-	let mut tooltip : Vec<String> = Vec::new();
-	for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
-		let idx = map.xy_idx(position.x, position.y);
+		let heading = match combat_stats.get(entity) {
+			Some(stats) => format!("{} ({})", &name.name, wound_description(stats)),
+			None => name.name.to_string(),
+		};
+		lines.push((palette.text, heading));
 
-		if position.x == mouse_pos.0
-		&& position.y == mouse_pos.1
-		&& map.visible_tiles[idx] {
-			tooltip.push(name.name.to_string());
+		if let Some(description) = descriptions.get(entity) {
+			for line in wrap_text(&description.text, 28) {
+				lines.push((palette.text_dim, line));
+			}
 		}
 	}
 
-	if tooltip.is_empty() { return; }
-
-	let mut width : i32 = 0;
-	for s in tooltip.iter() {
-		if width < s.len() as i32 {
-			width = s.len() as i32;
-		}
+	if lines.is_empty() {
+		lines.push((palette.text, tile_name(map.tiles[idx]).to_string()));
 	}
-	width += 3;
 
-	if mouse_pos.0 > 40 {
-		let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
-		let left_x = mouse_pos.0 - width;
-		let mut y = mouse_pos.1;
-
-		for s in tooltip.iter() {
-			ctx.print_color(
-				left_x, y,
-				RGB::named(rltk::BLACK),
-				RGB::named(rltk::GREY),
-				s,
-			);
-
-			let padding = (width - s.len() as i32) - 1;
-			for i in 0..padding {
-				ctx.print_color(
-					arrow_pos.x - i,
-					y,
-					RGB::named(rltk::BLACK),
-					RGB::named(rltk::GREY),
-					&" ".to_string(),
-				);
-			}
-			y += 1;
-		}
-		ctx.print_color(
-			arrow_pos.x,
-			arrow_pos.y,
-			RGB::named(rltk::BLACK),
-			RGB::named(rltk::GREY),
-			&"->".to_string(),
-		);
-	} else {
-		let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
-		let left_x = mouse_pos.0 + 3;
-		let mut y = mouse_pos.1;
-
-		for s in tooltip.iter() {
-			ctx.print_color(
-				left_x + 1, y,
-				RGB::named(rltk::BLACK),
-				RGB::named(rltk::GREY),
-				s,
-			);
+	lines
+}
 
-			let padding = (width - s.len() as i32) - 1;
-			for i in 0..padding {
-				ctx.print_color(
-					arrow_pos.x + 1 + i,
-					y,
-					RGB::named(rltk::BLACK),
-					RGB::named(rltk::GREY),
-					&" ".to_string(),
-				);
-			}
-			y += 1;
-		}
-		ctx.print_color(
-			arrow_pos.x,
-			arrow_pos.y,
-			RGB::named(rltk::BLACK),
-			RGB::named(rltk::GREY),
-			&"<-".to_string(),
-		);
+fn tile_name (tile: TileType) -> &'static str {
+	match tile {
+		TileType::Floor => "Floor",
+		TileType::Wall => "Wall",
+		TileType::DownStairs => "Stairs leading down",
+		TileType::Water => "Shallow water",
+		TileType::Void | TileType::Placeholder => "Nothing",
 	}
 }
 
-// Inventory
-// =========================================================================
+/// A rough idea of how hurt something is, without giving away numbers
+fn wound_description (stats: &CombatStats) -> &'static str {
+	let health = stats.hp * 100 / i32::max(1, stats.max_hp);
 
-pub fn show_inventory (gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-	let player_entity = gs.ecs.fetch::<Entity>();
-	let names = gs.ecs.read_storage::<Name>();
-	let backpack = gs.ecs.read_storage::<InBackpack>();
-	let entities = gs.ecs.entities();
+	if health >= 100 { "unhurt" }
+	else if health >= 66 { "lightly wounded" }
+	else if health >= 33 { "badly wounded" }
+	else { "nearly dead" }
+}
 
-	let inventory = (&backpack, &names).join()
-		.filter(|item| item.0.owner == *player_entity);
-	let count = inventory.count();
+// Help
+// =========================================================================
 
-	let mut y = (25 - (count / 2)) as i32;
-	ctx.draw_box(
-		15, y - 2, 32, (count + 3) as i32,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
+/// What the symbols on the map mean
+const LEGEND : &[(char, &str)] = &[
+	('@', "You"),
+	('r', "Creatures, red ones are hostile"),
+	('▼', "Stairs down"),
+	('+', "Door"),
+	('¡', "Potion"),
+	('~', "Scroll"),
+	('%', "Food"),
+	('►', "Weapon"),
+	('/', "Weapon"),
+	('(', "Shield"),
+	('[', "Shield"),
+	('^', "Trap"),
+];
+
+/// Every key binding and what the symbols mean, until a key is pressed
+pub fn show_help (gs: &State, ctx: &mut Rltk) -> ItemMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let bindings = gs.ecs.fetch::<KeyBindings>();
+
+	let (width, height) = screen_size(ctx);
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
 	);
-	ctx.print_color(
-		18, y - 2,
-		RGB::named(rltk::GOLD),
-		RGB::named(rltk::BLACK),
-		" Inventory "
+	draw.print_color(
+		Point::new(3, 0),
+		" Help ",
+		ColorPair::new(palette.title, palette.background),
 	);
-	ctx.print_color(
-		18, y + count as i32 + 1,
-		RGB::named(rltk::GREY),
-		RGB::named(rltk::BLACK),
-		" ESCAPE to cancel "
+	draw.print_color(
+		Point::new(3, height - 1),
+		" Press any key to close ",
+		ColorPair::new(palette.text_dim, palette.background),
 	);
 
-	let mut equippable : Vec<Entity> = Vec::new();
-	let mut j = 0;
-	let inventory_items = (&entities, &backpack, &names).join()
-		.filter(|item| item.1.owner == *player_entity);
-
-	for (entity, _, name) in inventory_items {
-		ctx.set(
-			17, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437('('),
-		);
-		ctx.set(
-			18, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			97 + j as rltk::FontCharType,
-		);
-		ctx.set(
-			19, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437(')'),
-		);
+	draw.print_color(Point::new(3, 2), "Keys", ColorPair::new(palette.value, palette.background));
+	for (i, command) in COMMANDS.iter().enumerate() {
+		let y = 4 + i as i32;
+		draw.print_color(Point::new(3, y), &bindings.describe_keys(*command), ColorPair::new(palette.highlight, palette.background));
+		draw.print(Point::new(18, y), command.description());
+	}
 
-		ctx.print(21, y, &name.name.to_string());
-		equippable.push(entity);
-		y += 1;
-		j += 1;
+	draw.print_color(Point::new(46, 2), "Symbols", ColorPair::new(palette.value, palette.background));
+	for (i, (glyph, meaning)) in LEGEND.iter().enumerate() {
+		let y = 4 + i as i32;
+		draw.set(Point::new(46, y), ColorPair::new(palette.highlight, palette.background), rltk::to_cp437(*glyph));
+		draw.print(Point::new(50, y), meaning);
 	}
 
 	match ctx.key {
-		None => (ItemMenuResult::NoResponse, None),
-		Some(key) => {
-			match key {
-				VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-				_ => {
-					let selection = rltk::letter_to_option(key);
-					if selection > -1 && selection < count as i32 {
-						return (
-							ItemMenuResult::Selected,
-							Some(equippable[selection as usize])
-						);
-					}
-					return (ItemMenuResult::NoResponse, None);
-				},
-			}
-		}
+		None => ItemMenuResult::NoResponse,
+		Some(_) => ItemMenuResult::Cancel,
 	}
 }
 
-// Drop Item Menu
+// Records
 // =========================================================================
 
-pub fn drop_item_menu (gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-	let player_entity = gs.ecs.fetch::<Entity>();
-	let names = gs.ecs.read_storage::<Name>();
-	let backpack = gs.ecs.read_storage::<InBackpack>();
-	let entities = gs.ecs.entities();
+/// Everything the records hold across every run: how they went, what did
+/// the player in, what they killed and what they used most
+pub fn show_records (gs: &State, ctx: &mut Rltk) -> ItemMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let records = Records::load();
+	let (width, height) = screen_size(ctx);
+
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, 0),
+		" Records ",
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, height - 1),
+		" Press any key to close ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
 
-	let inventory = (&backpack, &names).join()
-		.filter(|item| item.0.owner == *player_entity);
-	let count = inventory.count();
+	let overall = [
+		("Runs", records.runs),
+		("Victories", records.victories),
+		("Deepest depth", records.deepest),
+	];
+	for (i, (label, value)) in overall.iter().enumerate() {
+		let y = 3 + i as i32;
+		draw.print_color(Point::new(3, y), label, ColorPair::new(palette.text_dim, palette.background));
+		draw.print_color(Point::new(27, y), &value.to_string(), ColorPair::new(palette.value, palette.background));
+	}
 
-	let mut y = (25 - (count / 2)) as i32;
-	ctx.draw_box(
-		15, y - 2, 32, (count + 3) as i32,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
+	// Deaths and items share the left side under the overall numbers, and
+	// kills get the right to themselves
+	let rows = i32::max(0, (height - 15) / 2) as usize;
+	draw_tally(&mut draw, &palette, "Deaths", &records.deaths, 3, 8, rows);
+	draw_tally(&mut draw, &palette, "Favourite items", &records.items, 3, 11 + rows as i32, rows);
+	draw_tally(&mut draw, &palette, "Kills", &records.kills, width / 2, 3, i32::max(0, height - 6) as usize);
+
+	match ctx.key {
+		None => ItemMenuResult::NoResponse,
+		Some(_) => ItemMenuResult::Cancel,
+	}
+}
+
+/// The best runs so far, with the one that's just ended picked out if it
+/// made the table
+pub fn show_high_scores (gs: &State, ctx: &mut Rltk, highlight: Option<usize>) -> ItemMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let records = Records::load();
+	let (width, height) = screen_size(ctx);
+
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
 	);
-	ctx.print_color(
-		18, y - 2,
-		RGB::named(rltk::GOLD),
-		RGB::named(rltk::BLACK),
-		" Drop Which Item? "
+	draw.print_color(
+		Point::new(3, 0),
+		" High Scores ",
+		ColorPair::new(palette.title, palette.background),
 	);
-	ctx.print_color(
-		18, y + count as i32 + 1,
-		RGB::named(rltk::GREY),
-		RGB::named(rltk::BLACK),
-		" ESCAPE to cancel "
+	draw.print_color(
+		Point::new(3, height - 1),
+		" Press any key to close ",
+		ColorPair::new(palette.text_dim, palette.background),
 	);
 
-	let mut equippable : Vec<Entity> = Vec::new();
-	let mut j = 0;
-	let inventory_items = (&entities, &backpack, &names).join()
-		.filter(|item| item.1.owner == *player_entity);
+	let columns = [(3, "#"), (7, "Score"), (15, "Depth"), (22, "Kills"), (29, "Turns"), (37, "Fate")];
+	for (x, heading) in columns.iter() {
+		draw.print_color(Point::new(*x, 3), heading, ColorPair::new(palette.value, palette.background));
+	}
 
-	for (entity, _, name) in inventory_items {
-		ctx.set(
-			17, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437('('),
-		);
-		ctx.set(
-			18, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			97 + j as rltk::FontCharType,
-		);
-		ctx.set(
-			19, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437(')'),
-		);
+	if records.high_scores.is_empty() {
+		draw.print_color(Point::new(3, 5), "No runs yet", ColorPair::new(palette.faint, palette.background));
+	}
+	for (i, high) in records.high_scores.iter().take(HIGH_SCORES).enumerate() {
+		let y = 5 + i as i32 * 2;
+		let fg = if highlight == Some(i) { palette.highlight } else { palette.text };
+		let row = [
+			(i + 1).to_string(),
+			high.score.to_string(),
+			high.depth.to_string(),
+			high.kills.to_string(),
+			high.turns.to_string(),
+			truncate(&high.fate(), width - 41),
+		];
+		for ((x, _), text) in columns.iter().zip(row.iter()) {
+			draw.print_color(Point::new(*x, y), text, ColorPair::new(fg, palette.background));
+		}
+	}
 
-		ctx.print(21, y, &name.name.to_string());
-		equippable.push(entity);
-		y += 1;
-		j += 1;
+	if let Some(rank) = highlight {
+		draw.print_color_centered(
+			6 + HIGH_SCORES as i32 * 2,
+			&format!("A new high score, in at #{}!", rank + 1),
+			ColorPair::new(palette.title, palette.background),
+		);
 	}
 
 	match ctx.key {
-		None => (ItemMenuResult::NoResponse, None),
-		Some(key) => {
-			match key {
-				VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-				_ => {
-					let selection = rltk::letter_to_option(key);
-					if selection > -1 && selection < count as i32 {
-						return (
-							ItemMenuResult::Selected,
-							Some(equippable[selection as usize])
-						);
-					}
-					return (ItemMenuResult::NoResponse, None);
-				},
-			}
-		}
+		None => ItemMenuResult::NoResponse,
+		Some(_) => ItemMenuResult::Cancel,
 	}
 }
 
-// Remove Item
+/// A titled list of the biggest counts in a tally, as many as fit in `rows`
+fn draw_tally (draw: &mut DrawBatch, palette: &Palette, title: &str, tally: &BTreeMap<String, i32>, x: i32, y: i32, rows: usize) {
+	draw.print_color(Point::new(x, y), title, ColorPair::new(palette.value, palette.background));
+	if tally.is_empty() {
+		draw.print_color(Point::new(x + 2, y + 2), "None yet", ColorPair::new(palette.faint, palette.background));
+		return;
+	}
+	for (i, (name, count)) in most(tally).iter().take(rows).enumerate() {
+		let row = y + 2 + i as i32;
+		draw.print_color(Point::new(x + 2, row), &truncate(name, 20), ColorPair::new(palette.text, palette.background));
+		draw.print_color(Point::new(x + 24, row), &count.to_string(), ColorPair::new(palette.value, palette.background));
+	}
+}
+
+// Map Overview
 // =========================================================================
 
-pub fn remove_item_menu (gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-	let player_entity = gs.ecs.fetch::<Entity>();
+/// The whole of the explored level, shrunk to fit the screen. Where several
+/// tiles share a cell the most telling one wins: the player, then the stairs,
+/// then any item seen lying there, then walls over floor.
+pub fn map_overview (gs: &State, ctx: &mut Rltk) -> ItemMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let map = gs.ecs.fetch::<Map>();
+	let player_pos = *gs.ecs.fetch::<Point>();
+	let (width, height) = screen_size(ctx);
+
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, 0),
+		&format!(" Depth {} ", map.depth),
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, height - 1),
+		" Press any key to close ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	// How many map tiles go in each cell, each way
+	let (room_x, room_y) = (width - 2, height - 4);
+	let scale = i32::max(1, i32::max(
+		(map.width + room_x - 1) / room_x,
+		(map.height + room_y - 1) / room_y,
+	));
+	let (cells_x, cells_y) = ((map.width + scale - 1) / scale, (map.height + scale - 1) / scale);
+	let (left, top) = ((width - cells_x) / 2, 2 + (room_y - cells_y) / 2);
+
+	// Items the player's seen where they lie
+	let items = gs.ecs.read_storage::<Item>();
+	let positions = gs.ecs.read_storage::<Position>();
+	let renderables = gs.ecs.read_storage::<Renderable>();
 	let names = gs.ecs.read_storage::<Name>();
-	let equipped = gs.ecs.read_storage::<Equipped>();
-	let entities = gs.ecs.entities();
+	let seen_items : Vec<(usize, &Renderable)> = (&items, &positions, &renderables, &names).join()
+		.map(|(_, pos, render, name)| (map.xy_idx(pos.x, pos.y), render, name))
+		.filter(|(idx, _, name)| {
+			map.remembered.get(idx).map_or(false, |seen| seen.contains(&name.name))
+		})
+		.map(|(idx, render, _)| (idx, render))
+		.collect();
+
+	for cy in 0 .. cells_y {
+		for cx in 0 .. cells_x {
+			// The best thing to show so far, and how much it matters
+			let mut best : Option<(i32, rltk::FontCharType, RGB)> = None;
+
+			for y in cy * scale .. i32::min(map.height, (cy + 1) * scale) {
+				for x in cx * scale .. i32::min(map.width, (cx + 1) * scale) {
+					let idx = map.xy_idx(x, y);
+					if !map.is_revealed(idx) { continue; }
+
+					let found =
+						if player_pos.x == x && player_pos.y == y { Some((4, rltk::to_cp437('@'), palette.value)) }
+						else if map.tiles[idx] == TileType::DownStairs { Some((3, rltk::to_cp437('▼'), palette.stairs)) }
+						else if let Some((_, render)) = seen_items.iter().find(|(i, _)| *i == idx) { Some((2, render.glyph, render.fg)) }
+						else if map.tiles[idx] == TileType::Wall { Some((1, rltk::to_cp437('#'), palette.wall)) }
+						else if map.tiles[idx] == TileType::Water { Some((1, rltk::to_cp437('≈'), palette.water)) }
+						else if map.tiles[idx] == TileType::Floor { Some((0, rltk::to_cp437('.'), palette.floor)) }
+						else { None };
+
+					if let Some(found) = found {
+						if best.map_or(true, |best| found.0 > best.0) { best = Some(found); }
+					}
+				}
+			}
 
-	let inventory = (&equipped, &names).join()
-		.filter(|item| item.0.owner == *player_entity);
-	let count = inventory.count();
+			if let Some((_, glyph, fg)) = best {
+				draw.set(Point::new(left + cx, top + cy), ColorPair::new(fg, palette.background), glyph);
+			}
+		}
+	}
+
+	match ctx.key {
+		None => ItemMenuResult::NoResponse,
+		Some(_) => ItemMenuResult::Cancel,
+	}
+}
+
+// Key Bindings
+// =========================================================================
 
-	let mut y = (25 - (count / 2)) as i32;
-	ctx.draw_box(
-		15, y - 2, 31, count as i32 + 3,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
+/// Lists every command with the keys bound to it. ENTER waits for a new key
+/// to bind to the selected command, BACKSPACE unbinds it, and the bindings
+/// are saved on the way out.
+pub fn key_bindings_menu (gs: &mut State, ctx: &mut Rltk, selection: usize, rebinding: bool) -> KeyBindingsMenuResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let mut bindings = gs.ecs.fetch_mut::<KeyBindings>();
+
+	let (width, height) = screen_size(ctx);
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
 	);
-	ctx.print_color(
-		18, y - 2,
-		RGB::named(rltk::GOLD),
-		RGB::named(rltk::BLACK),
-		" Remove which item? "
+	draw.print_color(
+		Point::new(3, 0),
+		" Key Bindings ",
+		ColorPair::new(palette.title, palette.background),
 	);
-	ctx.print_color(
-		18, y + count as i32 + 1,
-		RGB::named(rltk::GREY),
-		RGB::named(rltk::BLACK),
-		" ESCAPE to cancel "
+	draw.print_color(
+		Point::new(3, height - 1),
+		if rebinding { " Press the new key, ESCAPE to cancel " }
+		else { " ENTER to add a key, BACKSPACE to clear, F5 for defaults, ESCAPE to save " },
+		ColorPair::new(palette.text_dim, palette.background),
 	);
 
-	let mut equippable : Vec<Entity> = Vec::new();
-	let mut j = 0;
-	let inventory_items = (&entities, &equipped, &names).join()
-		.filter(|item| item.1.owner == *player_entity);
-
-	for (entity, _, name) in inventory_items {
-		ctx.set(
-			17, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437('('),
-		);
-		ctx.set(
-			18, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			97 + j as rltk::FontCharType,
-		);
-		ctx.set(
-			19, y,
-			RGB::named(rltk::WHITE),
-			RGB::named(rltk::BLACK),
-			rltk::to_cp437(')'),
-		);
+	for (i, command) in COMMANDS.iter().enumerate() {
+		let y = 3 + i as i32 * 2;
+		let fg = if i == selection { palette.highlight } else { palette.text };
+		draw.print_color(Point::new(5, y), command.description(), ColorPair::new(fg, palette.background));
 
-		ctx.print(21, y, &name.name.to_string());
-		equippable.push(entity);
-		y += 1;
-		j += 1;
+		let keys = if i == selection && rebinding { "...".to_string() } else { bindings.describe_keys(*command) };
+		draw.print_color(Point::new(30, y), &keys, ColorPair::new(palette.value, palette.background));
 	}
 
-	match ctx.key {
-		None => (ItemMenuResult::NoResponse, None),
-		Some(key) => {
-			match key {
-				VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-				_ => {
-					let selection = rltk::letter_to_option(key);
-					if selection > -1 && selection < count as i32 {
-						return (ItemMenuResult::Selected, Some(equippable[selection as usize]));
-					}
+	let key = match ctx.key {
+		None => return KeyBindingsMenuResult::NoResponse,
+		Some(key) => key,
+	};
 
-					return (ItemMenuResult::NoResponse, None);
-				}
-			}
+	if rebinding {
+		if key != VirtualKeyCode::Escape {
+			bindings.bind(key, COMMANDS[selection]);
 		}
+		return KeyBindingsMenuResult::Edit { selection, rebinding: false };
+	}
+
+	match key {
+		VirtualKeyCode::Escape => {
+			settings::save(&gs.ecs.fetch::<DisplaySettings>(), &gs.ecs.fetch::<GameplaySettings>(), &bindings);
+			KeyBindingsMenuResult::Close
+		},
+		VirtualKeyCode::Up => KeyBindingsMenuResult::Edit {
+			selection: (selection + COMMANDS.len() - 1) % COMMANDS.len(),
+			rebinding: false,
+		},
+		VirtualKeyCode::Down => KeyBindingsMenuResult::Edit {
+			selection: (selection + 1) % COMMANDS.len(),
+			rebinding: false,
+		},
+		VirtualKeyCode::Return => KeyBindingsMenuResult::Edit { selection, rebinding: true },
+		VirtualKeyCode::Back | VirtualKeyCode::Delete => {
+			bindings.clear(COMMANDS[selection]);
+			KeyBindingsMenuResult::NoResponse
+		},
+		VirtualKeyCode::F5 => {
+			*bindings = KeyBindings::defaults();
+			KeyBindingsMenuResult::NoResponse
+		},
+		_ => KeyBindingsMenuResult::NoResponse,
 	}
 }
 
-// Ranged Targeting
+// Display Options
 // =========================================================================
 
-pub fn ranged_target (gs: &mut State, ctx: &mut Rltk, range: i32)
-	-> (ItemMenuResult, Option<Point>)
-{
-	let player_entity = gs.ecs.fetch::<Entity>();
-	let player_pos = gs.ecs.fetch::<Point>();
-	let viewsheds = gs.ecs.read_storage::<Viewshed>();
+/// Picks the display and gameplay settings, saved on the way out. The
+/// console size and font can't be changed under a running window, so they
+/// take hold on the next start.
+pub fn display_options_menu (gs: &mut State, ctx: &mut Rltk, selection: usize) -> DisplayOptionsResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let mut display = gs.ecs.fetch_mut::<DisplaySettings>();
+	let mut gameplay = gs.ecs.fetch_mut::<GameplaySettings>();
+	let (width, height) = screen_size(ctx);
+
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, 0),
+		" Display ",
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, height - 1),
+		" LEFT / RIGHT to change, ESCAPE to save ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
 
-	ctx.print_color(
-		5, 0,
-		RGB::named(rltk::YELLOW),
-		RGB::named(rltk::BLACK),
-		" Select Target: ",
+	let (console_width, console_height) = CONSOLE_SIZES[display.console_size];
+	let options = [
+		("Console size", format!("{} x {}", console_width, console_height)),
+		("Font size", format!("{}px", display.tile_size())),
+		("Graphics", String::from(
+			if !display.sprites { "Text" }
+			else if display.use_sprites() { "Sprites" }
+			else { "Sprites (no tileset, using text)" }
+		)),
+		("Colours", String::from(display.palette.label())),
+		("Damage feedback", String::from(if display.damage_feedback { "On" } else { "Off" })),
+		("Autosave", match gameplay.autosave_turns() {
+			0 => String::from("Off"),
+			turns => format!("Every {} turns", turns),
+		}),
+		("Autosave on new level", String::from(if gameplay.autosave_levels { "On" } else { "Off" })),
+		("Edited saves", String::from(if gameplay.refuse_edited_saves { "Refuse" } else { "Load with a warning" })),
+		("Scanlines", String::from(if display.scanlines { "On" } else { "Off" })),
+		("Ironman (new runs)", String::from(if gameplay.ironman { "On" } else { "Off" })),
+	];
+	for (i, (label, value)) in options.iter().enumerate() {
+		let y = 3 + i as i32 * 2;
+		let fg = if i == selection { palette.highlight } else { palette.text };
+		draw.print_color(Point::new(5, y), label, ColorPair::new(fg, palette.background));
+		draw.print_color(Point::new(30, y), &format!("< {} >", value), ColorPair::new(palette.value, palette.background));
+	}
+	draw.print_color(
+		Point::new(5, 4 + options.len() as i32 * 2),
+		"Size, font and graphics change the next time the game starts",
+		ColorPair::new(palette.text_dim, palette.background),
 	);
 
-	// Highlight available target cells
-	let mut available_cells = Vec::new();
-	let visible = viewsheds.get(*player_entity);
-	if let Some(visible) = visible {
-		for idx in visible.visible_tiles.iter() {
-			let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
-			if distance <= range as f32 {
-				ctx.set_bg(
-					idx.x, idx.y,
-					RGB::named(rltk::BLUE),
-				);
-				available_cells.push(idx);
+	let step = |value: usize, by: i32, count: usize| ((value as i32 + by).rem_euclid(count as i32)) as usize;
+	let mut change = |by: i32| {
+		match selection {
+			0 => display.console_size = step(display.console_size, by, CONSOLE_SIZES.len()),
+			1 => display.font_scale = step(display.font_scale, by, FONT_SCALES.len()),
+			2 => display.sprites = !display.sprites,
+			3 => {
+				display.palette = display.palette.step(by);
+				*gs.ecs.fetch_mut::<Palette>() = Palette::new(display.palette);
 			}
+			4 => display.damage_feedback = !display.damage_feedback,
+			5 => gameplay.autosave_interval = step(gameplay.autosave_interval, by, AUTOSAVE_INTERVALS.len()),
+			6 => gameplay.autosave_levels = !gameplay.autosave_levels,
+			7 => gameplay.refuse_edited_saves = !gameplay.refuse_edited_saves,
+			8 => display.scanlines = !display.scanlines,
+			_ => gameplay.ironman = !gameplay.ironman,
 		}
-	} else {
-		return (ItemMenuResult::Cancel, None);
+		DisplayOptionsResult::NoResponse
+	};
+
+	let result = match ctx.key {
+		None => DisplayOptionsResult::NoResponse,
+		Some(key) => match key {
+			VirtualKeyCode::Escape => {
+				settings::save(&display, &gameplay, &gs.ecs.fetch::<KeyBindings>());
+				DisplayOptionsResult::Close
+			},
+			VirtualKeyCode::Up => DisplayOptionsResult::Select { selection: (selection + options.len() - 1) % options.len() },
+			VirtualKeyCode::Down => DisplayOptionsResult::Select { selection: (selection + 1) % options.len() },
+			VirtualKeyCode::Left => change(-1),
+			VirtualKeyCode::Right | VirtualKeyCode::Return => change(1),
+			_ => DisplayOptionsResult::NoResponse,
+		},
+	};
+
+	// Scanlines can change on the spot
+	ctx.with_post_scanlines(display.scanlines);
+	result
+}
+
+// Level Generation
+// =========================================================================
+
+/// Something to look at while the next level's built, so a slow one doesn't
+/// look like a hang
+pub fn generating_level (gs: &State, ctx: &mut Rltk, depth: i32, elapsed_ms: f32) {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let middle = middle_row(ctx);
+
+	const SPINNER : [char; 4] = ['|', '/', '-', '\\'];
+	let frame = (elapsed_ms / 100.) as usize % SPINNER.len();
+
+	draw.print_color_centered(
+		middle - 2,
+		&format!("Descending to depth {}", depth),
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color_centered(
+		middle,
+		&format!("{} Generating level {}", SPINNER[frame], SPINNER[frame]),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color_centered(
+		middle + 2,
+		&format!("{:.1}s", elapsed_ms / 1000.),
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	if elapsed_ms > 2000. {
+		draw.print_color_centered(
+			middle + 4,
+			"The forest is slow to settle here...",
+			ColorPair::new(palette.text_dim, palette.background),
+		);
 	}
+}
 
-	// Draw mouse cursor
-	let mouse_pos = ctx.mouse_pos();
-	let mut valid_target = false;
+// Log Viewer
+// =========================================================================
 
-	for idx in available_cells.iter() {
-		if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
-			valid_target = true;
+/// The whole message log, newest at the bottom, scrolled `offset` lines up
+/// from the end. With a filter only entries of that sort are shown.
+pub fn show_log (gs: &State, ctx: &mut Rltk, offset: i32, filter: Option<LogCategory>) -> LogViewerResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let log = gs.ecs.fetch::<GameLog>();
+	let (width, height) = screen_size(ctx);
+
+	// How many lines of the log fit on screen at once
+	let log_rows = height - 5;
+
+	let lines : Vec<(LogCategory, String)> = log.entries.iter()
+		.filter(|entry| filter.map_or(true, |category| entry.category == category))
+		.flat_map(|entry| {
+			wrap_text(&entry.display(), width as usize - 6).into_iter()
+				.map(move |line| (entry.category, line))
+		})
+		.collect();
+	let max_offset = i32::max(0, lines.len() as i32 - log_rows);
+	let offset = offset.clamp(0, max_offset);
+
+	draw.draw_box(
+		Rect::with_size(0, 0, width - 1, height - 1),
+		ColorPair::new(palette.text, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, 0),
+		&format!(" Message Log: {} ", filter.map_or("All", |category| category.name())),
+		ColorPair::new(palette.title, palette.background),
+	);
+	draw.print_color(
+		Point::new(3, height - 1),
+		" PGUP / PGDN / UP / DOWN to scroll, TAB to filter, ESCAPE to close ",
+		ColorPair::new(palette.text_dim, palette.background),
+	);
+
+	let end = lines.len() as i32 - offset;
+	let start = i32::max(0, end - log_rows);
+	for (y, (category, line)) in lines[start as usize .. end as usize].iter().enumerate() {
+		draw.print_color(Point::new(3, 2 + y as i32), line, ColorPair::new(palette.log(*category), palette.background));
+	}
+
+	if offset < max_offset {
+		draw.print_color(Point::new(width - 4, 1), "^", ColorPair::new(palette.text_dim, palette.background));
+	}
+	if offset > 0 {
+		draw.print_color(Point::new(width - 4, height - 2), "v", ColorPair::new(palette.text_dim, palette.background));
+	}
+
+	let scrolled = |by: i32| LogViewerResult::Scroll { offset: (offset + by).clamp(0, max_offset), filter };
+
+	match ctx.key {
+		None => LogViewerResult::NoResponse,
+		Some(key) => match key {
+			VirtualKeyCode::Escape => LogViewerResult::Close,
+			VirtualKeyCode::Tab => LogViewerResult::Scroll { offset: 0, filter: next_log_filter(filter) },
+			VirtualKeyCode::PageUp => scrolled(log_rows - 1),
+			VirtualKeyCode::PageDown => scrolled(1 - log_rows),
+			VirtualKeyCode::Up => scrolled(1),
+			VirtualKeyCode::Down => scrolled(-1),
+			VirtualKeyCode::Home => scrolled(max_offset),
+			VirtualKeyCode::End => scrolled(-max_offset),
+			_ if gs.ecs.fetch::<KeyBindings>().command_for(key) == Some(Command::Log) => LogViewerResult::Close,
+			_ => LogViewerResult::NoResponse,
+		},
+	}
+}
+
+/// All, then each category in turn, then back to all
+fn next_log_filter (filter: Option<LogCategory>) -> Option<LogCategory> {
+	match filter {
+		None => Some(LogCategory::ALL[0]),
+		Some(category) => {
+			let i = LogCategory::ALL.iter().position(|c| *c == category).unwrap_or(0);
+			LogCategory::ALL.get(i + 1).copied()
 		}
 	}
+}
 
-	if valid_target {
-		ctx.set_bg(
-			mouse_pos.0, mouse_pos.1,
-			RGB::named(rltk::CYAN),
-		);
+/// Breaks the text into lines no wider than `width`, between words where it
+/// can
+fn wrap_text (text: &str, width: usize) -> Vec<String> {
+	let mut lines : Vec<String> = Vec::new();
+	let mut line = String::new();
 
-		if ctx.left_click {
-			return (
-				ItemMenuResult::Selected,
-				Some(Point::new(mouse_pos.0, mouse_pos.1))
-			);
+	for word in text.split_whitespace() {
+		let mut word = word.to_string();
+
+		if !line.is_empty() && line.len() + 1 + word.len() > width {
+			lines.push(line);
+			line = String::new();
 		}
-	} else {
-		ctx.set_bg(
-			mouse_pos.0, mouse_pos.1,
-			RGB::named(rltk::RED),
-		);
 
-		if ctx.left_click {
-			return (ItemMenuResult::Cancel, None);
+		// Too long for any line, so chop it up
+		while word.len() > width {
+			let rest = word.split_off(width);
+			lines.push(word);
+			word = rest;
 		}
+
+		if !line.is_empty() { line.push(' '); }
+		line.push_str(&word);
 	}
 
-	return (ItemMenuResult::NoResponse, None);
+	if !line.is_empty() || lines.is_empty() { lines.push(line); }
+	lines
 }
 
 // Game Over
 // =========================================================================
 
-pub fn game_over (ctx: &mut Rltk) -> GameOverResult {
-	ctx.print_color_centered(
-		15,
-		RGB::named(rltk::GOLD),
-		RGB::named(rltk::BLACK),
-		"You die"
-	);
-	ctx.print_color_centered(
-		17,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
-		"Lost and alone"
-	);
-	ctx.print_color_centered(
-		19,
-		RGB::named(rltk::WHITE),
-		RGB::named(rltk::BLACK),
-		"Forgotten"
-	);
-
-	ctx.print_color_centered(
-		24,
-		RGB::named(rltk::GREY),
-		RGB::named(rltk::BLACK),
-		"Press space"
+/// How the run went: how deep and how long the player lasted, what they got
+/// through and what finished them off
+pub fn game_over (gs: &State, ctx: &mut Rltk) -> GameOverResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let stats = gs.ecs.fetch::<EventCounter>();
+
+	draw.print_color_centered(4, "You die", ColorPair::new(palette.title, palette.background));
+	draw.print_color_centered(
+		6,
+		&match &stats.killed_by {
+			Some(cause) => format!("Killed by {} on depth {}", cause, stats.deepest),
+			None => format!("Lost and alone on depth {}", stats.deepest),
+		},
+		ColorPair::new(palette.text, palette.background),
+	);
+
+	draw_run_summary(&mut draw, ctx, &palette, &stats, false)
+}
+
+/// The ending, for those who make off with the artifact
+pub fn victory (gs: &State, ctx: &mut Rltk) -> GameOverResult {
+	let palette = *gs.ecs.fetch::<Palette>();
+	let mut draw = gs.ecs.write_resource::<FrameBatch>();
+	let stats = gs.ecs.fetch::<EventCounter>();
+
+	draw.print_color_centered(4, "Victory!", ColorPair::new(palette.title, palette.background));
+	draw.print_color_centered(
+		6,
+		"With the Heart of the Forest in hand, the gloom lifts at last",
+		ColorPair::new(palette.text, palette.background),
+	);
+
+	draw_run_summary(&mut draw, ctx, &palette, &stats, true)
+}
+
+/// The run's tallies under the heading, and the prompt to move on
+fn draw_run_summary (draw: &mut DrawBatch, ctx: &mut Rltk, palette: &Palette, stats: &EventCounter, victory: bool) -> GameOverResult {
+	let (width, height) = screen_size(ctx);
+	let x = width / 2 - 14;
+	let summary = [
+		("Deepest depth", stats.deepest),
+		("Turns survived", stats.turns),
+		("Items used", stats.items_used),
+		("Monsters slain", stats.total_kills()),
+		("Score", stats.score(victory)),
+	];
+	for (i, (label, value)) in summary.iter().enumerate() {
+		let y = 9 + i as i32;
+		draw.print_color(Point::new(x, y), label, ColorPair::new(palette.text_dim, palette.background));
+		draw.print_color(Point::new(x + 24, y), &value.to_string(), ColorPair::new(palette.value, palette.background));
+	}
+	draw.print_color(Point::new(x, 9 + summary.len() as i32), "Time played", ColorPair::new(palette.text_dim, palette.background));
+	draw.print_color(Point::new(x + 24, 9 + summary.len() as i32), &stats.playtime(), ColorPair::new(palette.value, palette.background));
+	draw.print_color_centered(height - 5, &format!("Seed {}", stats.seed), ColorPair::new(palette.text_dim, palette.background));
+
+	// The tally of kills, as much of it as fits
+	let rows = i32::max(0, height - 22) as usize;
+	let mut y = 16;
+	for (i, (name, count)) in stats.kills.iter().enumerate() {
+		if i == rows && stats.kills.len() > rows + 1 {
+			let rest = format!("...and {} more kinds", stats.kills.len() - rows);
+			draw.print_color(Point::new(x + 2, y), &rest, ColorPair::new(palette.text_dim, palette.background));
+			break;
+		}
+		draw.print_color(Point::new(x + 2, y), &truncate(name, 20), ColorPair::new(palette.text, palette.background));
+		draw.print_color(Point::new(x + 24, y), &count.to_string(), ColorPair::new(palette.value, palette.background));
+		y += 1;
+	}
+
+	draw.print_color_centered(
+		height - 3,
+		"Press space",
+		ColorPair::new(palette.text_dim, palette.background),
 	);
 
 	match ctx.key {
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use specs::prelude::*;
+use crate::config::{Config, CONFIG_FILE};
+use crate::gamelog::GameLog;
+use crate::scripting::{ScriptEngine, SCRIPT_DIR};
+
+/// How often the files are looked over, since there's no need to every frame
+const CHECK_EVERY_MS : f32 = 1000.;
+
+/// Keeps an eye on config.toml and the scripts in debug builds, and takes in
+/// any changes as soon as they're saved, so tinkering with the numbers or an
+/// effect doesn't need a restart. There are no raws to reload in this tree;
+/// the config and the scripts are the content that lives outside the code.
+/// The web build has them baked in, so there's nothing to watch there.
+pub struct HotReload {
+	since_check_ms : f32,
+	/// When each file was last changed, as of the last look
+	stamps         : HashMap<PathBuf, SystemTime>,
+}
+
+impl Default for HotReload {
+	fn default () -> HotReload { HotReload::new() }
+}
+
+impl HotReload {
+	pub fn new () -> HotReload {
+		HotReload { since_check_ms: 0., stamps: watched_files() }
+	}
+
+	/// Reloads whatever's changed since the last look, once a second or so
+	pub fn poll (ecs: &mut World, frame_time_ms: f32) {
+		let changed : Vec<PathBuf> = {
+			let mut watch = ecs.write_resource::<HotReload>();
+			watch.since_check_ms += frame_time_ms;
+			if watch.since_check_ms < CHECK_EVERY_MS { return; }
+			watch.since_check_ms = 0.;
+
+			let stamps = watched_files();
+			let changed = stamps.iter()
+				.filter(|(path, stamp)| watch.stamps.get(*path) != Some(stamp))
+				.map(|(path, _)| path.clone())
+				.collect();
+			watch.stamps = stamps;
+			changed
+		};
+
+		for path in changed.iter() {
+			if path == Path::new(CONFIG_FILE) {
+				*ecs.write_resource::<Config>() = Config::load();
+			} else {
+				// Scripts are cheap to read again, so they all go
+				ecs.write_resource::<ScriptEngine>().forget_scripts();
+			}
+
+			log::info!("Reloaded {}", path.display());
+			ecs.write_resource::<GameLog>().system(format!("Reloaded {}", path.display()));
+		}
+	}
+}
+
+/// Every file being watched, and when it was last changed
+fn watched_files () -> HashMap<PathBuf, SystemTime> {
+	let mut files : Vec<PathBuf> = vec![PathBuf::from(CONFIG_FILE)];
+	if let Ok(scripts) = fs::read_dir(SCRIPT_DIR) {
+		files.extend(scripts.filter_map(|entry| entry.ok()).map(|entry| entry.path()));
+	}
+
+	files.into_iter()
+		.filter_map(|path| {
+			let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+			Some((path, modified))
+		})
+		.collect()
+}
@@ -0,0 +1,160 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+use rltk::{Rltk, VirtualKeyCode};
+use crate::keybindings::{Command, KeyBindings};
+
+/// How far a stick has to be pushed before it counts, out of 1
+const STICK_DEADZONE : f32 = 0.5;
+
+// Pads
+// =========================================================================
+
+/// A press on a pad, already boiled down to the buttons the game cares about
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PadPress {
+	/// The d-pad, or the left stick pushed one way
+	Direction { dx: i32, dy: i32 },
+	South,
+	East,
+	West,
+	North,
+	LeftBumper,
+	RightBumper,
+	Start,
+	Select,
+}
+
+/// Whatever pads are plugged in. There's only ever one player, so it doesn't
+/// matter which one a press comes from.
+pub struct Gamepads {
+	/// Missing when there's no way of reading pads on this machine
+	gilrs : Option<Gilrs>,
+	/// Where the left stick is, to tell a fresh push from one being held
+	stick : (f32, f32),
+}
+
+impl Default for Gamepads {
+	fn default () -> Gamepads { Gamepads::new() }
+}
+
+impl Gamepads {
+	pub fn new () -> Gamepads {
+		let gilrs = Gilrs::new()
+			.map_err(|e| log::info!("No gamepads to be had ({})", e))
+			.ok();
+		Gamepads { gilrs, stick: (0., 0.) }
+	}
+
+	/// The first press since the last frame, if there was one. Anything else
+	/// pressed in the same frame is dropped, just as rltk drops a second key.
+	pub fn poll (&mut self) -> Option<PadPress> {
+		let gilrs = self.gilrs.as_mut()?;
+		let mut press = None;
+
+		while let Some(event) = gilrs.next_event() {
+			let pressed = match event.event {
+				EventType::ButtonPressed(button, _) => button_press(button),
+				EventType::AxisChanged(axis, value, _) => {
+					let before = stick_direction(self.stick);
+					match axis {
+						Axis::LeftStickX => self.stick.0 = value,
+						// Up's positive on the stick, but up the screen on the map
+						Axis::LeftStickY => self.stick.1 = -value,
+						_ => {}
+					}
+
+					let after = stick_direction(self.stick);
+					if after != before && after != (0, 0) {
+						Some(PadPress::Direction { dx: after.0, dy: after.1 })
+					} else {
+						None
+					}
+				}
+				_ => None,
+			};
+
+			if press.is_none() { press = pressed; }
+		}
+
+		press
+	}
+}
+
+fn button_press (button: Button) -> Option<PadPress> {
+	match button {
+		Button::DPadUp => Some(PadPress::Direction { dx: 0, dy: -1 }),
+		Button::DPadDown => Some(PadPress::Direction { dx: 0, dy: 1 }),
+		Button::DPadLeft => Some(PadPress::Direction { dx: -1, dy: 0 }),
+		Button::DPadRight => Some(PadPress::Direction { dx: 1, dy: 0 }),
+		Button::South => Some(PadPress::South),
+		Button::East => Some(PadPress::East),
+		Button::West => Some(PadPress::West),
+		Button::North => Some(PadPress::North),
+		Button::LeftTrigger => Some(PadPress::LeftBumper),
+		Button::RightTrigger => Some(PadPress::RightBumper),
+		Button::Start => Some(PadPress::Start),
+		Button::Select => Some(PadPress::Select),
+		_ => None,
+	}
+}
+
+/// Which of the eight ways the stick's pushed, if it's pushed far enough
+fn stick_direction ((x, y): (f32, f32)) -> (i32, i32) {
+	let axis = |value: f32| if value > STICK_DEADZONE { 1 } else if value < -STICK_DEADZONE { -1 } else { 0 };
+	(axis(x), axis(y))
+}
+
+/// What a press does on the map. The pad's layout is fixed, unlike the keys.
+fn pad_command (press: PadPress) -> Option<Command> {
+	match press {
+		PadPress::Direction { dx, dy } => Some(Command::Move { dx, dy }),
+		PadPress::South => Some(Command::Interact),
+		PadPress::East => Some(Command::SkipTurn),
+		PadPress::West => Some(Command::Inventory),
+		PadPress::North => Some(Command::Examine),
+		PadPress::LeftBumper => Some(Command::Drop),
+		PadPress::RightBumper => Some(Command::Remove),
+		PadPress::Start => Some(Command::Pause),
+		PadPress::Select => Some(Command::Log),
+	}
+}
+
+/// The key a press stands in for in the menus and while targeting, which
+/// all listen for the keyboard
+fn pad_key (press: PadPress) -> Option<VirtualKeyCode> {
+	match press {
+		PadPress::Direction { dx: 0, dy: -1 } => Some(VirtualKeyCode::Up),
+		PadPress::Direction { dx: 0, dy: 1 } => Some(VirtualKeyCode::Down),
+		PadPress::Direction { dx: -1, dy: 0 } => Some(VirtualKeyCode::Left),
+		PadPress::Direction { dx: 1, dy: 0 } => Some(VirtualKeyCode::Right),
+		PadPress::Direction { .. } => None,
+		PadPress::South => Some(VirtualKeyCode::Return),
+		PadPress::East | PadPress::Start => Some(VirtualKeyCode::Escape),
+		// Next target along
+		PadPress::RightBumper => Some(VirtualKeyCode::Tab),
+		_ => None,
+	}
+}
+
+// Input
+// =========================================================================
+
+/// What the player asked for this frame, from the keyboard or a pad
+#[derive(Default, Copy, Clone)]
+pub struct Input {
+	pub command : Option<Command>,
+}
+
+impl Input {
+	/// Reads the frame's input. A pad press with no key pressed is passed
+	/// off as the key it stands in for, so the menus needn't know about
+	/// pads at all.
+	pub fn read (ctx: &mut Rltk, bindings: &KeyBindings, pad: Option<PadPress>) -> Input {
+		if let Some(key) = ctx.key {
+			return Input { command: bindings.command_for(key) };
+		}
+
+		let Some(press) = pad else { return Input::default() };
+		ctx.key = pad_key(press);
+		Input { command: pad_command(press) }
+	}
+}
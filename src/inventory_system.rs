@@ -1,9 +1,21 @@
-use rltk::RGB;
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
 use crate::gamelog::GameLog;
-use crate::{CombatStats, Consumable, InBackpack, Name, Position, ProvidesHealing, WantsToUseItem, WantsToDropItem, WantsToPickupItem, InflictsDamage, SufferDamage, AreaOfEffect, Confusion, Equippable, Equipped, WantsToRemoveItem, ProvidesFood, HungerClock, HungerState, MagicMapper, RunState};
+use crate::{CombatStats, Consumable, InBackpack, Name, NameOf, Position, ProvidesHealing, WantsToUseItem, WantsToDropItem, WantsToPickupItem, InflictsDamage, SufferDamage, AreaOfEffect, InflictsStatus, StatusEffects, Equippable, Equipped, WantsToRemoveItem, ProvidesFood, HungerClock, HungerState, ThirstClock, ThirstState, Waterskin, MagicMapper, TurnState, Befriended, Faction, GoalArtifact, ScriptedEffect, WantsToThrowItem, Renderable, MeleePowerBonus, Fragile, Charges};
 use crate::map::Map;
-use crate::particle_system::ParticleBuilder;
+use crate::spatial::{LiveMap, SpatialIndex};
+use crate::palette::Palette;
+use crate::particle_system::{Particle, ParticleBuilder};
+use crate::scripting::{apply_commands, Blame, ScriptEngine, ScriptTarget};
+
+/// How long a blast takes to spread out by another tile
+const BLAST_STEP_MS : f32 = 40.;
+
+/// How long a bolt takes to cross each tile on its way to the target
+const BOLT_STEP_MS : f32 = 25.;
 
 // Item Collection
 // =========================================================================
@@ -18,25 +30,34 @@ impl<'a> System<'a> for ItemCollectionSystem {
 		WriteStorage<'a, Position>,
 		ReadStorage<'a, Name>,
 		WriteStorage<'a, InBackpack>,
+		ReadStorage<'a, GoalArtifact>,
+		WriteExpect<'a, TurnState>,
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
 			player_entity, mut gamelog, mut wants_pickup, mut positions, names,
-			mut backpack,
+			mut backpack, artifacts, mut turn_state,
 		) = data;
 
 		for pickup in wants_pickup.join() {
-			positions.remove(pickup.item);
-			backpack.insert(pickup.item, InBackpack {
-				owner: pickup.collected_by,
-			}).expect("Failed to add item to backpack");
-
-			if pickup.collected_by == *player_entity {
-				gamelog.entries.push(format!(
-					"You pick up the {}.",
-					names.get(pickup.item).unwrap().name
-				));
+			for item in pickup.items.iter() {
+				positions.remove(*item);
+				backpack.insert(*item, InBackpack {
+					owner: pickup.collected_by,
+				}).or_warn("Failed to add item to backpack");
+
+				if pickup.collected_by == *player_entity {
+					gamelog.loot(format!(
+						"You pick up the {}.",
+						names.name_of(*item)
+					));
+
+					if artifacts.get(*item).is_some() {
+						gamelog.system("The forest stirs, and the gloom begins to lift.".to_string());
+						*turn_state = TurnState::Victory;
+					}
+				}
 			}
 		}
 
@@ -67,32 +88,160 @@ impl<'a> System<'a> for ItemDropSystem {
 		) = data;
 
 		for (entity, to_drop) in (&entities, &wants_drop).join() {
-			let mut dropper_pos : Position = Position { x: 0, y: 0 };
+			let Some(dropper_pos) = positions.get(entity).cloned() else {
+				log::warn!("Something with nowhere to stand tried to drop an item");
+				continue;
+			};
+
+			for item in to_drop.items.iter() {
+				positions.insert(
+					*item,
+					Position { x: dropper_pos.x, y: dropper_pos.y },
+				).or_warn("Failed to insert drop position");
+				backpack.remove(*item);
+
+				if entity == *player_entity {
+					gamelog.loot(format!(
+						"You drop the {}",
+						names.name_of(*item)
+					));
+				}
+			}
+		}
+
+		wants_drop.clear();
+	}
+}
 
-			{
-				let dropped_pos = positions.get(entity).unwrap();
-				dropper_pos.x = dropped_pos.x;
-				dropper_pos.y = dropped_pos.y;
+// Item Throw
+// =========================================================================
+
+pub struct ItemThrowSystem {}
+
+impl<'a> System<'a> for ItemThrowSystem {
+	type SystemData = (
+		Entities<'a>,
+		(ReadExpect<'a, Map>, ReadExpect<'a, SpatialIndex>),
+		ReadExpect<'a, Entity>,
+		WriteExpect<'a, GameLog>,
+		WriteStorage<'a, WantsToThrowItem>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, Renderable>,
+		WriteStorage<'a, Position>,
+		WriteStorage<'a, InBackpack>,
+		WriteStorage<'a, CombatStats>,
+		WriteStorage<'a, SufferDamage>,
+		ReadStorage<'a, MeleePowerBonus>,
+		ReadStorage<'a, Fragile>,
+		ReadStorage<'a, ProvidesHealing>,
+		ReadStorage<'a, InflictsStatus>,
+		WriteStorage<'a, StatusEffects>,
+		(WriteExpect<'a, ParticleBuilder>, ReadExpect<'a, Palette>, ReadExpect<'a, Config>),
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, (map, spatial), player_entity, mut gamelog, mut wants_throw,
+			names, renderables, mut positions, mut backpack, mut combat_stats,
+			mut suffer_damage, power_bonuses, fragile, healing, inflicts_status,
+			mut statuses, (mut particle_builder, palette, config),
+		) = data;
+
+		for (entity, throw) in (&entities, &wants_throw).join() {
+			let Some(from) = positions.get(entity).map(|pos| Point::new(pos.x, pos.y)) else {
+				log::warn!("Something with nowhere to stand tried to throw an item");
+				continue;
+			};
+			let is_player = entity == *player_entity;
+			let item_name = names.name_of(throw.item);
+			backpack.remove(throw.item);
+
+			let landing = landing_spot(&map, &spatial, &combat_stats, from, throw.target);
+			let (fg, glyph) = renderables.get(throw.item)
+				.map_or((palette.text, rltk::to_cp437('*')), |r| (r.fg, r.glyph));
+			particle_builder.request_line(from, landing, Particle { fg, bg: palette.background, glyph, lifetime: BOLT_STEP_MS * 2. }, BOLT_STEP_MS);
+
+			// Potions break, and everyone splashed gets a taste
+			if fragile.get(throw.item).is_some() {
+				let splashed = blast_area(&map, &spatial, landing, config.throwing.splash_radius);
+				for tile in splashed.iter() {
+					for mob in spatial.content(map.xy_idx(tile.x, tile.y)) {
+						let Some(stats) = combat_stats.get_mut(mob) else { continue };
+						if let Some(heal) = healing.get(throw.item) {
+							stats.hp = i32::min(stats.max_hp, stats.hp + heal.heal_amount);
+						}
+						if let Some(status) = inflicts_status.get(throw.item) {
+							StatusEffects::add(&mut statuses, mob, status.kind, status.duration);
+						}
+					}
+				}
+				particle_builder.request_ring(
+					landing, &splashed,
+					Particle { fg, bg: palette.background, glyph: rltk::to_cp437('░'), lifetime: config.particles.effect_ms },
+					BLAST_STEP_MS,
+				);
+
+				if is_player {
+					gamelog.loot(format!("The {} shatters, splashing everything nearby", item_name));
+				}
+				entities.delete(throw.item).or_warn("The pieces wouldn't go away");
+				continue;
 			}
 
-			positions.insert(
-				to_drop.item,
-				Position { x: dropper_pos.x, y: dropper_pos.y },
-			).expect("Failed to insert drop position");
-			backpack.remove(to_drop.item);
-
-			if entity == *player_entity {
-				gamelog.entries.push(format!(
-					"You drop the {}",
-					names.get(to_drop.item).unwrap().name
-				));
+			// Weapons hurt whoever they hit, and anything else just bounces off
+			let victim = spatial.content(map.xy_idx(landing.x, landing.y))
+				.find(|mob| *mob != entity && combat_stats.get(*mob).is_some());
+			match (power_bonuses.get(throw.item), victim) {
+				(Some(bonus), Some(victim)) => {
+					let thrower_power = combat_stats.get(entity).map_or(0, |s| s.power);
+					let damage = i32::max(1, thrower_power / 2 + bonus.power);
+					SufferDamage::new_damage(&mut suffer_damage, victim, damage, item_name, is_player);
+					particle_builder.request(
+						landing.x, landing.y,
+						Particle { fg: palette.damage, bg: palette.background, glyph: rltk::to_cp437('‼'), lifetime: config.particles.hit_ms },
+					);
+
+					if is_player {
+						gamelog.combat(format!(
+							"You throw the {} at {}, dealing {}hp damage!",
+							item_name,
+							names.name_of(victim),
+							damage,
+						));
+					}
+				}
+				_ => if is_player {
+					gamelog.loot(format!("You throw the {}", item_name));
+				}
 			}
+
+			positions.insert(throw.item, Position { x: landing.x, y: landing.y })
+				.or_warn("Thrown item never came down");
 		}
 
-		wants_drop.clear();
+		wants_throw.clear();
 	}
 }
 
+/// Where something thrown from `from` at `target` comes down: on the first
+/// thing in the way, or just short of a wall
+fn landing_spot (map: &Map, spatial: &SpatialIndex, combat_stats: &WriteStorage<CombatStats>, from: Point, target: Point) -> Point {
+	let mut landing = from;
+	for tile in rltk::line2d(rltk::LineAlg::Bresenham, from, target).iter().skip(1) {
+		if map.is_void_or_wall(tile.x, tile.y) { break; }
+
+		// Closed doors and the like stop it dead, but anyone stood there
+		// takes the hit
+		let idx = map.xy_idx(tile.x, tile.y);
+		let someone_there = spatial.content(idx).any(|e| combat_stats.get(e).is_some());
+		if spatial.is_blocked(idx) && !someone_there { break; }
+
+		landing = *tile;
+		if someone_there { break; }
+	}
+	landing
+}
+
 // Item Use
 // =========================================================================
 
@@ -101,96 +250,112 @@ pub struct ItemUseSystem {}
 impl<'a> System<'a> for ItemUseSystem {
 	type SystemData = (
 		Entities<'a>,
-		ReadExpect<'a, Map>,
+		(ReadExpect<'a, Map>, ReadExpect<'a, SpatialIndex>),
 		ReadExpect<'a, Entity>,
 		WriteExpect<'a, GameLog>,
 		WriteStorage<'a, WantsToUseItem>,
 		ReadStorage<'a, Name>,
 		ReadStorage<'a, ProvidesHealing>,
 		WriteStorage<'a, CombatStats>,
-		ReadStorage<'a, Consumable>,
+		(ReadStorage<'a, Consumable>, WriteStorage<'a, Charges>),
 		ReadStorage<'a, InflictsDamage>,
 		WriteStorage<'a, SufferDamage>,
 		ReadStorage<'a, AreaOfEffect>,
-		WriteStorage<'a, Confusion>,
+		ReadStorage<'a, InflictsStatus>,
+		WriteStorage<'a, StatusEffects>,
 		ReadStorage<'a, Equippable>,
 		WriteStorage<'a, Equipped>,
 		WriteStorage<'a, InBackpack>,
-		WriteExpect<'a, ParticleBuilder>,
-		ReadStorage<'a, Position>,
-		ReadStorage<'a, ProvidesFood>,
-		WriteStorage<'a, HungerClock>,
+		// Kept together to stay inside the number of things a system can fetch
+		(WriteExpect<'a, ParticleBuilder>, ReadExpect<'a, Palette>, WriteExpect<'a, EventCounter>, ReadExpect<'a, Config>, WriteExpect<'a, RandomNumberGenerator>),
+		WriteStorage<'a, Position>,
+		// Food and drink
+		(ReadStorage<'a, ProvidesFood>, WriteStorage<'a, HungerClock>, WriteStorage<'a, Waterskin>, WriteStorage<'a, ThirstClock>),
 		ReadStorage<'a, MagicMapper>,
-		WriteExpect<'a, RunState>,
+		WriteExpect<'a, TurnState>,
+		WriteStorage<'a, Befriended>,
+		ReadStorage<'a, Faction>,
+		(ReadStorage<'a, ScriptedEffect>, WriteExpect<'a, ScriptEngine>),
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
 			entities,
-			map,
+			(map, spatial),
 			player_entity,
 			mut gamelog,
 			mut wants_use,
 			names,
 			healing,
 			mut combat_stats,
-			consumables,
+			(consumables, mut charges),
 			inflict_damage,
 			mut suffer_damage,
 			aoe,
-			mut confused,
+			inflicts_status,
+			mut statuses,
 			equippable,
 			mut equipped,
 			mut backpack,
-			mut particle_builder,
-			positions,
-			provides_food,
-			mut hunger_clock,
+			(mut particle_builder, palette, mut counter, config, mut rng),
+			mut positions,
+			(provides_food, mut hunger_clock, mut waterskins, mut thirst_clock),
 			magic_mapper,
-			mut runstate,
+			mut turn_state,
+			mut befriended,
+			factions,
+			(scripted, mut scripts),
 		) = data;
 
 		for (entity, item) in (&entities, &wants_use).join() {
 			let mut used_item = true;
 
+			// Let the player know when they see a monster use something
+			if entity != *player_entity {
+				if let Some(pos) = positions.get(entity) {
+					if map.is_visible(map.xy_idx(pos.x, pos.y)) {
+						gamelog.combat(format!(
+							"{} uses the {}",
+							names.name_of(entity),
+							names.name_of(item.item),
+						));
+					}
+				}
+			}
+
 			// Targeting
 			let mut targets : Vec<Entity> = Vec::new();
 			match item.target {
-				None => { targets.push(*player_entity) }
+				None => { targets.push(entity) }
 				Some(target) => {
 					let area_effect = aoe.get(item.item);
 					match area_effect {
 						None => {
 							// Single tile target
 							let idx = map.xy_idx(target.x, target.y);
-							for mob in map.tile_content[idx].iter() {
-								targets.push(*mob);
+							targets.extend(spatial.content(idx));
+
+							// Bolts crackle across to whatever they're aimed at
+							if let (Some(_), Some(pos)) = (inflict_damage.get(item.item), positions.get(entity)) {
+								particle_builder.request_line(
+									Point::new(pos.x, pos.y), target,
+									Particle { fg: palette.blast, bg: palette.background, glyph: rltk::to_cp437('*'), lifetime: BOLT_STEP_MS * 2. },
+									BOLT_STEP_MS,
+								);
 							}
 						}
 						Some(area_effect) => {
 							// AoE
-							let mut blast_tiles = rltk::field_of_view(
-								target,
-								area_effect.radius,
-								&*map
-							);
-							blast_tiles.retain(|p|
-								p.x > 0 && p.x < map.width - 1
-									&& p.y > 0 && p.y < map.height - 1
-							);
+							let blast_tiles = blast_area(&map, &spatial, target, area_effect.radius);
 							for tile_pos in blast_tiles.iter() {
 								let idx = map.xy_idx(tile_pos.x, tile_pos.y);
-								for mob in map.tile_content[idx].iter() {
-									targets.push(*mob);
-								}
-								particle_builder.request(
-									tile_pos.x, tile_pos.y,
-									RGB::named(rltk::ORANGERED),
-									RGB::named(rltk::BLACK),
-									rltk::to_cp437('░'),
-									150.,
-								);
+								targets.extend(spatial.content(idx));
 							}
+							particle_builder.request_ring(
+								target, &blast_tiles,
+								Particle { fg: palette.blast, bg: palette.background, glyph: rltk::to_cp437('░'), lifetime: config.particles.blast_ms },
+								BLAST_STEP_MS,
+							);
 						}
 					}
 				}
@@ -211,7 +376,7 @@ impl<'a> System<'a> for ItemUseSystem {
 							to_unequip.push(item_entity);
 
 							if target == *player_entity {
-								gamelog.entries.push(format!(
+								gamelog.loot(format!(
 									"You unequip the {}",
 									name.name,
 								));
@@ -223,20 +388,20 @@ impl<'a> System<'a> for ItemUseSystem {
 						equipped.remove(*item);
 						backpack.insert(*item, InBackpack {
 							owner: target,
-						}).expect("Failed to move equipped to backpack");
+						}).or_warn("Failed to move equipped to backpack");
 					}
 
 					// Wield item
 					equipped.insert(item.item, Equipped {
 						owner: target,
 						slot: target_slot,
-					}).expect("Failed to equip item");
+					}).or_warn("Failed to equip item");
 					backpack.remove(item.item);
 
 					if target == *player_entity {
-						gamelog.entries.push(format!(
+						gamelog.loot(format!(
 							"You equip the {}",
-							names.get(item.item).unwrap().name,
+							names.name_of(item.item),
 						));
 					}
 				}
@@ -256,9 +421,9 @@ impl<'a> System<'a> for ItemUseSystem {
 							);
 
 							if entity == *player_entity {
-								gamelog.entries.push(format!(
+								gamelog.loot(format!(
 									"You drink {}, healing {}hp",
-									names.get(item.item).unwrap().name,
+									names.name_of(item.item),
 									healer.heal_amount,
 								));
 							}
@@ -267,10 +432,7 @@ impl<'a> System<'a> for ItemUseSystem {
 							if let Some(pos) = pos {
 								particle_builder.request(
 									pos.x, pos.y,
-									RGB::named(rltk::GREEN),
-									RGB::named(rltk::BLACK),
-									rltk::to_cp437('♥'),
-									250.,
+									Particle { fg: palette.heal, bg: palette.background, glyph: rltk::to_cp437('♥'), lifetime: config.particles.effect_ms },
 								);
 							}
 						}
@@ -288,18 +450,30 @@ impl<'a> System<'a> for ItemUseSystem {
 					for mob in targets.iter() {
 						if combat_stats.get(*mob).is_none() { continue }
 
+						// Friends don't take kindly to that
+						if entity == *player_entity {
+							if let Some(friend) = befriended.get_mut(*mob) {
+								friend.broken = true;
+							}
+						}
+
+						// Monsters' abilities are named for what they do, so
+						// it's who used them that gets the blame
+						let cause = if entity == *player_entity { item.item } else { entity };
 						SufferDamage::new_damage(
 							&mut suffer_damage,
 							*mob, damage.damage,
+							names.name_of(cause),
+							entity == *player_entity,
 						);
 
 						if entity == *player_entity {
-							let mob_name = names.get(*mob).unwrap();
-							let item_name = names.get(item.item).unwrap();
-							gamelog.entries.push(format!(
+							let mob_name = names.name_of(*mob);
+							let item_name = names.name_of(item.item);
+							gamelog.combat(format!(
 								"You use {} on {}, dealing {}hp damage!",
-								item_name.name,
-								mob_name.name,
+								item_name,
+								mob_name,
 								damage.damage,
 							));
 						}
@@ -310,33 +484,35 @@ impl<'a> System<'a> for ItemUseSystem {
 						if let Some(pos) = pos {
 							particle_builder.request(
 								pos.x, pos.y,
-								RGB::named(rltk::RED),
-								RGB::named(rltk::BLACK),
-								rltk::to_cp437('‼'),
-								150.,
+								Particle { fg: palette.damage, bg: palette.background, glyph: rltk::to_cp437('‼'), lifetime: config.particles.hit_ms },
 							);
 						}
 					}
 				}
 			}
 
-			// Confusion
-			let mut add_confusion = Vec::new();
-			let causes_confusion = confused.get(item.item);
-			match causes_confusion {
+			// Status Effects
+			let causes_status = inflicts_status.get(item.item);
+			match causes_status {
 				None => {}
-				Some(confusion) => {
+				Some(status) => {
 					used_item = false;
 					for mob in targets.iter() {
-						add_confusion.push((*mob, confusion.turns));
+						StatusEffects::add(
+							&mut statuses,
+							*mob,
+							status.kind,
+							status.duration,
+						);
 
 						if entity == *player_entity {
-							let mob_name = names.get(*mob).unwrap();
-							let item_name = names.get(item.item).unwrap();
-							gamelog.entries.push(format!(
-								"You use {} on {}, confusing them!",
-								item_name.name,
-								mob_name.name,
+							let mob_name = names.name_of(*mob);
+							let item_name = names.name_of(item.item);
+							gamelog.combat(format!(
+								"You use {} on {}, {}!",
+								item_name,
+								mob_name,
+								status.kind.inflicted_message(),
 							))
 						}
 
@@ -346,20 +522,37 @@ impl<'a> System<'a> for ItemUseSystem {
 						if let Some(pos) = pos {
 							particle_builder.request(
 								pos.x, pos.y,
-								RGB::named(rltk::BLUEVIOLET),
-								RGB::named(rltk::BLACK),
-								rltk::to_cp437('?'),
-								250.,
+								Particle { fg: status.kind.colour(), bg: palette.background, glyph: status.kind.glyph(), lifetime: config.particles.effect_ms },
 							);
 						}
 					}
 				}
 			}
-			for (target, turns) in add_confusion.iter() {
-				confused.insert(
-					*target,
-					Confusion { turns: *turns },
-				).expect("Failed to make confused");
+
+			// Scripted Effects
+			if let Some(scripted) = scripted.get(item.item) {
+				let user = names.name_of(entity);
+				let item_name = names.name_of(item.item);
+				for mob in targets.iter() {
+					let (target_hp, target_max_hp) = combat_stats.get(*mob).map_or((0, 0), |s| (s.hp, s.max_hp));
+					let on = ScriptTarget {
+						user,
+						target: names.name_of(*mob),
+						target_hp,
+						target_max_hp,
+						depth: map.depth,
+					};
+					match scripts.run(&scripted.script, &on) {
+						Ok(commands) => apply_commands(
+							commands, *mob, Blame { cause: item_name, by_player: entity == *player_entity },
+							&mut combat_stats, &mut suffer_damage, &mut statuses, &mut gamelog,
+						),
+						Err(e) => {
+							gamelog.system(format!("The {} fizzles. ({})", item_name, e));
+							break;
+						}
+					}
+				}
 			}
 
 			// Map
@@ -368,8 +561,8 @@ impl<'a> System<'a> for ItemUseSystem {
 				None => {}
 				Some(_) => {
 					used_item = true;
-					gamelog.entries.push("You see evErYTHING!".to_string());
-					*runstate = RunState::MagicMapReveal { row: 0 };
+					gamelog.system("You see evErYTHING!".to_string());
+					*turn_state = TurnState::MagicMapReveal { row: 0 };
 				}
 			}
 
@@ -378,18 +571,79 @@ impl<'a> System<'a> for ItemUseSystem {
 			match item_edible {
 				None => {}
 				Some(_) => {
-					used_item = true;
-					let target = targets[0];
-					let hc = hunger_clock.get_mut(target);
-					if let Some(hc) = hc {
-						hc.state = HungerState::WellFed;
-						hc.duration = 20;
-						gamelog.entries.push(
-							format!(
-								"You eat the {}",
-								names.get(item.item).unwrap().name,
-							)
-						);
+					used_item = false;
+
+					for target in targets.iter() {
+						// Thrown to an animal, it might take a shine to you
+						let animal_faction = factions.get(*target)
+							.map(|f| f.kind)
+							.filter(|f| f.is_animal() && *target != entity);
+						if let Some(faction) = animal_faction {
+							used_item = true;
+
+							let animal = names.name_of(*target);
+							let food = names.name_of(item.item);
+
+							if rng.roll_dice(1, 3) > 1 {
+								befriended.insert(*target, Befriended {
+									faction,
+									charmed: false,
+									broken: false,
+								}).or_warn("Animal is a picky eater");
+								gamelog.dialogue(format!(
+									"{} wolfs down the {}, and takes a liking to you!",
+									animal, food,
+								));
+							} else {
+								gamelog.dialogue(format!(
+									"{} sniffs at the {}, then ignores it.",
+									animal, food,
+								));
+							}
+							break;
+						}
+
+						let hc = hunger_clock.get_mut(*target);
+						if let Some(hc) = hc {
+							used_item = true;
+							hc.state = HungerState::WellFed;
+							hc.duration = config.hunger.well_fed_turns;
+							gamelog.loot(
+								format!(
+									"You eat the {}",
+									names.name_of(item.item),
+								)
+							);
+							break;
+						}
+					}
+
+					// Thrown to nothing that wants it, it stays where it lands
+					if let (false, Some(target)) = (used_item, item.target) {
+						let food = names.name_of(item.item);
+						match targets.iter().find(|t| combat_stats.get(**t).is_some()) {
+							Some(snubbed) => gamelog.dialogue(format!("{} ignores the {}.", names.name_of(*snubbed), food)),
+							None => gamelog.loot(format!("The {} lands on the floor.", food)),
+						}
+						backpack.remove(item.item);
+						positions.insert(item.item, Position { x: target.x, y: target.y })
+							.or_warn("The meat vanished mid-air");
+					}
+				}
+			}
+
+			// Drink
+			if let Some(skin) = waterskins.get_mut(item.item) {
+				if skin.sips <= 0 {
+					if entity == *player_entity {
+						gamelog.loot("Your waterskin is bone dry".to_string());
+					}
+				} else if let Some(tc) = thirst_clock.get_mut(entity) {
+					skin.sips -= 1;
+					tc.state = ThirstState::Quenched;
+					tc.duration = config.thirst.quenched_turns;
+					if entity == *player_entity {
+						gamelog.loot(format!("You take a swig from the waterskin, {} sips left", skin.sips));
 					}
 				}
 			}
@@ -400,7 +654,18 @@ impl<'a> System<'a> for ItemUseSystem {
 				match consumable {
 					None => {}
 					Some(_) => {
-						entities.delete(item.item).expect("Failed to delete item");
+						if entity == *player_entity {
+							counter.use_item(names.name_of(item.item));
+						}
+
+						// Anything with charges left over sticks around
+						let spent = charges.get_mut(item.item).map_or(true, |c| {
+							c.uses -= 1;
+							c.uses <= 0
+						});
+						if spent {
+							entities.delete(item.item).or_warn("Failed to delete item");
+						}
 					}
 				}
 			}
@@ -410,6 +675,17 @@ impl<'a> System<'a> for ItemUseSystem {
 	}
 }
 
+/// The tiles a blast centred on `target` reaches: everything it can see
+/// within the radius, short of the edge of the map and closed doors
+pub fn blast_area (map: &Map, spatial: &SpatialIndex, target: Point, radius: i32) -> Vec<Point> {
+	let mut tiles = rltk::field_of_view(target, radius, &LiveMap { map, spatial, can_open_doors: false });
+	tiles.retain(|p|
+		p.x > 0 && p.x < map.width - 1
+			&& p.y > 0 && p.y < map.height - 1
+	);
+	tiles
+}
+
 // Item Remove System
 // =========================================================================
 
@@ -432,10 +708,10 @@ impl<'a> System<'a> for ItemRemoveSystem {
 			equipped.remove(to_remove.item);
 			backpack.insert(to_remove.item, InBackpack {
 				owner: entity,
-			}).expect("Failed to put unequipped item in backpack");
-			log.entries.push(format!(
+			}).or_warn("Failed to put unequipped item in backpack");
+			log.loot(format!(
 				"You remove the {}",
-				names.get(to_remove.item).unwrap().name,
+				names.name_of(to_remove.item),
 			));
 		}
 
@@ -0,0 +1,272 @@
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+
+// Commands
+// =========================================================================
+
+/// Everything the player can do from the map
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Command {
+	Move { dx: i32, dy: i32 },
+	Interact,
+	Drop,
+	Inventory,
+	Remove,
+	Log,
+	Overview,
+	Examine,
+	Help,
+	#[serde(alias = "SaveAndQuit")]
+	Pause,
+	QuickSave,
+	QuickLoad,
+	Screenshot,
+	SkipLevel,
+	ExportMap,
+	SkipTurn,
+	DebugOverlay,
+	PerfOverlay,
+}
+
+impl Command {
+	/// What the help screen and key binding menu call it
+	pub fn description (&self) -> &'static str {
+		match self {
+			Command::Move { dx: 0, dy: -1 } => "Move north",
+			Command::Move { dx: 0, dy: 1 } => "Move south",
+			Command::Move { dx: -1, dy: 0 } => "Move west",
+			Command::Move { dx: 1, dy: 0 } => "Move east",
+			Command::Move { dx: 1, dy: -1 } => "Move north east",
+			Command::Move { dx: -1, dy: -1 } => "Move north west",
+			Command::Move { dx: 1, dy: 1 } => "Move south east",
+			Command::Move { dx: -1, dy: 1 } => "Move south west",
+			Command::Move { .. } => "Move",
+			Command::Interact => "Pick up / take the stairs / drink",
+			Command::Drop => "Drop an item",
+			Command::Inventory => "Use an item",
+			Command::Remove => "Unequip an item",
+			Command::Log => "Message log",
+			Command::Overview => "Map overview",
+			Command::Examine => "Examine",
+			Command::Help => "Help",
+			Command::Pause => "Pause menu",
+			Command::QuickSave => "Quick save",
+			Command::QuickLoad => "Quick load",
+			Command::Screenshot => "Screenshot",
+			Command::SkipLevel => "Skip level (debug)",
+			Command::ExportMap => "Export map (debug)",
+			Command::SkipTurn => "Wait a turn",
+			Command::DebugOverlay => "Warnings overlay (debug)",
+			Command::PerfOverlay => "Performance overlay (debug)",
+		}
+	}
+}
+
+/// Every command, in the order the help screen and key binding menu list them
+pub const COMMANDS : [Command; 25] = [
+	Command::Move { dx: 0, dy: -1 },
+	Command::Move { dx: 0, dy: 1 },
+	Command::Move { dx: -1, dy: 0 },
+	Command::Move { dx: 1, dy: 0 },
+	Command::Move { dx: -1, dy: -1 },
+	Command::Move { dx: 1, dy: -1 },
+	Command::Move { dx: -1, dy: 1 },
+	Command::Move { dx: 1, dy: 1 },
+	Command::Interact,
+	Command::Drop,
+	Command::Inventory,
+	Command::Remove,
+	Command::SkipTurn,
+	Command::Log,
+	Command::Overview,
+	Command::Examine,
+	Command::Help,
+	Command::Pause,
+	Command::QuickSave,
+	Command::QuickLoad,
+	Command::Screenshot,
+	Command::SkipLevel,
+	Command::ExportMap,
+	Command::DebugOverlay,
+	Command::PerfOverlay,
+];
+
+const DEFAULT_BINDINGS : &[(VirtualKeyCode, Command)] = &[
+	// Cardinal
+	(VirtualKeyCode::W, Command::Move { dx: 0, dy: -1 }),
+	(VirtualKeyCode::A, Command::Move { dx: -1, dy: 0 }),
+	(VirtualKeyCode::S, Command::Move { dx: 0, dy: 1 }),
+	(VirtualKeyCode::D, Command::Move { dx: 1, dy: 0 }),
+
+	// Diagonal
+	(VirtualKeyCode::E, Command::Move { dx: 1, dy: -1 }),
+	(VirtualKeyCode::Q, Command::Move { dx: -1, dy: -1 }),
+	(VirtualKeyCode::C, Command::Move { dx: 1, dy: 1 }),
+	(VirtualKeyCode::Z, Command::Move { dx: -1, dy: 1 }),
+
+	// Arrow keys, with the keys around them for diagonals
+	(VirtualKeyCode::Up, Command::Move { dx: 0, dy: -1 }),
+	(VirtualKeyCode::Left, Command::Move { dx: -1, dy: 0 }),
+	(VirtualKeyCode::Down, Command::Move { dx: 0, dy: 1 }),
+	(VirtualKeyCode::Right, Command::Move { dx: 1, dy: 0 }),
+	(VirtualKeyCode::PageUp, Command::Move { dx: 1, dy: -1 }),
+	(VirtualKeyCode::Home, Command::Move { dx: -1, dy: -1 }),
+	(VirtualKeyCode::PageDown, Command::Move { dx: 1, dy: 1 }),
+	(VirtualKeyCode::End, Command::Move { dx: -1, dy: 1 }),
+
+	// Numpad
+	(VirtualKeyCode::Numpad8, Command::Move { dx: 0, dy: -1 }),
+	(VirtualKeyCode::Numpad4, Command::Move { dx: -1, dy: 0 }),
+	(VirtualKeyCode::Numpad2, Command::Move { dx: 0, dy: 1 }),
+	(VirtualKeyCode::Numpad6, Command::Move { dx: 1, dy: 0 }),
+	(VirtualKeyCode::Numpad9, Command::Move { dx: 1, dy: -1 }),
+	(VirtualKeyCode::Numpad7, Command::Move { dx: -1, dy: -1 }),
+	(VirtualKeyCode::Numpad3, Command::Move { dx: 1, dy: 1 }),
+	(VirtualKeyCode::Numpad1, Command::Move { dx: -1, dy: 1 }),
+	(VirtualKeyCode::Numpad5, Command::SkipTurn),
+
+	// Vi keys
+	(VirtualKeyCode::K, Command::Move { dx: 0, dy: -1 }),
+	(VirtualKeyCode::H, Command::Move { dx: -1, dy: 0 }),
+	(VirtualKeyCode::J, Command::Move { dx: 0, dy: 1 }),
+	(VirtualKeyCode::L, Command::Move { dx: 1, dy: 0 }),
+	(VirtualKeyCode::U, Command::Move { dx: 1, dy: -1 }),
+	(VirtualKeyCode::Y, Command::Move { dx: -1, dy: -1 }),
+	(VirtualKeyCode::N, Command::Move { dx: 1, dy: 1 }),
+	(VirtualKeyCode::B, Command::Move { dx: -1, dy: 1 }),
+	(VirtualKeyCode::Period, Command::SkipTurn),
+
+	(VirtualKeyCode::F, Command::Interact),
+	(VirtualKeyCode::P, Command::Drop),
+	(VirtualKeyCode::I, Command::Inventory),
+	(VirtualKeyCode::R, Command::Remove),
+	(VirtualKeyCode::M, Command::Log),
+	(VirtualKeyCode::Tab, Command::Overview),
+	(VirtualKeyCode::X, Command::Examine),
+	(VirtualKeyCode::Slash, Command::Help),
+	(VirtualKeyCode::F1, Command::Help),
+	(VirtualKeyCode::Space, Command::SkipTurn),
+	(VirtualKeyCode::Escape, Command::Pause),
+	(VirtualKeyCode::F5, Command::QuickSave),
+	(VirtualKeyCode::F9, Command::QuickLoad),
+	(VirtualKeyCode::F10, Command::Screenshot),
+
+	// [DEBUG]
+	(VirtualKeyCode::F12, Command::SkipLevel),
+	(VirtualKeyCode::F11, Command::ExportMap),
+	(VirtualKeyCode::F3, Command::DebugOverlay),
+	(VirtualKeyCode::F4, Command::PerfOverlay),
+];
+
+/// Every key that can be bound to something, looked up by name when the
+/// bindings are loaded
+const BINDABLE_KEYS : &[VirtualKeyCode] = &[
+	VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D,
+	VirtualKeyCode::E, VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H,
+	VirtualKeyCode::I, VirtualKeyCode::J, VirtualKeyCode::K, VirtualKeyCode::L,
+	VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O, VirtualKeyCode::P,
+	VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+	VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X,
+	VirtualKeyCode::Y, VirtualKeyCode::Z,
+	VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+	VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7,
+	VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+	VirtualKeyCode::Numpad0, VirtualKeyCode::Numpad1, VirtualKeyCode::Numpad2, VirtualKeyCode::Numpad3,
+	VirtualKeyCode::Numpad4, VirtualKeyCode::Numpad5, VirtualKeyCode::Numpad6, VirtualKeyCode::Numpad7,
+	VirtualKeyCode::Numpad8, VirtualKeyCode::Numpad9,
+	VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4,
+	VirtualKeyCode::F5, VirtualKeyCode::F6, VirtualKeyCode::F7, VirtualKeyCode::F8,
+	VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11, VirtualKeyCode::F12,
+	VirtualKeyCode::Up, VirtualKeyCode::Down, VirtualKeyCode::Left, VirtualKeyCode::Right,
+	VirtualKeyCode::Home, VirtualKeyCode::End, VirtualKeyCode::PageUp, VirtualKeyCode::PageDown,
+	VirtualKeyCode::Insert, VirtualKeyCode::Delete,
+	VirtualKeyCode::Space, VirtualKeyCode::Escape, VirtualKeyCode::Tab, VirtualKeyCode::Back,
+	VirtualKeyCode::Comma, VirtualKeyCode::Period, VirtualKeyCode::Slash, VirtualKeyCode::Semicolon,
+	VirtualKeyCode::Apostrophe, VirtualKeyCode::LBracket, VirtualKeyCode::RBracket,
+	VirtualKeyCode::Minus, VirtualKeyCode::Equals, VirtualKeyCode::Grave, VirtualKeyCode::Backslash,
+];
+
+/// Always pauses, whatever else is bound, so the options and quitting can't
+/// be locked away
+const PAUSE_KEY : VirtualKeyCode = VirtualKeyCode::Escape;
+
+// Bindings
+// =========================================================================
+
+/// A binding as it's kept in the settings file, with the key by name
+#[derive(Serialize, Deserialize)]
+pub struct SavedBinding {
+	key     : String,
+	command : Command,
+}
+
+/// Which keys do what on the map. Kept in the settings file so players can
+/// set up whichever scheme suits them.
+pub struct KeyBindings {
+	pub bindings : Vec<(VirtualKeyCode, Command)>,
+}
+
+impl KeyBindings {
+	pub fn defaults () -> KeyBindings {
+		KeyBindings { bindings: DEFAULT_BINDINGS.to_vec() }
+	}
+
+	/// The bindings as they were saved, leaving out any keys that can't be
+	/// bound, and with the pause key put back if it's gone
+	pub fn from_saved (saved: Vec<SavedBinding>) -> KeyBindings {
+		let mut bindings : Vec<(VirtualKeyCode, Command)> = saved.iter()
+			.filter_map(|b| {
+				BINDABLE_KEYS.iter()
+					.find(|key| format!("{:?}", key) == b.key)
+					.map(|key| (*key, b.command))
+			})
+			.filter(|(key, _)| *key != PAUSE_KEY)
+			.collect();
+		bindings.push((PAUSE_KEY, Command::Pause));
+
+		KeyBindings { bindings }
+	}
+
+	pub fn to_saved (&self) -> Vec<SavedBinding> {
+		self.bindings.iter()
+			.map(|(key, command)| SavedBinding { key: format!("{:?}", key), command: *command })
+			.collect()
+	}
+
+	pub fn command_for (&self, key: VirtualKeyCode) -> Option<Command> {
+		self.bindings.iter().find(|b| b.0 == key).map(|b| b.1)
+	}
+
+	pub fn keys_for (&self, command: Command) -> Vec<VirtualKeyCode> {
+		self.bindings.iter().filter(|b| b.1 == command).map(|b| b.0).collect()
+	}
+
+	/// Binds the key to the command, taking it off whatever it did before.
+	/// The pause key stays as it is.
+	pub fn bind (&mut self, key: VirtualKeyCode, command: Command) {
+		if !BINDABLE_KEYS.contains(&key) || key == PAUSE_KEY { return; }
+		self.bindings.retain(|b| b.0 != key);
+		self.bindings.push((key, command));
+	}
+
+	/// Unbinds every key for the command, bar the pause key
+	pub fn clear (&mut self, command: Command) {
+		self.bindings.retain(|b| b.1 != command || b.0 == PAUSE_KEY);
+	}
+
+	/// The bound keys for the command, written out for the player
+	pub fn describe_keys (&self, command: Command) -> String {
+		let keys : Vec<String> = self.keys_for(command).iter().map(|key| key_name(*key)).collect();
+		if keys.is_empty() { "-".to_string() } else { keys.join(", ") }
+	}
+}
+
+/// How a key is written on screen
+pub fn key_name (key: VirtualKeyCode) -> String {
+	match key {
+		VirtualKeyCode::Slash => "?".to_string(),
+		VirtualKeyCode::Escape => "Esc".to_string(),
+		VirtualKeyCode::Back => "Backspace".to_string(),
+		key => format!("{:?}", key),
+	}
+}
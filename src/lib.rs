@@ -0,0 +1,877 @@
+#![feature(macro_metavar_expr)]
+extern crate serde;
+
+pub mod components;
+pub mod map;
+pub mod player;
+pub mod rect;
+pub mod visibility_system;
+pub mod ai;
+pub mod map_indexing_system;
+pub mod melee_combat_system;
+pub mod damage_system;
+pub mod gui;
+pub mod gamelog;
+pub mod event_counter;
+pub mod keybindings;
+pub mod display;
+pub mod palette;
+pub mod spawner;
+pub mod inventory_system;
+pub mod saveload_system;
+pub mod settings;
+pub mod config;
+pub mod records;
+pub mod random_table;
+pub mod particle_system;
+pub mod needs_system;
+pub mod encumbrance_system;
+pub mod light_system;
+pub mod input;
+pub mod status_effect_system;
+pub mod trigger_system;
+pub mod door_system;
+pub mod befriend_system;
+pub mod map_builder;
+pub mod rex_assets;
+pub mod spatial;
+pub mod scripting;
+pub mod debug_log;
+pub mod perf;
+pub mod schedule;
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub mod hot_reload;
+pub mod states;
+#[cfg(test)]
+mod testing;
+
+pub use components::*;
+pub use map::*;
+pub use player::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(target_arch = "wasm32"))]
+use std::process;
+#[cfg(not(target_arch = "wasm32"))]
+use std::str::FromStr;
+use rltk::{Rltk, GameState, RGB, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use specs::saveload::SimpleMarkerAllocator;
+use crate::ai::{NoiseBuilder, PlayerFlowMap, SummonBuilder};
+use crate::config::Config;
+use crate::damage_system::DamageSystem;
+use crate::debug_log::{DebugOverlay, OrWarn};
+use crate::event_counter::EventCounter;
+use crate::gamelog::{GameLog, LogCategory};
+use crate::display::{ActiveDisplay, DisplaySettings, FrameBatch, Viewport};
+use crate::palette::Palette;
+use crate::particle_system::AnimationClock;
+use crate::perf::PerfOverlay;
+use crate::gui::{ItemSelection, MainMenuSelection, PauseSelection, Targeting};
+use crate::needs_system::NeedsSystem;
+use crate::input::{Gamepads, Input};
+use crate::keybindings::KeyBindings;
+use crate::map_builder::{LevelBuild, MapBuilder};
+use crate::saveload_system::SaveSlot;
+use crate::schedule::Schedule;
+use crate::settings::GameplaySettings;
+use crate::scripting::ScriptEngine;
+use crate::spatial::SpatialIndex;
+use crate::states::GameStateHandler;
+
+// Game
+// =========================================================================
+
+/// Where the game's got to in the turn. The systems can move it on too,
+/// when something they've done ends the run or calls for a magic map.
+#[derive(PartialEq, Copy, Clone)]
+pub enum TurnState {
+    PreRun,
+    AwaitingInput,
+    PlayerTurn,
+    MonsterTurn,
+    HastedTurn,
+    NextLevel,
+    GeneratingLevel,
+    GameOver,
+    Victory,
+    MagicMapReveal { row: i32 },
+    #[cfg(feature = "mapgen_visualiser")] MapGeneration,
+}
+
+/// A menu or screen over the top of the game. They stack up, and the turn
+/// waits until they've all been closed.
+#[derive(PartialEq, Copy, Clone)]
+pub enum UiState {
+    ShowInventory { selection: usize },
+    ItemActions { item: Entity, selection: usize },
+    ExamineItem { item: Entity },
+    ShowLog { offset: i32, filter: Option<LogCategory> },
+    Examine { x: i32, y: i32 },
+    ShowHelp,
+    ShowMap,
+    KeyBindings { selection: usize, rebinding: bool },
+    DisplayOptions { selection: usize },
+    Paused { selection: PauseSelection, confirming: bool },
+    ConfirmQuickLoad,
+    ShowDropItem { selection: usize },
+    ShowPickupItem { selection: usize },
+    ShowRemoveItem { selection: usize },
+    ShowTargeting {
+        range    : i32,
+        item     : Entity,
+        throwing : bool,
+    },
+    MainMenu {
+        menu_selection: MainMenuSelection,
+    },
+    EnterSeed { seed: Option<u64> },
+    ShowRecords,
+    HighScores { highlight: Option<usize>, after_run: bool },
+}
+
+/// What a frame's handler wants to happen next
+#[derive(PartialEq, Copy, Clone)]
+pub enum Transition {
+    /// Carry on as things are
+    Stay,
+    /// Close every menu and go on with the turn from here
+    Turn(TurnState),
+    /// Open a menu over the top of whatever's showing
+    Push(UiState),
+    /// Swap the menu on top for another, or the same one moved on
+    Replace(UiState),
+    /// Close the menu on top
+    Pop,
+    /// Close every menu and open this one on its own
+    Reset(UiState),
+}
+
+pub struct State {
+    pub ecs: World,
+
+    /// The menus open over the game, topmost last
+    ui            : Vec<UiState>,
+
+    #[cfg(feature = "mapgen_visualiser")] mapgen_name    : String,
+    #[cfg(feature = "mapgen_visualiser")] mapgen_running : bool,
+    #[cfg(feature = "mapgen_visualiser")] mapgen_history : Vec<Map>,
+    #[cfg(feature = "mapgen_visualiser")] mapgen_index   : usize,
+    #[cfg(feature = "mapgen_visualiser")] mapgen_timer   : f32,
+
+    /// The next level, while it's being built
+    level_build   : Option<LevelBuild>,
+
+    /// Why the save wouldn't load, for the main menu to own up to
+    load_error    : Option<String>,
+
+    /// How the last thing picked on the main menu went, if it's worth saying
+    menu_notice   : Option<String>,
+
+    /// The turn the game was last autosaved on
+    last_autosave : i32,
+
+    /// Whether the run's over and added to the records
+    run_recorded  : bool,
+
+    /// Where the run that's just ended came on the high score table
+    run_rank      : Option<usize>,
+
+    /// Every system that runs each turn, in order, for each part of the turn
+    schedule      : Schedule,
+
+    /// Any pads plugged in, kept out of the world since they can't be shared
+    /// between threads
+    gamepads      : Gamepads,
+
+    /// What the player asked for this frame
+    pub input     : Input,
+}
+
+impl State {
+    /// Starts the systems afresh, throwing away anything they've been
+    /// keeping from the level before
+    fn reset_systems (&mut self) {
+        self.schedule = Schedule::new();
+    }
+
+    fn run_systems (&mut self) {
+        let start = perf::now_ms();
+        if *self.ecs.fetch::<TurnState>() == TurnState::PlayerTurn {
+            let config = self.ecs.fetch::<Config>();
+            self.ecs.write_resource::<AnimationClock>().next_turn(&config.particles);
+        }
+        self.schedule.dispatch(&self.ecs);
+
+        spawner::spawn_summons(&mut self.ecs);
+
+        self.ecs.maintain();
+        self.ecs.write_resource::<PerfOverlay>().record_turn((perf::now_ms() - start) as f32);
+    }
+
+    fn entities_to_remove_on_level_change (&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player = self.ecs.read_storage::<Player>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let equipped = self.ecs.read_storage::<Equipped>();
+        let allies = self.ecs.read_storage::<Ally>();
+        let abilities = self.ecs.read_storage::<Ability>();
+
+        // The player's party, who all come along
+        let in_party = |entity: Entity| entity == *player_entity || allies.get(entity).is_some();
+
+        let mut to_delete : Vec<Entity> = Vec::new();
+        for entity in entities.join() {
+            // Don't delete the player
+            let p = player.get(entity);
+            if let Some(_p) = p { continue }
+
+            // Don't delete allies
+            if allies.get(entity).is_some() { continue }
+
+            // Don't delete inventory items
+            let i = backpack.get(entity);
+            if let Some(i) = i {
+                if in_party(i.owner) { continue }
+            }
+
+            // Don't delete equipped
+            let e = equipped.get(entity);
+            if let Some(e) = e {
+                if in_party(e.owner) { continue }
+            }
+
+            // Don't delete allies' abilities
+            let a = abilities.get(entity);
+            if let Some(a) = a {
+                if in_party(a.owner) { continue }
+            }
+
+            to_delete.push(entity);
+        }
+
+        to_delete
+    }
+
+    /// Sets the next level building, to be moved into once it's done
+    fn goto_next_level(&mut self) {
+        let current_depth;
+        {
+            let worldmap_res = self.ecs.fetch::<Map>();
+            current_depth = worldmap_res.depth;
+        }
+        let seed = self.ecs.fetch::<EventCounter>().seed;
+        let forced = self.ecs.fetch::<LaunchOptions>().builder.clone();
+        self.level_build = Some(LevelBuild::start(seed, current_depth + 1, forced));
+    }
+
+    fn arrive_on_next_level (&mut self, build: LevelBuild) {
+        // Delete all entities not related to the player
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs.delete_entity(target)
+                .or_warn("Failed to delete old entity on level change");
+        }
+
+        let depth = build.depth;
+        self.place_world_map(depth, build.finish());
+
+        // Notify the player
+        {
+            let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+            gamelog.system("You descend, taking a moment to catch your breath...".to_string());
+        }
+
+        // Heal the player
+        {
+            let player_entity = self.ecs.fetch::<Entity>();
+            let heal_to = self.ecs.fetch::<Config>().descend.heal_to_percent;
+            let mut hp_store = self.ecs.write_storage::<CombatStats>();
+            let player_hp = hp_store.get_mut(*player_entity);
+            if let Some(player_hp) = player_hp {
+                player_hp.hp = i32::max(player_hp.hp, player_hp.max_hp * heal_to / 100);
+            }
+        }
+
+        if self.ecs.fetch::<GameplaySettings>().autosave_levels {
+            self.autosave();
+        }
+    }
+
+    /// Autosaves once enough turns have gone by since the last one, or every
+    /// turn in ironman
+    fn autosave_if_due (&mut self) {
+        let every =
+            if self.ecs.fetch::<EventCounter>().ironman { 1 }
+            else { self.ecs.fetch::<GameplaySettings>().autosave_turns() };
+        let turns = self.ecs.fetch::<EventCounter>().turns;
+        if every > 0 && turns - self.last_autosave >= every {
+            self.autosave();
+        }
+    }
+
+    /// Plays the run about to start in ironman, if that's what's been picked
+    fn choose_ironman (&mut self) {
+        let ironman = self.ecs.fetch::<GameplaySettings>().ironman;
+        self.ecs.write_resource::<EventCounter>().ironman = ironman;
+    }
+
+    fn autosave (&mut self) {
+        saveload_system::save_game(&mut self.ecs, SaveSlot::Auto).or_warn("The autosave didn't take");
+        self.last_autosave = self.ecs.fetch::<EventCounter>().turns;
+    }
+
+    fn game_over_cleanup(&mut self) {
+        self.new_run(random_seed());
+    }
+
+    /// Clears out the old run and sets up a new one from `seed`, ready for
+    /// the player to start
+    fn new_run (&mut self, seed: u64) {
+        // Delete all the things
+        let mut to_delete = Vec::new();
+        for e in self.ecs.entities().join() {
+            to_delete.push(e);
+        }
+        for del in to_delete.iter() {
+            self.ecs.delete_entity(*del).or_warn("Delete failed");
+        }
+        self.ecs.insert(EventCounter { seed, ..EventCounter::default() });
+        self.ecs.insert(RandomNumberGenerator::seeded(seed));
+        self.ecs.insert(TurnState::PreRun);
+        self.last_autosave = 0;
+        self.run_recorded = false;
+        self.run_rank = None;
+
+        // Spawn new player
+        {
+            let player_entity = spawner::player(&mut self.ecs, 0, 0);
+            let mut player_writer = self.ecs.write_resource::<Entity>();
+            *player_writer = player_entity;
+        }
+        spawner::dog(&mut self.ecs, 0, 0);
+
+        // Generate map
+        #[cfg(feature = "mapgen_visualiser")]
+        {
+            let depth;
+            {
+                let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+                depth = rng.roll_dice(1, 4);
+            }
+            self.generate_world_map(depth);
+        }
+        #[cfg(not(feature = "mapgen_visualiser"))]
+        {
+            let depth = self.ecs.fetch::<LaunchOptions>().depth;
+            self.generate_world_map(depth);
+        }
+    }
+
+    fn generate_world_map (&mut self, depth: i32) {
+        #[cfg(feature = "mapgen_visualiser")]
+        {
+            self.mapgen_name = "".to_string();
+            self.mapgen_running = true;
+            self.mapgen_index = 0;
+            self.mapgen_timer = 0.;
+            self.mapgen_history.clear();
+        }
+
+        let seed = self.ecs.fetch::<EventCounter>().seed;
+        let forced = self.ecs.fetch::<LaunchOptions>().builder.clone();
+        self.place_world_map(depth, map_builder::build_level(seed, depth, forced.as_deref()));
+    }
+
+    /// Swaps in a freshly built map, populates it and puts the player's
+    /// party at the start
+    fn place_world_map (&mut self, depth: i32, mut builder: Box<dyn MapBuilder>) {
+        // Held on to here until everything's spawned on it, so it can be
+        // taken off the builder rather than copied
+        let mut map = builder.take_map();
+        let player_start = builder.get_starting_position();
+
+        // Pools to drink from, from a generator of their own so a seeded run
+        // gets them in the same places
+        let seed = self.ecs.fetch::<EventCounter>().seed;
+        let mut rng = RandomNumberGenerator::seeded(map_builder::level_seed(seed, depth).wrapping_add(2));
+        map_builder::add_pools(&mut map, &mut rng, &player_start);
+        if !self.ecs.fetch::<Config>().light.is_dark(depth) { map.light_rooms(); }
+        self.ecs.write_resource::<SpatialIndex>().reset(&map);
+        self.ecs.write_resource::<PlayerFlowMap>().invalidate();
+        self.ecs.write_resource::<AutoWalk>().cancel();
+        self.reset_systems();
+        {
+            let mut stats = self.ecs.write_resource::<EventCounter>();
+            stats.deepest = i32::max(stats.deepest, depth);
+        }
+
+        // Spawn entities, from a generator of their own so a seeded run
+        // gets the same monsters and loot too
+        self.ecs.insert(RandomNumberGenerator::seeded(map_builder::level_seed(seed, depth).wrapping_add(1)));
+        builder.spawn(&mut self.ecs, &map);
+        spawner::spawn_patrol(&mut self.ecs, &map, &player_start);
+        self.ecs.insert(map);
+
+        // There's nowhere further down; what's waiting on the stairs instead
+        // is the way out
+        if depth >= FINAL_DEPTH {
+            let stairs = {
+                let mut worldmap = self.ecs.write_resource::<Map>();
+                let stairs = worldmap.tiles.iter().position(|tile| *tile == TileType::DownStairs);
+                if let Some(idx) = stairs { worldmap.tiles[idx] = TileType::Floor; }
+                stairs.map(|idx| (idx as i32 % worldmap.width, idx as i32 / worldmap.width))
+            };
+            if let Some((x, y)) = stairs {
+                spawner::goal_artifact(&mut self.ecs, x, y);
+            }
+        }
+
+        #[cfg(feature = "mapgen_visualiser")]
+        {
+            self.mapgen_name = builder.get_name();
+            self.mapgen_history = builder.get_snapshot_history();
+        }
+
+        // Wizards know their way about
+        if self.ecs.fetch::<LaunchOptions>().wizard {
+            let mut worldmap = self.ecs.write_resource::<Map>();
+            worldmap.reveal_all();
+        }
+
+        // Place player
+        let mut player_pos = self.ecs.write_resource::<Point>();
+        *player_pos = Point::new(player_start.x, player_start.y);
+
+        let mut pos_comps = self.ecs.write_storage::<Position>();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let player_pos_comp = pos_comps.get_mut(*player_entity);
+        if let Some(player_pos_comp) = player_pos_comp {
+            player_pos_comp.x = player_start.x;
+            player_pos_comp.y = player_start.y;
+        }
+
+        let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+        let vs = viewsheds.get_mut(*player_entity);
+        if let Some(vs) = vs { vs.dirty = true; }
+
+        // Bring the allies along
+        let map = self.ecs.fetch::<Map>();
+        let entities = self.ecs.entities();
+        let allies = self.ecs.read_storage::<Ally>();
+        let blockers = self.ecs.read_storage::<BlocksTile>();
+
+        let mut taken : Vec<(i32, i32)> = vec![(player_start.x, player_start.y)];
+        for (entity, pos, _blocker) in (&entities, &pos_comps, &blockers).join() {
+            if allies.get(entity).is_none() { taken.push((pos.x, pos.y)); }
+        }
+
+        let mut arrivals : Vec<(Entity, (i32, i32))> = Vec::new();
+        for (entity, _ally) in (&entities, &allies).join() {
+            let spot = map.nearest_free_tile((player_start.x, player_start.y), &taken);
+            if let Some(spot) = spot {
+                taken.push(spot);
+                arrivals.push((entity, spot));
+            }
+        }
+
+        for (entity, (x, y)) in arrivals {
+            if let Some(pos) = pos_comps.get_mut(entity) {
+                pos.x = x;
+                pos.y = y;
+            }
+            if let Some(vs) = viewsheds.get_mut(entity) { vs.dirty = true; }
+        }
+    }
+
+}
+
+impl State {
+    /// What's looking after the frame: the menu on top, or the turn if
+    /// there's none open
+    fn handler (&self) -> Box<dyn GameStateHandler> {
+        match self.ui.last() {
+            Some(ui) => ui.handler(),
+            None => self.ecs.fetch::<TurnState>().handler(),
+        }
+    }
+
+    fn apply (&mut self, transition: Transition) {
+        match transition {
+            Transition::Stay => {}
+            Transition::Turn(turn) => {
+                self.ui.clear();
+                *self.ecs.write_resource::<TurnState>() = turn;
+            }
+            Transition::Push(ui) => self.ui.push(ui),
+            Transition::Replace(ui) => {
+                self.ui.pop();
+                self.ui.push(ui);
+            }
+            Transition::Pop => { self.ui.pop(); }
+            Transition::Reset(ui) => {
+                self.ui.clear();
+                self.ui.push(ui);
+            }
+        }
+    }
+
+    /// A last-ditch save to the recovery slot when a frame panics, if there's
+    /// a run going. The world may be too far gone to save, in which case
+    /// there's nothing more to be done.
+    fn emergency_save (&mut self) {
+        // Everything off the main menu is stacked over it, and after a run
+        // the high scores come up before it
+        if self.ui.iter().any(|ui| matches!(ui, UiState::MainMenu { .. } | UiState::HighScores { .. })) { return; }
+        match *self.ecs.fetch::<TurnState>() {
+            TurnState::GameOver | TurnState::Victory => return,
+            _ => {}
+        }
+
+        let ecs = &mut self.ecs;
+        match panic::catch_unwind(AssertUnwindSafe(|| saveload_system::save_game(ecs, SaveSlot::Recovery))) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("The run couldn't be saved before the crash ({})", e),
+            Err(_) => log::error!("The run couldn't be saved before the crash"),
+        }
+        saveload_system::wait_for_writes();
+    }
+
+    fn frame (&mut self, ctx : &mut Rltk) {
+        // Get current state
+        let mut handler = self.handler();
+
+        // The clock only runs while there's a game going
+        if !handler.stops_clock() {
+            self.ecs.write_resource::<EventCounter>().playtime_ms += ctx.frame_time_ms as f64;
+        }
+        self.ecs.write_resource::<PerfOverlay>().record_frame(ctx.frame_time_ms);
+
+        // Take in any changes to the config or scripts while developing
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+        hot_reload::HotReload::poll(&mut self.ecs, ctx.frame_time_ms);
+
+        // Pads stand in for the keyboard wherever the keys are read
+        let pad = self.gamepads.poll();
+        self.input = Input::read(ctx, &self.ecs.fetch::<KeyBindings>(), pad);
+
+        // Clear console
+        ctx.cls();
+        let sprites = self.ecs.fetch::<ActiveDisplay>().sprites;
+        let palette = *self.ecs.fetch::<Palette>();
+        if sprites { display::clear_sprites(ctx); }
+        {
+            let paused = !self.ui.is_empty();
+            let config = self.ecs.fetch::<Config>();
+            self.ecs.write_resource::<AnimationClock>().tick(ctx.frame_time_ms, paused, &config.particles);
+        }
+        particle_system::cull_dead_particles(&mut self.ecs);
+        particle_system::spawn_scheduled_particles(&mut self.ecs);
+        DamageSystem::fade_flashes(&mut self.ecs);
+        NeedsSystem::fade_flashes(&mut self.ecs);
+
+        // Render game (or not)
+        if !handler.full_screen() {
+            let viewport = Viewport::for_player(&self.ecs, ctx);
+            let mut draw = self.ecs.write_resource::<FrameBatch>();
+            if sprites {
+                draw_map(&self.ecs.fetch::<Map>(), &viewport, &palette, sprites, &mut draw, ctx);
+            } else {
+                self.ecs.write_resource::<MapRenderer>().draw(&self.ecs.fetch::<Map>(), &viewport, &palette, &mut draw);
+            }
+
+            let positions = self.ecs.read_storage::<Position>();
+            let renderables = self.ecs.read_storage::<Renderable>();
+            let hidden = self.ecs.read_storage::<Hidden>();
+            let map = self.ecs.fetch::<Map>();
+
+            let mut data = (&positions, &renderables, !&hidden).join().collect::<Vec<_>>();
+            data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+            for (pos, render, _hidden) in data.iter() {
+                let idx = map.xy_idx(pos.x, pos.y);
+                if map.is_visible(idx) {
+                    let mut bg = render.bg;
+
+                    // Show bloodstain on entity that doesn't have a background
+                    if bg == RGB::named(rltk::BLACK) && map.is_bloodstained(idx) {
+                        bg = palette.blood;
+                    }
+
+                    if let Some((x, y)) = viewport.to_screen(pos.x, pos.y) {
+                        display::draw_renderable(&mut draw, ctx, sprites, x, y, render, bg);
+                    }
+                }
+            }
+        } else {
+            self.ecs.write_resource::<MapRenderer>().clear(&mut self.ecs.write_resource::<FrameBatch>());
+        }
+
+        // Handle states
+        let transition = handler.update(self, ctx);
+        self.apply(transition);
+
+        // Render GUI
+        if !self.handler().full_screen() {
+            gui::draw_ui(&self.ecs, ctx);
+            if self.ecs.fetch::<DebugOverlay>().shown {
+                debug_log::draw_overlay(&mut self.ecs.write_resource::<FrameBatch>(), &palette);
+            }
+            if self.ecs.fetch::<PerfOverlay>().shown {
+                perf::draw_overlay(&self.ecs, ctx, &palette);
+            }
+        }
+
+        // Own up to how saves written in the background went
+        let ironman = self.ecs.fetch::<EventCounter>().ironman;
+        for result in saveload_system::finished_writes() {
+            let message = match result {
+                // Every turn would be a lot of news
+                Ok(SaveSlot::Auto) if ironman => continue,
+                Ok(SaveSlot::Auto) => "Autosaved.".to_string(),
+                Ok(SaveSlot::Quick) => "Quick saved.".to_string(),
+                Ok(_) => "Game saved.".to_string(),
+                Err(error) => error,
+            };
+            self.ecs.write_resource::<GameLog>().system(message);
+        }
+
+        // Everything drawn this frame goes to the consoles in one go
+        self.ecs.write_resource::<FrameBatch>().submit(0).or_warn("The frame wouldn't go in the queue");
+        rltk::render_draw_buffer(ctx).or_warn("The frame wouldn't draw");
+
+        // Delete dead entities
+        DamageSystem::delete_the_dead(&mut self.ecs);
+    }
+}
+
+impl GameState for State {
+    /// Runs a frame, saving the run first if it panics so a crash doesn't
+    /// take the run down with it
+    fn tick (&mut self, ctx : &mut Rltk) {
+        if let Err(cause) = panic::catch_unwind(AssertUnwindSafe(|| self.frame(ctx))) {
+            self.emergency_save();
+            panic::resume_unwind(cause);
+        }
+    }
+}
+
+/// A seed for a run nobody asked for a particular seed for
+fn random_seed () -> u64 {
+    RandomNumberGenerator::new().next_u64()
+}
+
+// Command Line
+// =========================================================================
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE : &str = "\
+Usage: rogue [options]
+
+    --seed <number>    Start a run from this seed
+    --builder <name>   Build every level with this map builder
+    --depth <number>   Start the run this far down
+    --wizard           Can't die, and every level's map is known
+    --no-save          Never write a save, for runs that don't matter
+
+Any of the first four go straight into a run, skipping the main menu.";
+
+/// How the game was started, for testers who want to go straight to a
+/// particular map or depth
+pub struct LaunchOptions {
+    pub seed    : Option<u64>,
+    pub builder : Option<String>,
+    /// The depth every run starts at
+    pub depth   : i32,
+    pub wizard  : bool,
+    pub no_save : bool,
+}
+
+impl Default for LaunchOptions {
+    fn default () -> LaunchOptions {
+        LaunchOptions { seed: None, builder: None, depth: 1, wizard: false, no_save: false }
+    }
+}
+
+impl LaunchOptions {
+    /// Whether anything was asked for that means skipping the main menu
+    #[cfg(not(feature = "mapgen_visualiser"))]
+    fn skips_menu (&self) -> bool {
+        self.seed.is_some() || self.builder.is_some() || self.depth != 1 || self.wizard
+    }
+}
+
+/// Reads the command line, or explains how to use it and quits when it
+/// can't make sense of it
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_args () -> LaunchOptions {
+    let mut options = LaunchOptions::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = Some(arg_value(&mut args, &arg)),
+            "--depth" => {
+                let depth = arg_value(&mut args, &arg);
+                if !(1 ..= FINAL_DEPTH).contains(&depth) {
+                    usage_error(&format!("--depth goes from 1 to {}", FINAL_DEPTH));
+                }
+                options.depth = depth;
+            }
+            "--builder" => {
+                let name : String = arg_value(&mut args, &arg);
+                if !map_builder::BUILDER_NAMES.contains(&name.as_str()) {
+                    usage_error(&format!(
+                        "There's no builder called {}. Try one of: {}",
+                        name, map_builder::BUILDER_NAMES.join(", "),
+                    ));
+                }
+                options.builder = Some(name);
+            }
+            "--wizard" => options.wizard = true,
+            "--no-save" => options.no_save = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => usage_error(&format!("Unknown option {}", arg)),
+        }
+    }
+
+    options
+}
+
+/// The value following an option, read as whatever it's meant to be
+#[cfg(not(target_arch = "wasm32"))]
+fn arg_value<T : FromStr> (args: &mut impl Iterator<Item = String>, option: &str) -> T {
+    args.next()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| usage_error(&format!("{} needs a value", option)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn usage_error (message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+/// Sets up the world with everything registered, the player and their dog
+/// placed, and the first level built, ready for the main menu or a run
+fn build_state (display: DisplaySettings, gameplay: GameplaySettings, bindings: KeyBindings, config: Config, options: LaunchOptions) -> State {
+    let mut gs = State {
+        ecs: World::new(),
+        ui: Vec::new(),
+
+        #[cfg(feature = "mapgen_visualiser")] mapgen_name: String::new(),
+        #[cfg(feature = "mapgen_visualiser")] mapgen_running: true,
+        #[cfg(feature = "mapgen_visualiser")] mapgen_index: 0,
+        #[cfg(feature = "mapgen_visualiser")] mapgen_history: Vec::new(),
+        #[cfg(feature = "mapgen_visualiser")] mapgen_timer: 0.,
+
+        level_build: None,
+        load_error: None,
+        menu_notice: None,
+        last_autosave: 0,
+        run_recorded: false,
+        run_rank: None,
+        schedule: Schedule::new(),
+        gamepads: Gamepads::new(),
+        input: Input::default(),
+    };
+
+    // Register Components
+    // -------------------------------------------------------------------------
+
+    components::register_components(&mut gs.ecs);
+
+    // Register Resources
+    // -------------------------------------------------------------------------
+
+    #[cfg(feature = "mapgen_visualiser")]
+    gs.ecs.insert(TurnState::MapGeneration);
+    #[cfg(not(feature = "mapgen_visualiser"))]
+    {
+        gs.ecs.insert(TurnState::PreRun);
+        if !options.skips_menu() {
+            gs.ui.push(UiState::MainMenu { menu_selection: MainMenuSelection::NewGame });
+        }
+    }
+
+    // Resource to get next marker identity
+    gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+    // Everything random in the run comes from this one seed
+    let seed = options.seed.unwrap_or_else(random_seed);
+    gs.ecs.insert(rex_assets::RexAssets::new());
+    gs.ecs.insert(config);
+    gs.ecs.insert(RandomNumberGenerator::seeded(seed));
+    gs.ecs.insert(particle_system::ParticleBuilder::new());
+    gs.ecs.insert(AnimationClock::default());
+    gs.ecs.insert(NoiseBuilder::new());
+    gs.ecs.insert(SummonBuilder::new());
+    gs.ecs.insert(PlayerFlowMap::new());
+    gs.ecs.insert(SpatialIndex::default());
+    gs.ecs.insert(ScriptEngine::new());
+    gs.ecs.insert(Targeting::default());
+    gs.ecs.insert(ItemSelection::default());
+    gs.ecs.insert(AutoWalk::default());
+    gs.ecs.insert(DebugOverlay::default());
+    gs.ecs.insert(PerfOverlay::default());
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    gs.ecs.insert(hot_reload::HotReload::new());
+    gs.ecs.insert(bindings);
+    gs.ecs.insert(ActiveDisplay { sprites: display.use_sprites() });
+    gs.ecs.insert(MapRenderer::default());
+    gs.ecs.insert(FrameBatch::default());
+    gs.ecs.insert(Palette::new(display.palette));
+    gs.ecs.insert(display);
+    gs.ecs.insert(gameplay);
+    let mut gamelog = GameLog::default();
+    gamelog.system("You awake in a dense, gloomy forest...".to_string());
+    gs.ecs.insert(gamelog);
+    gs.ecs.insert(EventCounter { seed, ..EventCounter::default() });
+    gs.ecs.insert(Map::new(MAP_WIDTH as i32, MAP_HEIGHT as i32, 1, None));
+
+    // Player
+    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
+    // Add player entity as resource
+    gs.ecs.insert(player_entity);
+    gs.ecs.insert(Point::new(0, 0)); // Player Pos
+    spawner::dog(&mut gs.ecs, 0, 0);
+
+    let depth = options.depth;
+    gs.ecs.insert(options);
+    gs.generate_world_map(depth);
+
+    gs
+}
+
+/// Starts the game from the command line, which is all the binary does.
+/// Everything else lives in the library so benchmarks can get at it.
+pub fn run () -> rltk::BError {
+    debug_log::init();
+    #[cfg(not(target_arch = "wasm32"))]
+    let options = parse_args();
+    // There's no command line in the browser
+    #[cfg(target_arch = "wasm32")]
+    let options = LaunchOptions::default();
+    let (display, gameplay, bindings) = settings::load();
+    let mut context = display.build_context()?;
+
+    context.with_post_scanlines(display.scanlines);
+    context.screen_burn_color = RGB::named(rltk::ROYALBLUE2);
+
+    let gs = build_state(display, gameplay, bindings, Config::load(), options);
+    return rltk::main_loop(context, gs);
+}
+
+/// Where the browser starts the game, once the page has loaded the module
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
+pub fn start () {
+    if let Err(e) = run() {
+        log::error!("The game wouldn't start: {}", e);
+    }
+}
@@ -0,0 +1,77 @@
+use specs::prelude::*;
+use crate::{Equipped, LightSource, Name, NameOf, TurnState, Viewshed};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+use crate::map::Map;
+
+/// Burns down the fuel in every light being held, a turn at a time on its
+/// holder's turn, and works out how far the player can see by it
+pub struct LightSystem {}
+
+impl<'a> System<'a> for LightSystem {
+	type SystemData = (
+		Entities<'a>,
+		ReadStorage<'a, Equipped>,
+		WriteStorage<'a, LightSource>,
+		WriteStorage<'a, Viewshed>,
+		ReadStorage<'a, Name>,
+		ReadExpect<'a, Entity>,
+		ReadExpect<'a, TurnState>,
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, GameLog>,
+		ReadExpect<'a, Config>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, equipped, mut lights, mut viewsheds, names, player_entity,
+			turn_state, map, mut log, config,
+		) = data;
+
+		// Only burning on the holder's side's turn, as with the needs
+		let their_turn = |entity: Entity| match *turn_state {
+			TurnState::PlayerTurn => entity == *player_entity,
+			TurnState::MonsterTurn => entity != *player_entity,
+			_ => false,
+		};
+
+		let mut burnt_out : Vec<Entity> = Vec::new();
+		for (item, held, light) in (&entities, &equipped, &mut lights).join() {
+			if !their_turn(held.owner) { continue; }
+
+			light.fuel -= 1;
+			if light.fuel > 0 { continue; }
+
+			burnt_out.push(item);
+			if held.owner == *player_entity {
+				log.system(format!(
+					"Your {} gutters out",
+					names.name_or(item, "light"),
+				));
+			}
+		}
+		// Put out straight away, so it's no help with the sight below
+		for item in burnt_out.iter() {
+			lights.remove(*item);
+			entities.delete(*item).or_warn("The ashes wouldn't blow away");
+		}
+
+		// The dark only lets the player see as far as what they're holding
+		let range =
+			if !config.light.is_dark(map.depth) { config.sight.player }
+			else {
+				(&equipped, &lights).join()
+					.filter(|(held, _)| held.owner == *player_entity)
+					.map(|(_, light)| light.radius)
+					.fold(config.light.unlit_sight, i32::max)
+			};
+
+		if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+			if viewshed.range != range {
+				viewshed.range = range;
+				viewshed.dirty = true;
+			}
+		}
+	}
+}
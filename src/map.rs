@@ -1,7 +1,8 @@
-use rltk::{RGB, Rltk, Algorithm2D, Point, BaseMap, SmallVec, DistanceAlg};
-use std::collections::HashSet;
+use rltk::{ColorPair, DrawBatch, RGB, Rltk, Algorithm2D, Point, BaseMap, SmallVec, DistanceAlg};
+use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
-use specs::{Entity};
+use crate::display::{draw_sprite, Viewport, MAP_CONSOLE, TEXT_CONSOLE};
+use crate::palette::Palette;
 
 // region: Rendering
 
@@ -12,62 +13,146 @@ pub enum TileType {
 	Wall,
 	Floor,
 	DownStairs,
+	/// Shallow enough to wade through, and good to drink
+	Water,
 }
 
-pub fn draw_map (map: &Map, ctx: &mut Rltk) {
-	let mut x = 0;
-	let mut y = 0;
-	for (idx, tile) in map.tiles.iter().enumerate() {
-		if map.revealed_tiles[idx] {
-			let glyph;
-			let mut fg;
-			let mut bg = RGB::from(rltk::BLACK);
-
-			match tile {
-				TileType::Floor => {
-					glyph = rltk::to_cp437('.');
-					fg = RGB::from_f32(0.1, 0.4, 0.1);
-				}
-				TileType::Wall => {
-					glyph = wall_glyph(&*map, x, y);
-					fg = RGB::from_f32(0.1, 0.4, 0.1);
-				}
-				TileType::DownStairs => {
-					glyph = rltk::to_cp437('▼');
-					fg = RGB::named(rltk::WHEAT4);
-				}
-				TileType::Placeholder => {
-					glyph = rltk::to_cp437('#');
-					fg = RGB::named(rltk::SLATEGRAY);
-				}
-				TileType::Void => {
-					glyph = 0;
-					fg = RGB::new();
-				}
+/// A character cell: glyph, foreground and background
+type Cell = (rltk::FontCharType, RGB, RGB);
+
+/// Draws the part of the map inside the viewport, as sprites from the tileset
+/// in sprite mode
+pub fn draw_map (map: &Map, viewport: &Viewport, palette: &Palette, sprites: bool, draw: &mut DrawBatch, ctx: &mut Rltk) {
+	for sy in 0 .. viewport.height {
+		for sx in 0 .. viewport.width {
+			let (x, y) = (sx + viewport.x, sy + viewport.y);
+			let Some((glyph, fg, bg)) = tile_cell(map, palette, x, y) else { continue };
+
+			if sprites {
+				let idx = map.xy_idx(x, y);
+				// The tileset has no joined-up walls
+				let index = if map.tiles[idx] == TileType::Wall { rltk::to_cp437('#') } else { glyph } as usize;
+				let tint =
+					if !map.is_visible(idx) { RGB::named(rltk::GREY50) }
+					else if map.is_bloodstained(idx) { palette.blood.lerp(RGB::named(rltk::WHITE), 0.5) }
+					else { RGB::named(rltk::WHITE) };
+
+				draw_sprite(ctx, sx, sy, 0, tint, index);
+				continue;
 			}
 
-			if map.bloodstains.contains(&idx) {
-				bg = RGB::named(rltk::DARK_RED);
-			}
+			draw.set(Point::new(sx, sy), ColorPair::new(fg, bg), glyph);
+		}
+	}
+}
 
-			if *tile != TileType::Void {
-				if !map.visible_tiles[idx] {
-					fg = fg.to_greyscale();
-					if map.bloodstains.contains(&idx) {
-						bg = RGB::from(rltk::DARKSLATEGREY);
-					}
-				}
+/// How a tile looks in text, or nothing if it isn't drawn at all
+fn tile_cell (map: &Map, palette: &Palette, x: i32, y: i32) -> Option<Cell> {
+	let idx = map.xy_idx(x, y);
+	if !map.is_revealed(idx) { return None; }
+
+	let (glyph, mut fg) = match map.tiles[idx] {
+		TileType::Floor => (rltk::to_cp437('.'), palette.floor),
+		TileType::Wall => (wall_glyph(map, x, y), palette.wall),
+		TileType::DownStairs => (rltk::to_cp437('▼'), palette.stairs),
+		TileType::Water => (rltk::to_cp437('≈'), palette.water),
+		TileType::Placeholder => (rltk::to_cp437('#'), palette.placeholder),
+		TileType::Void => return None,
+	};
+	let mut bg = if map.is_bloodstained(idx) { palette.blood } else { palette.background };
+
+	if !map.is_visible(idx) {
+		fg = fg.to_greyscale();
+		if map.is_bloodstained(idx) {
+			bg = palette.remembered_blood;
+		}
+	}
+
+	Some((glyph, fg, bg))
+}
+
+/// Keeps the map console in step with the map in text mode, redrawing only
+/// the tiles that have come into or gone out of sight, been bled on or
+/// changed since the last frame. Everything on the map goes on the text
+/// console over it, which is wiped every frame, so moving about and particles
+/// don't touch the map underneath. Sprite mode has to hand over every sprite
+/// each frame anyway, so it draws the lot with `draw_map`.
+#[derive(Default)]
+pub struct MapRenderer {
+	/// The map as it's drawn on the console, or nothing if the console's been
+	/// wiped since
+	drawn : Option<DrawnMap>,
+}
 
-				ctx.set(x, y, fg, bg, glyph);
+struct DrawnMap {
+	viewport : (i32, i32, i32, i32),
+	palette  : Palette,
+	tiles    : Vec<TileType>,
+	flags    : Vec<TileFlags>,
+}
+
+impl MapRenderer {
+	/// Redraws whatever's changed in view, or all of it if the view's moved,
+	/// leaving the batch drawing on the text console
+	pub fn draw (&mut self, map: &Map, viewport: &Viewport, palette: &Palette, draw: &mut DrawBatch) {
+		let view = (viewport.x, viewport.y, viewport.width, viewport.height);
+		draw.target(MAP_CONSOLE);
+
+		let dirty : Vec<usize> = match self.drawn.as_mut() {
+			Some(drawn) if drawn.viewport == view && drawn.palette == *palette && drawn.tiles.len() == map.tiles.len() => {
+				let dirty = changed_tiles(drawn, map);
+				drawn.tiles.copy_from_slice(&map.tiles);
+				drawn.flags.copy_from_slice(&map.flags);
+				dirty
+			}
+			_ => {
+				draw.cls();
+				self.drawn = Some(DrawnMap { viewport: view, palette: *palette, tiles: map.tiles.clone(), flags: map.flags.clone() });
+				(0 .. map.tiles.len()).collect()
 			}
+		};
+
+		for idx in dirty {
+			let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+			let Some((sx, sy)) = viewport.to_screen(x, y) else { continue };
+			let (glyph, fg, bg) = tile_cell(map, palette, x, y)
+				.unwrap_or((rltk::to_cp437(' '), RGB::named(rltk::BLACK), RGB::named(rltk::BLACK)));
+			draw.set(Point::new(sx, sy), ColorPair::new(fg, bg), glyph);
 		}
 
-		x += 1;
-		if x > 79 {
-			x = 0;
-			y += 1;
+		draw.target(TEXT_CONSOLE);
+	}
+
+	/// Wipes the map off its console, for screens that take up the whole
+	/// window
+	pub fn clear (&mut self, draw: &mut DrawBatch) {
+		if self.drawn.take().is_none() { return; }
+
+		draw.target(MAP_CONSOLE).cls().target(TEXT_CONSOLE);
+	}
+}
+
+/// Every tile that looks different from when it was last drawn
+fn changed_tiles (drawn: &DrawnMap, map: &Map) -> Vec<usize> {
+	let mut dirty = Vec::new();
+	for idx in 0 .. map.tiles.len() {
+		if drawn.tiles[idx] != map.tiles[idx] {
+			// Walls join up with their neighbours, so those change with it
+			let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+			for ny in y - 1 ..= y + 1 {
+				for nx in x - 1 ..= x + 1 {
+					if nx < 0 || nx > map.width - 1 || ny < 0 || ny > map.height - 1 { continue; }
+					dirty.push(map.xy_idx(nx, ny));
+				}
+			}
+		} else if drawn.flags[idx] != map.flags[idx] {
+			dirty.push(idx);
 		}
 	}
+
+	dirty.sort_unstable();
+	dirty.dedup();
+	dirty
 }
 
 fn is_revealed_and_wall (map: &Map, x: i32, y: i32) -> bool {
@@ -114,20 +199,85 @@ pub const MAP_WIDTH  : usize = 80;
 pub const MAP_HEIGHT : usize = 43;
 pub const MAP_SIZE   : usize = MAP_HEIGHT * MAP_WIDTH;
 
+/// The deepest level, where the stairs give way to the artifact that wins
+/// the game
+pub const FINAL_DEPTH : i32 = 10;
+
+/// What's true of a tile besides what it's made of, packed into a byte
+#[derive(PartialEq, Eq, Copy, Clone, Default, Serialize, Deserialize, Debug)]
+pub struct TileFlags(u8);
+
+impl TileFlags {
+	pub const REVEALED     : TileFlags = TileFlags(1 << 0);
+	pub const VISIBLE      : TileFlags = TileFlags(1 << 1);
+	pub const BLOODSTAINED : TileFlags = TileFlags(1 << 2);
+	/// In a room rather than a corridor, worked out when the level's placed
+	pub const LIT          : TileFlags = TileFlags(1 << 3);
+
+	pub fn contains (&self, flag: TileFlags) -> bool { self.0 & flag.0 != 0 }
+
+	pub fn set (&mut self, flag: TileFlags, on: bool) {
+		if on { self.0 |= flag.0 } else { self.0 &= !flag.0 }
+	}
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(from = "SavedMap")]
 pub struct Map {
-	pub tiles          : Vec<TileType>,
-	pub width          : i32,
-	pub height         : i32,
-	pub revealed_tiles : Vec<bool>,
-	pub visible_tiles  : Vec<bool>,
-	pub blocked        : Vec<bool>,
-	pub depth          : i32,
-	pub bloodstains    : HashSet<usize>,
+	pub tiles      : Vec<TileType>,
+	pub width      : i32,
+	pub height     : i32,
+	pub flags      : Vec<TileFlags>,
+	pub depth      : i32,
+
+	/// What the player last saw on each explored tile
+	pub remembered : HashMap<usize, Vec<String>>,
+}
+
+/// A map as it's read from a save. Saves from before the tile flags kept
+/// what's been seen and bled on separately, and are folded into flags here.
+#[derive(Deserialize)]
+struct SavedMap {
+	tiles          : Vec<TileType>,
+	width          : i32,
+	height         : i32,
+	#[serde(default)]
+	flags          : Vec<TileFlags>,
+	depth          : i32,
+	#[serde(default)]
+	remembered     : HashMap<usize, Vec<String>>,
+	#[serde(default)]
+	revealed_tiles : Vec<bool>,
+	#[serde(default)]
+	visible_tiles  : Vec<bool>,
+	#[serde(default)]
+	bloodstains    : HashSet<usize>,
+}
+
+impl From<SavedMap> for Map {
+	fn from (saved: SavedMap) -> Map {
+		if !saved.flags.is_empty() {
+			return Map {
+				tiles: saved.tiles,
+				width: saved.width,
+				height: saved.height,
+				flags: saved.flags,
+				depth: saved.depth,
+				remembered: saved.remembered,
+			};
+		}
 
-	#[serde(skip_serializing)]
-	#[serde(skip_deserializing)]
-	pub tile_content   : Vec<Vec<Entity>>,
+		let mut map = Map::new(saved.width, saved.height, saved.depth, None);
+		map.tiles = saved.tiles;
+		map.remembered = saved.remembered;
+		for (idx, flags) in map.flags.iter_mut().enumerate() {
+			flags.set(TileFlags::REVEALED, saved.revealed_tiles.get(idx) == Some(&true));
+			flags.set(TileFlags::VISIBLE, saved.visible_tiles.get(idx) == Some(&true));
+			flags.set(TileFlags::BLOODSTAINED, saved.bloodstains.contains(&idx));
+		}
+		map.light_rooms();
+		map
+	}
 }
 
 impl Map {
@@ -144,12 +294,9 @@ impl Map {
 			tiles : vec![fill_tile.unwrap_or(TileType::Void); l],
 			width,
 			height,
-			revealed_tiles: vec![false; l],
-			visible_tiles: vec![false; l],
-			blocked: vec![false; l],
+			flags: vec![TileFlags::default(); l],
 			depth,
-			bloodstains: HashSet::new(),
-			tile_content: vec![Vec::new(); l],
+			remembered: HashMap::new(),
 		}
 	}
 
@@ -161,26 +308,65 @@ impl Map {
 		(y as usize * self.width as usize) + x as usize
 	}
 
-	// prev: is_exit_valid
-	fn is_walkable (&self, x: i32, y: i32) -> bool {
-		if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
-			return false;
-		}
+	// Tile Flags
+	// =====================================================================
 
-		let idx = self.xy_idx(x, y);
-		return !self.blocked[idx];
+	pub fn is_revealed (&self, idx: usize) -> bool { self.flags[idx].contains(TileFlags::REVEALED) }
+
+	pub fn is_visible (&self, idx: usize) -> bool { self.flags[idx].contains(TileFlags::VISIBLE) }
+
+	pub fn is_bloodstained (&self, idx: usize) -> bool { self.flags[idx].contains(TileFlags::BLOODSTAINED) }
+
+	pub fn is_lit (&self, idx: usize) -> bool { self.flags[idx].contains(TileFlags::LIT) }
+
+	pub fn reveal (&mut self, idx: usize) { self.flags[idx].set(TileFlags::REVEALED, true); }
+
+	pub fn bloodstain (&mut self, idx: usize) { self.flags[idx].set(TileFlags::BLOODSTAINED, true); }
+
+	/// Marks a tile as in sight, which also means it's been seen
+	pub fn show (&mut self, idx: usize) {
+		self.flags[idx].set(TileFlags::VISIBLE, true);
+		self.flags[idx].set(TileFlags::REVEALED, true);
 	}
 
-	pub fn populate_blocked (&mut self) {
-		for (i, tile) in self.tiles.iter_mut().enumerate() {
-			self.blocked[i] = *tile == TileType::Wall || *tile == TileType::Void;
-		}
+	/// Takes every tile out of sight, ready for the player's view to be put
+	/// back
+	pub fn hide_all (&mut self) {
+		for flags in self.flags.iter_mut() { flags.set(TileFlags::VISIBLE, false); }
 	}
 
-	pub fn clear_content_index(&mut self) {
-		for content in self.tile_content.iter_mut() {
-			content.clear();
-		}
+	/// Shows the whole level, as the map scroll and wizard mode do
+	pub fn reveal_all (&mut self) {
+		for flags in self.flags.iter_mut() { flags.set(TileFlags::REVEALED, true); }
+	}
+
+	/// Exits over the bare floor plan, ignoring anything standing in the way
+	/// and treating doors as open
+	pub fn terrain_exits (&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+		self.exits_where(idx, |x, y| {
+			x > 0 && x < self.width && y > 0 && y < self.height && !self.is_void_or_wall(x, y)
+		})
+	}
+
+	pub fn exits_where<F : Fn(i32, i32) -> bool> (&self, idx: usize, walkable: F) -> SmallVec<[(usize, f32); 10]> {
+		let mut exists = SmallVec::new();
+		let x = idx as i32 % self.width;
+		let y = idx as i32 / self.width;
+		let w = self.width as usize;
+
+		// Cardinal Directions
+		if walkable(x - 1, y) { exists.push((idx - 1, 1.)) };
+		if walkable(x + 1, y) { exists.push((idx + 1, 1.)) };
+		if walkable(x, y - 1) { exists.push((idx - w, 1.)) };
+		if walkable(x, y + 1) { exists.push((idx + w, 1.)) };
+
+		// Diagonals
+		if walkable(x - 1, y - 1) { exists.push(((idx - w) - 1, 1.45)) }
+		if walkable(x + 1, y - 1) { exists.push(((idx - w) + 1, 1.45)) }
+		if walkable(x - 1, y + 1) { exists.push(((idx + w) - 1, 1.45)) }
+		if walkable(x + 1, y + 1) { exists.push(((idx + w) + 1, 1.45)) }
+
+		exists
 	}
 
 	pub fn is_void_or_wall (&self, x: i32, y: i32) -> bool {
@@ -188,36 +374,88 @@ impl Map {
 		self.tiles[idx] == TileType::Wall || self.tiles[idx] == TileType::Void
 	}
 
+	/// Lights the rooms, leaving corridors and poky little nooks dark. A tile
+	/// counts as being in a room if most of its neighbours are open floor.
+	pub fn light_rooms (&mut self) {
+		for idx in 0 .. self.tiles.len() {
+			let x = idx as i32 % self.width;
+			let y = idx as i32 / self.width;
+
+			let mut open = 0;
+			for dy in -1 ..= 1 {
+				for dx in -1 ..= 1 {
+					if dx == 0 && dy == 0 { continue; }
+					let (nx, ny) = (x + dx, y + dy);
+					if nx < 0 || nx > self.width - 1 || ny < 0 || ny > self.height - 1 { continue; }
+					if !self.is_void_or_wall(nx, ny) { open += 1; }
+				}
+			}
+
+			self.flags[idx].set(TileFlags::LIT, open >= 5);
+		}
+	}
+
+	/// The closest floor tile to `from` that isn't in `taken`, searching
+	/// outwards a ring at a time
+	pub fn nearest_free_tile (&self, from: (i32, i32), taken: &[(i32, i32)]) -> Option<(i32, i32)> {
+		for radius in 1 ..= 5i32 {
+			for dy in -radius ..= radius {
+				for dx in -radius ..= radius {
+					if dx.abs() != radius && dy.abs() != radius { continue; }
+
+					let (x, y) = (from.0 + dx, from.1 + dy);
+					if x < 1 || x > self.width - 2 || y < 1 || y > self.height - 2 { continue; }
+					if self.is_void_or_wall(x, y) || taken.contains(&(x, y)) { continue; }
+
+					return Some((x, y));
+				}
+			}
+		}
+
+		None
+	}
+
+	/// What the player's seen of the level as plain text, a line per row,
+	/// with the player marked where they stand
+	pub fn revealed_ascii (&self, player: Point) -> String {
+		let mut text = String::new();
+		for y in 0 .. self.height {
+			for x in 0 .. self.width {
+				let idx = self.xy_idx(x, y);
+				text.push(
+					if x == player.x && y == player.y { '@' }
+					else if !self.is_revealed(idx) { ' ' }
+					else {
+						match self.tiles[idx] {
+							TileType::Wall => '#',
+							TileType::Floor => '.',
+							TileType::DownStairs => '>',
+							TileType::Water => '~',
+							TileType::Placeholder | TileType::Void => ' ',
+						}
+					}
+				);
+			}
+			text.push('\n');
+		}
+		text
+	}
+
 }
 
 impl Algorithm2D for Map {
 	fn dimensions (&self) -> Point { Point::new(self.width, self.height) }
 }
 
+/// The bare floor plan, with nothing but walls in the way. What's standing
+/// about on it is in the spatial index, and LiveMap takes both into account.
 impl BaseMap for Map {
 	fn is_opaque(&self, idx: usize) -> bool {
 		self.tiles[idx] == TileType::Wall
 	}
 
 	fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
-		let mut exists = SmallVec::new();
-		let x = idx as i32 % self.width;
-		let y = idx as i32 / self.width;
-		let w = self.width as usize;
-
-		// Cardinal Directions
-		if self.is_walkable(x - 1, y) { exists.push((idx - 1, 1.)) };
-		if self.is_walkable(x + 1, y) { exists.push((idx + 1, 1.)) };
-		if self.is_walkable(x, y - 1) { exists.push((idx - w, 1.)) };
-		if self.is_walkable(x, y + 1) { exists.push((idx + w, 1.)) };
-
-		// Diagonals
-		if self.is_walkable(x - 1, y - 1) { exists.push(((idx - w) - 1, 1.45)) }
-		if self.is_walkable(x + 1, y - 1) { exists.push(((idx - w) + 1, 1.45)) }
-		if self.is_walkable(x - 1, y + 1) { exists.push(((idx + w) - 1, 1.45)) }
-		if self.is_walkable(x + 1, y + 1) { exists.push(((idx + w) + 1, 1.45)) }
-
-		return exists;
+		self.terrain_exits(idx)
 	}
 
 	fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -227,4 +465,4 @@ impl BaseMap for Map {
 
 		return DistanceAlg::Pythagoras.distance2d(p1, p2);
 	}
-}
\ No newline at end of file
+}
@@ -4,7 +4,7 @@ use crate::map::Map;
 use crate::map_builder::MapBuilder;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{apply_room_to_map, draw_corridor};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::rect::Rect;
 
 pub struct BspDungeonBuilder {
@@ -13,7 +13,7 @@ pub struct BspDungeonBuilder {
 	depth: i32,
 	rooms: Vec<Rect>,
 	rects: Vec<Rect>,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl BspDungeonBuilder {
@@ -25,7 +25,7 @@ impl BspDungeonBuilder {
 			depth,
 			rooms: Vec::new(),
 			rects: Vec::new(),
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -96,17 +96,15 @@ impl BspDungeonBuilder {
 }
 
 impl MapBuilder for BspDungeonBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		self.rects.clear();
 		// Place the first, big, room
 		self.rects.push(Rect::new(
@@ -123,8 +121,8 @@ impl MapBuilder for BspDungeonBuilder {
 		// a room, add one
 		let mut n_rooms = 0;
 		while n_rooms < 240 {
-			let rect = self.get_random_rect(&mut rng);
-			let candidate = self.get_random_sub_rect(rect, &mut rng);
+			let rect = self.get_random_rect(rng);
+			let candidate = self.get_random_sub_rect(rect, rng);
 
 			if self.is_possible(candidate) {
 				apply_room_to_map(&mut self.map, &candidate);
@@ -164,12 +162,10 @@ impl MapBuilder for BspDungeonBuilder {
 		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for room in self.rooms.iter().skip(1) {
-			spawner::spawn_room(ecs, room, self.depth, &self.map);
+			spawner::spawn_room(ecs, room, self.depth, map);
 		}
-
-		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
@@ -177,11 +173,11 @@ impl MapBuilder for BspDungeonBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use rltk::RandomNumberGenerator;
 use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 use crate::rect::Rect;
 
@@ -14,7 +14,7 @@ pub struct BspInteriorBuilder {
 	depth: i32,
 	rooms: Vec<Rect>,
 	rects: Vec<Rect>,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl BspInteriorBuilder {
@@ -31,7 +31,7 @@ impl BspInteriorBuilder {
 			depth,
 			rooms: Vec::new(),
 			rects: Vec::new(),
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -96,17 +96,15 @@ impl BspInteriorBuilder {
 }
 
 impl MapBuilder for BspInteriorBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		self.rects.clear();
 		self.rects.push(Rect::new(
 			1, 1,
@@ -115,7 +113,7 @@ impl MapBuilder for BspInteriorBuilder {
 		));
 
 		let first_room = self.rects[0];
-		self.add_subrects(first_room, &mut rng);
+		self.add_subrects(first_room, rng);
 
 		let rooms = self.rects.clone();
 		for r in rooms.iter() {
@@ -157,12 +155,10 @@ impl MapBuilder for BspInteriorBuilder {
 		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for room in self.rooms.iter().skip(1) {
-			spawner::spawn_room(ecs, &room, self.depth, &self.map);
+			spawner::spawn_room(ecs, &room, self.depth, map);
 		}
-
-		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
@@ -172,11 +168,11 @@ impl MapBuilder for BspInteriorBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
\ No newline at end of file
@@ -4,7 +4,7 @@ use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 
 pub struct CellularAutomataBuilder {
@@ -12,7 +12,7 @@ pub struct CellularAutomataBuilder {
 	starting_position: Position,
 	depth: i32,
 	noise_areas: HashMap<i32, Vec<usize>>,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl CellularAutomataBuilder {
@@ -28,23 +28,21 @@ impl CellularAutomataBuilder {
 			starting_position: Position { x: 0, y: 0 },
 			depth,
 			noise_areas: HashMap::new(),
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 }
 
 impl MapBuilder for CellularAutomataBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		for y in 1 .. self.map.height - 1 {
 			for x in 1 .. self.map.width - 1 {
 				let roll = rng.roll_dice(1, 100);
@@ -115,13 +113,13 @@ impl MapBuilder for CellularAutomataBuilder {
 		// Build noise map for entity spawning
 		self.noise_areas = generate_voronoi_spawn_regions(
 			&self.map,
-			&mut rng,
+			rng,
 		);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for area in self.noise_areas.iter() {
-			spawner::spawn_region(ecs, area.1, self.depth, &self.map);
+			spawner::spawn_region(ecs, area.1, self.depth, map);
 		}
 	}
 
@@ -132,10 +130,10 @@ impl MapBuilder for CellularAutomataBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
\ No newline at end of file
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use rltk::RandomNumberGenerator;
 use crate::map::Map;
 use crate::rect::Rect;
-use crate::{TileType};
+use crate::{Position, TileType};
 
 #[allow(dead_code)]
 #[derive(PartialEq, Copy, Clone)]
@@ -14,12 +14,50 @@ pub enum Symmetry {
 	Both,
 }
 
+/// Each step of building a map, for the visualiser. Only the tiles that
+/// changed since the step before are kept, rather than a whole map a step,
+/// and the maps are put back together when the visualiser asks for them.
 #[cfg(feature = "mapgen_visualiser")]
-pub fn snapshot (map: &Map) -> Map {
-	let mut snapshot = map.clone();
-	for v in snapshot.revealed_tiles.iter_mut() { *v = true; }
-	for v in snapshot.visible_tiles.iter_mut() { *v = true; }
-	snapshot
+#[derive(Default)]
+pub struct SnapshotHistory {
+	/// The tiles as of the latest step, to tell what's changed since
+	last    : Vec<TileType>,
+	/// Which tiles changed at each step, and what to
+	changes : Vec<Vec<(usize, TileType)>>,
+	width   : i32,
+	height  : i32,
+	depth   : i32,
+}
+
+#[cfg(feature = "mapgen_visualiser")]
+impl SnapshotHistory {
+	pub fn take (&mut self, map: &Map) {
+		// Anything a different size starts again from nothing
+		if self.last.len() != map.tiles.len() {
+			self.last = vec![TileType::Void; map.tiles.len()];
+		}
+
+		let changed = map.tiles.iter().enumerate()
+			.filter(|(idx, tile)| self.last[*idx] != **tile)
+			.map(|(idx, tile)| (idx, *tile))
+			.collect();
+		self.changes.push(changed);
+		self.last.copy_from_slice(&map.tiles);
+		(self.width, self.height, self.depth) = (map.width, map.height, map.depth);
+	}
+
+	/// Every step as a whole map, with all of it shown
+	pub fn maps (&self) -> Vec<Map> {
+		let mut map = Map::new(self.width, self.height, self.depth, None);
+		for idx in 0 .. map.tiles.len() { map.show(idx); }
+
+		self.changes.iter()
+			.map(|changed| {
+				for (idx, tile) in changed.iter() { map.tiles[*idx] = *tile; }
+				map.clone()
+			})
+			.collect()
+	}
 }
 
 pub fn apply_room_to_map (map: &mut Map, room: &Rect) {
@@ -80,8 +118,6 @@ pub fn apply_vertical_tunnel (map: &mut Map, y1: i32, y2: i32, x: i32) {
 }
 
 pub fn remove_unreachable_areas_returning_most_distant (map: &mut Map, start_idx: usize) -> usize {
-	map.populate_blocked();
-
 	let map_starts : Vec<usize> = vec![start_idx];
 	let dijkstra_map = rltk::DijkstraMap::new(
 		map.width, map.height,
@@ -190,4 +226,34 @@ pub fn apply_paint (map: &mut Map, brush_size: i32, x: i32, y: i32, tile: TileTy
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+/// Floods a few patches of floor with shallow water to drink from, kept a
+/// walk away from where the player starts
+pub fn add_pools (map: &mut Map, rng: &mut RandomNumberGenerator, start: &Position) {
+	let floor : Vec<usize> = (0 .. map.tiles.len())
+		.filter(|idx| map.tiles[*idx] == TileType::Floor)
+		.filter(|idx| {
+			let (x, y) = (*idx as i32 % map.width, *idx as i32 / map.width);
+			i32::abs(x - start.x) + i32::abs(y - start.y) > 10
+		})
+		.collect();
+	if floor.is_empty() { return; }
+
+	for _ in 0 .. rng.roll_dice(1, 3) {
+		let centre = floor[rng.range(0, floor.len() as i32) as usize];
+		let (cx, cy) = (centre as i32 % map.width, centre as i32 / map.width);
+
+		for y in cy - 1 ..= cy + 1 {
+			for x in cx - 1 ..= cx + 1 {
+				if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { continue; }
+				let idx = map.xy_idx(x, y);
+
+				// Ragged round the edges
+				if map.tiles[idx] == TileType::Floor && (idx == centre || rng.roll_dice(1, 3) > 1) {
+					map.tiles[idx] = TileType::Water;
+				}
+			}
+		}
+	}
+}
@@ -4,7 +4,7 @@ use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{generate_voronoi_spawn_regions, paint, remove_unreachable_areas_returning_most_distant, Symmetry};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -24,7 +24,7 @@ pub struct DLABuilder {
 	symmetry: Symmetry,
 	floor_percent: f32,
 	#[cfg(feature = "mapgen_visualiser")] name: String,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl DLABuilder {
@@ -50,7 +50,7 @@ impl DLABuilder {
 			symmetry,
 			floor_percent: 0.25,
 			#[cfg(feature = "mapgen_visualiser")] name,
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -100,17 +100,15 @@ impl DLABuilder {
 }
 
 impl MapBuilder for DLABuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		// Carve starting seed
 		self.starting_position = Position {
 			x: self.map.width / 2,
@@ -226,13 +224,13 @@ impl MapBuilder for DLABuilder {
 		// Build noise map for entity spawning
 		self.noise_areas = generate_voronoi_spawn_regions(
 			&self.map,
-			&mut rng,
+			rng,
 		);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for area in self.noise_areas.iter() {
-			spawner::spawn_region(ecs, area.1, self.depth, &self.map);
+			spawner::spawn_region(ecs, area.1, self.depth, map);
 		}
 	}
 
@@ -243,11 +241,11 @@ impl MapBuilder for DLABuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
\ No newline at end of file
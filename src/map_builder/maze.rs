@@ -4,7 +4,7 @@ use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 
 const TOP    : usize = 0;
@@ -201,7 +201,7 @@ pub struct MazeBuilder {
 	starting_position: Position,
 	depth: i32,
 	noise_areas: HashMap<i32, Vec<usize>>,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl MazeBuilder {
@@ -217,27 +217,25 @@ impl MazeBuilder {
 			starting_position: Position { x: 0, y: 0 },
 			depth,
 			noise_areas: HashMap::new(),
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 }
 
 impl MapBuilder for MazeBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		let mut grid = Grid::new(
 			(self.map.width / 2) - 2,
 			(self.map.height / 2) - 2,
-			&mut rng,
+			rng,
 		);
 		grid.generate_maze(self);
 
@@ -255,12 +253,12 @@ impl MapBuilder for MazeBuilder {
 		self.map.tiles[exit_tile] = TileType::DownStairs;
 		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 
-		self.noise_areas = generate_voronoi_spawn_regions(&self.map, &mut rng);
+		self.noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for area in self.noise_areas.iter() {
-			spawner::spawn_region(ecs, area.1, self.depth, &self.map);
+			spawner::spawn_region(ecs, area.1, self.depth, map);
 		}
 	}
 
@@ -271,11 +269,11 @@ impl MapBuilder for MazeBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map))
+		self.history.take(&self.map)
 	}
 }
\ No newline at end of file
@@ -10,6 +10,8 @@ mod voronoi;
 mod waveform_collapse;
 mod prefab_builder;
 
+use std::thread::JoinHandle;
+use rltk::RandomNumberGenerator;
 use specs::World;
 use crate::Position;
 use super::Map;
@@ -23,16 +25,21 @@ use crate::map_builder::{
 	maze::MazeBuilder,
 	dla::DLABuilder,
 	voronoi::VoronoiBuilder,
-	waveform_collapse::WaveformCollapseBuilder,
 };
 use crate::map_builder::prefab_builder::PrefabBuilder;
+pub use crate::map_builder::common::add_pools;
+pub use crate::map_builder::waveform_collapse::{build_constraints, solve_once, WaveformCollapseBuilder};
 
-pub trait MapBuilder {
-	fn get_map (&mut self) -> Map;
+/// Builders are sent off to build on their own thread, so they have to be
+/// `Send`
+pub trait MapBuilder: Send {
+	/// Hands the finished map over, leaving the builder without one
+	fn take_map (&mut self) -> Map;
 	fn get_starting_position (&mut self) -> Position;
 
-	fn build (&mut self);
-	fn spawn (&mut self, ecs: &mut World);
+	fn build (&mut self, rng: &mut RandomNumberGenerator);
+	/// Fills the map that was taken with monsters and loot
+	fn spawn (&mut self, ecs: &mut World, map: &Map);
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_name (&self) -> String;
@@ -44,8 +51,8 @@ pub trait MapBuilder {
 
 #[allow(unused_macros)]
 macro_rules! pick_random {
-	($depth:expr, $($x:expr),* $(,)?) => {{
-		let mut rng = rltk::RandomNumberGenerator::new();
+	($depth:expr, $rng:expr, $($x:expr),* $(,)?) => {{
+		let rng : &mut RandomNumberGenerator = $rng;
 		let builder = (rng.roll_dice(1, ${count(x, 0)}) - 1) as u8;
 		let mut result : Box<dyn MapBuilder>;
 		match builder {
@@ -61,8 +68,8 @@ macro_rules! pick_random {
 	}};
 }
 
-pub fn random_builder (depth: i32) -> Box<dyn MapBuilder> {
-	pick_random!(depth,
+pub fn random_builder (depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+	pick_random!(depth, rng,
 		SimpleMapBuilder::new,
 		BspInteriorBuilder::new,
 		CellularAutomataBuilder::new,
@@ -83,3 +90,96 @@ pub fn random_builder (depth: i32) -> Box<dyn MapBuilder> {
 	)
 	// Box::new(PrefabBuilder::new(depth))
 }
+
+/// Every builder that can be asked for by name, as on the command line
+pub const BUILDER_NAMES : [&str; 18] = [
+	"simple", "bsp_interior", "cellular_automata", "bsp_dungeon",
+	"open_area", "open_halls", "winding_passages", "fat_passages", "fearful_symmetry",
+	"maze", "walk_inwards", "walk_outwards", "central_attractor", "insectoid",
+	"voronoi_pythagoras", "voronoi_manhattan", "voronoi_chebyshev", "prefab",
+];
+
+/// The builder going by `name`, if there is one. Unlike the random pick it's
+/// never run through Waveform Collapse after, so it's seen as it is.
+pub fn named_builder (name: &str, depth: i32) -> Option<Box<dyn MapBuilder>> {
+	let builder : Box<dyn MapBuilder> = match name {
+		"simple" => Box::new(SimpleMapBuilder::new(depth)),
+		"bsp_interior" => Box::new(BspInteriorBuilder::new(depth)),
+		"cellular_automata" => Box::new(CellularAutomataBuilder::new(depth)),
+		"bsp_dungeon" => Box::new(BspDungeonBuilder::new(depth)),
+		"open_area" => Box::new(DrunkardWalkBuilder::open_area(depth)),
+		"open_halls" => Box::new(DrunkardWalkBuilder::open_halls(depth)),
+		"winding_passages" => Box::new(DrunkardWalkBuilder::winding_passages(depth)),
+		"fat_passages" => Box::new(DrunkardWalkBuilder::fat_passages(depth)),
+		"fearful_symmetry" => Box::new(DrunkardWalkBuilder::fearful_symmetry(depth)),
+		"maze" => Box::new(MazeBuilder::new(depth)),
+		"walk_inwards" => Box::new(DLABuilder::walk_inwards(depth)),
+		"walk_outwards" => Box::new(DLABuilder::walk_outwards(depth)),
+		"central_attractor" => Box::new(DLABuilder::central_attractor(depth)),
+		"insectoid" => Box::new(DLABuilder::insectoid(depth)),
+		"voronoi_pythagoras" => Box::new(VoronoiBuilder::pythagoras(depth)),
+		"voronoi_manhattan" => Box::new(VoronoiBuilder::manhattan(depth)),
+		"voronoi_chebyshev" => Box::new(VoronoiBuilder::chebyshev(depth)),
+		"prefab" => Box::new(PrefabBuilder::new(depth)),
+		_ => return None,
+	};
+	Some(builder)
+}
+
+/// The seed for one level of a run, so every level of a seeded run comes out
+/// the same however it's reached
+pub fn level_seed (run_seed: u64, depth: i32) -> u64 {
+	run_seed.wrapping_add((depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Picks and builds the level for `depth`, everything drawn from the one
+/// generator seeded for it. A builder asked for by name is used instead of a
+/// random one.
+pub fn build_level (run_seed: u64, depth: i32, forced: Option<&str>) -> Box<dyn MapBuilder> {
+	let mut rng = RandomNumberGenerator::seeded(level_seed(run_seed, depth));
+	let mut builder = forced
+		.and_then(|name| named_builder(name, depth))
+		.unwrap_or_else(|| random_builder(depth, &mut rng));
+	builder.build(&mut rng);
+	builder
+}
+
+/// A level being built off the main thread, which can take a while when
+/// Waveform Collapse has to start over a few times. The game keeps drawing in
+/// the meantime.
+pub struct LevelBuild {
+	pub depth      : i32,
+	pub elapsed_ms : f32,
+	handle         : Option<JoinHandle<Box<dyn MapBuilder>>>,
+	built          : Option<Box<dyn MapBuilder>>,
+}
+
+impl LevelBuild {
+	/// Starts building a level for `depth`. Where there are no threads to be
+	/// had it's all done up front instead.
+	pub fn start (run_seed: u64, depth: i32, forced: Option<String>) -> LevelBuild {
+		#[cfg(not(target_arch = "wasm32"))]
+		return LevelBuild {
+			depth,
+			elapsed_ms: 0.,
+			handle: Some(std::thread::spawn(move || build_level(run_seed, depth, forced.as_deref()))),
+			built: None,
+		};
+
+		#[cfg(target_arch = "wasm32")]
+		LevelBuild { depth, elapsed_ms: 0., handle: None, built: Some(build_level(run_seed, depth, forced.as_deref())) }
+	}
+
+	pub fn is_finished (&self) -> bool {
+		self.handle.as_ref().map_or(true, |handle| handle.is_finished())
+	}
+
+	/// The finished builder, ready to spawn into the world. Waits on it if it
+	/// isn't done yet.
+	pub fn finish (self) -> Box<dyn MapBuilder> {
+		match self.handle {
+			Some(handle) => handle.join().expect("The level collapsed under its own weight"),
+			None => self.built.expect("Lost the level somewhere"),
+		}
+	}
+}
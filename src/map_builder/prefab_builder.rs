@@ -1,7 +1,8 @@
+use rltk::RandomNumberGenerator;
 use specs::World;
 use crate::map::Map;
 #[cfg(feature = "mapgen_visualiser")]
-use crate::map_builder::common::snapshot;
+use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 use crate::{Position, TileType};
 
@@ -16,7 +17,7 @@ pub struct PrefabBuilder {
 	starting_position: Position,
 	depth: i32,
 	mode: PrefabMode,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl PrefabBuilder {
@@ -26,7 +27,7 @@ impl PrefabBuilder {
 			starting_position: Position { x: 0, y: 0 },
 			depth,
 			mode: PrefabMode::RexLevel { template: "../resources/wfc-demo1.xp" },
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -46,6 +47,7 @@ impl PrefabBuilder {
 					self.map.tiles[idx] = match (cell.ch as u8) as char {
 						' ' => TileType::Floor, // Space
 						'#' => TileType::Wall,  // Hash
+						'>' => TileType::DownStairs,
 						 c  => panic!("Unknown REX map character: {}", c),
 					}
 				}
@@ -55,15 +57,15 @@ impl PrefabBuilder {
 }
 
 impl MapBuilder for PrefabBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
+	fn build(&mut self, _rng: &mut RandomNumberGenerator) {
 		match self.mode {
 			PrefabMode::RexLevel {template} => self.load_rex_map(&template),
 		}
@@ -87,9 +89,7 @@ impl MapBuilder for PrefabBuilder {
 		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
-		// todo!()
-	}
+	fn spawn(&mut self, _ecs: &mut World, _map: &Map) {}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_name(&self) -> String {
@@ -98,11 +98,11 @@ impl MapBuilder for PrefabBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
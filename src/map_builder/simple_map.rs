@@ -4,7 +4,7 @@ use crate::map::Map;
 use crate::map_builder::MapBuilder;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::rect::Rect;
 
 pub struct SimpleMapBuilder {
@@ -13,27 +13,29 @@ pub struct SimpleMapBuilder {
 	depth: i32,
 	rooms: Vec<Rect>,
 	#[cfg(feature = "mapgen_visualiser")]
-	history: Vec<Map>,
+	history: SnapshotHistory,
 }
 
 impl MapBuilder for SimpleMapBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		self.rooms_and_corridors();
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
+		self.rooms_and_corridors(rng);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
+		for room in self.rooms.iter() {
+			spawner::spawn_doors(ecs, room, map);
+		}
 		for room in self.rooms.iter().skip(1) {
-			spawner::spawn_room(ecs, room, self.depth, &self.map);
+			spawner::spawn_room(ecs, room, self.depth, map);
 		}
-		#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
@@ -41,12 +43,12 @@ impl MapBuilder for SimpleMapBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
 
@@ -64,17 +66,15 @@ impl SimpleMapBuilder {
 			depth,
 			rooms: Vec::new(),
 			#[cfg(feature = "mapgen_visualiser")]
-			history: Vec::new(),
+			history: SnapshotHistory::default(),
 		}
 	}
 
-	fn rooms_and_corridors (&mut self) {
+	fn rooms_and_corridors (&mut self, rng: &mut RandomNumberGenerator) {
 		const MAX_ROOMS : i32 = 30;
 		const MIN_SIZE  : i32 = 6;
 		const MAX_SIZE  : i32 = 10;
 
-		let mut rng = RandomNumberGenerator::new();
-
 		'generateRooms: for _ in 0..MAX_ROOMS {
 			let w = rng.range(MIN_SIZE, MAX_SIZE);
 			let h = rng.range(MIN_SIZE, MAX_SIZE);
@@ -4,7 +4,7 @@ use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 use crate::map_builder::common::{generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant};
-#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::snapshot;
+#[cfg(feature = "mapgen_visualiser")] use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::MapBuilder;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -22,7 +22,7 @@ pub struct VoronoiBuilder {
 	n_seeds: usize,
 	distance_algorithm: DistanceAlgorithm,
 	#[cfg(feature = "mapgen_visualiser")] name: String,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl VoronoiBuilder {
@@ -45,7 +45,7 @@ impl VoronoiBuilder {
 			n_seeds: 64,
 			distance_algorithm,
 			#[cfg(feature = "mapgen_visualiser")] name,
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -78,17 +78,15 @@ impl VoronoiBuilder {
 }
 
 impl MapBuilder for VoronoiBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		// Start at center
 		self.starting_position = Position {
 			x: self.map.width / 2,
@@ -176,13 +174,13 @@ impl MapBuilder for VoronoiBuilder {
 		// Build noise map for entity spawning
 		self.noise_areas = generate_voronoi_spawn_regions(
 			&self.map,
-			&mut rng,
+			rng,
 		);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for area in self.noise_areas.iter() {
-			spawner::spawn_region(ecs, area.1, self.depth, &self.map);
+			spawner::spawn_region(ecs, area.1, self.depth, map);
 		}
 	}
 
@@ -193,11 +191,11 @@ impl MapBuilder for VoronoiBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
\ No newline at end of file
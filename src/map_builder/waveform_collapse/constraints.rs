@@ -77,7 +77,7 @@ pub fn render_pattern_to_map (
 		for x in 0..chunk_size {
 			let idx = map.xy_idx(start_x + x, start_y + y);
 			map.tiles[idx] = chunk.pattern[i];
-			map.visible_tiles[idx] = true;
+			map.show(idx);
 			i += 1;
 		}
 	}
@@ -8,13 +8,38 @@ use specs::World;
 use crate::map::Map;
 use crate::{MAP_HEIGHT, MAP_WIDTH, Position, spawner, TileType};
 #[cfg(feature = "mapgen_visualiser")]
-use crate::map_builder::common::snapshot;
+use crate::map_builder::common::SnapshotHistory;
 use crate::map_builder::common::{generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant};
 use crate::map_builder::MapBuilder;
 use crate::map_builder::waveform_collapse::common::MapChunk;
 use crate::map_builder::waveform_collapse::constraints::{build_patterns, patterns_to_constraints, render_pattern_to_map};
 use crate::map_builder::waveform_collapse::solver::Solver;
 
+/// How many tiles across each chunk of the source map is
+const CHUNK_SIZE : i32 = 8;
+
+/// Chops `source` into chunks and works out which can sit beside which
+pub fn build_constraints (source: &Map) -> Vec<MapChunk> {
+	let patterns = build_patterns(source, CHUNK_SIZE, true, true);
+	patterns_to_constraints(patterns, CHUNK_SIZE)
+}
+
+/// Has one go at piecing `map` together from the constraints, calling `step`
+/// after each chunk goes down. Gives back whether it worked, since it can
+/// paint itself into a corner and need to start over.
+pub fn solve_once (
+	constraints: &[MapChunk],
+	map: &mut Map,
+	rng: &mut RandomNumberGenerator,
+	mut step: impl FnMut(&Map),
+) -> bool {
+	let mut solver = Solver::new(constraints.to_vec(), CHUNK_SIZE, map);
+	while !solver.iteration(map, rng) {
+		step(map);
+	}
+	solver.possible
+}
+
 pub struct WaveformCollapseBuilder {
 	map: Map,
 	starting_position: Position,
@@ -22,7 +47,7 @@ pub struct WaveformCollapseBuilder {
 	noise_areas: HashMap<i32, Vec<usize>>,
 	derive_from: Option<Box<dyn MapBuilder>>,
 	#[cfg(feature = "mapgen_visualiser")] name: String,
-	#[cfg(feature = "mapgen_visualiser")] history: Vec<Map>,
+	#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory,
 }
 
 impl WaveformCollapseBuilder {
@@ -43,7 +68,7 @@ impl WaveformCollapseBuilder {
 			noise_areas: HashMap::new(),
 			derive_from,
 			#[cfg(feature = "mapgen_visualiser")] name,
-			#[cfg(feature = "mapgen_visualiser")] history: Vec::new(),
+			#[cfg(feature = "mapgen_visualiser")] history: SnapshotHistory::default(),
 		}
 	}
 
@@ -103,55 +128,38 @@ impl WaveformCollapseBuilder {
 }
 
 impl MapBuilder for WaveformCollapseBuilder {
-	fn get_map(&mut self) -> Map {
-		self.map.clone()
+	fn take_map(&mut self) -> Map {
+		std::mem::take(&mut self.map)
 	}
 
 	fn get_starting_position(&mut self) -> Position {
 		self.starting_position.clone()
 	}
 
-	fn build(&mut self) {
-		let mut rng = RandomNumberGenerator::new();
-
-		// Waveform Collapse
-		const CHUNK_SIZE: i32 = 8;
-
+	fn build(&mut self, rng: &mut RandomNumberGenerator) {
 		let mut source_map: Map;
 
 		let prebuilder = &mut self.derive_from.as_mut().unwrap();
-		prebuilder.build();
-		source_map = prebuilder.get_map();
+		prebuilder.build(rng);
+		source_map = prebuilder.take_map();
 		for t in source_map.tiles.iter_mut() {
 			if *t == TileType::DownStairs { *t = TileType::Floor }
 		}
 
-		let patterns = build_patterns(
-			&source_map,
-			CHUNK_SIZE,
-			true,
-			true,
-		);
-
-		let constraints = patterns_to_constraints(patterns, CHUNK_SIZE);
+		let constraints = build_constraints(&source_map);
 
 		// #[cfg(feature = "mapgen_visualiser")]
 		// self.render_tile_gallery(&constraints, CHUNK_SIZE);
 
 		loop {
-			let mut solver = Solver::new(
-				constraints.clone(),
-				CHUNK_SIZE,
-				&self.map,
-			);
-
-			while !solver.iteration(&mut self.map, &mut rng) {
-				#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
-			}
+			#[cfg(feature = "mapgen_visualiser")] let history = &mut self.history;
+			let solved = solve_once(&constraints, &mut self.map, rng, |_map| {
+				#[cfg(feature = "mapgen_visualiser")] history.take(_map);
+			});
 
 			#[cfg(feature = "mapgen_visualiser")] self.take_snapshot();
 
-			if solver.possible { break }
+			if solved { break }
 		}
 
 		// Starting pos
@@ -185,13 +193,13 @@ impl MapBuilder for WaveformCollapseBuilder {
 		// Build noise map for entity spawning
 		self.noise_areas = generate_voronoi_spawn_regions(
 			&self.map,
-			&mut rng,
+			rng,
 		);
 	}
 
-	fn spawn(&mut self, ecs: &mut World) {
+	fn spawn(&mut self, ecs: &mut World, map: &Map) {
 		for area in self.noise_areas.iter() {
-			spawner::spawn_region(ecs, area.1, self.depth, &self.map);
+			spawner::spawn_region(ecs, area.1, self.depth, map);
 		}
 	}
 
@@ -202,11 +210,11 @@ impl MapBuilder for WaveformCollapseBuilder {
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn get_snapshot_history(&self) -> Vec<Map> {
-		self.history.clone()
+		self.history.maps()
 	}
 
 	#[cfg(feature = "mapgen_visualiser")]
 	fn take_snapshot(&mut self) {
-		self.history.push(snapshot(&self.map));
+		self.history.take(&self.map);
 	}
 }
@@ -1,34 +1,35 @@
 use specs::prelude::*;
-use crate::{BlocksTile, Position};
+use crate::{BlocksTile, Door, Position};
 use crate::map::Map;
+use crate::spatial::SpatialIndex;
 
 pub struct MapIndexingSystem {}
 
 impl<'a> System<'a> for MapIndexingSystem {
 	type SystemData = (
-		WriteExpect<'a, Map>,
+		ReadExpect<'a, Map>,
+		WriteExpect<'a, SpatialIndex>,
 		ReadStorage<'a, Position>,
 		ReadStorage<'a, BlocksTile>,
 		Entities<'a>,
+		ReadStorage<'a, Door>,
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
-		let (mut map, position, blockers, entities) = data;
+		let (map, mut spatial, position, blockers, entities, doors) = data;
 
-		map.populate_blocked();
-		map.clear_content_index();
+		spatial.clear();
 
 		for (entity, position) in (&entities, &position).join() {
 			let idx = map.xy_idx(position.x, position.y);
 
-			// If the block, update the blocking list
-			let _p : Option<&BlocksTile> = blockers.get(entity);
-			if let Some(_p) = _p {
-				map.blocked[idx] = true;
-			}
+			spatial.index_entity(entity, idx, blockers.get(entity).is_some());
 
-			// Push a copy ref of the entity to the appropriate index slot
-			map.tile_content[idx].push(entity);
+			if let Some(door) = doors.get(entity) {
+				if !door.open {
+					spatial.set_door(idx, true);
+				}
+			}
 		}
 	}
-}
\ No newline at end of file
+}
@@ -1,8 +1,17 @@
-use rltk::RGB;
 use specs::prelude::*;
-use crate::{CombatStats, DefenseBonus, Equipped, HungerClock, HungerState, MeleePowerBonus, Name, Position, SufferDamage, WantsToMelee};
+use crate::{Awareness, AwarenessState, CombatStats, PackMember, Swarm, DefenseBonus, Equipped, HungerClock, HungerState, MeleePowerBonus, Name, NameOf, Position, SufferDamage, WantsToMelee};
+use crate::ai::NoiseBuilder;
+use crate::config::Config;
 use crate::gamelog::GameLog;
-use crate::particle_system::ParticleBuilder;
+use crate::map::Map;
+use crate::palette::Palette;
+use crate::particle_system::{Particle, ParticleBuilder};
+
+/// How far away the clash of a fight can be heard
+const COMBAT_NOISE : i32 = 8;
+
+/// Extra power a swarm member gets for each swarm mate next to its target
+const SWARM_BONUS : i32 = 1;
 
 pub struct MeleeCombatSystem {}
 
@@ -18,15 +27,24 @@ impl<'a> System<'a> for MeleeCombatSystem {
 		ReadStorage<'a, DefenseBonus>,
 		ReadStorage<'a, Equipped>,
 		WriteExpect<'a, ParticleBuilder>,
+		ReadExpect<'a, Palette>,
 		ReadStorage<'a, Position>,
 		ReadStorage<'a, HungerClock>,
+		ReadExpect<'a, Map>,
+		ReadExpect<'a, Entity>,
+		ReadStorage<'a, Awareness>,
+		WriteExpect<'a, NoiseBuilder>,
+		ReadStorage<'a, Swarm>,
+		ReadStorage<'a, PackMember>,
+		ReadExpect<'a, Config>,
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
 			entities, mut wants_melee, names, combat_stats, mut inflict_damage,
 			mut log, melee_power_bonuses, defense_bonuses, equipped,
-			mut particle_builder, positions, hunger,
+			mut particle_builder, palette, positions, hunger, map, player_entity,
+			awareness, mut noise, swarms, pack_members, config,
 		) = data;
 
 		let query = (&entities, &wants_melee, &names, &combat_stats).join();
@@ -48,7 +66,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
 
 				if let Some(target_stats) = combat_stats.get(wants_melee.target) {
 					if target_stats.hp > 0 {
-						let target_name = names.get(wants_melee.target).unwrap();
+						let target_name = names.name_of(wants_melee.target);
 
 						let mut defensive_bonus = 0;
 						for (_item_entity, defense_bonus, equipped_by) in (&entities, &defense_bonuses, &equipped).join() {
@@ -57,37 +75,74 @@ impl<'a> System<'a> for MeleeCombatSystem {
 							}
 						}
 
+						// Only report fights the player is in or can see
+						let seen = _entity == *player_entity
+							|| wants_melee.target == *player_entity
+							|| positions.get(wants_melee.target)
+								.map_or(false, |pos| map.is_visible(map.xy_idx(pos.x, pos.y)));
+
 						let pos = positions.get(wants_melee.target);
 						if let Some(pos) = pos {
+							noise.request(pos.x, pos.y, COMBAT_NOISE);
 							particle_builder.request(
 								pos.x, pos.y,
-								RGB::named(rltk::ORANGERED),
-								RGB::named(rltk::BLACK),
-								rltk::to_cp437('‼'),
-								150.,
+								Particle { fg: palette.hit, bg: palette.background, glyph: rltk::to_cp437('‼'), lifetime: config.particles.hit_ms },
 							);
 						}
 
-						let damage = i32::max(0, (stats.power + offensive_bonus) - (target_stats.defence + defensive_bonus));
+						// Catching a monster unawares hits twice as hard
+						let mut attack = stats.power + offensive_bonus;
+						let sneak_attack = _entity == *player_entity
+							&& awareness.get(wants_melee.target)
+								.map_or(false, |a| a.state != AwarenessState::Hunting);
+						if sneak_attack {
+							attack *= 2;
+							log.combat(format!(
+								"You catch {} unawares!",
+								target_name,
+							));
+						}
+
+						// Swarms pile on, each mate on the target lending a bite
+						if let (Some(_), Some(member), Some(target_pos)) = (
+							swarms.get(_entity),
+							pack_members.get(_entity),
+							positions.get(wants_melee.target),
+						) {
+							for (mate, _swarm, mate_member, mate_pos) in (&entities, &swarms, &pack_members, &positions).join() {
+								if mate == _entity || mate_member.pack_id != member.pack_id { continue; }
+								if (mate_pos.x - target_pos.x).abs() <= 1 && (mate_pos.y - target_pos.y).abs() <= 1 {
+									attack += SWARM_BONUS;
+								}
+							}
+						}
+
+						let damage = i32::max(0, attack - (target_stats.defence + defensive_bonus));
 
 						if damage == 0 {
-							log.entries.push(format!(
-								"{} did no damage to {}!",
-								&name.name,
-								&target_name.name,
-							));
+							if seen {
+								log.combat(format!(
+									"{} did no damage to {}!",
+									&name.name,
+									target_name,
+								));
+							}
 						} else {
-							log.entries.push(format!(
-								"{} hits {} for {}hp!",
-								&name.name,
-								&target_name.name,
-								damage,
-							));
+							if seen {
+								log.combat(format!(
+									"{} hits {} for {}hp!",
+									&name.name,
+									target_name,
+									damage,
+								));
+							}
 
 							SufferDamage::new_damage(
 								&mut inflict_damage,
 								wants_melee.target,
 								damage,
+								&name.name,
+								_entity == *player_entity,
 							);
 						}
 					}
@@ -0,0 +1,145 @@
+use specs::prelude::*;
+use crate::{HungerClock, HungerState, NeedsFlash, SufferDamage, ThirstClock, ThirstState, TurnState};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::gamelog::GameLog;
+use crate::particle_system::AnimationClock;
+
+/// How long the food or water bar flashes for when going without hurts
+const FLASH_MS : f32 = 400.;
+
+/// Runs down everyone's hunger and thirst a turn at a time, and hurts them
+/// once they've gone without for too long
+pub struct NeedsSystem {}
+
+impl<'a> System<'a> for NeedsSystem {
+	type SystemData = (
+		Entities<'a>,
+		WriteStorage<'a, HungerClock>,
+		WriteStorage<'a, ThirstClock>,
+		ReadExpect<'a, Entity>,
+		ReadExpect<'a, TurnState>,
+		WriteStorage<'a, SufferDamage>,
+		WriteExpect<'a, GameLog>,
+		WriteStorage<'a, NeedsFlash>,
+		ReadExpect<'a, Config>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, mut hunger_clock, mut thirst_clock, player_entity, turn_state,
+			mut inflict_damage, mut log, mut flashes, config,
+		) = data;
+
+		// Everyone's clock only runs on their own side's turn
+		let their_turn = |entity: Entity| match *turn_state {
+			TurnState::PlayerTurn => entity == *player_entity,
+			TurnState::MonsterTurn => entity != *player_entity,
+			_ => false,
+		};
+
+		for (entity, mut clock) in (&entities, &mut hunger_clock).join() {
+			if !their_turn(entity) { continue; }
+			let is_player = entity == *player_entity;
+
+			clock.duration -= 1;
+			if clock.duration > 0 { continue; }
+
+			match clock.state {
+				HungerState::WellFed => {
+					clock.state = HungerState::Normal;
+					clock.duration = config.hunger.stage_turns;
+					if is_player {
+						log.system("It's been a while since you ate, it's now safe to swim".to_string());
+					}
+				}
+				HungerState::Normal => {
+					clock.state = HungerState::Hungry;
+					clock.duration = config.hunger.stage_turns;
+					if is_player {
+						log.system("Your stomach starts to growl".to_string());
+					}
+				}
+				HungerState::Hungry => {
+					clock.state = HungerState::Starving;
+					clock.duration = config.hunger.stage_turns;
+					if is_player {
+						log.system("Your stomach is about to go on strike".to_string());
+					}
+				}
+				HungerState::Starving => {
+					if is_player {
+						log.system("Your stomach is rioting".to_string());
+						flashes.entry(entity)
+							.map(|flash| flash.or_insert_with(NeedsFlash::default).food_ms = FLASH_MS)
+							.or_warn("Too weak to flash");
+					}
+
+					SufferDamage::new_damage(&mut inflict_damage, entity, 1, "starvation", false);
+				}
+			}
+		}
+
+		for (entity, clock) in (&entities, &mut thirst_clock).join() {
+			if !their_turn(entity) { continue; }
+			let is_player = entity == *player_entity;
+
+			clock.duration -= 1;
+			if clock.duration > 0 { continue; }
+
+			match clock.state {
+				ThirstState::Quenched => {
+					clock.state = ThirstState::Normal;
+					clock.duration = config.thirst.stage_turns;
+					if is_player {
+						log.system("Your last drink's worn off".to_string());
+					}
+				}
+				ThirstState::Normal => {
+					clock.state = ThirstState::Thirsty;
+					clock.duration = config.thirst.stage_turns;
+					if is_player {
+						log.system("Your mouth's getting dry".to_string());
+					}
+				}
+				ThirstState::Thirsty => {
+					clock.state = ThirstState::Parched;
+					clock.duration = config.thirst.stage_turns;
+					if is_player {
+						log.system("Your tongue sticks to the roof of your mouth".to_string());
+					}
+				}
+				ThirstState::Parched => {
+					if is_player {
+						log.system("You're dizzy with thirst".to_string());
+						flashes.entry(entity)
+							.map(|flash| flash.or_insert_with(NeedsFlash::default).water_ms = FLASH_MS)
+							.or_warn("Too weak to flash");
+					}
+
+					SufferDamage::new_damage(&mut inflict_damage, entity, 1, "dehydration", false);
+				}
+			}
+		}
+	}
+}
+
+impl NeedsSystem {
+	/// Runs down the food and water bar flashes, once a frame
+	pub fn fade_flashes (ecs: &mut World) {
+		let step_ms = ecs.fetch::<AnimationClock>().step_ms;
+		let mut faded : Vec<Entity> = Vec::new();
+		{
+			let mut flashes = ecs.write_storage::<NeedsFlash>();
+			let entities = ecs.entities();
+			for (entity, flash) in (&entities, &mut flashes).join() {
+				flash.food_ms -= step_ms;
+				flash.water_ms -= step_ms;
+				if flash.food_ms < 0. && flash.water_ms < 0. { faded.push(entity); }
+			}
+		}
+
+		let mut flashes = ecs.write_storage::<NeedsFlash>();
+		for entity in faded { flashes.remove(entity); }
+	}
+}
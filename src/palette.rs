@@ -0,0 +1,188 @@
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use crate::gamelog::LogCategory;
+
+/// The colour schemes on offer in the display options
+#[derive(Serialize, Deserialize, PartialEq, Copy, Clone, Debug, Default)]
+pub enum PaletteKind {
+	#[default]
+	Default,
+	/// Keeps clear of red against green, leaning on blue and orange instead
+	Deuteranopia,
+	HighContrast,
+}
+
+impl PaletteKind {
+	pub const ALL : [PaletteKind; 3] = [PaletteKind::Default, PaletteKind::Deuteranopia, PaletteKind::HighContrast];
+
+	pub fn label (&self) -> &'static str {
+		match self {
+			PaletteKind::Default => "Default",
+			PaletteKind::Deuteranopia => "Deuteranopia",
+			PaletteKind::HighContrast => "High contrast",
+		}
+	}
+
+	/// The palette `by` places along the list, wrapping round at the ends
+	pub fn step (&self, by: i32) -> PaletteKind {
+		let i = PaletteKind::ALL.iter().position(|kind| kind == self).unwrap_or(0) as i32;
+		PaletteKind::ALL[(i + by).rem_euclid(PaletteKind::ALL.len() as i32) as usize]
+	}
+}
+
+/// What everything other than the monsters and items themselves is drawn in,
+/// looked up by what it's for rather than what colour it happens to be
+#[derive(PartialEq, Copy, Clone)]
+pub struct Palette {
+	// Map
+	pub floor            : RGB,
+	pub wall             : RGB,
+	pub stairs           : RGB,
+	pub water            : RGB,
+	pub placeholder      : RGB,
+	pub blood            : RGB,
+	pub remembered_blood : RGB,
+
+	// Interface
+	pub background       : RGB,
+	pub text             : RGB,
+	pub text_dim         : RGB,
+	pub faint            : RGB,
+	pub highlight        : RGB,
+	pub title            : RGB,
+	pub value            : RGB,
+	pub health           : RGB,
+	pub good             : RGB,
+	pub warning          : RGB,
+	pub danger           : RGB,
+	pub target           : RGB,
+
+	// Particles
+	pub blast            : RGB,
+	pub heal             : RGB,
+	pub hit              : RGB,
+	pub damage           : RGB,
+
+	// Log
+	pub log_combat       : RGB,
+	pub log_loot         : RGB,
+	pub log_system       : RGB,
+	pub log_dialogue     : RGB,
+}
+
+impl Palette {
+	pub fn new (kind: PaletteKind) -> Palette {
+		match kind {
+			PaletteKind::Default => Palette {
+				floor: RGB::from_f32(0.1, 0.4, 0.1),
+				wall: RGB::from_f32(0.1, 0.4, 0.1),
+				stairs: RGB::named(rltk::WHEAT4),
+				water: RGB::named(rltk::STEELBLUE),
+				placeholder: RGB::named(rltk::SLATEGRAY),
+				blood: RGB::named(rltk::DARK_RED),
+				remembered_blood: RGB::named(rltk::DARKSLATEGREY),
+
+				background: RGB::named(rltk::BLACK),
+				text: RGB::named(rltk::WHITE),
+				text_dim: RGB::named(rltk::GREY),
+				faint: RGB::named(rltk::DARK_GRAY),
+				highlight: RGB::named(rltk::CYAN),
+				title: RGB::named(rltk::GOLD),
+				value: RGB::named(rltk::YELLOW),
+				health: RGB::named(rltk::RED),
+				good: RGB::named(rltk::LAWN_GREEN),
+				warning: RGB::named(rltk::ORANGE),
+				danger: RGB::named(rltk::RED3),
+				target: RGB::named(rltk::BLUE),
+
+				blast: RGB::named(rltk::ORANGERED),
+				heal: RGB::named(rltk::GREEN),
+				hit: RGB::named(rltk::ORANGERED),
+				damage: RGB::named(rltk::RED),
+
+				log_combat: RGB::named(rltk::SALMON),
+				log_loot: RGB::named(rltk::KHAKI),
+				log_system: RGB::named(rltk::LIGHTGRAY),
+				log_dialogue: RGB::named(rltk::LIGHTSKYBLUE),
+			},
+
+			// Okabe & Ito's colours, which hold up for most kinds of colour
+			// blindness
+			PaletteKind::Deuteranopia => Palette {
+				floor: RGB::from_f32(0.2, 0.3, 0.5),
+				wall: RGB::from_f32(0.35, 0.45, 0.7),
+				stairs: RGB::from_u8(230, 159, 0),
+				water: RGB::from_u8(0, 114, 178),
+				placeholder: RGB::named(rltk::SLATEGRAY),
+				blood: RGB::from_u8(110, 60, 0),
+				remembered_blood: RGB::named(rltk::DARKSLATEGREY),
+
+				background: RGB::named(rltk::BLACK),
+				text: RGB::named(rltk::WHITE),
+				text_dim: RGB::named(rltk::GREY),
+				faint: RGB::named(rltk::DARK_GRAY),
+				highlight: RGB::from_u8(86, 180, 233),
+				title: RGB::from_u8(240, 228, 66),
+				value: RGB::from_u8(240, 228, 66),
+				health: RGB::from_u8(213, 94, 0),
+				good: RGB::from_u8(86, 180, 233),
+				warning: RGB::from_u8(230, 159, 0),
+				danger: RGB::from_u8(213, 94, 0),
+				target: RGB::from_u8(0, 114, 178),
+
+				blast: RGB::from_u8(230, 159, 0),
+				heal: RGB::from_u8(86, 180, 233),
+				hit: RGB::from_u8(213, 94, 0),
+				damage: RGB::from_u8(213, 94, 0),
+
+				log_combat: RGB::from_u8(213, 94, 0),
+				log_loot: RGB::from_u8(240, 228, 66),
+				log_system: RGB::named(rltk::LIGHTGRAY),
+				log_dialogue: RGB::from_u8(86, 180, 233),
+			},
+
+			PaletteKind::HighContrast => Palette {
+				floor: RGB::named(rltk::GREY50),
+				wall: RGB::named(rltk::WHITE),
+				stairs: RGB::named(rltk::YELLOW),
+				water: RGB::named(rltk::DODGERBLUE),
+				placeholder: RGB::named(rltk::GREY),
+				blood: RGB::from_f32(0.6, 0., 0.),
+				remembered_blood: RGB::named(rltk::GREY30),
+
+				background: RGB::named(rltk::BLACK),
+				text: RGB::named(rltk::WHITE),
+				text_dim: RGB::named(rltk::LIGHTGRAY),
+				faint: RGB::named(rltk::GREY),
+				highlight: RGB::named(rltk::YELLOW),
+				title: RGB::named(rltk::WHITE),
+				value: RGB::named(rltk::CYAN),
+				health: RGB::named(rltk::RED),
+				good: RGB::named(rltk::GREEN),
+				warning: RGB::named(rltk::ORANGE),
+				danger: RGB::named(rltk::RED),
+				target: RGB::named(rltk::NAVY),
+
+				blast: RGB::named(rltk::YELLOW),
+				heal: RGB::named(rltk::GREEN),
+				hit: RGB::named(rltk::RED),
+				damage: RGB::named(rltk::RED),
+
+				log_combat: RGB::named(rltk::RED),
+				log_loot: RGB::named(rltk::YELLOW),
+				log_system: RGB::named(rltk::WHITE),
+				log_dialogue: RGB::named(rltk::CYAN),
+			},
+		}
+	}
+
+	/// What log entries of the given sort are written in
+	pub fn log (&self, category: LogCategory) -> RGB {
+		match category {
+			LogCategory::Combat => self.log_combat,
+			LogCategory::Loot => self.log_loot,
+			LogCategory::System => self.log_system,
+			LogCategory::Dialogue => self.log_dialogue,
+		}
+	}
+}
@@ -1,62 +1,207 @@
-use rltk::{RGB, Rltk};
+use rltk::{DistanceAlg, Point, RGB};
 use specs::prelude::*;
 use crate::{ParticleLifetime, Position, Renderable};
+use crate::config::{AnimationTiming, ParticleConfig};
+use crate::debug_log::OrWarn;
+
+/// The most a frame can move the animations on by, so a slow frame doesn't
+/// see particles off before they've been drawn
+const MAX_STEP_MS : f32 = 50.;
+
+/// How many finished particles are kept about to be used again. Past that
+/// they're deleted like anything else.
+const MAX_SPARE : usize = 256;
 
 // Builder
 // =========================================================================
 
+/// What a particle looks like, and how long it hangs about for
+#[derive(Clone, Copy)]
+pub struct Particle {
+	pub fg       : RGB,
+	pub bg       : RGB,
+	pub glyph    : rltk::FontCharType,
+	pub lifetime : f32,
+}
+
 struct ParticleRequest {
 	x: i32,
 	y: i32,
-	fg: RGB,
-	bg: RGB,
-	glyph: rltk::FontCharType,
-	lifetime: f32,
+	particle: Particle,
+	/// How long to hold off before it appears
+	delay_ms: f32,
 }
 
 pub struct ParticleBuilder {
-	requests : Vec<ParticleRequest>,
+	requests  : Vec<ParticleRequest>,
+	/// Requests still waiting on their delay, counted down each frame
+	scheduled : Vec<ParticleRequest>,
+	/// Particles that have had their turn, stripped back to be put out again
+	/// rather than deleted and made afresh, so big effects don't churn
+	/// through entities
+	spare     : Vec<Entity>,
 }
 
 impl ParticleBuilder {
 	pub fn new() -> ParticleBuilder {
-		ParticleBuilder { requests: Vec::new() }
+		ParticleBuilder { requests: Vec::new(), scheduled: Vec::new(), spare: Vec::new() }
+	}
+
+	/// A spare particle, or a new one if there aren't any
+	fn reuse (&mut self, entities: &Entities) -> Entity {
+		while let Some(particle) = self.spare.pop() {
+			// Spares left on the last level were deleted along with it
+			if entities.is_alive(particle) { return particle; }
+		}
+		entities.create()
+	}
+
+	pub fn request (&mut self, x: i32, y: i32, particle: Particle) {
+		self.request_delayed(x, y, particle, 0.);
+	}
+
+	/// A particle that only shows up once `delay_ms` has gone by
+	pub fn request_delayed (&mut self, x: i32, y: i32, particle: Particle, delay_ms: f32) {
+		self.requests.push(ParticleRequest { x, y, particle, delay_ms });
+	}
+
+	/// Particles on each of `tiles`, spreading out from `centre` a tile every
+	/// `step_ms`, like a fireball going off
+	pub fn request_ring (
+		&mut self,
+		centre: Point,
+		tiles: &[Point],
+		particle: Particle,
+		step_ms: f32,
+	) {
+		for tile in tiles.iter() {
+			let distance = DistanceAlg::Pythagoras.distance2d(centre, *tile).round();
+			self.request_delayed(tile.x, tile.y, particle, distance * step_ms);
+		}
+	}
+
+	/// A line of particles from `from` to `to`, drawn a segment every
+	/// `step_ms` like a bolt of lightning. The start itself is left alone,
+	/// since there's usually someone stood on it.
+	pub fn request_line (
+		&mut self,
+		from: Point, to: Point,
+		particle: Particle,
+		step_ms: f32,
+	) {
+		for (i, tile) in rltk::line2d(rltk::LineAlg::Bresenham, from, to).iter().skip(1).enumerate() {
+			self.request_delayed(tile.x, tile.y, particle, i as f32 * step_ms);
+		}
 	}
 
-	pub fn request (
+	/// A line of text, one particle per character running rightwards from
+	/// `x`
+	pub fn request_text (
 		&mut self,
 		x: i32, y: i32,
 		fg: RGB, bg: RGB,
-		glyph: rltk::FontCharType,
+		text: &str,
 		lifetime: f32,
 	) {
-		self.requests.push(ParticleRequest {
-			x, y, fg, bg, glyph, lifetime,
-		});
+		for (i, c) in text.chars().enumerate() {
+			self.request(x + i as i32, y, Particle { fg, bg, glyph: rltk::to_cp437(c), lifetime });
+		}
+	}
+}
+
+// Clock
+// =========================================================================
+
+/// Times the particles and the side panel's flashes, rather than them each
+/// going by the frame time
+#[derive(Default)]
+pub struct AnimationClock {
+	/// How far everything moves on this frame
+	pub step_ms  : f32,
+	/// What's left of this turn's share, when timed by turns
+	turn_left_ms : f32,
+}
+
+impl AnimationClock {
+	/// Works out how far to move this frame. Nothing moves under a menu.
+	pub fn tick (&mut self, frame_ms: f32, paused: bool, config: &ParticleConfig) {
+		let step = if paused { 0. } else { f32::min(frame_ms, MAX_STEP_MS) };
+		self.step_ms = match config.timing {
+			AnimationTiming::Frames => step,
+			AnimationTiming::Turns => {
+				let step = f32::min(step, self.turn_left_ms);
+				self.turn_left_ms -= step;
+				step
+			}
+		};
+	}
+
+	/// A turn's gone by, so anything timed by turns gets its next share
+	pub fn next_turn (&mut self, config: &ParticleConfig) {
+		self.turn_left_ms = config.turn_ms;
 	}
 }
 
 // Systems
 // =========================================================================
 
-pub fn cull_dead_particles (ecs: &mut World, ctx: &Rltk) {
+pub fn cull_dead_particles (ecs: &mut World) {
+	let step_ms = ecs.fetch::<AnimationClock>().step_ms;
 	let mut dead_particles : Vec<Entity> = Vec::new();
 	{
 		let mut particles = ecs.write_storage::<ParticleLifetime>();
 		let entities = ecs.entities();
 		for (entity, mut particle) in (&entities, &mut particles).join() {
-			particle.lifetime_ms -= ctx.frame_time_ms;
+			particle.lifetime_ms -= step_ms;
 			if particle.lifetime_ms < 0. {
 				dead_particles.push(entity);
 			}
 		}
 	}
 
-	for dead in dead_particles.iter() {
-		ecs.delete_entity(*dead).expect("Particles just won't die");
+	// Without a position or a lifetime they're neither drawn nor counted
+	// down, and wait to be put out again
+	let mut overflow : Vec<Entity> = Vec::new();
+	{
+		let mut builder = ecs.write_resource::<ParticleBuilder>();
+		let mut positions = ecs.write_storage::<Position>();
+		let mut particles = ecs.write_storage::<ParticleLifetime>();
+		for dead in dead_particles {
+			if builder.spare.len() >= MAX_SPARE {
+				overflow.push(dead);
+				continue;
+			}
+			positions.remove(dead);
+			particles.remove(dead);
+			builder.spare.push(dead);
+		}
+	}
+
+	for dead in overflow.iter() {
+		ecs.delete_entity(*dead).or_warn("Particles just won't die");
 	}
 }
 
+/// Counts down the delays on scheduled particles and puts out any that are
+/// due, between turns as well as on them
+pub fn spawn_scheduled_particles (ecs: &mut World) {
+	let step_ms = ecs.fetch::<AnimationClock>().step_ms;
+	{
+		let mut builder = ecs.write_resource::<ParticleBuilder>();
+		for request in builder.scheduled.iter_mut() {
+			request.delay_ms -= step_ms;
+		}
+		let (due, waiting) : (Vec<ParticleRequest>, Vec<ParticleRequest>) =
+			builder.scheduled.drain(..).partition(|r| r.delay_ms <= 0.);
+		builder.scheduled = waiting;
+		builder.requests.extend(due);
+	}
+
+	ParticleSpawnSystem {}.run_now(ecs);
+}
+
+/// Puts out every particle that's been asked for, holding back any with a
+/// delay still to run until spawn_scheduled_particles says they're due
 pub struct ParticleSpawnSystem {}
 
 impl<'a> System<'a> for ParticleSpawnSystem {
@@ -74,23 +219,28 @@ impl<'a> System<'a> for ParticleSpawnSystem {
 			mut particle_builder,
 		) = data;
 
-		for new_particle in particle_builder.requests.iter() {
-			let p = entities.create();
+		let requests : Vec<ParticleRequest> = particle_builder.requests.drain(..).collect();
+		for new_particle in requests.into_iter() {
+			if new_particle.delay_ms > 0. {
+				particle_builder.scheduled.push(new_particle);
+				continue;
+			}
+
+			let p = particle_builder.reuse(&entities);
 			positions.insert(p, Position {
 				x: new_particle.x,
 				y: new_particle.y,
-			}).expect("Failed to position particles");
+			}).or_warn("Failed to position particles");
 			renderables.insert(p, Renderable {
-				fg: new_particle.fg,
-				bg: new_particle.bg,
-				glyph: new_particle.glyph,
+				fg: new_particle.particle.fg,
+				bg: new_particle.particle.bg,
+				glyph: new_particle.particle.glyph,
 				render_order: 0,
-			}).expect("Failed to render particles");
+				sprite: None,
+			}).or_warn("Failed to render particles");
 			particles.insert(p, ParticleLifetime {
-				lifetime_ms: new_particle.lifetime,
-			}).expect("Failed to force particle to die of old age");
+				lifetime_ms: new_particle.particle.lifetime,
+			}).or_warn("Failed to force particle to die of old age");
 		}
-
-		particle_builder.requests.clear();
 	}
 }
\ No newline at end of file
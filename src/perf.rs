@@ -0,0 +1,128 @@
+use std::sync::Mutex;
+use rltk::{ColorPair, Point, Rect, Rltk};
+use specs::prelude::*;
+use crate::ParticleLifetime;
+use crate::display::{FrameBatch, PANEL_WIDTH};
+use crate::palette::Palette;
+
+/// How much each new reading counts against the running average, so the
+/// numbers settle rather than flicker from frame to frame
+const SMOOTHING : f32 = 0.1;
+
+/// Each system's average run time, in the order they first ran. The systems
+/// are off on the dispatcher's threads when they're timed, so this can't be
+/// a resource without them all waiting on each other to write to it.
+static SYSTEM_MS : Mutex<Vec<(&'static str, f32)>> = Mutex::new(Vec::new());
+
+fn smooth (average: &mut f32, ms: f32) {
+	*average += (ms - *average) * SMOOTHING;
+}
+
+// Clock
+// =========================================================================
+
+/// Milliseconds since some point, only good for taking one reading away
+/// from another
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms () -> f64 {
+	use std::sync::OnceLock;
+	use std::time::Instant;
+
+	static START : OnceLock<Instant> = OnceLock::new();
+	START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.
+}
+
+/// There's no Instant in the browser, so it lends its own clock
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms () -> f64 {
+	web_sys::window().and_then(|w| w.performance()).map_or(0., |p| p.now())
+}
+
+// Timing
+// =========================================================================
+
+/// Times each run of the system it wraps, for the overlay. Every system in
+/// the schedule goes through one of these.
+pub struct Timed<S> {
+	system : S,
+	name   : &'static str,
+}
+
+impl<S> Timed<S> {
+	pub fn new (system: S, name: &'static str) -> Timed<S> {
+		Timed { system, name }
+	}
+}
+
+impl<'a, S : System<'a>> System<'a> for Timed<S> {
+	type SystemData = S::SystemData;
+
+	fn run (&mut self, data: Self::SystemData) {
+		let start = now_ms();
+		self.system.run(data);
+		let ms = (now_ms() - start) as f32;
+
+		let mut timings = SYSTEM_MS.lock().unwrap();
+		match timings.iter_mut().find(|(name, _)| *name == self.name) {
+			Some((_, average)) => smooth(average, ms),
+			None => timings.push((self.name, ms)),
+		}
+	}
+
+	fn setup (&mut self, world: &mut World) {
+		self.system.setup(world);
+	}
+}
+
+// Overlay
+// =========================================================================
+
+/// Whether the performance overlay is up, and the averages kept for it
+#[derive(Default)]
+pub struct PerfOverlay {
+	pub shown : bool,
+	frame_ms  : f32,
+	/// A whole run of the systems, with summoning and tidying up after
+	turn_ms   : f32,
+}
+
+impl PerfOverlay {
+	pub fn record_frame (&mut self, ms: f32) {
+		smooth(&mut self.frame_ms, ms);
+	}
+
+	pub fn record_turn (&mut self, ms: f32) {
+		smooth(&mut self.turn_ms, ms);
+	}
+}
+
+/// Frame rate, timings and counts in a box over the top right of the map
+pub fn draw_overlay (ecs: &World, ctx: &mut Rltk, palette: &Palette) {
+	let overlay = ecs.fetch::<PerfOverlay>();
+	let mut draw = ecs.write_resource::<FrameBatch>();
+	let timings = SYSTEM_MS.lock().unwrap();
+	let entities = ecs.entities().join().count();
+	let particles = ecs.read_storage::<ParticleLifetime>().join().count();
+
+	let mut lines : Vec<(String, String)> = vec![
+		("FPS".to_string(), format!("{:.0}", ctx.fps)),
+		("Frame".to_string(), format!("{:.2}ms", overlay.frame_ms)),
+		("Turn".to_string(), format!("{:.2}ms", overlay.turn_ms)),
+		("Entities".to_string(), entities.to_string()),
+		("Particles".to_string(), particles.to_string()),
+	];
+	lines.extend(timings.iter().map(|(name, ms)| (format!(" {}", name), format!("{:.3}ms", ms))));
+
+	let width = 26;
+	let (console_width, _) = ctx.get_char_size();
+	let x = console_width as i32 - PANEL_WIDTH - width - 2;
+
+	draw.draw_box(Rect::with_size(x, 0, width + 1, lines.len() as i32 + 1), ColorPair::new(palette.text, palette.background));
+	draw.print_color(Point::new(x + 2, 0), " Performance ", ColorPair::new(palette.value, palette.background));
+	for (i, (label, value)) in lines.iter().enumerate() {
+		let y = i as i32 + 1;
+		let colour = if label.starts_with(' ') { palette.text_dim } else { palette.text };
+		draw.print_color(Point::new(x + 1, y), label, ColorPair::new(colour, palette.background));
+		draw.print_color(Point::new(x + width - value.len() as i32, y), value, ColorPair::new(palette.value, palette.background));
+	}
+}
@@ -1,20 +1,83 @@
 use std::cmp::{max, min};
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{a_star_search, Point, RandomNumberGenerator, Rltk};
 use specs::prelude::*;
-use crate::{CombatStats, EntityMoved, HungerClock, HungerState, Item, Monster, RunState, TileType, Viewshed, WantsToMelee, WantsToPickupItem};
+use crate::{Ally, Burden, CombatStats, Door, EntityMoved, Faction, FactionKind, HungerClock, HungerState, InBackpack, Item, Monster, Prisoner, StatusEffectKind, StatusEffects, ThirstClock, ThirstState, TileType, Transition, TurnState, UiState, Viewshed, WantsToMelee, WantsToOpenDoor, WantsToPickupItem, Waterskin};
+use crate::config::Config;
+use crate::debug_log::{DebugOverlay, OrWarn};
+use crate::perf::PerfOverlay;
+use crate::display::{screenshot, Viewport};
+use crate::event_counter::EventCounter;
 use crate::gamelog::GameLog;
+use crate::gui::PauseSelection;
+use crate::keybindings::Command;
 use crate::map::Map;
+use crate::rex_assets::export_map;
+use crate::saveload_system::{self, SaveSlot};
+use crate::spatial::{LiveMap, SpatialIndex};
 use super::{Player, Position, State};
 
+const DIRECTIONS : [(i32, i32); 8] = [
+	(0, -1), (0, 1), (-1, 0), (1, 0),
+	(-1, -1), (1, -1), (-1, 1), (1, 1),
+];
+
+/// Where the player's headed after clicking on the map, walked a step a turn
+#[derive(Default)]
+pub struct AutoWalk {
+	path : Vec<usize>,
+}
+
+impl AutoWalk {
+	pub fn walking (&self) -> bool {
+		!self.path.is_empty()
+	}
+
+	pub fn cancel (&mut self) {
+		self.path.clear();
+	}
+}
+
 pub fn try_move_player (delta_x: i32, delta_y: i32, ecs: &mut World) {
+	// Webbed players can only struggle
+	{
+		let player_entity = ecs.fetch::<Entity>();
+		let statuses = ecs.read_storage::<StatusEffects>();
+		if statuses.get(*player_entity).map_or(false, |s| s.has(StatusEffectKind::Webbed)) {
+			ecs.write_resource::<GameLog>().combat(
+				"You struggle against the web!".to_string()
+			);
+			return;
+		}
+	}
+
+	// Confused players stumble in a random direction
+	let (delta_x, delta_y) = {
+		let player_entity = ecs.fetch::<Entity>();
+		let statuses = ecs.read_storage::<StatusEffects>();
+		match statuses.get(*player_entity) {
+			Some(s) if s.has(StatusEffectKind::Confused) => {
+				let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+				*rng.random_slice_entry(&DIRECTIONS).unwrap()
+			}
+			_ => (delta_x, delta_y),
+		}
+	};
+
 	let mut positions = ecs.write_storage::<Position>();
 	let players = ecs.read_storage::<Player>();
 	let mut viewsheds = ecs.write_storage::<Viewshed>();
 	let combat_stats = ecs.read_storage::<CombatStats>();
 	let map = ecs.fetch::<Map>();
+	let mut spatial = ecs.write_resource::<SpatialIndex>();
 	let entities = ecs.entities();
 	let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
 	let mut entity_moved = ecs.write_storage::<EntityMoved> ();
+	let doors = ecs.read_storage::<Door>();
+	let mut wants_open = ecs.write_storage::<WantsToOpenDoor>();
+	let mut allies = ecs.write_storage::<Ally>();
+	let mut prisoners = ecs.write_storage::<Prisoner>();
+	let mut factions = ecs.write_storage::<Faction>();
+	let mut swapped : Option<(Entity, i32, i32)> = None;
 
 	for (entity, _player, pos, viewshed)
 	 in (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -27,20 +90,54 @@ pub fn try_move_player (delta_x: i32, delta_y: i32, ecs: &mut World) {
 
 		let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
-		for potential_target in map.tile_content[destination_idx].iter() {
-			let target = combat_stats.get(*potential_target);
+		let prisoner = spatial.content(destination_idx).find(|e| prisoners.get(*e).is_some());
+		if let Some(prisoner) = prisoner {
+			prisoners.remove(prisoner);
+			allies.insert(prisoner, Ally {}).or_warn("Prisoner preferred the cell");
+			factions.insert(prisoner, Faction { kind: FactionKind::Player })
+				.or_warn("Prisoner has no loyalty");
+			ecs.write_resource::<GameLog>().system(
+				"You break the prisoner's chains, and they join you!".to_string()
+			);
+			return;
+		}
+
+		// Allies get out of the way by swapping places
+		let ally = spatial.content(destination_idx).find(|e| allies.get(*e).is_some());
+
+		for potential_target in spatial.content(destination_idx) {
+			if ally == Some(potential_target) { continue; }
+			let target = combat_stats.get(potential_target);
 			if let Some(_t) = target {
 				wants_to_melee.insert(
 					entity,
-					WantsToMelee { target: *potential_target }
-				).expect("Add melee target failed");
+					WantsToMelee { target: potential_target }
+				).or_warn("Add melee target failed");
 				return;
 			}
 		}
 
-		if !map.blocked[destination_idx] {
-			pos.x = min(79, max(0, pos.x + delta_x));
-			pos.y = min(49, max(0, pos.y + delta_y));
+		for potential_door in spatial.content(destination_idx) {
+			if let Some(door) = doors.get(potential_door) {
+				if !door.open {
+					wants_open.insert(
+						entity,
+						WantsToOpenDoor { door: potential_door }
+					).or_warn("Door handle came off");
+					return;
+				}
+			}
+		}
+
+		if !spatial.is_blocked(destination_idx) || ally.is_some() {
+			if let Some(ally) = ally {
+				swapped = Some((ally, pos.x, pos.y));
+			}
+
+			let from = map.xy_idx(pos.x, pos.y);
+			pos.x = min(map.width - 1, max(0, pos.x + delta_x));
+			pos.y = min(map.height - 1, max(0, pos.y + delta_y));
+			spatial.move_entity(entity, from, map.xy_idx(pos.x, pos.y));
 
 			let mut ppos = ecs.write_resource::<Point>();
 			ppos.x = pos.x;
@@ -48,60 +145,233 @@ pub fn try_move_player (delta_x: i32, delta_y: i32, ecs: &mut World) {
 
 			viewshed.dirty = true;
 			entity_moved.insert(entity, EntityMoved {})
-				.expect("Failed to use legs");
+				.or_warn("Failed to use legs");
 		}
 	}
+
+	if let Some((ally, x, y)) = swapped {
+		if let Some(ally_pos) = positions.get_mut(ally) {
+			spatial.move_entity(ally, map.xy_idx(ally_pos.x, ally_pos.y), map.xy_idx(x, y));
+			ally_pos.x = x;
+			ally_pos.y = y;
+		}
+		if let Some(viewshed) = viewsheds.get_mut(ally) {
+			viewshed.dirty = true;
+		}
+		entity_moved.insert(ally, EntityMoved {})
+			.or_warn("Ally has two left feet");
+	}
 }
 
-pub fn player_input (gs: &mut State, ctx: &mut Rltk) -> RunState {
-	// Movement
-	match ctx.key {
-		None => { return RunState::AwaitingInput }
-		Some(key) => match key {
-			// Cardinal
-			VirtualKeyCode::W => try_move_player(0, -1, &mut gs.ecs),
-			VirtualKeyCode::A => try_move_player(-1, 0, &mut gs.ecs),
-			VirtualKeyCode::S => try_move_player(0, 1, &mut gs.ecs),
-			VirtualKeyCode::D => try_move_player(1, 0, &mut gs.ecs),
-
-			// Diagonal
-			VirtualKeyCode::E => try_move_player(1, -1, &mut gs.ecs),
-			VirtualKeyCode::Q => try_move_player(-1, -1, &mut gs.ecs),
-			VirtualKeyCode::C => try_move_player(1, 1, &mut gs.ecs),
-			VirtualKeyCode::Z => try_move_player(-1, 1, &mut gs.ecs),
+pub fn player_input (gs: &mut State, ctx: &mut Rltk) -> Transition {
+	// Any key stops a walk in its tracks
+	let pressed = ctx.key.is_some() || gs.input.command.is_some();
+	if pressed {
+		gs.ecs.write_resource::<AutoWalk>().cancel();
+	} else if ctx.left_click {
+		let (x, y) = ctx.mouse_pos();
+		if let Some(tile) = Viewport::for_player(&gs.ecs, ctx).to_map(x, y) {
+			plan_walk(&mut gs.ecs, tile.x, tile.y);
+		}
+	}
+
+	if !pressed && gs.ecs.fetch::<AutoWalk>().walking() {
+		return walk_step(&mut gs.ecs);
+	}
+
+	match gs.input.command {
+		None => { return Transition::Stay },
+		Some(command) => match command {
+			Command::Move { dx, dy } => {
+				if dx != 0 && dy != 0 && overloaded(&gs.ecs) {
+					gs.ecs.write_resource::<GameLog>().system(
+						"You're too weighed down to cut corners.".to_string()
+					);
+					return Transition::Stay;
+				}
+				try_move_player(dx, dy, &mut gs.ecs);
+			},
 
 			// Pickup / Interact
-			VirtualKeyCode::F => {
+			Command::Interact => {
 				if try_next_level(&mut gs.ecs) {
-					return RunState::NextLevel;
+					return Transition::Turn(TurnState::NextLevel);
+				} else if try_drink(&mut gs.ecs) {
+					return Transition::Turn(TurnState::PlayerTurn);
 				} else {
-					get_item(&mut gs.ecs)
+					return get_item(&mut gs.ecs);
 				}
 			},
 
-			// Place (drop)
-			VirtualKeyCode::P => return RunState::ShowDropItem,
+			Command::Drop => return Transition::Push(UiState::ShowDropItem { selection: 0 }),
+			Command::Inventory => return Transition::Push(UiState::ShowInventory { selection: 0 }),
+			Command::Remove => return Transition::Push(UiState::ShowRemoveItem { selection: 0 }),
+			Command::Log => return Transition::Push(UiState::ShowLog { offset: 0, filter: None }),
 
-			// Inventory
-			VirtualKeyCode::I => return RunState::ShowInventory,
+			Command::Examine => {
+				let player_pos = gs.ecs.fetch::<Point>();
+				return Transition::Push(UiState::Examine { x: player_pos.x, y: player_pos.y });
+			},
 
-			// Equipped Items
-			VirtualKeyCode::R => return RunState::ShowRemoveItem,
+			Command::Overview => return Transition::Push(UiState::ShowMap),
+			Command::Help => return Transition::Push(UiState::ShowHelp),
+			Command::Pause => return Transition::Push(UiState::Paused { selection: PauseSelection::Resume, confirming: false }),
+			Command::QuickSave => {
+				saveload_system::save_game(&mut gs.ecs, SaveSlot::Quick).or_warn("The quicksave didn't take");
+				return Transition::Stay;
+			},
+			Command::QuickLoad => {
+				let message =
+					if gs.ecs.fetch::<EventCounter>().ironman { "There's no going back in ironman." }
+					else if SaveSlot::Quick.exists() { return Transition::Push(UiState::ConfirmQuickLoad) }
+					else { "There's no quick save to load." };
+				gs.ecs.write_resource::<GameLog>().system(message.to_string());
+				return Transition::Stay;
+			},
+			Command::Screenshot => {
+				let name = screenshot(&gs.ecs, ctx);
+				gs.ecs.write_resource::<GameLog>().system(format!("Screenshot saved as {}", name));
+				return Transition::Stay;
+			},
+			Command::SkipLevel => return Transition::Turn(TurnState::NextLevel),
+			Command::ExportMap => {
+				let path = export_map(&gs.ecs.fetch::<Map>());
+				gs.ecs.write_resource::<GameLog>().system(format!("Map written to {}", path));
+				return Transition::Stay;
+			},
+			Command::SkipTurn => return skip_turn(&mut gs.ecs),
+			Command::DebugOverlay => {
+				let mut overlay = gs.ecs.write_resource::<DebugOverlay>();
+				overlay.shown = !overlay.shown;
+				return Transition::Stay;
+			},
+			Command::PerfOverlay => {
+				let mut overlay = gs.ecs.write_resource::<PerfOverlay>();
+				overlay.shown = !overlay.shown;
+				return Transition::Stay;
+			},
+		}
+	}
+
+	Transition::Turn(TurnState::PlayerTurn)
+}
 
-			// Save & Quit
-			VirtualKeyCode::Escape => return RunState::SaveGame,
+/// Works out a path to the clicked tile, through what the player has seen of
+/// the level
+fn plan_walk (ecs: &mut World, x: i32, y: i32) {
+	if overloaded(ecs) {
+		ecs.write_resource::<GameLog>().system(
+			"You're carrying too much to hurry anywhere.".to_string()
+		);
+		return;
+	}
 
-			// [DEBUG] Skip Level
-			VirtualKeyCode::F12 => return RunState::NextLevel,
+	let map = ecs.fetch::<Map>();
+	if x < 0 || x > map.width - 1 || y < 0 || y > map.height - 1 { return; }
 
-			// Skip Turn
-			VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
+	let destination = map.xy_idx(x, y);
+	if !map.is_revealed(destination) || map.is_void_or_wall(x, y) { return; }
 
-			_ => { return RunState::AwaitingInput },
+	let player_pos = ecs.fetch::<Point>();
+	let start = map.xy_idx(player_pos.x, player_pos.y);
+	if start == destination { return; }
+
+	let path = a_star_search(start, destination, &LiveMap {
+		map: &map,
+		spatial: &ecs.fetch::<SpatialIndex>(),
+		can_open_doors: true,
+	});
+	let mut walk = ecs.write_resource::<AutoWalk>();
+
+	if !path.success || path.steps.iter().any(|idx| !map.is_revealed(*idx)) {
+		walk.cancel();
+		ecs.write_resource::<GameLog>().system(
+			"You can't find a way there.".to_string()
+		);
+		return;
+	}
+
+	walk.path = path.steps[1..].to_vec();
+}
+
+/// Takes the next step of a walk, unless there's trouble about or the way
+/// is blocked
+fn walk_step (ecs: &mut World) -> Transition {
+	if enemies_in_view(ecs) {
+		ecs.write_resource::<AutoWalk>().cancel();
+		ecs.write_resource::<GameLog>().system(
+			"You stop, wary of the monsters nearby.".to_string()
+		);
+		return Transition::Stay;
+	}
+
+	let step = {
+		let map = ecs.fetch::<Map>();
+		let spatial = ecs.fetch::<SpatialIndex>();
+		let player_pos = ecs.fetch::<Point>();
+		let allies = ecs.read_storage::<Ally>();
+		let next = ecs.fetch::<AutoWalk>().path[0];
+
+		let dx = next as i32 % map.width - player_pos.x;
+		let dy = next as i32 / map.width - player_pos.y;
+
+		// Allies swap places, and doors get opened on the way
+		let passable = spatial.is_walkable(next, true)
+			|| spatial.content(next).any(|e| allies.get(e).is_some());
+
+		if dx.abs() > 1 || dy.abs() > 1 || !passable { None }
+		else { Some((next, dx, dy)) }
+	};
+
+	let (next, dx, dy) = match step {
+		Some(step) => step,
+		None => {
+			ecs.write_resource::<AutoWalk>().cancel();
+			ecs.write_resource::<GameLog>().system(
+				"Something's in your way.".to_string()
+			);
+			return Transition::Stay;
 		}
+	};
+
+	try_move_player(dx, dy, ecs);
+
+	let arrived = {
+		let map = ecs.fetch::<Map>();
+		let player_pos = ecs.fetch::<Point>();
+		map.xy_idx(player_pos.x, player_pos.y) == next
+	};
+	if arrived {
+		ecs.write_resource::<AutoWalk>().path.remove(0);
 	}
 
-	return RunState::PlayerTurn;
+	Transition::Turn(TurnState::PlayerTurn)
+}
+
+/// Whether the player's carrying more than they can manage
+fn overloaded (ecs: &World) -> bool {
+	let player_entity = ecs.fetch::<Entity>();
+	ecs.read_storage::<Burden>().get(*player_entity).map_or(false, |b| b.overloaded())
+}
+
+/// Whether the player can see anything that isn't on their side
+fn enemies_in_view (ecs: &World) -> bool {
+	let player_entity = ecs.fetch::<Entity>();
+	let viewsheds = ecs.read_storage::<Viewshed>();
+	let monsters = ecs.read_storage::<Monster>();
+	let allies = ecs.read_storage::<Ally>();
+	let map = ecs.fetch::<Map>();
+	let spatial = ecs.fetch::<SpatialIndex>();
+
+	let viewshed = match viewsheds.get(*player_entity) {
+		Some(viewshed) => viewshed,
+		None => return false,
+	};
+
+	viewshed.visible_tiles.iter().any(|tile| {
+		spatial.content(map.xy_idx(tile.x, tile.y))
+			.any(|e| monsters.get(e).is_some() && allies.get(e).is_none())
+	})
 }
 
 fn try_next_level (ecs: &mut World) -> bool {
@@ -112,35 +382,76 @@ fn try_next_level (ecs: &mut World) -> bool {
 	return map.tiles[player_idx] == TileType::DownStairs;
 }
 
-fn get_item (ecs: &mut World) {
+/// Drinks from the water underfoot and fills up every waterskin carried.
+/// Anything lying in the water gets picked up instead.
+fn try_drink (ecs: &mut World) -> bool {
 	let player_pos = ecs.fetch::<Point>();
 	let player_entity = ecs.fetch::<Entity>();
+	let map = ecs.fetch::<Map>();
+	if map.tiles[map.xy_idx(player_pos.x, player_pos.y)] != TileType::Water { return false; }
+
 	let entities = ecs.entities();
 	let items = ecs.read_storage::<Item>();
 	let positions = ecs.read_storage::<Position>();
-	let mut gamelog = ecs.fetch_mut::<GameLog>();
+	let anything_underfoot = (&items, &positions).join()
+		.any(|(_, position)| position.x == player_pos.x && position.y == player_pos.y);
+	if anything_underfoot { return false; }
 
-	let mut target_item : Option<Entity> = None;
+	let config = ecs.fetch::<Config>();
+	if let Some(tc) = ecs.write_storage::<ThirstClock>().get_mut(*player_entity) {
+		tc.state = ThirstState::Quenched;
+		tc.duration = config.thirst.quenched_turns;
+	}
 
-	for (item_entity, _, position) in (&entities, &items, &positions).join() {
-		if position.x == player_pos.x && position.y == player_pos.y {
-			target_item = Some(item_entity);
+	let backpack = ecs.read_storage::<InBackpack>();
+	let mut waterskins = ecs.write_storage::<Waterskin>();
+	let mut refilled = 0;
+	for (_, pack, skin) in (&entities, &backpack, &mut waterskins).join() {
+		if pack.owner == *player_entity && skin.sips < skin.capacity {
+			skin.sips = skin.capacity;
+			refilled += 1;
 		}
 	}
 
-	match target_item {
-		None => gamelog.entries.push("There's nothing to pick up here!".to_string()),
-		Some(item) => {
+	let mut gamelog = ecs.fetch_mut::<GameLog>();
+	match refilled {
+		0 => gamelog.loot("You drink your fill".to_string()),
+		1 => gamelog.loot("You drink your fill, and top up your waterskin".to_string()),
+		_ => gamelog.loot("You drink your fill, and top up your waterskins".to_string()),
+	}
+	true
+}
+
+/// Picks up what's underfoot, or asks which when there's more than one thing
+fn get_item (ecs: &mut World) -> Transition {
+	let player_pos = ecs.fetch::<Point>();
+	let player_entity = ecs.fetch::<Entity>();
+	let entities = ecs.entities();
+	let items = ecs.read_storage::<Item>();
+	let positions = ecs.read_storage::<Position>();
+	let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+	let underfoot : Vec<Entity> = (&entities, &items, &positions).join()
+		.filter(|(_, _, position)| position.x == player_pos.x && position.y == player_pos.y)
+		.map(|(item_entity, _, _)| item_entity)
+		.collect();
+
+	match underfoot.len() {
+		0 => gamelog.loot("There's nothing to pick up here!".to_string()),
+		1 => {
 			let mut pickup = ecs.write_storage::<WantsToPickupItem>();
 			pickup.insert(*player_entity, WantsToPickupItem {
-				item,
+				items: underfoot,
 				collected_by: *player_entity,
-			}).expect("Failed to add want pickup to player");
+			}).or_warn("Failed to add want pickup to player");
 		},
+		_ => return Transition::Push(UiState::ShowPickupItem { selection: 0 }),
 	}
+
+	Transition::Turn(TurnState::PlayerTurn)
 }
 
-fn skip_turn (ecs: &mut World) -> RunState {
+fn skip_turn (ecs: &mut World) -> Transition {
 	let player_entity = ecs.fetch::<Entity>();
 	let hunger = ecs.read_storage::<HungerClock>();
 	let mut gamelog = ecs.fetch_mut::<GameLog>();
@@ -148,39 +459,40 @@ fn skip_turn (ecs: &mut World) -> RunState {
 	let hc = hunger.get(*player_entity);
 	if let Some(hc) = hc {
 		if hc.state == HungerState::Hungry || hc.state == HungerState::Starving {
-			gamelog.entries.push(
+			gamelog.system(
 				"Your want for food prevents you from resting".to_string()
 			);
-			return RunState::PlayerTurn;
+			return Transition::Turn(TurnState::PlayerTurn);
 		}
 	}
 
-	let viewsheds = ecs.read_storage::<Viewshed>();
-	let monsters = ecs.read_storage::<Monster>();
-	let worldmap_res = ecs.fetch::<Map>();
-
-	let viewshed = viewsheds.get(*player_entity).unwrap();
-	for tile in viewshed.visible_tiles.iter() {
-		let idx = worldmap_res.xy_idx(tile.x, tile.y);
-		for entity_id in worldmap_res.tile_content[idx].iter() {
-			let mob = monsters.get(*entity_id);
-			if mob.is_some() {
-				gamelog.entries.push(
-					"The sounds of nearby monsters keep you on edge!".to_string()
-				);
-				return RunState::PlayerTurn;
-			}
+	let thirst = ecs.read_storage::<ThirstClock>();
+	if let Some(tc) = thirst.get(*player_entity) {
+		if tc.state == ThirstState::Thirsty || tc.state == ThirstState::Parched {
+			gamelog.system(
+				"Your dry throat keeps you from resting".to_string()
+			);
+			return Transition::Turn(TurnState::PlayerTurn);
 		}
 	}
 
+	if enemies_in_view(ecs) {
+		gamelog.system(
+			"The sounds of nearby monsters keep you on edge!".to_string()
+		);
+		return Transition::Turn(TurnState::PlayerTurn);
+	}
+
 	let mut stats = ecs.write_storage::<CombatStats>();
-	let player_hp = stats.get_mut(*player_entity).unwrap();
+	let Some(player_hp) = stats.get_mut(*player_entity) else {
+		return Transition::Turn(TurnState::PlayerTurn);
+	};
 	if player_hp.hp == player_hp.max_hp {
-		gamelog.entries.push("You rest for a moment.".to_string());
+		gamelog.system("You rest for a moment.".to_string());
 	} else {
 		player_hp.hp += 1;
-		gamelog.entries.push("You rest for a moment, gaining 1hp.".to_string());
+		gamelog.system("You rest for a moment, gaining 1hp.".to_string());
 	}
 
-	return RunState::PlayerTurn;
+	Transition::Turn(TurnState::PlayerTurn)
 }
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
+use crate::saveload_system::storage;
+
+const RECORDS_FILE : &str = "./records.json";
+
+/// How many runs the high score table holds
+pub const HIGH_SCORES : usize = 10;
+
+/// A run that made it onto the high score table
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScore {
+	pub score   : i32,
+	pub depth   : i32,
+	pub kills   : i32,
+	pub turns   : i32,
+	pub victory : bool,
+	/// Whatever killed the player, if anything did
+	pub cause   : Option<String>,
+	pub seed    : u64,
+}
+
+impl HighScore {
+	/// How the run ended, in a few words
+	pub fn fate (&self) -> String {
+		match &self.cause {
+			_ if self.victory => "Victorious".to_string(),
+			Some(cause) => format!("Killed by {}", cause),
+			None => "Gave up".to_string(),
+		}
+	}
+}
+
+/// How every run's gone, added up across them all. Kept in records.json
+/// for the Records screen on the main menu.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Records {
+	pub runs        : i32,
+	pub victories   : i32,
+	pub deepest     : i32,
+	/// How many times each thing has killed the player
+	pub deaths      : BTreeMap<String, i32>,
+	/// How many of each kind of monster have died
+	pub kills       : BTreeMap<String, i32>,
+	/// How many of each kind of item have been used up
+	pub items       : BTreeMap<String, i32>,
+	/// The best runs, best first
+	#[serde(default)]
+	pub high_scores : Vec<HighScore>,
+}
+
+impl Records {
+	/// The records so far, or a clean slate if there aren't any (or they've
+	/// been mangled)
+	pub fn load () -> Records {
+		if !storage::exists(RECORDS_FILE) { return Records::default(); }
+
+		storage::read(RECORDS_FILE)
+			.ok()
+			.and_then(|data| serde_json::from_str(&data).ok())
+			.unwrap_or_default()
+	}
+
+	fn save (&self) {
+		let data = serde_json::to_string_pretty(self).expect("Records are beyond words");
+		storage::write(RECORDS_FILE, data.as_bytes()).or_warn("Couldn't find anywhere to put the records");
+	}
+
+	/// Adds a finished run to the records, giving back its place on the high
+	/// score table if it made it on
+	pub fn record_run (stats: &EventCounter, victory: bool) -> Option<usize> {
+		let mut records = Records::load();
+
+		records.runs += 1;
+		if victory { records.victories += 1; }
+		records.deepest = i32::max(records.deepest, stats.deepest);
+
+		if !victory {
+			let cause = stats.killed_by.clone().unwrap_or_else(|| "Gave up".to_string());
+			*records.deaths.entry(cause).or_insert(0) += 1;
+		}
+		for (name, count) in stats.kills.iter() {
+			*records.kills.entry(name.clone()).or_insert(0) += count;
+		}
+		for (name, count) in stats.items.iter() {
+			*records.items.entry(name.clone()).or_insert(0) += count;
+		}
+
+		let entry = HighScore {
+			score: stats.score(victory),
+			depth: stats.deepest,
+			kills: stats.total_kills(),
+			turns: stats.turns,
+			victory,
+			cause: stats.killed_by.clone(),
+			seed: stats.seed,
+		};
+		let rank = records.high_scores.iter().position(|high| entry.score > high.score)
+			.unwrap_or(records.high_scores.len());
+		records.high_scores.insert(rank, entry);
+		records.high_scores.truncate(HIGH_SCORES);
+
+		records.save();
+		if rank < HIGH_SCORES { Some(rank) } else { None }
+	}
+}
+
+/// The biggest counts in the tally, biggest first
+pub fn most (tally: &BTreeMap<String, i32>) -> Vec<(&String, i32)> {
+	let mut most : Vec<(&String, i32)> = tally.iter().map(|(name, count)| (name, *count)).collect();
+	most.sort_by(|a, b| b.1.cmp(&a.1));
+	most
+}
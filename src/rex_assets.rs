@@ -1,4 +1,7 @@
-use rltk::XpFile;
+use std::fs::File;
+use rltk::{XpColor, XpFile};
+use crate::debug_log::OrWarn;
+use crate::map::{Map, TileType};
 
 rltk::embedded_resource!(DUNGEON_BG, "../resources/dungeon-bg.xp");
 rltk::embedded_resource!(WFC_DEMO_IMAGE1, "../resources/wfc-demo1.xp");
@@ -16,4 +19,31 @@ impl RexAssets {
 			menu: XpFile::from_resource("../resources/dungeon-bg.xp").unwrap(),
 		}
 	}
+}
+
+/// Writes the level's tiles out as a REX Paint file, in the characters the
+/// prefab builder reads back in, and gives back where it went
+pub fn export_map (map: &Map) -> String {
+	let path = format!("./map_depth_{}.xp", map.depth);
+	let mut xp = XpFile::new(map.width as usize, map.height as usize);
+
+	for y in 0 .. map.height {
+		for x in 0 .. map.width {
+			let cell = xp.layers[0].get_mut(x as usize, y as usize)
+				.expect("Map fell off the edge of the canvas");
+			cell.ch = match map.tiles[map.xy_idx(x, y)] {
+				TileType::Wall => '#',
+				TileType::DownStairs => '>',
+				_ => ' ',
+			} as u32;
+			cell.fg = XpColor::new(255, 255, 255);
+			cell.bg = XpColor::BLACK;
+		}
+	}
+
+	match File::create(&path) {
+		Ok(mut file) => xp.write(&mut file).or_warn("REX Paint wouldn't take the map"),
+		Err(e) => log::warn!("Couldn't find anywhere to put the map ({})", e),
+	}
+	path
 }
\ No newline at end of file
@@ -1,15 +1,25 @@
-use std::fs;
-use std::fs::File;
+use std::io::Write;
 use std::ops::Deref;
-use std::path::Path;
-use rltk::Point;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rltk::{Point, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
 use specs::{Builder, Entity, Join, World, WorldExt};
 use specs::saveload::{MarkedBuilder, SimpleMarker, SerializeComponents, DeserializeComponents, SimpleMarkerAllocator};
 #[allow(deprecated)] use specs::error::NoError;
 use crate::map::Map;
-use crate::{MAP_SIZE, SerializationHelper, SerializeMe};
+use crate::{LaunchOptions, SerializationHelper, SerializeMe};
 use crate::components::*;
+use crate::ai::PlayerFlowMap;
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
 use crate::gamelog::GameLog;
+use crate::settings::GameplaySettings;
+use crate::spatial::SpatialIndex;
 
 macro_rules! serialize_individually {
 	($ecs:expr, $ser:expr, $data:expr, $($type:ty), * $(,)?) => { $(
@@ -32,85 +42,293 @@ macro_rules! deserialize_individually {
 			&mut $data.1, // Marker
 			&mut $data.2, // Allocator
 			&mut $de,
-		).unwrap();
+		).map_err(|e| format!("The save's {} are damaged ({})", stringify!($type), e))?;
 	)* };
 }
 
-pub fn save_game (ecs: &mut World) {
+/// The version of the save format this build writes. Bump it whenever a
+/// change to the components would stop older saves loading, and add a step
+/// to `migrate` that brings the old ones up to date.
+pub const SAVE_VERSION : u32 = 1;
+
+/// A few details about the save, on a line of its own at the top of the file
+/// so the main menu can show them without loading the whole thing
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveHeader {
+	/// Headers from before there were versions count as 0, and are too old
+	/// to load
+	#[serde(default)]
+	pub version  : u32,
+	pub depth    : i32,
+	pub turns    : i32,
+	pub hp       : i32,
+	pub max_hp   : i32,
+	/// Seconds since the epoch
+	pub saved_at : u64,
+	/// A hash of everything under the header, to catch saves that have been
+	/// edited or cut short. Older saves don't have one.
+	#[serde(default)]
+	pub checksum : Option<u64>,
+	#[serde(default)]
+	pub ironman  : bool,
+}
+
+impl SaveHeader {
+	fn new (ecs: &World) -> SaveHeader {
+		let player_entity = ecs.fetch::<Entity>();
+		let (hp, max_hp) = ecs.read_storage::<CombatStats>().get(*player_entity)
+			.map_or((0, 0), |stats| (stats.hp, stats.max_hp));
+
+		SaveHeader {
+			version: SAVE_VERSION,
+			depth: ecs.fetch::<Map>().depth,
+			turns: ecs.fetch::<EventCounter>().turns,
+			hp,
+			max_hp,
+			saved_at: now(),
+			checksum: None,
+			ironman: ecs.fetch::<EventCounter>().ironman,
+		}
+	}
+
+	/// How long ago the save was made, roughly
+	pub fn age (&self) -> String {
+		let seconds = now().saturating_sub(self.saved_at);
+		match seconds {
+			0 ..= 59 => "just now".to_string(),
+			60 ..= 3599 => format!("{} min ago", seconds / 60),
+			3600 ..= 86399 => format!("{} hr ago", seconds / 3600),
+			_ => format!("{} days ago", seconds / 86400),
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now () -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// SystemTime panics in the browser, so its own clock is asked instead
+#[cfg(target_arch = "wasm32")]
+fn now () -> u64 {
+	(js_sys::Date::now() / 1000.) as u64
+}
+
+/// FNV-1a, which is plenty for spotting a save that's been meddled with
+fn checksum (data: &[u8]) -> u64 {
+	data.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Where a game's saved to. Autosaves are kept apart from saving and
+/// quitting, so neither clobbers the other.
+#[derive(PartialEq, Copy, Clone)]
+pub enum SaveSlot {
+	Manual,
+	Auto,
+	/// Saved on the way down when the game crashes
+	Recovery,
+	/// Saved and loaded without leaving the game
+	Quick,
+}
+
+impl SaveSlot {
+	const ALL : [SaveSlot; 4] = [SaveSlot::Manual, SaveSlot::Auto, SaveSlot::Recovery, SaveSlot::Quick];
+
+	/// The file it's in, or its key in local storage on the web
+	fn path (&self) -> &'static str {
+		match self {
+			SaveSlot::Manual => "./savegame.json",
+			SaveSlot::Auto => "./autosave.json",
+			SaveSlot::Recovery => "./recovery.json",
+			SaveSlot::Quick => "./quicksave.json",
+		}
+	}
+
+	/// Where the save before last is kept, in case the last one's damaged
+	fn backup_path (&self) -> &'static str {
+		match self {
+			SaveSlot::Manual => "./savegame.json.bak",
+			SaveSlot::Auto => "./autosave.json.bak",
+			SaveSlot::Recovery => "./recovery.json.bak",
+			SaveSlot::Quick => "./quicksave.json.bak",
+		}
+	}
+
+	pub fn exists (&self) -> bool { storage::exists(self.path()) }
+
+	fn header (&self) -> Option<SaveHeader> {
+		serde_json::from_str(&storage::first_line(self.path())?).ok()
+	}
+}
+
+/// Whichever save was made last, which is the one Continue picks up
+fn newest_save () -> Option<SaveSlot> {
+	wait_for_writes();
+	SaveSlot::ALL.iter()
+		.filter(|slot| slot.exists())
+		.max_by_key(|slot| slot.header().map_or(0, |header| header.saved_at))
+		.copied()
+}
+
+/// Whether the save Continue would load has a backup to fall back on.
+/// Ironman runs don't get to go back.
+pub fn has_backup () -> bool {
+	newest_save()
+		.filter(|slot| !slot.header().map_or(false, |header| header.ironman))
+		.map_or(false, |slot| storage::exists(slot.backup_path()))
+}
+
+/// Puts the backup of the save Continue would load back in its place, for
+/// when the save itself won't load
+pub fn restore_backup () -> Result<(), String> {
+	let slot = newest_save().ok_or("There's no save to restore")?;
+	if !storage::exists(slot.backup_path()) {
+		return Err("There's no backup to restore".to_string());
+	}
+	storage::copy(slot.backup_path(), slot.path())
+		.map_err(|_| "The backup couldn't be put back".to_string())?;
+	storage::remove(slot.backup_path());
+	Ok(())
+}
+
+/// The header of the newest save, if there is one and it has a header (saves
+/// from before headers don't)
+pub fn read_save_header () -> Option<SaveHeader> {
+	newest_save().and_then(|slot| slot.header())
+}
+
+pub fn save_game (ecs: &mut World, slot: SaveSlot) -> Result<(), String> {
+	// Started with --no-save
+	if ecs.fetch::<LaunchOptions>().no_save { return Ok(()); }
+
+	// Half-played turns aren't saved, so they're dropped here as well, and
+	// playing on goes the same as loading would
+	ecs.write_storage::<MyTurn>().clear();
+	ecs.write_storage::<WantsToApproach>().clear();
+	ecs.write_storage::<WantsToFlee>().clear();
+	ecs.write_storage::<WantsToMelee>().clear();
+	ecs.write_storage::<SufferDamage>().clear();
+
+	let mut header = SaveHeader::new(ecs);
+
 	// Create helper
-	let mapcopy = ecs.get_mut::<Map>().unwrap().clone();
+	let mapcopy = ecs.try_fetch::<Map>().ok_or("There's no map to save")?.deref().clone();
 	let logcopy = ecs.fetch::<GameLog>().deref().clone();
+	let statscopy = ecs.fetch::<EventCounter>().deref().clone();
+	let rngcopy = ecs.fetch::<RandomNumberGenerator>().deref().clone();
 	let savehelper = ecs.create_entity()
 		.with(SerializationHelper {
 			map: mapcopy,
 			log: logcopy,
+			stats: statscopy,
+			rng: Some(rngcopy),
 		})
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 
 	// Actually Serialize
-	{
+	let written = (|| {
 		let data = (ecs.entities(), ecs.read_storage::<SimpleMarker<SerializeMe>>());
-		let writer = File::create("./savegame.json").unwrap();
-		let mut serializer = serde_json::Serializer::new(writer);
-		serialize_individually!(
-			ecs, serializer, data,
-			Player,
-			Monster,
-			BlocksTile,
-			Item,
-			Consumable,
-			Name,
-			Position,
-			Renderable,
-			Viewshed,
-			ParticleLifetime,
-			Hidden,
-			EntityTrigger,
-			EntityMoved,
-			SingleActivation,
-			CombatStats,
-			InflictsDamage,
-			SufferDamage,
-			Ranged,
-			AreaOfEffect,
-			HungerClock,
-			ProvidesFood,
-			WantsToMelee,
-			WantsToPickupItem,
-			WantsToDropItem,
-			WantsToUseItem,
-			WantsToRemoveItem,
-			InBackpack,
-			ProvidesHealing,
-			Confusion,
-			SerializationHelper,
-			Equippable,
-			Equipped,
-			MeleePowerBonus,
-			DefenseBonus,
-			MagicMapper,
-		);
+		let mut serializer = serde_json::Serializer::new(Vec::new());
+		saved_components!(serialize_individually!(ecs, serializer, data,));
+		let body = serializer.into_inner();
+		header.checksum = Some(checksum(&body));
+
+		let mut writer : Vec<u8> = Vec::new();
+		let header = serde_json::to_string(&header)
+			.map_err(|e| format!("The save's header couldn't be written ({})", e))?;
+		writeln!(writer, "{}", header)
+			.map_err(|e| format!("The save's header couldn't be written ({})", e))?;
+		writer.extend(body);
+
+		write_save(slot, writer);
+		Ok(())
+	})();
+
+	// Cleanup, whether it went or not
+	ecs.delete_entity(savehelper).or_warn("Failed to cleanup after save");
+	written
+}
+
+pub fn does_save_exist () -> bool { SaveSlot::ALL.iter().any(|slot| slot.exists()) }
+
+/// Brings the body of a save from an older version up to date, a version at
+/// a time
+fn migrate (version: u32, body: &str) -> Result<String, String> {
+	if version > SAVE_VERSION {
+		return Err("This save is from a newer version of the game".to_string());
+	}
+
+	// Saves from before versions laid their lists out differently, and
+	// there's no telling them apart well enough to rearrange them
+	if version == 0 {
+		return Err("This save is too old to load".to_string());
 	}
 
-	// Cleanup
-	ecs.delete_entity(savehelper).expect("Failed to cleanup after save");
+	// Each bump of SAVE_VERSION gets a step here, run in turn from the save's
+	// version up. The first version has nothing older to come up from.
+	Ok(body.to_string())
 }
 
-pub fn does_save_exist () -> bool { Path::new("./savegame.json").exists() }
+/// Loads the newest save over whatever's in the world, and says which slot
+/// it came from
+pub fn load_game (ecs: &mut World) -> Result<SaveSlot, String> {
+	let slot = newest_save().ok_or("There's no save to load")?;
+	load_slot(ecs, slot).map(|_| slot)
+}
+
+/// Loads the save in the slot over whatever's in the world. If it can't be
+/// loaded the world's left in pieces, and needs setting up afresh.
+pub fn load_slot (ecs: &mut World, slot: SaveSlot) -> Result<(), String> {
+	let save = read_slot(ecs, slot)?;
+	apply_save(ecs, save)
+}
+
+/// A save that's been read in and brought up to date, but not yet loaded
+pub struct ReadSave {
+	body     : String,
+	tampered : bool,
+}
+
+/// Reads the save in the slot and brings it up to date, leaving the world be,
+/// so a save that won't load never costs the game being played. Saves that
+/// don't match their checksum load with a warning, or not at all if edited
+/// saves are refused.
+pub fn read_slot (ecs: &World, slot: SaveSlot) -> Result<ReadSave, String> {
+	if slot == SaveSlot::Quick && ecs.fetch::<EventCounter>().ironman {
+		return Err("There's no quick loading in ironman".to_string());
+	}
+	wait_for_writes();
+	let data = storage::read(slot.path())?;
+	// Saves from before headers start straight in on their lists
+	let (header, body) = data.split_once('\n')
+		.and_then(|(header, body)| Some((serde_json::from_str::<SaveHeader>(header).ok()?, body)))
+		.ok_or("This save is too old to load")?;
+	let (version, expected) = (header.version, header.checksum);
+
+	let tampered = expected.map_or(false, |expected| expected != checksum(body.as_bytes()));
+	if tampered && ecs.fetch::<GameplaySettings>().refuse_edited_saves {
+		return Err("This save has been edited or cut short".to_string());
+	}
+
+	Ok(ReadSave { body: migrate(version, body)?, tampered })
+}
+
+/// Loads a save that's been read over whatever's in the world. If it can't
+/// be loaded the world's left in pieces, and needs setting up afresh.
+pub fn apply_save (ecs: &mut World, save: ReadSave) -> Result<(), String> {
+	let ReadSave { body, tampered } = save;
 
-pub fn load_game (ecs: &mut World) {
 	// Delete everything
 	{
 		let mut to_delete = Vec::new();
 		for e in ecs.entities().join() { to_delete.push(e) }
 		for del in to_delete.iter() {
-			ecs.delete_entity(*del).expect("Deletion Failed")
+			ecs.delete_entity(*del).or_warn("Deletion Failed");
 		}
 	}
 
-	let data = fs::read_to_string("./savegame.json").unwrap();
-	let mut de = serde_json::Deserializer::from_str(&data);
+	let mut de = serde_json::Deserializer::from_str(&body);
 
 	{
 		let mut d = (
@@ -119,44 +337,7 @@ pub fn load_game (ecs: &mut World) {
 			&mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
 		);
 
-		deserialize_individually!(
-			ecs, de, d,
-			Player,
-			Monster,
-			BlocksTile,
-			Item,
-			Consumable,
-			Name,
-			Position,
-			Renderable,
-			Viewshed,
-			ParticleLifetime,
-			Hidden,
-			EntityTrigger,
-			EntityMoved,
-			SingleActivation,
-			CombatStats,
-			InflictsDamage,
-			SufferDamage,
-			Ranged,
-			AreaOfEffect,
-			HungerClock,
-			ProvidesFood,
-			WantsToMelee,
-			WantsToPickupItem,
-			WantsToDropItem,
-			WantsToUseItem,
-			WantsToRemoveItem,
-			InBackpack,
-			ProvidesHealing,
-			Confusion,
-			SerializationHelper,
-			Equippable,
-			Equipped,
-			MeleePowerBonus,
-			DefenseBonus,
-			MagicMapper,
-		);
+		saved_components!(deserialize_individually!(ecs, de, d,));
 	}
 
 	let mut deleteme : Option<Entity> = None;
@@ -169,11 +350,19 @@ pub fn load_game (ecs: &mut World) {
 		for (e, h) in (&entities, &helper).join() {
 			let mut worldmap = ecs.write_resource::<Map>();
 			*worldmap = h.map.clone();
-			worldmap.tile_content = vec![Vec::new(); MAP_SIZE];
+			ecs.write_resource::<SpatialIndex>().reset(&worldmap);
+			ecs.write_resource::<PlayerFlowMap>().invalidate();
 
 			let mut log = ecs.write_resource::<GameLog>();
 			*log = h.log.clone();
 
+			let mut stats = ecs.write_resource::<EventCounter>();
+			*stats = h.stats.clone();
+
+			if let Some(rng) = &h.rng {
+				*ecs.write_resource::<RandomNumberGenerator>() = rng.clone();
+			}
+
 			deleteme = Some(e);
 		}
 
@@ -185,13 +374,248 @@ pub fn load_game (ecs: &mut World) {
 		}
 	}
 
-	ecs.delete_entity(deleteme.unwrap())
-		.expect("Failed to delete load helper");
+	let deleteme = deleteme.ok_or("The save has no map in it")?;
+	ecs.delete_entity(deleteme)
+		.or_warn("Failed to delete load helper");
+
+	if tampered {
+		ecs.write_resource::<GameLog>().system("This save doesn't match its checksum, and may have been edited.".to_string());
+	}
+
+	Ok(())
 }
 
+/// The newest save, compressed and written out as a string that can be
+/// pasted into another copy of the game. Says where the string went.
+pub fn export_save () -> Result<String, String> {
+	let slot = newest_save().ok_or("There's no save to export")?;
+	if slot.header().map_or(false, |header| header.ironman) {
+		return Err("Ironman runs can't be exported".to_string());
+	}
+	let data = storage::read(slot.path())?;
+	let compressed = miniz_oxide::deflate::compress_to_vec(data.as_bytes(), 9);
+	storage::share(&BASE64.encode(compressed))
+}
+
+/// Takes in a save string from `export_save`, in place of the current save
+pub fn import_save () -> Result<(), String> {
+	let text = storage::shared().ok_or("There's no save string to import")?;
+	let compressed = BASE64.decode(text.trim())
+		.map_err(|_| "That isn't a save string".to_string())?;
+	let data = miniz_oxide::inflate::decompress_to_vec(&compressed)
+		.map_err(|_| "The save string is damaged".to_string())?;
+	let data = String::from_utf8(data)
+		.map_err(|_| "The save string is damaged".to_string())?;
+
+	let (header, body) = data.split_once('\n').ok_or("The save string is damaged")?;
+	let header : SaveHeader = serde_json::from_str(header)
+		.map_err(|_| "The save string is damaged".to_string())?;
+	if header.checksum.map_or(false, |expected| expected != checksum(body.as_bytes())) {
+		return Err("The save string is damaged".to_string());
+	}
+
+	// Only the one run can be going at a time
+	wait_for_writes();
+	for slot in SaveSlot::ALL.iter().filter(|slot| **slot != SaveSlot::Manual) {
+		for path in [slot.path(), slot.backup_path()].iter() {
+			if storage::exists(path) { storage::remove(path); }
+		}
+	}
+	let slot = SaveSlot::Manual;
+	if slot.exists() { storage::copy(slot.path(), slot.backup_path())?; }
+	storage::write(slot.path(), data.as_bytes())
+}
+
+/// Gets rid of every save in every slot, and their backups, so a run
+/// can't be picked up again once it's over
 pub fn delete_save () {
-	if does_save_exist() {
-		fs::remove_file("./savegame.json")
-			.expect("Failed to delete save");
+	wait_for_writes();
+	for slot in SaveSlot::ALL.iter() {
+		for path in [slot.path(), slot.backup_path()].iter() {
+			if storage::exists(path) { storage::remove(path); }
+		}
+	}
+}
+
+/// Gets rid of the save in the slot and its backup, once it's been loaded
+pub fn delete_slot (slot: SaveSlot) {
+	wait_for_writes();
+	for path in [slot.path(), slot.backup_path()].iter() {
+		if storage::exists(path) { storage::remove(path); }
+	}
+}
+
+// Writing
+// =========================================================================
+
+/// A save being written out, and the slot it's going to
+type SaveWrite = (SaveSlot, JoinHandle<Result<SaveSlot, String>>);
+
+/// Saves being written out on threads of their own, so the game doesn't
+/// stop for the disk
+static WRITING : Mutex<Vec<SaveWrite>> = Mutex::new(Vec::new());
+
+/// How the saves that are done went, waiting to be reported
+static WRITTEN : Mutex<Vec<Result<SaveSlot, String>>> = Mutex::new(Vec::new());
+
+/// Backs up what's in the slot and writes the new save in its place, off the
+/// main thread where there are threads to be had. Only one save goes to a
+/// slot at a time, so an older one can't land after a newer one.
+fn write_save (slot: SaveSlot, data: Vec<u8>) {
+	let write = move || {
+		if slot.exists() { storage::copy(slot.path(), slot.backup_path())?; }
+		storage::write(slot.path(), &data).map(|_| slot)
+	};
+
+	#[cfg(not(target_arch = "wasm32"))]
+	{
+		let mut writing = WRITING.lock().expect("Save threads got tangled");
+		let (same_slot, others) : (Vec<_>, Vec<_>) = writing.drain(..).partition(|(writing_to, _)| *writing_to == slot);
+		*writing = others;
+		let results : Vec<_> = same_slot.into_iter().map(|(_, handle)| join_write(handle)).collect();
+		WRITTEN.lock().expect("Save threads got tangled").extend(results);
+		writing.push((slot, std::thread::spawn(write)));
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	WRITTEN.lock().expect("Save threads got tangled").push(write());
+}
+
+fn join_write (handle: JoinHandle<Result<SaveSlot, String>>) -> Result<SaveSlot, String> {
+	handle.join().unwrap_or_else(|_| Err("The save fell over while being written".to_string()))
+}
+
+/// Holds on until every save being written is done, so nothing reads or
+/// removes one halfway through. Says whether they all went.
+pub fn wait_for_writes () -> bool {
+	let handles : Vec<_> = WRITING.lock().expect("Save threads got tangled").drain(..).collect();
+	let results : Vec<_> = handles.into_iter().map(|(_, handle)| join_write(handle)).collect();
+	let written = results.iter().all(|result| result.is_ok());
+	WRITTEN.lock().expect("Save threads got tangled").extend(results);
+	written
+}
+
+/// How every save that's finished since last time went, for the log
+pub fn finished_writes () -> Vec<Result<SaveSlot, String>> {
+	let mut writing = WRITING.lock().expect("Save threads got tangled");
+	let (done, still_going) : (Vec<_>, Vec<_>) = writing.drain(..).partition(|(_, handle)| handle.is_finished());
+	*writing = still_going;
+
+	let mut finished : Vec<_> = WRITTEN.lock().expect("Save threads got tangled").drain(..).collect();
+	finished.extend(done.into_iter().map(|(_, handle)| join_write(handle)));
+	finished
+}
+
+// Storage
+// =========================================================================
+
+/// Saves go in files on the desktop. Settings and records go through here
+/// too, so they keep on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod storage {
+	use std::fs;
+	use std::fs::File;
+	use std::io::{BufRead, BufReader};
+	use std::path::Path;
+	use crate::debug_log::OrWarn;
+
+	pub fn exists (path: &str) -> bool { Path::new(path).exists() }
+
+	/// Just the first line, without reading the rest of the file
+	pub fn first_line (path: &str) -> Option<String> {
+		let file = File::open(path).ok()?;
+		let mut line = String::new();
+		BufReader::new(file).read_line(&mut line).ok()?;
+		Some(line)
+	}
+
+	pub fn read (path: &str) -> Result<String, String> {
+		fs::read_to_string(path).map_err(|e| format!("The save couldn't be read ({})", e))
+	}
+
+	/// Written beside the file and moved over it once it's all there, so
+	/// what's on disk is never half a save
+	pub fn write (path: &str, data: &[u8]) -> Result<(), String> {
+		let partial = format!("{}.tmp", path);
+		fs::write(&partial, data)
+			.and_then(|_| fs::rename(&partial, path))
+			.map_err(|e| format!("The save couldn't be written ({})", e))
+	}
+
+	pub fn copy (from: &str, to: &str) -> Result<(), String> {
+		fs::copy(from, to)
+			.map(|_| ())
+			.map_err(|e| format!("The save couldn't be backed up ({})", e))
+	}
+
+	pub fn remove (path: &str) {
+		fs::remove_file(path).or_warn("Failed to delete save");
+	}
+
+	/// Where save strings are written out and read back in
+	const SHARE_FILE : &str = "./save_string.txt";
+
+	pub fn share (text: &str) -> Result<String, String> {
+		fs::write(SHARE_FILE, text)
+			.map(|_| format!("Save string written to {}", SHARE_FILE))
+			.map_err(|e| format!("The save string couldn't be written ({})", e))
+	}
+
+	pub fn shared () -> Option<String> { fs::read_to_string(SHARE_FILE).ok() }
+}
+
+/// The browser has no files to speak of, so on the web saves go in local
+/// storage under their paths instead
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod storage {
+	use crate::debug_log::OrWarn;
+
+	fn local_storage () -> Option<web_sys::Storage> {
+		web_sys::window()?.local_storage().ok()?
+	}
+
+	pub fn exists (path: &str) -> bool {
+		local_storage().map_or(false, |storage| matches!(storage.get_item(path), Ok(Some(_))))
+	}
+
+	pub fn first_line (path: &str) -> Option<String> {
+		let data = local_storage()?.get_item(path).ok()??;
+		data.lines().next().map(|line| line.to_string())
+	}
+
+	pub fn read (path: &str) -> Result<String, String> {
+		local_storage()
+			.and_then(|storage| storage.get_item(path).ok().flatten())
+			.ok_or_else(|| "The save couldn't be read from the browser's storage".to_string())
+	}
+
+	pub fn write (path: &str, data: &[u8]) -> Result<(), String> {
+		let data = std::str::from_utf8(data).map_err(|_| "The save came out garbled".to_string())?;
+		local_storage()
+			.ok_or("The browser won't lend any storage")?
+			.set_item(path, data)
+			.map_err(|_| "The browser's storage is full".to_string())
+	}
+
+	pub fn copy (from: &str, to: &str) -> Result<(), String> {
+		write(to, read(from)?.as_bytes())
+	}
+
+	pub fn remove (path: &str) {
+		if let Some(storage) = local_storage() {
+			storage.remove_item(path).map_err(|e| format!("{:?}", e)).or_warn("Failed to delete save");
+		}
+	}
+	/// There's no clipboard to hand, so the string's shown in a prompt where
+	/// it can be copied from
+	pub fn share (text: &str) -> Result<String, String> {
+		if let Some(window) = web_sys::window() {
+			let _ = window.prompt_with_message_and_default("Copy this save string:", text);
+		}
+		Ok("Save string exported".to_string())
+	}
+
+	pub fn shared () -> Option<String> {
+		web_sys::window()?.prompt_with_message("Paste a save string:").ok()?
 	}
 }
\ No newline at end of file
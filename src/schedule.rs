@@ -0,0 +1,175 @@
+use specs::prelude::*;
+use crate::TurnState;
+use crate::ai::{AbilityAI, ApproachAI, AwarenessAI, BossAI, DefaultMoveAI, FleeAI, HearingAI, ItemAI, QuipSystem, TurnSystem, VisibleAI};
+use crate::befriend_system::BefriendSystem;
+use crate::damage_system::DamageSystem;
+use crate::door_system::DoorSystem;
+use crate::encumbrance_system::EncumbranceSystem;
+use crate::inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemRemoveSystem, ItemThrowSystem, ItemUseSystem};
+use crate::light_system::LightSystem;
+use crate::map_indexing_system::MapIndexingSystem;
+use crate::melee_combat_system::MeleeCombatSystem;
+use crate::needs_system::NeedsSystem;
+use crate::particle_system::ParticleSpawnSystem;
+use crate::perf::Timed;
+use crate::status_effect_system::StatusEffectSystem;
+use crate::trigger_system::TriggerSystem;
+use crate::visibility_system::VisibilitySystem;
+
+// Phases
+// =========================================================================
+
+/// The parts of a turn the systems run in. Everything else, like waiting on
+/// the player or building the next level, leaves the world alone.
+#[derive(PartialEq, Copy, Clone)]
+pub enum Phase {
+	/// Settling a level before the player's first go on it
+	Settle,
+	Player,
+	/// Everyone else, and the second go for anything hasted
+	Monsters,
+}
+
+impl Phase {
+	pub fn of (turn: TurnState) -> Option<Phase> {
+		match turn {
+			TurnState::PreRun => Some(Phase::Settle),
+			TurnState::PlayerTurn => Some(Phase::Player),
+			TurnState::MonsterTurn | TurnState::HastedTurn => Some(Phase::Monsters),
+			_ => None,
+		}
+	}
+}
+
+const EVERY_PHASE : &[Phase] = &[Phase::Settle, Phase::Player, Phase::Monsters];
+
+/// Whoever's turn it is, but not while the level settles
+const TURNS : &[Phase] = &[Phase::Player, Phase::Monsters];
+
+const SETTLE : &[Phase] = &[Phase::Settle];
+
+const PLAYER : &[Phase] = &[Phase::Player];
+
+/// The AI, which only acts for whoever TurnSystem gives a turn to
+const MONSTERS : &[Phase] = &[Phase::Monsters];
+
+// Table
+// =========================================================================
+
+/// Builds a phase's dispatcher out of the rows that run in it. Each row
+/// waits on the rows it names, which have to run in the same phase and be
+/// listed above it, or the schedule won't build. Rows that don't wait on one
+/// another can go in any order, or at once. Each one is timed under its name
+/// for the performance overlay.
+macro_rules! schedule {
+	($( ($system:expr, $name:literal, [$($dep:literal),*], $phases:expr), )*) => {
+		fn build_dispatcher (phase: Phase) -> Dispatcher<'static, 'static> {
+			let mut builder = DispatcherBuilder::new();
+			let mut added : Vec<&str> = Vec::new();
+			$(
+				if $phases.contains(&phase) {
+					let after : &[&str] = &[$($dep),*];
+					for dep in after.iter() {
+						assert!(added.contains(dep), "\"{}\" waits on \"{}\", which doesn't run before it in that phase", $name, dep);
+					}
+					builder.add(Timed::new($system, $name), $name, after);
+					added.push($name);
+				}
+			)*
+			builder.build()
+		}
+	};
+}
+
+// The turn's systems, each after whatever it depends on having run, and the
+// phases it runs in. Systems that wait on different things depending on the
+// phase get a row for each.
+//
+// Most of a turn is one long chain, since each step acts on what the one
+// before decided: the AI a step at a time, each taking turns off the next,
+// then everything that acts on what the AI and player decided. So little
+// runs at once. Hunger and thirst only need the damage dealt so far, so
+// they're free to run alongside the items, and light and encumbrance
+// alongside each other. Needs and statuses still check whose turn it is,
+// ticking down the player's on theirs and everyone else's on the monsters'.
+schedule! {
+	(VisibilitySystem {}, "visibility", [], EVERY_PHASE),
+
+	// AI
+	(TurnSystem {}, "turns", ["visibility"], MONSTERS),
+	(HearingAI {}, "hearing", ["visibility"], PLAYER),
+	(HearingAI {}, "hearing", ["turns"], MONSTERS),
+	(AwarenessAI {}, "awareness", ["hearing"], MONSTERS),
+	(BossAI {}, "bosses", ["awareness"], MONSTERS),
+	(QuipSystem {}, "quips", ["bosses"], MONSTERS),
+	(ItemAI {}, "item_ai", ["quips"], MONSTERS),
+	(AbilityAI {}, "abilities", ["item_ai"], MONSTERS),
+	(VisibleAI {}, "visible_ai", ["abilities"], MONSTERS),
+	(FleeAI::default(), "flee", ["visible_ai"], MONSTERS),
+	(ApproachAI {}, "approach", ["flee"], MONSTERS),
+	(DefaultMoveAI {}, "default_move", ["approach"], MONSTERS),
+
+	(DoorSystem {}, "doors", ["hearing"], PLAYER),
+	(DoorSystem {}, "doors", ["default_move"], MONSTERS),
+	(TriggerSystem {}, "triggers", ["doors"], TURNS),
+	(MapIndexingSystem {}, "map_index", ["visibility"], SETTLE),
+	(MapIndexingSystem {}, "map_index", ["triggers"], TURNS),
+	(MeleeCombatSystem {}, "melee", ["map_index"], TURNS),
+	(DamageSystem {}, "damage", ["melee"], TURNS),
+	(ItemCollectionSystem {}, "pickup", ["damage"], TURNS),
+	(ItemDropSystem {}, "drop", ["pickup"], TURNS),
+	(ItemThrowSystem {}, "throw", ["drop"], TURNS),
+	(ItemUseSystem {}, "item_use", ["throw"], TURNS),
+	(ItemRemoveSystem {}, "item_remove", ["item_use"], TURNS),
+	(EncumbranceSystem {}, "encumbrance", ["map_index"], SETTLE),
+	(EncumbranceSystem {}, "encumbrance", ["item_remove"], TURNS),
+	(LightSystem {}, "light", ["map_index"], SETTLE),
+	(LightSystem {}, "light", ["item_remove"], TURNS),
+	(NeedsSystem {}, "needs", ["damage"], TURNS),
+	(StatusEffectSystem {}, "status_effects", ["item_remove", "needs"], TURNS),
+	(BefriendSystem {}, "befriend", ["status_effects"], TURNS),
+
+	// Last, once everything's asked for its particles
+	(ParticleSpawnSystem {}, "particles", ["encumbrance", "light"], SETTLE),
+	(ParticleSpawnSystem {}, "particles", ["befriend", "encumbrance", "light"], TURNS),
+}
+
+// Schedule
+// =========================================================================
+
+/// A dispatcher for each phase, built from the table
+pub struct Schedule {
+	settle   : Dispatcher<'static, 'static>,
+	player   : Dispatcher<'static, 'static>,
+	monsters : Dispatcher<'static, 'static>,
+}
+
+impl Default for Schedule {
+	fn default () -> Schedule { Schedule::new() }
+}
+
+impl Schedule {
+	pub fn new () -> Schedule {
+		Schedule {
+			settle: build_dispatcher(Phase::Settle),
+			player: build_dispatcher(Phase::Player),
+			monsters: build_dispatcher(Phase::Monsters),
+		}
+	}
+
+	/// Runs whichever systems belong to the turn the world's on. Nothing
+	/// should be running them outside of a turn, so that's logged and the
+	/// systems are left be.
+	pub fn dispatch (&mut self, ecs: &World) {
+		let Some(phase) = Phase::of(*ecs.fetch::<TurnState>()) else {
+			log::warn!("Systems were asked to run outside of anybody's turn");
+			return;
+		};
+		let dispatcher = match phase {
+			Phase::Settle => &mut self.settle,
+			Phase::Player => &mut self.player,
+			Phase::Monsters => &mut self.monsters,
+		};
+		dispatcher.dispatch(ecs);
+	}
+}
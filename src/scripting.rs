@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::sync::{Arc, Mutex, MutexGuard};
+use rhai::{Engine, Scope, AST, INT};
+use specs::prelude::*;
+use crate::{CombatStats, StatusEffectKind, StatusEffects, SufferDamage};
+use crate::gamelog::GameLog;
+
+/// Where the scripts named by items and traps are kept, beside the config
+pub const SCRIPT_DIR : &str = "./scripts";
+
+/// How much work a script gets through before it's stopped, so one that
+/// loops forever or recurses without end can't hang the turn
+const MAX_OPERATIONS : u64 = 100_000;
+const MAX_CALL_LEVELS : usize = 32;
+const MAX_EXPR_DEPTH : usize = 64;
+const MAX_FN_EXPR_DEPTH : usize = 32;
+
+/// Something a script asked for, done once it's finished
+pub enum ScriptCommand {
+	Damage(i32),
+	Heal(i32),
+	Inflict(StatusEffectKind, i32),
+	Log(String),
+}
+
+/// Who a script's being run on, and who by
+pub struct ScriptTarget<'a> {
+	pub user          : &'a str,
+	pub target        : &'a str,
+	pub target_hp     : i32,
+	pub target_max_hp : i32,
+	pub depth         : i32,
+}
+
+// Engine
+// =========================================================================
+
+/// Runs the scripts behind item and trap effects, so new ones can be made
+/// without a rebuild. Scripts can't touch the world themselves: they call
+/// `damage`, `heal`, `inflict` and `log`, which are queued up and handed
+/// back to whichever system ran them.
+pub struct ScriptEngine {
+	engine   : Engine,
+	compiled : HashMap<String, AST>,
+	queued   : Arc<Mutex<Vec<ScriptCommand>>>,
+}
+
+impl Default for ScriptEngine {
+	fn default () -> ScriptEngine { ScriptEngine::new() }
+}
+
+impl ScriptEngine {
+	pub fn new () -> ScriptEngine {
+		let queued : Arc<Mutex<Vec<ScriptCommand>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut engine = Engine::new();
+		engine.set_max_operations(MAX_OPERATIONS);
+		engine.set_max_call_levels(MAX_CALL_LEVELS);
+		engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FN_EXPR_DEPTH);
+
+		let q = queued.clone();
+		engine.register_fn("damage", move |amount: INT| {
+			queue(&q).push(ScriptCommand::Damage(amount as i32));
+		});
+		let q = queued.clone();
+		engine.register_fn("heal", move |amount: INT| {
+			queue(&q).push(ScriptCommand::Heal(amount as i32));
+		});
+		let q = queued.clone();
+		engine.register_fn("inflict", move |status: String, turns: INT| {
+			if let Some(kind) = StatusEffectKind::named(&status) {
+				queue(&q).push(ScriptCommand::Inflict(kind, turns as i32));
+			}
+		});
+		let q = queued.clone();
+		engine.register_fn("log", move |text: String| {
+			queue(&q).push(ScriptCommand::Log(text));
+		});
+
+		ScriptEngine { engine, compiled: HashMap::new(), queued }
+	}
+
+	/// Runs the named script on the target, and gives back what it asked for.
+	/// Scripts are read the first time they're needed and kept after that.
+	pub fn run (&mut self, script: &str, on: &ScriptTarget) -> Result<Vec<ScriptCommand>, String> {
+		if !self.compiled.contains_key(script) {
+			let path = format!("{}/{}.rhai", SCRIPT_DIR, script);
+			let source = read_script(&path)?;
+			let ast = self.engine.compile(source).map_err(|e| format!("{}: {}", path, e))?;
+			self.compiled.insert(script.to_string(), ast);
+		}
+
+		let mut scope = Scope::new();
+		scope.push("user", on.user.to_string());
+		scope.push("target", on.target.to_string());
+		scope.push("target_hp", on.target_hp as INT);
+		scope.push("target_max_hp", on.target_max_hp as INT);
+		scope.push("depth", on.depth as INT);
+
+		queue(&self.queued).clear();
+		let ran = self.engine.run_ast_with_scope(&mut scope, &self.compiled[script]);
+		let commands = queue(&self.queued).drain(..).collect();
+
+		ran.map(|_| commands).map_err(|e| format!("{}: {}", script, e))
+	}
+
+	/// Drops every script read so far, so they're read afresh next time
+	pub fn forget_scripts (&mut self) {
+		self.compiled.clear();
+	}
+}
+
+/// The queued commands. Nothing can panic while holding them, but if
+/// something ever did they're still taken as they are rather than
+/// panicking again mid-turn.
+fn queue (queued: &Mutex<Vec<ScriptCommand>>) -> MutexGuard<'_, Vec<ScriptCommand>> {
+	queued.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_script (path: &str) -> Result<String, String> {
+	fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// There's no scripts folder in the browser, so they're built in
+#[cfg(target_arch = "wasm32")]
+fn read_script (path: &str) -> Result<String, String> {
+	const SCRIPTS : &[(&str, &str)] = &[
+		("./scripts/hex_rune.rhai", include_str!("../scripts/hex_rune.rhai")),
+	];
+
+	SCRIPTS.iter()
+		.find(|(name, _)| *name == path)
+		.map(|(_, source)| source.to_string())
+		.ok_or_else(|| format!("{}: not built in", path))
+}
+
+/// Who takes the blame for any damage a script deals
+pub struct Blame<'a> {
+	pub cause     : &'a str,
+	/// Whether the player set it off, by using the item
+	pub by_player : bool,
+}
+
+/// Does what a script asked for to `target`, putting any damage down to
+/// `blame`
+pub fn apply_commands (
+	commands: Vec<ScriptCommand>,
+	target: Entity,
+	blame: Blame,
+	stats: &mut WriteStorage<CombatStats>,
+	suffer_damage: &mut WriteStorage<SufferDamage>,
+	statuses: &mut WriteStorage<StatusEffects>,
+	log: &mut GameLog,
+) {
+	for command in commands {
+		match command {
+			ScriptCommand::Damage(amount) => {
+				if stats.get(target).is_some() {
+					SufferDamage::new_damage(suffer_damage, target, amount, blame.cause, blame.by_player);
+				}
+			}
+			ScriptCommand::Heal(amount) => {
+				if let Some(stats) = stats.get_mut(target) {
+					stats.hp = i32::min(stats.max_hp, stats.hp + amount);
+				}
+			}
+			ScriptCommand::Inflict(kind, turns) => StatusEffects::add(statuses, target, kind, turns),
+			ScriptCommand::Log(text) => log.combat(text),
+		}
+	}
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use crate::debug_log::OrWarn;
+use crate::display::DisplaySettings;
+use crate::keybindings::{KeyBindings, SavedBinding};
+use crate::saveload_system::storage;
+
+const SETTINGS_FILE : &str = "./settings.toml";
+
+/// How many turns go by between autosaves, with 0 for never
+pub const AUTOSAVE_INTERVALS : [i32; 5] = [0, 50, 100, 250, 500];
+
+/// Everything in settings.toml, kept apart from the saves so starting afresh
+/// doesn't lose them
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+	#[serde(default = "DisplaySettings::defaults")]
+	display  : DisplaySettings,
+	/// Left out for the default gameplay settings
+	#[serde(default)]
+	gameplay : Option<GameplaySettings>,
+	/// Left out for the default bindings
+	#[serde(default)]
+	bindings : Option<Vec<SavedBinding>>,
+}
+
+impl SettingsFile {
+	fn defaults () -> SettingsFile {
+		SettingsFile { display: DisplaySettings::defaults(), gameplay: None, bindings: None }
+	}
+}
+
+// Gameplay
+// =========================================================================
+
+/// What's been picked in the options that change how the game's played
+/// rather than how it looks, kept in the settings file with the rest
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub struct GameplaySettings {
+	/// Which of the autosave intervals is picked
+	#[serde(default = "default_autosave_interval")]
+	pub autosave_interval   : usize,
+	/// Whether to autosave on arriving at each new level
+	#[serde(default = "enabled")]
+	pub autosave_levels     : bool,
+	/// Whether saves that don't match their checksum are turned away rather
+	/// than loaded with a warning
+	#[serde(default)]
+	pub refuse_edited_saves : bool,
+	/// Whether new runs are played in ironman mode
+	#[serde(default)]
+	pub ironman             : bool,
+}
+
+fn enabled () -> bool { true }
+
+fn default_autosave_interval () -> usize { 2 }
+
+impl GameplaySettings {
+	pub fn defaults () -> GameplaySettings {
+		GameplaySettings {
+			autosave_interval: default_autosave_interval(),
+			autosave_levels: true,
+			refuse_edited_saves: false,
+			ironman: false,
+		}
+	}
+
+	/// The settings as they were saved, or the defaults if they've been
+	/// mangled into something that can't be used
+	pub fn validated (self) -> GameplaySettings {
+		if self.autosave_interval < AUTOSAVE_INTERVALS.len() { self }
+		else { GameplaySettings::defaults() }
+	}
+
+	/// Turns between autosaves, or 0 if they're off
+	pub fn autosave_turns (&self) -> i32 { AUTOSAVE_INTERVALS[self.autosave_interval] }
+}
+
+// Loading and Saving
+// =========================================================================
+
+/// The saved settings, or the defaults for anything missing (or mangled)
+pub fn load () -> (DisplaySettings, GameplaySettings, KeyBindings) {
+	let file =
+		if storage::exists(SETTINGS_FILE) {
+			storage::read(SETTINGS_FILE)
+				.ok()
+				.and_then(|data| toml::from_str(&data).ok())
+				.unwrap_or_else(SettingsFile::defaults)
+		} else {
+			SettingsFile::defaults()
+		};
+
+	(
+		file.display.validated(),
+		file.gameplay.map_or_else(GameplaySettings::defaults, GameplaySettings::validated),
+		file.bindings.map_or_else(KeyBindings::defaults, KeyBindings::from_saved),
+	)
+}
+
+pub fn save (display: &DisplaySettings, gameplay: &GameplaySettings, bindings: &KeyBindings) {
+	let file = SettingsFile { display: *display, gameplay: Some(*gameplay), bindings: Some(bindings.to_saved()) };
+	let data = toml::to_string_pretty(&file).expect("Settings are beyond words");
+	storage::write(SETTINGS_FILE, data.as_bytes()).or_warn("Couldn't find anywhere to put the settings");
+}
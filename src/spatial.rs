@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use rltk::{Algorithm2D, BaseMap, Point, SmallVec};
+use specs::prelude::*;
+use crate::map::{Map, TileType};
+
+// Index
+// =========================================================================
+
+/// What's on each tile of the level and what's in the way, rebuilt by the
+/// MapIndexingSystem each turn and kept straight by anything that moves or
+/// dies in between. Looking up or moving something is a straight index into
+/// the tile.
+#[derive(Default)]
+pub struct SpatialIndex {
+	/// Walls and the void, which never go anywhere
+	terrain      : Vec<bool>,
+	/// Terrain, or something on the tile that blocks it
+	blocked      : Vec<bool>,
+	/// Everything on each tile, and whether it blocks it
+	content      : Vec<Vec<(Entity, bool)>>,
+	closed_doors : HashSet<usize>,
+}
+
+impl SpatialIndex {
+	/// Starts over for a new map, with nothing on it yet
+	pub fn reset (&mut self, map: &Map) {
+		self.terrain = map.tiles.iter()
+			.map(|tile| *tile == TileType::Wall || *tile == TileType::Void)
+			.collect();
+		self.content = vec![Vec::new(); map.tiles.len()];
+		self.clear();
+	}
+
+	/// Takes everything off the map, leaving the bare terrain
+	pub fn clear (&mut self) {
+		for content in self.content.iter_mut() {
+			content.clear();
+		}
+		self.blocked = self.terrain.clone();
+		self.closed_doors.clear();
+	}
+
+	pub fn index_entity (&mut self, entity: Entity, idx: usize, blocks: bool) {
+		self.content[idx].push((entity, blocks));
+		if blocks { self.blocked[idx] = true; }
+	}
+
+	/// Moves something from one tile to another, taking its blocking with it
+	pub fn move_entity (&mut self, entity: Entity, from: usize, to: usize) {
+		let mut blocks = false;
+		self.content[from].retain(|(e, b)| {
+			if *e == entity { blocks = *b; }
+			*e != entity
+		});
+		self.content[to].push((entity, blocks));
+
+		self.refresh(from);
+		self.refresh(to);
+	}
+
+	/// Takes something off the map, for when it's dead or picked up
+	pub fn remove_entity (&mut self, entity: Entity, idx: usize) {
+		self.content[idx].retain(|(e, _)| *e != entity);
+		self.refresh(idx);
+	}
+
+	/// Changes whether something blocks the tile it's on, like a door
+	/// swinging open
+	pub fn set_blocks (&mut self, entity: Entity, idx: usize, blocks: bool) {
+		for (e, b) in self.content[idx].iter_mut() {
+			if *e == entity { *b = blocks; }
+		}
+		self.refresh(idx);
+	}
+
+	/// Blocks or clears a tile by hand, for pathing round or through things
+	/// for a moment. Moving anything on or off the tile puts it right again.
+	pub fn set_blocked (&mut self, idx: usize, blocked: bool) {
+		self.blocked[idx] = blocked;
+	}
+
+	pub fn set_door (&mut self, idx: usize, closed: bool) {
+		if closed { self.closed_doors.insert(idx); }
+		else { self.closed_doors.remove(&idx); }
+	}
+
+	pub fn is_blocked (&self, idx: usize) -> bool {
+		self.blocked[idx]
+	}
+
+	pub fn is_closed_door (&self, idx: usize) -> bool {
+		self.closed_doors.contains(&idx)
+	}
+
+	/// Whether the tile can be walked onto, counting closed doors as open for
+	/// something that can open them
+	pub fn is_walkable (&self, idx: usize, can_open_doors: bool) -> bool {
+		!self.blocked[idx] || (can_open_doors && self.closed_doors.contains(&idx))
+	}
+
+	/// Everything on the tile
+	pub fn content (&self, idx: usize) -> impl Iterator<Item = Entity> + '_ {
+		self.content[idx].iter().map(|(entity, _)| *entity)
+	}
+
+	fn refresh (&mut self, idx: usize) {
+		self.blocked[idx] = self.terrain[idx] || self.content[idx].iter().any(|(_, blocks)| *blocks);
+	}
+}
+
+// Live Map
+// =========================================================================
+
+/// The level as it stands with everything on it, for paths that go round
+/// whatever's in the way and sight lines that stop at closed doors. The bare
+/// Map only knows its floor plan.
+pub struct LiveMap<'a> {
+	pub map            : &'a Map,
+	pub spatial        : &'a SpatialIndex,
+	/// Whether closed doors count as open for pathing
+	pub can_open_doors : bool,
+}
+
+impl<'a> Algorithm2D for LiveMap<'a> {
+	fn dimensions (&self) -> Point { self.map.dimensions() }
+}
+
+impl<'a> BaseMap for LiveMap<'a> {
+	fn is_opaque (&self, idx: usize) -> bool {
+		self.map.is_opaque(idx) || self.spatial.is_closed_door(idx)
+	}
+
+	fn get_available_exits (&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+		self.map.exits_where(idx, |x, y| {
+			x > 0 && x < self.map.width && y > 0 && y < self.map.height
+				&& self.spatial.is_walkable(self.map.xy_idx(x, y), self.can_open_doors)
+		})
+	}
+
+	fn get_pathing_distance (&self, idx1: usize, idx2: usize) -> f32 {
+		self.map.get_pathing_distance(idx1, idx2)
+	}
+}
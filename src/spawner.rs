@@ -2,19 +2,26 @@ use std::collections::HashMap;
 use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use crate::{AreaOfEffect, BlocksTile, CombatStats, Confusion, Consumable, DefenseBonus, EntityTrigger, EquipmentSlot, Equippable, Hidden, HungerClock, HungerState, InflictsDamage, Item, MagicMapper, MeleePowerBonus, Monster, Name, Player, Position, ProvidesFood, ProvidesHealing, Ranged, Renderable, SerializeMe, SingleActivation, TileType, Viewshed};
+use crate::{Ability, Ally, AreaOfEffect, Awareness, AwarenessState, BlocksTile, Blink, BossPhase, BossPhases, Bystander, CanOpenDoors, Charges, CombatStats, Consumable, DefenseBonus, Description, Door, EmitsNoise, EntityTrigger, EquipmentSlot, Equippable, Faction, FactionKind, Fragile, GoalArtifact, Hidden, HungerClock, HungerState, Idle, IdleBehaviour, InBackpack, InflictsDamage, InflictsStatus, Item, LightSource, MagicMapper, MeleePowerBonus, Memory, Monster, Morale, Name, PackMember, Patrol, Perception, PhaseAbility, Player, Position, Prisoner, ProvidesFood, Quips, ProvidesHealing, Ranged, Renderable, ScriptedEffect, SerializeMe, SingleActivation, StatusEffectKind, Swarm, ThirstClock, ThirstState, TileType, Viewshed, Waterskin, Weight};
+use crate::ai::SummonBuilder;
+use crate::config::Config;
+use crate::debug_log::OrWarn;
 use crate::map::Map;
 use crate::random_table::RandomTable;
 use crate::rect::Rect;
-
-const MAX_SPAWNS_PER_AREA : i32 = 4;
+use crate::spatial::SpatialIndex;
 
 // Player
 // =========================================================================
 
-/// Spawn player entity
+/// Spawn player entity, with a waterskin to start them off
 pub fn player (ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-	ecs
+	let (sight, well_fed, quenched) = {
+		let config = ecs.fetch::<Config>();
+		(config.sight.player, config.hunger.well_fed_turns, config.thirst.quenched_turns)
+	};
+
+	let player = ecs
 		.create_entity()
 		.with(Position { x: player_x, y: player_y })
 		.with(Renderable {
@@ -22,14 +29,16 @@ pub fn player (ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
 			fg: RGB::named(rltk::YELLOW),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 0,
+			sprite: None,
 		})
 		.with(Player {})
 		.with(Viewshed {
 			visible_tiles: Vec::new(),
-			range: 8,
+			range: sight,
 			dirty: true,
 		})
 		.with(Name { name: "you".to_string() })
+		.with(Faction { kind: FactionKind::Player })
 		.with(CombatStats {
 			max_hp: 30,
 			hp: 30,
@@ -38,18 +47,292 @@ pub fn player (ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
 		})
 		.with(HungerClock {
 			state: HungerState::WellFed,
-			duration: 20,
+			duration: well_fed,
+		})
+		.with(ThirstClock {
+			state: ThirstState::Quenched,
+			duration: quenched,
 		})
 		.marked::<SimpleMarker<SerializeMe>>()
-		.build()
+		.build();
+
+	let skin = waterskin(ecs, player_x, player_y);
+	ecs.write_storage::<Position>().remove(skin);
+	ecs.write_storage::<InBackpack>().insert(skin, InBackpack { owner: player })
+		.or_warn("Couldn't pack the waterskin");
+
+	player
 }
 
 // Mobs
 // =========================================================================
 
 // Monsters
-fn orc (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, 'o', "Ork") }
-fn goblin (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, 'g', "Goblin") }
+fn orc (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let orc = monster(ecs, x, y, 'o', "Ork", FactionKind::Orcs);
+	describe(ecs, orc, "A hulking brute with a notched blade and no patience.");
+	quips(ecs, orc, &["grunts at you", "bellows a war cry", "bangs its weapon on the floor"]);
+	opens_doors(ecs, orc);
+	orc
+}
+fn wolf (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let wolf = monster(ecs, x, y, 'w', "Wolf", FactionKind::Predators);
+	describe(ecs, wolf, "Lean and hungry. Wolves hunt in packs, and hang back until the pack is together.");
+	quips(ecs, wolf, &["growls", "howls", "bares its teeth"]);
+	wolf
+}
+fn giant_rat (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let rat = monster(ecs, x, y, 'r', "Giant Rat", FactionKind::Vermin);
+	describe(ecs, rat, "A rat the size of a dog. Where there's one there are dozens.");
+	toughen(ecs, rat, 6, 0, 3);
+	quips(ecs, rat, &["squeaks", "gnaws at nothing in particular"]);
+	rat
+}
+fn giant_ant (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let ant = monster(ecs, x, y, 'a', "Giant Ant", FactionKind::Vermin);
+	describe(ecs, ant, "A giant ant, armoured and relentless, part of a much bigger colony.");
+	toughen(ecs, ant, 5, 1, 3);
+	ant
+}
+fn bat (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let bat = monster(ecs, x, y, 'b', "Bat", FactionKind::Predators);
+	describe(ecs, bat, "It flits about in the dark, seeing as well without light as with it.");
+	toughen(ecs, bat, 6, 0, 3);
+	perceive(ecs, bat, 10, true, 0);
+	quips(ecs, bat, &["screeches", "flits about overhead"]);
+	bat
+}
+fn zombie (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let zombie = monster(ecs, x, y, 'z', "Zombie", FactionKind::Undead);
+	describe(ecs, zombie, "Barely sees a thing, but it can smell you from a long way off.");
+	toughen(ecs, zombie, 20, 1, 5);
+	perceive(ecs, zombie, 3, false, 10);
+	quips(ecs, zombie, &["groans", "sniffs hungrily", "shambles towards the smell of you"]);
+	zombie
+}
+fn goblin (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let goblin = monster(ecs, x, y, 'g', "Goblin", FactionKind::Goblins);
+	describe(ecs, goblin, "Small, vicious, and quick to run when things go badly.");
+	ecs.write_storage::<Morale>().insert(goblin, Morale {
+		flee_below: 25,
+		rally_above: 50,
+		fleeing: false,
+	}).or_warn("Goblin has no spine");
+	quips(ecs, goblin, &["sneers at you", "cackles", "shouts \"Shinies!\""]);
+	opens_doors(ecs, goblin);
+	goblin
+}
+fn troll (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let troll = monster(ecs, x, y, 'T', "Troll", FactionKind::Orcs);
+	describe(ecs, troll, "A great lump of a thing whose wounds close almost as fast as you make them.");
+	toughen(ecs, troll, 30, 2, 7);
+	quips(ecs, troll, &["roars", "sniffs the air", "scratches a festering wound"]);
+	opens_doors(ecs, troll);
+	ability(ecs, troll, "Regeneration", 8)
+		.with(ProvidesHealing { heal_amount: 12 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+	troll
+}
+fn imp (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let imp = monster(ecs, x, y, 'i', "Imp", FactionKind::Goblins);
+	describe(ecs, imp, "A giggling little devil that blinks away the moment it's in danger.");
+	toughen(ecs, imp, 10, 1, 4);
+	quips(ecs, imp, &["giggles", "vanishes and reappears", "pulls a face"]);
+	ability(ecs, imp, "Blink", 5)
+		.with(Blink { range: 8 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+	imp
+}
+fn giant_spider (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let spider = monster(ecs, x, y, 's', "Giant Spider", FactionKind::Predators);
+	describe(ecs, spider, "It spits sticky webs to pin its prey in place.");
+	toughen(ecs, spider, 18, 1, 5);
+	ability(ecs, spider, "Web", 8)
+		.with(Ranged { range: 6 })
+		.with(InflictsStatus { kind: StatusEffectKind::Webbed, duration: 3 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+	spider
+}
+fn dragon_whelp (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let whelp = monster(ecs, x, y, 'D', "Dragon Whelp", FactionKind::Predators);
+	describe(ecs, whelp, "Young for a dragon, which still makes it very dangerous. Mind its breath.");
+	toughen(ecs, whelp, 40, 3, 8);
+	quips(ecs, whelp, &["snorts a puff of smoke", "beats its wings"]);
+	ability(ecs, whelp, "Fire Breath", 10)
+		.with(Ranged { range: 6 })
+		.with(InflictsDamage { damage: 10 })
+		.with(AreaOfEffect { radius: 2 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+	whelp
+}
+fn goblin_warlord (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let warlord = monster(ecs, x, y, 'G', "Goblin Warlord", FactionKind::Goblins);
+	describe(ecs, warlord, "The goblins' chieftain. It will call for help, and only gets worse as it's worn down.");
+	toughen(ecs, warlord, 60, 3, 8);
+	quips(ecs, warlord, &["barks orders", "hefts a crude banner", "shouts \"Get 'em!\""]);
+	opens_doors(ecs, warlord);
+	ecs.write_storage::<BossPhases>().insert(warlord, BossPhases {
+		phases: vec![
+			BossPhase {
+				below: 66,
+				shout: "calls for help!".to_string(),
+				summons: vec!["Goblin".to_string(), "Goblin".to_string()],
+				enrage: 0,
+				haste: false,
+			},
+			BossPhase {
+				below: 33,
+				shout: "flies into a rage!".to_string(),
+				summons: Vec::new(),
+				enrage: 3,
+				haste: true,
+			},
+		],
+		current: 0,
+	}).or_warn("Warlord has no plan");
+	ability(ecs, warlord, "Hurl Spear", 4)
+		.with(Ranged { range: 6 })
+		.with(InflictsDamage { damage: 6 })
+		.with(PhaseAbility { phase: 1 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+	warlord
+}
+/// An orc that walks the given round of waypoints until it spots something
+pub fn sentry (ecs: &mut World, waypoints: Vec<(i32, i32)>) {
+	let (x, y) = waypoints[0];
+	let sentry = orc(ecs, x, y);
+	ecs.write_storage::<Name>().insert(sentry, Name { name: "Orc Sentry".to_string() })
+		.or_warn("Sentry forgot its name");
+	describe(ecs, sentry, "An orc walking its rounds, keeping half an eye out.");
+	ecs.write_storage::<Awareness>().insert(sentry, Awareness { state: AwarenessState::Unaware })
+		.or_warn("Sentry asleep on duty");
+	ecs.write_storage::<Patrol>().insert(sentry, Patrol { waypoints, next: 0 })
+		.or_warn("Sentry lost its orders");
+}
+
+/// Overrides the monster's default combat stats
+fn toughen (ecs: &mut World, entity: Entity, hp: i32, defence: i32, power: i32) {
+	ecs.write_storage::<CombatStats>().insert(entity, CombatStats {
+		max_hp: hp,
+		hp,
+		defence,
+		power,
+	}).or_warn("Monster is a pushover");
+}
+
+/// Overrides how far the monster can see and smell, widening its view to
+/// match
+fn perceive (ecs: &mut World, entity: Entity, vision: i32, dark_vision: bool, smell: i32) {
+	ecs.write_storage::<Perception>().insert(entity, Perception {
+		vision,
+		dark_vision,
+		smell,
+	}).or_warn("Monster lost its senses");
+
+	if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(entity) {
+		viewshed.range = i32::max(viewshed.range, vision);
+	}
+}
+
+/// Gives the monster a special ability, to be built with whatever effect
+/// components make it do something
+fn ability<'a> (ecs: &'a mut World, owner: Entity, name: &str, cooldown: i32) -> EntityBuilder<'a> {
+	ecs.create_entity()
+		.with(Name { name: name.to_string() })
+		.with(Ability { owner, cooldown, cooldown_left: 0 })
+}
+
+/// Gives the entity a line or two for the examine mode
+fn describe (ecs: &mut World, entity: Entity, text: &str) {
+	ecs.write_storage::<Description>().insert(entity, Description { text: text.to_string() })
+		.or_warn("Indescribable");
+}
+
+/// Gives the entity some flavour lines to come out with
+fn quips (ecs: &mut World, entity: Entity, lines: &[&str]) {
+	ecs.write_storage::<Quips>().insert(entity, Quips {
+		available: lines.iter().map(|l| l.to_string()).collect(),
+	}).or_warn("Cat got its tongue");
+}
+
+/// Lets the entity open doors, rather than having to path around them
+fn opens_doors (ecs: &mut World, entity: Entity) {
+	ecs.write_storage::<CanOpenDoors>().insert(entity, CanOpenDoors {})
+		.or_warn("No opposable thumbs");
+}
+
+/// Spawns a pack of monsters around the given point, all sharing a pack ID
+fn pack (
+	ecs: &mut World,
+	x: i32, y: i32,
+	map: &Map,
+	size: i32,
+	member: fn(&mut World, i32, i32) -> Entity,
+) {
+	group(ecs, x, y, map, size, size - 1, member);
+}
+
+/// Spawns a swarm of vermin around the given point, which don't wait for
+/// each other before piling in
+fn swarm (
+	ecs: &mut World,
+	x: i32, y: i32,
+	map: &Map,
+	size: i32,
+	member: fn(&mut World, i32, i32) -> Entity,
+) {
+	for entity in group(ecs, x, y, map, size, 1, member) {
+		ecs.write_storage::<Swarm>().insert(entity, Swarm {})
+			.or_warn("Wandered off from the swarm");
+	}
+}
+
+/// Spawns up to `size` pack members on the free floor around the given
+/// point, the nearest first
+fn group (
+	ecs: &mut World,
+	x: i32, y: i32,
+	map: &Map,
+	size: i32,
+	min_engage: i32,
+	member: fn(&mut World, i32, i32) -> Entity,
+) -> Vec<Entity> {
+	let pack_id = ecs.write_resource::<RandomNumberGenerator>().range(1, i32::MAX);
+	let mut spawned : Vec<Entity> = Vec::new();
+
+	// Whatever's already been spawned hasn't been indexed yet, so it's
+	// looked up here to keep from spawning on top of it
+	let mut taken : Vec<(i32, i32)> = {
+		let positions = ecs.read_storage::<Position>();
+		let blockers = ecs.read_storage::<BlocksTile>();
+		(&positions, &blockers).join().map(|(pos, _)| (pos.x, pos.y)).collect()
+	};
+
+	let mut spot =
+		if map.tiles[map.xy_idx(x, y)] == TileType::Floor && !taken.contains(&(x, y)) { Some((x, y)) }
+		else { map.nearest_free_tile((x, y), &taken) };
+
+	while let Some((mx, my)) = spot {
+		if spawned.len() as i32 >= size { break; }
+
+		let entity = member(ecs, mx, my);
+		ecs.write_storage::<PackMember>().insert(entity, PackMember {
+			pack_id,
+			min_engage,
+		}).or_warn("Lone wolf");
+		spawned.push(entity);
+
+		taken.push((mx, my));
+		spot = map.nearest_free_tile((x, y), &taken);
+	}
+
+	spawned
+}
 
 /// Spawns monster entity
 fn monster<S : ToString> (
@@ -57,7 +340,16 @@ fn monster<S : ToString> (
 	x: i32, y: i32,
 	glyph: char,
 	name: S,
-) {
+	faction: FactionKind,
+) -> Entity {
+	// Sleepers stay put once they wake
+	let (behaviour, state) = match ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3) {
+		1 => (IdleBehaviour::Wander, AwarenessState::Unaware),
+		2 => (IdleBehaviour::Guard, AwarenessState::Unaware),
+		_ => (IdleBehaviour::Guard, AwarenessState::Asleep),
+	};
+	let sight = ecs.fetch::<Config>().sight.monster;
+
 	ecs
 		.create_entity()
 		.with(Position { x, y })
@@ -66,14 +358,16 @@ fn monster<S : ToString> (
 			fg: RGB::named(rltk::RED),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 1,
+			sprite: None,
 		})
 		.with(Viewshed {
 			visible_tiles: Vec::new(),
-			range: 8,
+			range: sight,
 			dirty: true,
 		})
 		.with(Monster {})
 		.with(Name { name: format!("{}", name.to_string()) })
+		.with(Faction { kind: faction })
 		.with(BlocksTile {})
 		.with(CombatStats {
 			max_hp: 16,
@@ -81,8 +375,151 @@ fn monster<S : ToString> (
 			defence: 1,
 			power: 4,
 		})
+		.with(Idle { behaviour, home: (x, y) })
+		.with(Awareness { state })
+		.with(Perception { vision: 8, dark_vision: false, smell: 0 })
+		.with(Memory::default())
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build()
+}
+
+// Bystanders
+fn rat (ecs: &mut World, x: i32, y: i32) {
+	let rat = bystander(ecs, x, y, BystanderKind {
+		glyph: 'r', name: "Rat", fg: RGB::named(rltk::BURLYWOOD), hp: 4, faction: FactionKind::Prey,
+	});
+	describe(ecs, rat, "An ordinary rat, more scared of you than you are of it.");
+}
+fn deer (ecs: &mut World, x: i32, y: i32) {
+	let deer = bystander(ecs, x, y, BystanderKind {
+		glyph: 'd', name: "Deer", fg: RGB::named(rltk::SANDYBROWN), hp: 10, faction: FactionKind::Prey,
+	});
+	describe(ecs, deer, "Skittish, and gone at the first sign of trouble.");
+}
+fn villager (ecs: &mut World, x: i32, y: i32) {
+	let villager = bystander(ecs, x, y, BystanderKind {
+		glyph: 'p', name: "Villager", fg: RGB::named(rltk::LIGHTBLUE), hp: 12, faction: FactionKind::Townsfolk,
+	});
+	describe(ecs, villager, "Just trying to get on with their day.");
+	quips(ecs, villager, &["waves at you", "mutters about the weather", "asks if you've seen their cat"]);
+	opens_doors(ecs, villager);
+}
+
+/// What sets one sort of bystander apart from the next
+struct BystanderKind {
+	glyph   : char,
+	name    : &'static str,
+	fg      : RGB,
+	hp      : i32,
+	faction : FactionKind,
+}
+
+/// Spawns a harmless bystander entity that wanders about
+fn bystander (ecs: &mut World, x: i32, y: i32, kind: BystanderKind) -> Entity {
+	let BystanderKind { glyph, name, fg, hp, faction } = kind;
+	let sight = ecs.fetch::<Config>().sight.bystander;
+
+	ecs
+		.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437(glyph),
+			fg,
+			bg: RGB::named(rltk::BLACK),
+			render_order: 1,
+			sprite: None,
+		})
+		.with(Viewshed {
+			visible_tiles: Vec::new(),
+			range: sight,
+			dirty: true,
+		})
+		.with(Bystander {})
+		.with(Name { name: name.to_string() })
+		.with(Faction { kind: faction })
+		.with(BlocksTile {})
+		.with(CombatStats {
+			max_hp: hp,
+			hp,
+			defence: 0,
+			power: 0,
+		})
+		.with(Idle { behaviour: IdleBehaviour::Wander, home: (x, y) })
+		.with(Morale { flee_below: 100, rally_above: 101, fleeing: false })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build()
+}
+
+// Allies
+// =========================================================================
+
+/// The player's faithful hound, who starts every game at their side
+pub fn dog (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let sight = ecs.fetch::<Config>().sight.ally;
+
+	ecs
+		.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('d'),
+			fg: RGB::named(rltk::TAN),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 1,
+			sprite: None,
+		})
+		.with(Viewshed {
+			visible_tiles: Vec::new(),
+			range: sight,
+			dirty: true,
+		})
+		.with(Ally {})
+		.with(Name { name: "Dog".to_string() })
+		.with(Description { text: "Your faithful hound, who follows you everywhere.".to_string() })
+		.with(Faction { kind: FactionKind::Player })
+		.with(BlocksTile {})
+		.with(CombatStats {
+			max_hp: 12,
+			hp: 12,
+			defence: 0,
+			power: 3,
+		})
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build()
+}
+
+/// Stays chained where it is until the player bumps into it to free it
+fn prisoner (ecs: &mut World, x: i32, y: i32) {
+	let sight = ecs.fetch::<Config>().sight.ally;
+
+	let prisoner = ecs
+		.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('p'),
+			fg: RGB::named(rltk::LIGHT_BLUE),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 1,
+			sprite: None,
+		})
+		.with(Viewshed {
+			visible_tiles: Vec::new(),
+			range: sight,
+			dirty: true,
+		})
+		.with(Prisoner {})
+		.with(Name { name: "Prisoner".to_string() })
+		.with(Description { text: "Chained up and desperate. Freeing them might earn you a friend.".to_string() })
+		.with(BlocksTile {})
+		.with(CombatStats {
+			max_hp: 14,
+			hp: 14,
+			defence: 1,
+			power: 4,
+		})
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
+
+	opens_doors(ecs, prisoner);
 }
 
 // Items
@@ -96,15 +533,42 @@ fn health_potion (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::RED2),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Health Potion".to_string() })
+		.with(Description { text: "A little bottle of something red. Drinking it closes wounds, and so does having it smashed over you.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.5 })
 		.with(Consumable {})
+		.with(Fragile {})
 		.with(ProvidesHealing { heal_amount: 8 })
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 }
 
+/// Like a health potion, but good for a few sips
+fn healing_flask (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('!'),
+			fg: RGB::named(rltk::RED2),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Healing Flask".to_string() })
+		.with(Description { text: "A stoppered flask of the red stuff, weaker than a potion but with a few sips in it.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 1. })
+		.with(Consumable {})
+		.with(Charges { uses: 3, max_uses: 3 })
+		.with(Fragile {})
+		.with(ProvidesHealing { heal_amount: 4 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
 fn magic_missile_scroll (ecs: &mut World, x: i32, y: i32) {
 	ecs.create_entity()
 		.with(Position { x, y })
@@ -113,9 +577,12 @@ fn magic_missile_scroll (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Magic Missile Scroll".to_string() })
+		.with(Description { text: "Reading it aloud hurls a bolt of force at a single foe.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.1 })
 		.with(Consumable {})
 		.with(Ranged { range: 6 })
 		.with(InflictsDamage { damage: 8 })
@@ -131,9 +598,12 @@ fn fireball_scroll (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::ORANGE),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Fireball Scroll".to_string() })
+		.with(Description { text: "Reading it aloud sets off a ball of fire, burning everything caught in the blast.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.1 })
 		.with(Consumable {})
 		.with(Ranged { range: 6 })
 		.with(InflictsDamage { damage: 20 })
@@ -150,12 +620,36 @@ fn confusion_scroll (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::PINK),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Confusion Scroll".to_string() })
+		.with(Description { text: "Reading it aloud befuddles a foe, leaving it stumbling about for a while.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.1 })
 		.with(Consumable {})
 		.with(Ranged { range: 6 })
-		.with(Confusion { turns: 4 })
+		.with(InflictsStatus { kind: StatusEffectKind::Confused, duration: 4 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
+fn charm_scroll (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('~'),
+			fg: RGB::named(rltk::HOTPINK),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Charm Scroll".to_string() })
+		.with(Description { text: "Reading it aloud wins a foe over to your side, until the charm wears off.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 0.1 })
+		.with(Consumable {})
+		.with(Ranged { range: 6 })
+		.with(InflictsStatus { kind: StatusEffectKind::Charmed, duration: 15 })
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 }
@@ -168,15 +662,38 @@ fn magic_mapping_scroll (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN3),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Scroll of Mapping".to_string() })
+		.with(Description { text: "Reading it reveals the layout of the whole level.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.1 })
 		.with(Consumable {})
 		.with(MagicMapper {})
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 }
 
+/// What the player came down here for
+pub fn goal_artifact (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('♦'),
+			fg: RGB::named(rltk::MAGENTA),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Heart of the Forest".to_string() })
+		.with(Description { text: "An ancient, faintly warm gem. Taking it will wake the forest from its gloom.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 2. })
+		.with(GoalArtifact {})
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
 fn rations (ecs: &mut World, x: i32, y: i32) {
 	ecs.create_entity()
 		.with(Position { x, y })
@@ -185,15 +702,61 @@ fn rations (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::LIME_GREEN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Rations".to_string() })
+		.with(Description { text: "Dry, tasteless, and filling.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 0.5 })
+		.with(ProvidesFood {})
+		.with(Consumable {})
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
+/// Eaten like rations, or thrown to an animal to try and tame it
+fn raw_meat (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('%'),
+			fg: RGB::named(rltk::INDIANRED),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Raw Meat".to_string() })
+		.with(Description { text: "Not much use to you, but thrown to an animal it might make a friend.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 0.5 })
 		.with(ProvidesFood {})
 		.with(Consumable {})
+		.with(Ranged { range: 4 })
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 }
 
+/// Drunk from a sip at a time, and filled back up at any water
+fn waterskin (ecs: &mut World, x: i32, y: i32) -> Entity {
+	let sips = ecs.fetch::<Config>().thirst.waterskin_sips;
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('!'),
+			fg: RGB::named(rltk::TAN),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Waterskin".to_string() })
+		.with(Description { text: "A leather bag that sloshes. Fill it up wherever there's water.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 1. })
+		.with(Waterskin { sips, capacity: sips })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build()
+}
+
 // Equippables
 // =========================================================================
 
@@ -205,9 +768,12 @@ fn dagger (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Dagger".to_string() })
+		.with(Description { text: "A short blade. Better than bare hands.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 1. })
 		.with(Equippable { slot: EquipmentSlot::Melee })
 		.with(MeleePowerBonus { power: 2 })
 		.marked::<SimpleMarker<SerializeMe>>()
@@ -222,10 +788,13 @@ fn shield (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Shield".to_string() })
+		.with(Description { text: "A battered wooden shield.".to_string() })
 		.with(Item {})
-		.with(Equippable { slot: EquipmentSlot::Shield })
+		.with(Weight { kg: 5. })
+		.with(Equippable { slot: EquipmentSlot::OffHand })
 		.with(DefenseBonus { defense: 1 })
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
@@ -239,9 +808,12 @@ fn longsword (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Longsword".to_string() })
+		.with(Description { text: "A long, well balanced blade.".to_string() })
 		.with(Item {})
+		.with(Weight { kg: 3. })
 		.with(Equippable { slot: EquipmentSlot::Melee })
 		.with(MeleePowerBonus { power: 4 })
 		.marked::<SimpleMarker<SerializeMe>>()
@@ -256,15 +828,45 @@ fn tower_shield (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::CYAN),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Tower Shield".to_string() })
+		.with(Description { text: "A great slab of a shield, heavy and hard to see round.".to_string() })
 		.with(Item {})
-		.with(Equippable { slot: EquipmentSlot::Shield })
+		.with(Weight { kg: 12. })
+		.with(Equippable { slot: EquipmentSlot::OffHand })
 		.with(DefenseBonus { defense: 3 })
 		.marked::<SimpleMarker<SerializeMe>>()
 		.build();
 }
 
+/// Held in the off hand, and all that stands between the player and the dark
+/// further down
+fn torch (ecs: &mut World, x: i32, y: i32) {
+	let (radius, fuel) = {
+		let config = ecs.fetch::<Config>();
+		(config.light.torch_radius, config.light.torch_fuel)
+	};
+
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('/'),
+			fg: RGB::named(rltk::ORANGE),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Torch".to_string() })
+		.with(Description { text: "A stick with pitch-soaked rags wrapped round the end. It won't burn forever.".to_string() })
+		.with(Item {})
+		.with(Weight { kg: 1. })
+		.with(Equippable { slot: EquipmentSlot::OffHand })
+		.with(LightSource { radius, fuel })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
 // Traps
 // =========================================================================
 
@@ -276,8 +878,10 @@ fn bear_trap (ecs: &mut World, x: i32, y: i32) {
 			fg: RGB::named(rltk::RED),
 			bg: RGB::named(rltk::BLACK),
 			render_order: 2,
+			sprite: None,
 		})
 		.with(Name { name: "Bear Trap".to_string() })
+		.with(Description { text: "Iron jaws waiting to snap shut on an unwary foot.".to_string() })
 		.with(Hidden {})
 		.with(EntityTrigger {})
 		.with(SingleActivation {})
@@ -286,6 +890,167 @@ fn bear_trap (ecs: &mut World, x: i32, y: i32) {
 		.build();
 }
 
+fn shrieking_trap (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('^'),
+			fg: RGB::named(rltk::YELLOW),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Shrieking Trap".to_string() })
+		.with(Description { text: "A tripwire strung to something that will wake the whole dungeon.".to_string() })
+		.with(Hidden {})
+		.with(EntityTrigger {})
+		.with(SingleActivation {})
+		.with(EmitsNoise { radius: 20 })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
+fn hex_rune (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('^'),
+			fg: RGB::named(rltk::MAGENTA),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Hex Rune".to_string() })
+		.with(Description { text: "A sigil scratched into the floor, still faintly warm.".to_string() })
+		.with(Hidden {})
+		.with(EntityTrigger {})
+		.with(SingleActivation {})
+		.with(ScriptedEffect { script: "hex_rune".to_string() })
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
+// Doors
+// =========================================================================
+
+fn door (ecs: &mut World, x: i32, y: i32) {
+	ecs.create_entity()
+		.with(Position { x, y })
+		.with(Renderable {
+			glyph: rltk::to_cp437('+'),
+			fg: RGB::named(rltk::CHOCOLATE),
+			bg: RGB::named(rltk::BLACK),
+			render_order: 2,
+			sprite: None,
+		})
+		.with(Name { name: "Door".to_string() })
+		.with(Description { text: "A heavy wooden door.".to_string() })
+		.with(Door { open: false })
+		.with(BlocksTile {})
+		.marked::<SimpleMarker<SerializeMe>>()
+		.build();
+}
+
+/// A summoned add, and where it's to turn up
+type SummonSpot = (fn(&mut World, i32, i32) -> Entity, i32, i32);
+
+/// Brings in the adds bosses have called for, as close by them as there's
+/// room, already after the player
+pub fn spawn_summons (ecs: &mut World) {
+	let requests = ecs.write_resource::<SummonBuilder>().take();
+	if requests.is_empty() { return; }
+
+	let mut spots : Vec<SummonSpot> = Vec::new();
+
+	{
+		let map = ecs.fetch::<Map>();
+		let spatial = ecs.fetch::<SpatialIndex>();
+		let mut taken : Vec<(i32, i32)> = Vec::new();
+
+		for (name, x, y) in requests {
+			let member : fn(&mut World, i32, i32) -> Entity = match name.as_ref() {
+				"Goblin" => goblin,
+				"Orc" => orc,
+				"Wolf" => wolf,
+				"Giant Rat" => giant_rat,
+				_ => continue,
+			};
+
+			// Skip past anything already standing there
+			let mut spot = map.nearest_free_tile((x, y), &taken);
+			while let Some((sx, sy)) = spot {
+				taken.push((sx, sy));
+				if !spatial.is_blocked(map.xy_idx(sx, sy)) { break; }
+				spot = map.nearest_free_tile((x, y), &taken);
+			}
+
+			if let Some((sx, sy)) = spot {
+				spots.push((member, sx, sy));
+			}
+		}
+	}
+
+	for (member, x, y) in spots {
+		let entity = member(ecs, x, y);
+		ecs.write_storage::<Awareness>().insert(entity, Awareness { state: AwarenessState::Hunting })
+			.or_warn("Reinforcements dozed off");
+	}
+}
+
+/// Sometimes posts a sentry walking a round between a few spots spread about
+/// the level, well away from where the player starts. Spots something's
+/// already stood on are passed over, so the sentry never starts on top of it.
+pub fn spawn_patrol (ecs: &mut World, map: &Map, start: &Position) {
+	let taken : Vec<(i32, i32)> = {
+		let positions = ecs.read_storage::<Position>();
+		let blockers = ecs.read_storage::<BlocksTile>();
+		(&positions, &blockers).join().map(|(pos, _)| (pos.x, pos.y)).collect()
+	};
+
+	let waypoints = {
+		let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+		if rng.roll_dice(1, 2) != 1 { return; }
+
+		let apart = |a: (i32, i32), b: (i32, i32)| (a.0 - b.0).abs().max((a.1 - b.1).abs()) >= 10;
+		let mut waypoints : Vec<(i32, i32)> = Vec::new();
+		for _try in 0 .. 50 {
+			if waypoints.len() >= 4 { break; }
+
+			let spot = (rng.range(1, map.width - 1), rng.range(1, map.height - 1));
+			if map.tiles[map.xy_idx(spot.0, spot.1)] != TileType::Floor || taken.contains(&spot) { continue; }
+			if !apart(spot, (start.x, start.y)) || waypoints.iter().any(|w| !apart(spot, *w)) { continue; }
+
+			waypoints.push(spot);
+		}
+		waypoints
+	};
+
+	if waypoints.len() >= 2 { sentry(ecs, waypoints); }
+}
+
+/// Puts doors in the gaps where corridors break through the walls of a room
+pub fn spawn_doors (ecs: &mut World, room: &Rect, map: &Map) {
+	let mut gaps : Vec<(i32, i32)> = Vec::new();
+	for x in room.x1 + 1 ..= room.x2 {
+		gaps.push((x, room.y1));
+		gaps.push((x, room.y2 + 1));
+	}
+	for y in room.y1 + 1 ..= room.y2 {
+		gaps.push((room.x1, y));
+		gaps.push((room.x2 + 1, y));
+	}
+
+	for (x, y) in gaps {
+		if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { continue; }
+		if map.tiles[map.xy_idx(x, y)] != TileType::Floor { continue; }
+
+		// Only proper doorways, with wall on either side
+		let across = map.is_void_or_wall(x - 1, y) && map.is_void_or_wall(x + 1, y);
+		let down = map.is_void_or_wall(x, y - 1) && map.is_void_or_wall(x, y + 1);
+		if across || down { door(ecs, x, y); }
+	}
+}
+
 // Rooms
 // =========================================================================
 
@@ -293,17 +1058,48 @@ fn room_table (map_depth: i32) -> RandomTable {
 	RandomTable::new()
 		.add("Goblin", 10)
 		.add("Orc", 1 + map_depth)
+		.add("Wolf Pack", map_depth)
+		.add("Goblin Squad", map_depth - 1)
+		.add("Bat", 1 + map_depth / 2)
+		.add("Zombie", map_depth - 1)
+		.add("Rat Swarm", 2 + map_depth / 2)
+		.add("Ant Swarm", map_depth - 2)
+		.add("Imp", map_depth - 1)
+		.add("Giant Spider", map_depth - 1)
+		.add("Troll", map_depth - 2)
+		.add("Dragon Whelp", map_depth - 4)
+		.add("Goblin Warlord", map_depth - 5)
+		.add("Rat", 3)
+		.add("Deer", 2)
+		.add("Villager", 2)
+		.add("Prisoner", map_depth - 1)
 		.add("Health Potion", 7)
+		.add("Healing Flask", 2 + map_depth / 2)
 		.add("Fireball Scroll", 2 + map_depth)
 		.add("Confusion Scroll", 2 + map_depth)
+		.add("Charm Scroll", map_depth)
 		.add("Magic Missile Scroll", 4)
 		.add("Dagger", 3)
 		.add("Shield", 3)
 		.add("Long Sword", map_depth - 1)
 		.add("Tower Shield", map_depth - 1)
+		.add("Torch", 2 + map_depth)
 		.add("Rations", 10)
+		.add("Raw Meat", 3)
+		.add("Waterskin", 3)
 		.add("Magic Mapping Scroll", 2)
 		.add("Bear Trap", 2)
+		.add("Shrieking Trap", 1 + map_depth / 2)
+		.add("Hex Rune", map_depth - 2)
+}
+
+/// Spawns whatever goes by `name` at the given spot, as the spawn tables
+/// would, for tests to set up what they need
+#[cfg(test)]
+pub fn spawn_named (ecs: &mut World, name: &str, x: i32, y: i32) {
+	let map = (*ecs.fetch::<Map>()).clone();
+	let idx = map.xy_idx(x, y);
+	spawn_entity(ecs, &(&idx, &name.to_string()), &map);
 }
 
 /// Spawns a named entity at the given map IDx
@@ -313,19 +1109,41 @@ fn spawn_entity (ecs: &mut World, spawn: &(&usize, &String), map: &Map) {
 	let y = (*spawn.0 / map.width as usize) as i32;
 
 	match spawn.1.as_ref() {
-		"Goblin" => goblin(ecs, x, y),
-		"Orc" => orc(ecs, x, y),
+		"Goblin" => { goblin(ecs, x, y); }
+		"Orc" => { orc(ecs, x, y); }
+		"Wolf Pack" => pack(ecs, x, y, map, 3, wolf),
+		"Goblin Squad" => pack(ecs, x, y, map, 3, goblin),
+		"Bat" => { bat(ecs, x, y); }
+		"Zombie" => { zombie(ecs, x, y); }
+		"Rat Swarm" => swarm(ecs, x, y, map, 5, giant_rat),
+		"Ant Swarm" => swarm(ecs, x, y, map, 7, giant_ant),
+		"Imp" => { imp(ecs, x, y); }
+		"Giant Spider" => { giant_spider(ecs, x, y); }
+		"Troll" => { troll(ecs, x, y); }
+		"Dragon Whelp" => { dragon_whelp(ecs, x, y); }
+		"Goblin Warlord" => { goblin_warlord(ecs, x, y); }
+		"Rat" => rat(ecs, x, y),
+		"Deer" => deer(ecs, x, y),
+		"Villager" => villager(ecs, x, y),
+		"Prisoner" => prisoner(ecs, x, y),
 		"Health Potion" => health_potion(ecs, x, y),
+		"Healing Flask" => healing_flask(ecs, x, y),
 		"Fireball Scroll" => fireball_scroll(ecs, x, y),
 		"Confusion Scroll" => confusion_scroll(ecs, x, y),
+		"Charm Scroll" => charm_scroll(ecs, x, y),
 		"Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
 		"Dagger" => dagger(ecs, x, y),
 		"Shield" => shield(ecs, x, y),
 		"Long Sword" => longsword(ecs, x, y),
 		"Tower Shield" => tower_shield(ecs, x, y),
+		"Torch" => torch(ecs, x, y),
 		"Rations" => rations(ecs, x, y),
+		"Raw Meat" => raw_meat(ecs, x, y),
+		"Waterskin" => { waterskin(ecs, x, y); }
 		"Magic Mapping Scroll" => magic_mapping_scroll(ecs, x, y),
 		"Bear Trap" => bear_trap(ecs, x, y),
+		"Shrieking Trap" => shrieking_trap(ecs, x, y),
+		"Hex Rune" => hex_rune(ecs, x, y),
 		_ => {}
 	}
 }
@@ -336,10 +1154,11 @@ pub fn spawn_region (ecs: &mut World, area: &[usize], depth: i32, map: &Map) {
 	let mut areas : Vec<usize> = Vec::from(area);
 
 	{
+		let max_spawns = ecs.fetch::<Config>().spawns.max_per_area;
 		let mut rng = ecs.write_resource::<RandomNumberGenerator>();
 		let num_spawns = i32::min(
 			areas.len() as i32,
-			rng.roll_dice(1, MAX_SPAWNS_PER_AREA + 3) + (depth - 1) - 3,
+			rng.roll_dice(1, max_spawns + 3) + (depth - 1) - 3,
 		);
 
 		if num_spawns == 0 { return; }
@@ -362,14 +1181,11 @@ pub fn spawn_region (ecs: &mut World, area: &[usize], depth: i32, map: &Map) {
 pub fn spawn_room (ecs: &mut World, room: &Rect, map_depth: i32, map: &Map) {
 	let mut possible_targets : Vec<usize> = Vec::new();
 
-	{
-		let map = ecs.fetch::<Map>();
-		for y in room.y1 + 1 .. room.y2 {
-			for x in room.x1 + 1 .. room.x2 {
-				let idx = map.xy_idx(x, y);
-				if map.tiles[idx] == TileType::Floor {
-					possible_targets.push(idx);
-				}
+	for y in room.y1 + 1 .. room.y2 {
+		for x in room.x1 + 1 .. room.x2 {
+			let idx = map.xy_idx(x, y);
+			if map.tiles[idx] == TileType::Floor {
+				possible_targets.push(idx);
 			}
 		}
 	}
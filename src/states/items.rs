@@ -0,0 +1,183 @@
+use rltk::Rltk;
+use specs::prelude::*;
+use crate::{gui, AreaOfEffect, Fragile, Ranged, State, Transition, TurnState, UiState, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem, WantsToThrowItem, WantsToUseItem};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
+use crate::gui::{drop_item_menu, ItemAction, item_action_menu, ItemMenuResult, ListMenuResult, pickup_item_menu, ranged_target, show_inventory};
+use crate::states::GameStateHandler;
+
+// Inventory
+// =========================================================================
+
+pub struct ShowInventory {
+	pub selection : usize,
+}
+
+impl GameStateHandler for ShowInventory {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match show_inventory(gs, ctx, self.selection) {
+			ListMenuResult::Cancel => Transition::Pop,
+			ListMenuResult::NoResponse => Transition::Stay,
+			ListMenuResult::Select { selection } => Transition::Replace(UiState::ShowInventory { selection }),
+			ListMenuResult::Selected(item) => Transition::Push(UiState::ItemActions { item, selection: 0 }),
+		}
+	}
+}
+
+/// What to do with the item picked from the inventory
+pub struct ItemActions {
+	pub item      : Entity,
+	pub selection : usize,
+}
+
+impl GameStateHandler for ItemActions {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		let item = self.item;
+		match item_action_menu(gs, ctx, item, self.selection) {
+			ListMenuResult::Cancel => Transition::Pop,
+			ListMenuResult::NoResponse => Transition::Stay,
+			ListMenuResult::Select { selection } => Transition::Replace(UiState::ItemActions { item, selection }),
+			ListMenuResult::Selected(ItemAction::Use | ItemAction::Equip) => {
+				if let Some(ranged) = gs.ecs.read_storage::<Ranged>().get(item) {
+					return Transition::Push(UiState::ShowTargeting { range: ranged.range, item, throwing: false });
+				}
+
+				gs.ecs.write_storage::<WantsToUseItem>().insert(
+					*gs.ecs.fetch::<Entity>(),
+					WantsToUseItem { item, target: None },
+				).or_warn("Failed to insert drink intent");
+				Transition::Turn(TurnState::PlayerTurn)
+			},
+			ListMenuResult::Selected(ItemAction::Throw) => {
+				let range = gs.ecs.fetch::<Config>().throwing.range;
+				Transition::Push(UiState::ShowTargeting { range, item, throwing: true })
+			},
+			ListMenuResult::Selected(ItemAction::Drop) => {
+				gs.ecs.write_storage::<WantsToDropItem>().insert(
+					*gs.ecs.fetch::<Entity>(),
+					WantsToDropItem { items: vec![item] },
+				).or_warn("Failed to insert drop intent");
+				Transition::Turn(TurnState::PlayerTurn)
+			},
+			ListMenuResult::Selected(ItemAction::Examine) => Transition::Push(UiState::ExamineItem { item }),
+		}
+	}
+}
+
+pub struct ExamineItem {
+	pub item : Entity,
+}
+
+impl GameStateHandler for ExamineItem {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if gui::examine_item(gs, ctx, self.item) == ItemMenuResult::Cancel { Transition::Pop }
+		else { Transition::Stay }
+	}
+}
+
+pub struct ShowDropItem {
+	pub selection : usize,
+}
+
+impl GameStateHandler for ShowDropItem {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match drop_item_menu(gs, ctx, self.selection) {
+			ListMenuResult::Cancel => Transition::Pop,
+			ListMenuResult::NoResponse => Transition::Stay,
+			ListMenuResult::Select { selection } => Transition::Replace(UiState::ShowDropItem { selection }),
+			ListMenuResult::Selected(items) => {
+				gs.ecs.write_storage::<WantsToDropItem>().insert(
+					*gs.ecs.fetch::<Entity>(),
+					WantsToDropItem { items },
+				).or_warn("Failed to insert drop intent");
+				Transition::Turn(TurnState::PlayerTurn)
+			}
+		}
+	}
+}
+
+pub struct ShowPickupItem {
+	pub selection : usize,
+}
+
+impl GameStateHandler for ShowPickupItem {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match pickup_item_menu(gs, ctx, self.selection) {
+			ListMenuResult::Cancel => Transition::Pop,
+			ListMenuResult::NoResponse => Transition::Stay,
+			ListMenuResult::Select { selection } => Transition::Replace(UiState::ShowPickupItem { selection }),
+			ListMenuResult::Selected(items) => {
+				let player_entity = *gs.ecs.fetch::<Entity>();
+				gs.ecs.write_storage::<WantsToPickupItem>().insert(
+					player_entity,
+					WantsToPickupItem { collected_by: player_entity, items },
+				).or_warn("Failed to add want pickup to player");
+				Transition::Turn(TurnState::PlayerTurn)
+			}
+		}
+	}
+}
+
+pub struct ShowRemoveItem {
+	pub selection : usize,
+}
+
+impl GameStateHandler for ShowRemoveItem {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::remove_item_menu(gs, ctx, self.selection) {
+			ListMenuResult::Cancel => Transition::Pop,
+			ListMenuResult::NoResponse => Transition::Stay,
+			ListMenuResult::Select { selection } => Transition::Replace(UiState::ShowRemoveItem { selection }),
+			ListMenuResult::Selected(item) => {
+				gs.ecs.write_storage::<WantsToRemoveItem>().insert(
+					*gs.ecs.fetch::<Entity>(),
+					WantsToRemoveItem { item },
+				).or_warn("Failed to unequip item");
+				Transition::Turn(TurnState::PlayerTurn)
+			}
+		}
+	}
+}
+
+// Targeting
+// =========================================================================
+
+/// Picking where to aim something ranged, or where to throw it
+pub struct ShowTargeting {
+	pub range    : i32,
+	pub item     : Entity,
+	pub throwing : bool,
+}
+
+impl GameStateHandler for ShowTargeting {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		let radius =
+			if !self.throwing { gs.ecs.read_storage::<AreaOfEffect>().get(self.item).map(|aoe| aoe.radius) }
+			else if gs.ecs.read_storage::<Fragile>().get(self.item).is_some() { Some(gs.ecs.fetch::<Config>().throwing.splash_radius) }
+			else { None };
+		let target = ranged_target(gs, ctx, self.range, radius);
+		match target.0 {
+			// Back to the game rather than the menus the item was picked from
+			ItemMenuResult::Cancel => Transition::Turn(TurnState::AwaitingInput),
+			ItemMenuResult::NoResponse => Transition::Stay,
+			ItemMenuResult::Selected => {
+				let player_entity = *gs.ecs.fetch::<Entity>();
+				match (self.throwing, target.1) {
+					(true, Some(target)) => {
+						gs.ecs.write_storage::<WantsToThrowItem>().insert(
+							player_entity,
+							WantsToThrowItem { item: self.item, target },
+						).or_warn("Failed to insert throw intent");
+					}
+					_ => {
+						gs.ecs.write_storage::<WantsToUseItem>().insert(
+							player_entity,
+							WantsToUseItem { item: self.item, target: target.1 },
+						).or_warn("Failed to insert use intent");
+					}
+				}
+				Transition::Turn(TurnState::PlayerTurn)
+			}
+		}
+	}
+}
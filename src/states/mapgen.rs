@@ -0,0 +1,116 @@
+use rltk::{ColorPair, Point, RGB, Rltk, VirtualKeyCode};
+use specs::prelude::*;
+use crate::{display, Position, Renderable, State, Transition, TurnState};
+use crate::display::{ActiveDisplay, FrameBatch, Viewport};
+use crate::map::{draw_map, Map, MAP_HEIGHT, MAP_WIDTH};
+use crate::palette::Palette;
+use crate::states::GameStateHandler;
+
+// Map Generation
+// =========================================================================
+
+/// Plays back each step of building the level, then shows what's on it
+pub struct MapGeneration;
+
+impl GameStateHandler for MapGeneration {
+	fn full_screen (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		let sprites = gs.ecs.fetch::<ActiveDisplay>().sprites;
+		let palette = *gs.ecs.fetch::<Palette>();
+		let mut draw = gs.ecs.write_resource::<FrameBatch>();
+
+		let map = &gs.mapgen_history[gs.mapgen_index];
+		draw_map(map, &Viewport::new(map, ctx, Point::new(map.width / 2, map.height / 2)), &palette, sprites, &mut draw, ctx);
+
+		if gs.mapgen_running {
+			gs.mapgen_timer += ctx.frame_time_ms;
+			if gs.mapgen_timer > 200. {
+				gs.mapgen_timer = 0.;
+				gs.mapgen_index += 1;
+				if gs.mapgen_index >= gs.mapgen_history.len() {
+					gs.mapgen_index = gs.mapgen_history.len() - 1;
+					gs.mapgen_running = false;
+				}
+			}
+
+			let map = gs.ecs.fetch::<Map>();
+			let msg = format!(
+				" Generating {} Map{}",
+				gs.mapgen_name,
+				match gs.mapgen_index % 4 {
+					0 => ".   ",
+					1 => "..  ",
+					2 => "... ",
+					_ => "    ",
+				},
+			);
+
+			draw.print_color_centered(
+				MAP_HEIGHT + 2,
+				msg,
+				ColorPair::new(RGB::named(rltk::CORAL), RGB::named(rltk::BLACK)),
+			);
+			draw.print_color_right(
+				Point::new(MAP_WIDTH / 2 - 16, MAP_HEIGHT + 4),
+				format!("Depth {}", map.depth),
+				ColorPair::new(RGB::named(rltk::GREY50), RGB::named(rltk::BLACK)),
+			);
+			draw.bar_horizontal(
+				Point::new(MAP_WIDTH / 2 - 15, MAP_HEIGHT + 4),
+				30,
+				gs.mapgen_index,
+				gs.mapgen_history.len(),
+				ColorPair::new(RGB::named(rltk::GREY30), RGB::named(rltk::GRAY24)),
+			);
+			draw.print_color(
+				Point::new(MAP_WIDTH / 2 + 16, MAP_HEIGHT + 4),
+				format!("{: >3} / {: <3}", gs.mapgen_index, gs.mapgen_history.len()),
+				ColorPair::new(RGB::named(rltk::GREY50), RGB::named(rltk::BLACK)),
+			);
+		} else {
+			// Draw entities
+			{
+				let map = &gs.mapgen_history[gs.mapgen_index];
+				let viewport = Viewport::new(map, ctx, Point::new(map.width / 2, map.height / 2));
+				let positions = gs.ecs.read_storage::<Position>();
+				let renderables = gs.ecs.read_storage::<Renderable>();
+				let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
+				data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+				for (pos, render) in data.iter() {
+					if let Some((x, y)) = viewport.to_screen(pos.x, pos.y) {
+						display::draw_renderable(&mut draw, ctx, sprites, x, y, render, render.bg);
+					}
+				}
+			}
+
+			draw.print_color_centered(
+				MAP_HEIGHT + 2,
+				" Map Generated ",
+				ColorPair::new(RGB::named(rltk::SPRINGGREEN), RGB::named(rltk::BLACK)),
+			);
+			draw.print_color_right(
+				Point::new(MAP_WIDTH / 2 - 16, MAP_HEIGHT + 4),
+				format!("Depth {}", &gs.mapgen_history[0].depth),
+				ColorPair::new(RGB::named(rltk::GREY50), RGB::named(rltk::BLACK)),
+			);
+			draw.print_color_centered(
+				MAP_HEIGHT + 4,
+				"Press SPACE to regenerate",
+				ColorPair::new(RGB::named(rltk::GREY), RGB::named(rltk::BLACK)),
+			);
+			draw.print_color(
+				Point::new(MAP_WIDTH / 2 + 16, MAP_HEIGHT + 4),
+				format!("{0: >3} / {0: <3}", gs.mapgen_history.len()),
+				ColorPair::new(RGB::named(rltk::GREY50), RGB::named(rltk::BLACK)),
+			);
+			if ctx.key.unwrap_or(VirtualKeyCode::Key0) == VirtualKeyCode::Space {
+				drop(draw);
+				gs.game_over_cleanup();
+			}
+		}
+
+		// Starting over puts the turn back at the beginning, so keep it here
+		Transition::Turn(TurnState::MapGeneration)
+	}
+}
@@ -0,0 +1,162 @@
+use rltk::Rltk;
+use crate::{gui, saveload_system, State, Transition, TurnState, UiState};
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
+use crate::gui::{draw_main_menu, ItemMenuResult, MainMenuResult, MainMenuSelection, SeedEntryResult};
+use crate::saveload_system::SaveSlot;
+use crate::states::GameStateHandler;
+
+// Main Menu
+// =========================================================================
+
+pub struct MainMenu {
+	pub menu_selection : MainMenuSelection,
+}
+
+impl GameStateHandler for MainMenu {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match draw_main_menu(gs, ctx, self.menu_selection) {
+			MainMenuResult::NoSelection { selected } => Transition::Replace(UiState::MainMenu { menu_selection: selected }),
+			MainMenuResult::RestoreBackup => {
+				gs.load_error = saveload_system::restore_backup().err();
+				Transition::Replace(UiState::MainMenu { menu_selection: MainMenuSelection::LoadGame })
+			}
+			MainMenuResult::Selected { selected } => {
+				gs.menu_notice = None;
+				select(gs, selected)
+			}
+		}
+	}
+}
+
+/// Does whatever's been picked from the main menu
+fn select (gs: &mut State, selected: MainMenuSelection) -> Transition {
+	match selected {
+		MainMenuSelection::NewGame => {
+			gs.load_error = None;
+			gs.choose_ironman();
+			Transition::Turn(TurnState::PreRun)
+		},
+		MainMenuSelection::SeededGame => Transition::Push(UiState::EnterSeed { seed: None }),
+		MainMenuSelection::LoadGame => match saveload_system::load_game(&mut gs.ecs) {
+			Ok(slot) => {
+				gs.last_autosave = gs.ecs.fetch::<EventCounter>().turns;
+				gs.reset_systems();
+
+				// An ironman run is never without its save, so the one loaded
+				// stays until the autosave taking over from it is down
+				if gs.ecs.fetch::<EventCounter>().ironman {
+					let saved = saveload_system::save_game(&mut gs.ecs, SaveSlot::Auto);
+					let autosaved = saved.is_ok() && saveload_system::wait_for_writes();
+					saved.or_warn("The autosave didn't take");
+					if autosaved && slot != SaveSlot::Auto { saveload_system::delete_slot(slot); }
+				} else {
+					saveload_system::delete_slot(slot);
+				}
+				Transition::Turn(TurnState::AwaitingInput)
+			}
+			Err(error) => {
+				// Start over with a fresh world, keeping the save
+				gs.game_over_cleanup();
+				gs.load_error = Some(error);
+				Transition::Replace(UiState::MainMenu { menu_selection: MainMenuSelection::LoadGame })
+			}
+		},
+		MainMenuSelection::ExportSave => {
+			match saveload_system::export_save() {
+				Ok(notice) => gs.menu_notice = Some(notice),
+				Err(error) => gs.load_error = Some(error),
+			}
+			Transition::Stay
+		},
+		MainMenuSelection::ImportSave => match saveload_system::import_save() {
+			Ok(()) => {
+				gs.load_error = None;
+				gs.menu_notice = Some("Save imported".to_string());
+				Transition::Replace(UiState::MainMenu { menu_selection: MainMenuSelection::LoadGame })
+			}
+			Err(error) => {
+				gs.load_error = Some(error);
+				Transition::Stay
+			}
+		},
+		MainMenuSelection::Records => Transition::Push(UiState::ShowRecords),
+		MainMenuSelection::HighScores => Transition::Push(UiState::HighScores { highlight: None, after_run: false }),
+		MainMenuSelection::KeyBindings => Transition::Push(UiState::KeyBindings { selection: 0, rebinding: false }),
+		MainMenuSelection::Display => Transition::Push(UiState::DisplayOptions { selection: 0 }),
+		MainMenuSelection::Quit => {
+			saveload_system::wait_for_writes();
+			#[cfg(not(target_arch = "wasm32"))]
+			std::process::exit(0);
+			// The tab's closed rather than the game
+			#[cfg(target_arch = "wasm32")]
+			Transition::Stay
+		},
+	}
+}
+
+/// Typing in the seed for a seeded run
+pub struct EnterSeed {
+	pub seed : Option<u64>,
+}
+
+impl GameStateHandler for EnterSeed {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::enter_seed(gs, ctx, self.seed) {
+			SeedEntryResult::NoResponse => Transition::Stay,
+			SeedEntryResult::Edit { seed } => Transition::Replace(UiState::EnterSeed { seed }),
+			SeedEntryResult::Cancel => Transition::Pop,
+			SeedEntryResult::Start { seed } => {
+				gs.load_error = None;
+				gs.new_run(seed);
+				gs.choose_ironman();
+				Transition::Turn(TurnState::PreRun)
+			}
+		}
+	}
+}
+
+// Records
+// =========================================================================
+
+pub struct ShowRecords;
+
+impl GameStateHandler for ShowRecords {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if gui::show_records(gs, ctx) == ItemMenuResult::Cancel { Transition::Pop }
+		else { Transition::Stay }
+	}
+}
+
+/// The high score table, with the run that's just ended picked out if it
+/// made it on. After a run there's nothing under it, so the main menu's
+/// opened in its place.
+pub struct HighScores {
+	pub highlight : Option<usize>,
+	pub after_run : bool,
+}
+
+impl GameStateHandler for HighScores {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if gui::show_high_scores(gs, ctx, self.highlight) != ItemMenuResult::Cancel { return Transition::Stay; }
+
+		if self.after_run { Transition::Reset(UiState::MainMenu { menu_selection: MainMenuSelection::NewGame }) }
+		else { Transition::Pop }
+	}
+}
@@ -0,0 +1,77 @@
+use rltk::Rltk;
+use crate::{State, Transition, TurnState, UiState};
+
+mod turns;
+mod items;
+mod screens;
+mod menus;
+mod options;
+mod run_over;
+#[cfg(feature = "mapgen_visualiser")] mod mapgen;
+
+/// Looks after the game for as long as it's in one TurnState, or one
+/// UiState is on top: handling the frame's input and drawing whatever goes
+/// over the map
+pub trait GameStateHandler {
+	/// Whether it takes over the whole screen, with no map or interface drawn
+	/// underneath
+	fn full_screen (&self) -> bool { false }
+
+	/// Whether the playtime clock stops while it's up
+	fn stops_clock (&self) -> bool { false }
+
+	/// Runs the frame, saying what should happen next
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition;
+}
+
+impl TurnState {
+	/// What looks after the game while it's at this point in the turn
+	pub fn handler (self) -> Box<dyn GameStateHandler> {
+		match self {
+			TurnState::PreRun => Box::new(turns::PreRun),
+			TurnState::AwaitingInput => Box::new(turns::AwaitingInput),
+			TurnState::PlayerTurn => Box::new(turns::PlayerTurn),
+			TurnState::MonsterTurn => Box::new(turns::MonsterTurn),
+			TurnState::HastedTurn => Box::new(turns::HastedTurn),
+			TurnState::MagicMapReveal { row } => Box::new(turns::MagicMapReveal { row }),
+			TurnState::NextLevel => Box::new(turns::NextLevel),
+			TurnState::GeneratingLevel => Box::new(turns::GeneratingLevel),
+
+			TurnState::GameOver => Box::new(run_over::RunOver { victory: false }),
+			TurnState::Victory => Box::new(run_over::RunOver { victory: true }),
+
+			#[cfg(feature = "mapgen_visualiser")]
+			TurnState::MapGeneration => Box::new(mapgen::MapGeneration),
+		}
+	}
+}
+
+impl UiState {
+	/// What looks after the game while this is on top
+	pub fn handler (self) -> Box<dyn GameStateHandler> {
+		match self {
+			UiState::ShowInventory { selection } => Box::new(items::ShowInventory { selection }),
+			UiState::ItemActions { item, selection } => Box::new(items::ItemActions { item, selection }),
+			UiState::ExamineItem { item } => Box::new(items::ExamineItem { item }),
+			UiState::ShowDropItem { selection } => Box::new(items::ShowDropItem { selection }),
+			UiState::ShowPickupItem { selection } => Box::new(items::ShowPickupItem { selection }),
+			UiState::ShowRemoveItem { selection } => Box::new(items::ShowRemoveItem { selection }),
+			UiState::ShowTargeting { range, item, throwing } => Box::new(items::ShowTargeting { range, item, throwing }),
+
+			UiState::ShowLog { offset, filter } => Box::new(screens::ShowLog { offset, filter }),
+			UiState::ShowHelp => Box::new(screens::ShowHelp),
+			UiState::ShowMap => Box::new(screens::ShowMap),
+			UiState::Examine { x, y } => Box::new(screens::Examine { x, y }),
+
+			UiState::MainMenu { menu_selection } => Box::new(menus::MainMenu { menu_selection }),
+			UiState::EnterSeed { seed } => Box::new(menus::EnterSeed { seed }),
+			UiState::ShowRecords => Box::new(menus::ShowRecords),
+			UiState::HighScores { highlight, after_run } => Box::new(menus::HighScores { highlight, after_run }),
+
+			UiState::KeyBindings { selection, rebinding } => Box::new(options::KeyBindings { selection, rebinding }),
+			UiState::DisplayOptions { selection } => Box::new(options::DisplayOptions { selection }),
+			UiState::Paused { selection, confirming } => Box::new(options::Paused { selection, confirming }),
+			UiState::ConfirmQuickLoad => Box::new(options::ConfirmQuickLoad),
+		}
+	}
+}
@@ -0,0 +1,122 @@
+use rltk::Rltk;
+use specs::prelude::*;
+use crate::{gui, saveload_system, AutoWalk, State, Transition, TurnState, UiState};
+use crate::debug_log::OrWarn;
+use crate::event_counter::EventCounter;
+use crate::gamelog::GameLog;
+use crate::gui::{ConfirmResult, DisplayOptionsResult, KeyBindingsMenuResult, MainMenuSelection, PauseMenuResult, PauseSelection};
+use crate::saveload_system::SaveSlot;
+use crate::states::GameStateHandler;
+
+// Options
+// =========================================================================
+
+pub struct KeyBindings {
+	pub selection : usize,
+	pub rebinding : bool,
+}
+
+impl GameStateHandler for KeyBindings {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::key_bindings_menu(gs, ctx, self.selection, self.rebinding) {
+			KeyBindingsMenuResult::NoResponse => Transition::Stay,
+			KeyBindingsMenuResult::Close => Transition::Pop,
+			KeyBindingsMenuResult::Edit { selection, rebinding } => Transition::Replace(UiState::KeyBindings { selection, rebinding }),
+		}
+	}
+}
+
+/// The display options, from the main menu or the pause menu mid-run
+pub struct DisplayOptions {
+	pub selection : usize,
+}
+
+impl GameStateHandler for DisplayOptions {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::display_options_menu(gs, ctx, self.selection) {
+			DisplayOptionsResult::NoResponse => Transition::Stay,
+			DisplayOptionsResult::Close => Transition::Pop,
+			DisplayOptionsResult::Select { selection } => Transition::Replace(UiState::DisplayOptions { selection }),
+		}
+	}
+}
+
+// Pausing
+// =========================================================================
+
+pub struct Paused {
+	pub selection  : PauseSelection,
+	pub confirming : bool,
+}
+
+impl GameStateHandler for Paused {
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::pause_menu(gs, ctx, self.selection, self.confirming) {
+			PauseMenuResult::NoResponse => Transition::Stay,
+			PauseMenuResult::Resume => Transition::Pop,
+			PauseMenuResult::Select { selection, confirming } => Transition::Replace(UiState::Paused { selection, confirming }),
+			PauseMenuResult::Selected { selection } => match selection {
+				PauseSelection::Resume => Transition::Pop,
+				PauseSelection::Options => Transition::Push(UiState::DisplayOptions { selection: 0 }),
+				PauseSelection::Help => Transition::Push(UiState::ShowHelp),
+				PauseSelection::SaveAndQuit => {
+					saveload_system::save_game(&mut gs.ecs, SaveSlot::Manual).or_warn("The run couldn't be saved");
+					Transition::Reset(UiState::MainMenu { menu_selection: MainMenuSelection::LoadGame })
+				}
+				PauseSelection::Abandon => {
+					saveload_system::delete_save();
+					Transition::Turn(TurnState::GameOver)
+				}
+			},
+		}
+	}
+}
+
+/// Making sure before throwing away everything since the quick save
+pub struct ConfirmQuickLoad;
+
+impl GameStateHandler for ConfirmQuickLoad {
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::confirm_quick_load(gs, ctx) {
+			ConfirmResult::NoResponse => Transition::Stay,
+			ConfirmResult::No => Transition::Pop,
+			ConfirmResult::Yes => {
+				// A save that won't even read leaves the run as it was
+				let save = match saveload_system::read_slot(&gs.ecs, SaveSlot::Quick) {
+					Ok(save) => save,
+					Err(error) => {
+						gs.ecs.write_resource::<GameLog>().system(error);
+						return Transition::Pop;
+					}
+				};
+
+				match saveload_system::apply_save(&mut gs.ecs, save) {
+					Ok(()) => {
+						gs.last_autosave = gs.ecs.fetch::<EventCounter>().turns;
+						gs.reset_systems();
+						*gs.ecs.write_resource::<AutoWalk>() = AutoWalk::default();
+						gs.ecs.write_resource::<GameLog>().system("Quick loaded.".to_string());
+						Transition::Turn(TurnState::AwaitingInput)
+					}
+					Err(error) => {
+						gs.game_over_cleanup();
+						gs.load_error = Some(error);
+						Transition::Reset(UiState::MainMenu { menu_selection: MainMenuSelection::LoadGame })
+					}
+				}
+			},
+		}
+	}
+}
@@ -0,0 +1,42 @@
+use rltk::Rltk;
+use crate::{gui, LaunchOptions, State, Transition, UiState};
+use crate::event_counter::EventCounter;
+use crate::gui::GameOverResult;
+use crate::records::Records;
+use crate::states::GameStateHandler;
+
+// Run Over
+// =========================================================================
+
+/// The summary at the end of a run, won or lost. The run's added to the
+/// records the first time round, unless it was played as a wizard.
+pub struct RunOver {
+	pub victory : bool,
+}
+
+impl GameStateHandler for RunOver {
+	fn full_screen (&self) -> bool { true }
+
+	fn stops_clock (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if !gs.run_recorded {
+			if !gs.ecs.fetch::<LaunchOptions>().wizard {
+				gs.run_rank = Records::record_run(&gs.ecs.fetch::<EventCounter>(), self.victory);
+			}
+			gs.run_recorded = true;
+		}
+
+		let result =
+			if self.victory { gui::victory(gs, ctx) }
+			else { gui::game_over(gs, ctx) };
+		match result {
+			GameOverResult::NoSelection => Transition::Stay,
+			GameOverResult::QuitToMenu => {
+				let rank = gs.run_rank;
+				gs.game_over_cleanup();
+				Transition::Reset(UiState::HighScores { highlight: rank, after_run: true })
+			}
+		}
+	}
+}
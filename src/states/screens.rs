@@ -0,0 +1,65 @@
+use rltk::Rltk;
+use crate::{gui, State, Transition, UiState};
+use crate::gamelog::LogCategory;
+use crate::gui::{ExamineResult, ItemMenuResult, LogViewerResult};
+use crate::states::GameStateHandler;
+
+// Screens
+// =========================================================================
+
+/// The whole message log, scrolled back and filtered
+pub struct ShowLog {
+	pub offset : i32,
+	pub filter : Option<LogCategory>,
+}
+
+impl GameStateHandler for ShowLog {
+	fn full_screen (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::show_log(gs, ctx, self.offset, self.filter) {
+			LogViewerResult::NoResponse => Transition::Stay,
+			LogViewerResult::Close => Transition::Pop,
+			LogViewerResult::Scroll { offset, filter } => Transition::Replace(UiState::ShowLog { offset, filter }),
+		}
+	}
+}
+
+pub struct ShowHelp;
+
+impl GameStateHandler for ShowHelp {
+	fn full_screen (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if gui::show_help(gs, ctx) == ItemMenuResult::Cancel { Transition::Pop }
+		else { Transition::Stay }
+	}
+}
+
+/// The whole level at once, as much of it as has been seen
+pub struct ShowMap;
+
+impl GameStateHandler for ShowMap {
+	fn full_screen (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		if gui::map_overview(gs, ctx) == ItemMenuResult::Cancel { Transition::Pop }
+		else { Transition::Stay }
+	}
+}
+
+/// Looking around with a cursor
+pub struct Examine {
+	pub x : i32,
+	pub y : i32,
+}
+
+impl GameStateHandler for Examine {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gui::examine(gs, ctx, self.x, self.y) {
+			ExamineResult::NoResponse => Transition::Stay,
+			ExamineResult::Close => Transition::Pop,
+			ExamineResult::Move { x, y } => Transition::Replace(UiState::Examine { x, y }),
+		}
+	}
+}
@@ -0,0 +1,127 @@
+use rltk::Rltk;
+use specs::prelude::*;
+use crate::{gui, player_input, saveload_system, State, Transition, TurnState};
+use crate::ai::TurnSystem;
+use crate::event_counter::EventCounter;
+use crate::map::{Map, MAP_HEIGHT, MAP_WIDTH};
+use crate::states::GameStateHandler;
+
+// Turns
+// =========================================================================
+
+/// Settles the world before the player gets their first go on a level
+pub struct PreRun;
+
+impl GameStateHandler for PreRun {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		gs.run_systems();
+		Transition::Turn(TurnState::AwaitingInput)
+	}
+}
+
+pub struct AwaitingInput;
+
+impl GameStateHandler for AwaitingInput {
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		player_input(gs, ctx)
+	}
+}
+
+pub struct PlayerTurn;
+
+impl GameStateHandler for PlayerTurn {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		gs.ecs.write_resource::<EventCounter>().turns += 1;
+		gs.run_systems();
+
+		// The systems may have asked for something else to happen next
+		let next = *gs.ecs.fetch::<TurnState>();
+		Transition::Turn(match next {
+			TurnState::MagicMapReveal { .. } => TurnState::MagicMapReveal { row: 0 },
+			TurnState::Victory => {
+				saveload_system::delete_save();
+				TurnState::Victory
+			}
+			_ => TurnState::MonsterTurn,
+		})
+	}
+}
+
+pub struct MonsterTurn;
+
+impl GameStateHandler for MonsterTurn {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		gs.run_systems();
+		if TurnSystem::anyone_goes_twice(&gs.ecs) { return Transition::Turn(TurnState::HastedTurn); }
+
+		gs.autosave_if_due();
+		Transition::Turn(TurnState::AwaitingInput)
+	}
+}
+
+/// A second go for anything hasted, or for everyone while the player lags
+/// behind under their load
+pub struct HastedTurn;
+
+impl GameStateHandler for HastedTurn {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		gs.run_systems();
+		gs.autosave_if_due();
+		Transition::Turn(TurnState::AwaitingInput)
+	}
+}
+
+/// The map revealed a row a frame, after reading magic mapping
+pub struct MagicMapReveal {
+	pub row : i32,
+}
+
+impl GameStateHandler for MagicMapReveal {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		let mut map = gs.ecs.fetch_mut::<Map>();
+		for x in 0..MAP_WIDTH {
+			let idx = map.xy_idx(x as i32, self.row);
+			map.reveal(idx);
+		}
+
+		Transition::Turn(
+			if self.row as usize == MAP_HEIGHT - 1 { TurnState::MonsterTurn }
+			else { TurnState::MagicMapReveal { row: self.row + 1 } }
+		)
+	}
+}
+
+// Levels
+// =========================================================================
+
+pub struct NextLevel;
+
+impl GameStateHandler for NextLevel {
+	fn update (&mut self, gs: &mut State, _ctx: &mut Rltk) -> Transition {
+		gs.goto_next_level();
+		Transition::Turn(TurnState::GeneratingLevel)
+	}
+}
+
+/// Waits on the next level being built, then moves the player into it
+pub struct GeneratingLevel;
+
+impl GameStateHandler for GeneratingLevel {
+	fn full_screen (&self) -> bool { true }
+
+	fn update (&mut self, gs: &mut State, ctx: &mut Rltk) -> Transition {
+		match gs.level_build.take() {
+			None => Transition::Turn(TurnState::PreRun),
+			Some(build) if build.is_finished() => {
+				gs.arrive_on_next_level(build);
+				Transition::Turn(TurnState::PreRun)
+			}
+			Some(mut build) => {
+				build.elapsed_ms += ctx.frame_time_ms;
+				gui::generating_level(gs, ctx, build.depth, build.elapsed_ms);
+				gs.level_build = Some(build);
+				Transition::Stay
+			}
+		}
+	}
+}
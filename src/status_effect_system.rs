@@ -0,0 +1,66 @@
+use specs::prelude::*;
+use crate::{Name, Position, StatusEffectKind, StatusEffects, TurnState};
+use crate::gamelog::GameLog;
+use crate::map::Map;
+
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+	type SystemData = (
+		Entities<'a>,
+		WriteStorage<'a, StatusEffects>,
+		ReadExpect<'a, Entity>,
+		ReadExpect<'a, TurnState>,
+		WriteExpect<'a, GameLog>,
+		ReadStorage<'a, Name>,
+		ReadStorage<'a, Position>,
+		ReadExpect<'a, Map>,
+	);
+
+	fn run(&mut self, data: Self::SystemData) {
+		let (
+			entities, mut statuses, player_entity, turn_state, mut log,
+			names, positions, map,
+		) = data;
+
+		let mut cured : Vec<Entity> = Vec::new();
+
+		for (entity, statuses) in (&entities, &mut statuses).join() {
+			let is_player = entity == *player_entity;
+
+			// Statuses only tick down on their owner's turn
+			match *turn_state {
+				TurnState::PlayerTurn => { if !is_player { continue } }
+				TurnState::MonsterTurn => { if is_player { continue } }
+				_ => continue,
+			}
+
+			// Others are only mentioned where the player can see them
+			let seen = positions.get(entity)
+				.map_or(false, |pos| map.is_visible(map.xy_idx(pos.x, pos.y)));
+
+			for effect in statuses.effects.iter_mut() {
+				effect.duration -= 1;
+				if effect.duration > 0 { continue; }
+
+				if is_player {
+					log.combat(effect.kind.expired_message(None));
+				} else if let (true, Some(name)) = (seen, names.get(entity)) {
+					// The befriending has its own say when a charm wears off
+					if effect.kind != StatusEffectKind::Charmed {
+						log.combat(effect.kind.expired_message(Some(&name.name)));
+					}
+				}
+			}
+
+			statuses.effects.retain(|e| e.duration > 0);
+			if statuses.effects.is_empty() {
+				cured.push(entity);
+			}
+		}
+
+		for entity in cured.iter() {
+			statuses.remove(*entity);
+		}
+	}
+}
@@ -0,0 +1,298 @@
+use rltk::Point;
+use specs::prelude::*;
+use crate::{Awareness, AwarenessState, Burden, Equipped, HungerClock, LightSource, MyTurn, Position, SufferDamage, ThirstClock, ThirstState, WantsToFlee};
+use crate::ai::{FleeAI, TurnSystem};
+use crate::config::Config;
+use crate::damage_system::DamageSystem;
+use crate::input::PadPress;
+use crate::map::Map;
+use super::{Action, TestGame};
+
+/// Every test starts from the same dungeon
+const SEED : u64 = 1234;
+
+// Items
+// =========================================================================
+
+#[test]
+fn picks_up_whats_underfoot () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Health Potion", 0, 0);
+
+	game.act(&Action::PickUp);
+
+	assert!(game.inventory().contains(&"Health Potion".to_string()));
+	assert!(game.logged("Health Potion"));
+}
+
+#[test]
+fn drinking_a_potion_heals_and_uses_it_up () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.set_player_hp(5);
+	game.spawn("Health Potion", 0, 0);
+
+	game.play(&[Action::PickUp, Action::Use("Health Potion", None)]);
+
+	assert!(game.player_hp() > 5);
+	assert!(!game.inventory().contains(&"Health Potion".to_string()));
+	assert!(game.logged("You drink"));
+}
+
+#[test]
+fn a_pad_can_pick_up_and_drink_through_the_menus () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.set_player_hp(5);
+	game.spawn("Dagger", 0, 0);
+	game.spawn("Health Potion", 0, 0);
+
+	// Two things underfoot, so it asks which: mark both and take them
+	game.press(PadPress::South);
+	game.press(PadPress::Direction { dx: 1, dy: 0 });
+	game.press(PadPress::Direction { dx: 0, dy: 1 });
+	game.press(PadPress::Direction { dx: 1, dy: 0 });
+	game.press(PadPress::South);
+	assert!(game.inventory().contains(&"Dagger".to_string()));
+	assert!(game.inventory().contains(&"Health Potion".to_string()));
+
+	// Down the inventory to the potion, then Use, the first thing offered
+	let row = game.inventory().iter().position(|name| name == "Health Potion").unwrap();
+	game.press(PadPress::West);
+	for _ in 0..row { game.press(PadPress::Direction { dx: 0, dy: 1 }); }
+	game.press(PadPress::South);
+	game.press(PadPress::South);
+
+	assert!(game.player_hp() > 5);
+	assert!(!game.inventory().contains(&"Health Potion".to_string()));
+}
+
+#[test]
+fn a_flask_lasts_until_its_last_sip () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Healing Flask", 0, 0);
+
+	game.play(&[Action::PickUp, Action::Use("Healing Flask", None), Action::Use("Healing Flask", None)]);
+	assert!(game.inventory().contains(&"Healing Flask".to_string()));
+
+	game.act(&Action::Use("Healing Flask", None));
+	assert!(!game.inventory().contains(&"Healing Flask".to_string()));
+}
+
+#[test]
+fn a_sip_from_the_waterskin_quenches_thirst () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	let player = *game.gs.ecs.fetch::<Entity>();
+	game.gs.ecs.write_storage::<ThirstClock>().get_mut(player).expect("The player's never thirsty").state = ThirstState::Parched;
+
+	game.act(&Action::Use("Waterskin", None));
+
+	let state = game.gs.ecs.read_storage::<ThirstClock>().get(player).map(|tc| tc.state);
+	assert!(state == Some(ThirstState::Quenched));
+	assert!(game.inventory().contains(&"Waterskin".to_string()));
+	assert!(game.logged("sips left"));
+}
+
+#[test]
+fn picking_up_too_much_weighs_you_down () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Tower Shield", 0, 0);
+	game.spawn("Shield", 0, 0);
+	// Whether everyone else got a second go on any of the next two turns
+	let falls_behind = |game: &mut TestGame| (0 .. 2).any(|_| {
+		game.act(&Action::Wait);
+		TurnSystem::anyone_goes_twice(&game.gs.ecs)
+	});
+	assert!(!falls_behind(&mut game));
+
+	game.act(&Action::PickUp);
+
+	let player = *game.gs.ecs.fetch::<Entity>();
+	let overloaded = game.gs.ecs.read_storage::<Burden>().get(player).map_or(false, |b| b.overloaded());
+	assert!(overloaded);
+	assert!(game.logged("more than you can manage"));
+	assert!(falls_behind(&mut game));
+}
+
+#[test]
+fn a_thrown_dagger_hurts_and_can_be_picked_back_up () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Dagger", 0, 0);
+	game.spawn("Goblin", 2, 0);
+	let before = game.hp_of("Goblin").expect("The goblin never turned up");
+	let start = game.player_pos();
+
+	game.play(&[Action::PickUp, Action::Throw("Dagger", Point::new(start.x + 2, start.y))]);
+
+	assert!(game.hp_of("Goblin").map_or(true, |hp| hp < before));
+	assert!(!game.inventory().contains(&"Dagger".to_string()));
+	assert!(game.logged("You throw the Dagger"));
+
+	// Out of the way, so the walk over to it doesn't turn into a fight
+	game.clear_monsters();
+	let dagger = game.find("Dagger").expect("The dagger went missing");
+	let landed = game.gs.ecs.read_storage::<Position>().get(dagger).map(|pos| Point::new(pos.x, pos.y))
+		.expect("The dagger never came down");
+	for _ in 0 .. 4 {
+		let here = game.player_pos();
+		if here == landed { break; }
+		game.act(&Action::Move((landed.x - here.x).signum(), (landed.y - here.y).signum()));
+	}
+	game.act(&Action::PickUp);
+
+	assert!(game.inventory().contains(&"Dagger".to_string()));
+}
+
+#[test]
+fn a_held_torch_burns_down () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Torch", 0, 0);
+
+	game.play(&[Action::PickUp, Action::Use("Torch", None), Action::Wait, Action::Wait]);
+
+	let player = *game.gs.ecs.fetch::<Entity>();
+	let lights = game.gs.ecs.read_storage::<LightSource>();
+	let equipped = game.gs.ecs.read_storage::<Equipped>();
+	let fuel = (&lights, &equipped).join()
+		.find(|(_, held)| held.owner == player)
+		.map(|(light, _)| light.fuel);
+	let full = Config::default().light.torch_fuel;
+	assert!(fuel.map_or(false, |fuel| fuel < full));
+}
+
+// Combat
+// =========================================================================
+
+#[test]
+fn bumping_into_a_monster_attacks_it () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Goblin", 1, 0);
+	let start = game.player_pos();
+	let before = game.hp_of("Goblin").expect("The goblin never turned up");
+
+	game.act(&Action::Move(1, 0));
+
+	assert_eq!(game.player_pos(), start);
+	assert!(game.hp_of("Goblin").map_or(true, |hp| hp < before));
+}
+
+#[test]
+fn monsters_die_when_beaten () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Goblin", 1, 0);
+
+	for _ in 0 .. 20 {
+		if game.hp_of("Goblin").is_none() { break; }
+		game.act(&Action::Move(1, 0));
+	}
+
+	assert!(game.hp_of("Goblin").is_none());
+	assert!(game.logged("Goblin is dead"));
+}
+
+#[test]
+fn only_the_players_blows_set_a_monster_hunting () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Goblin", 3, 0);
+	let goblin = game.find("Goblin").expect("The goblin never turned up");
+	let hurt = |game: &mut TestGame, by_player: bool| {
+		game.gs.ecs.write_storage::<Awareness>()
+			.insert(goblin, Awareness { state: AwarenessState::Asleep })
+			.expect("The goblin won't nod off");
+		SufferDamage::new_damage(&mut game.gs.ecs.write_storage::<SufferDamage>(), goblin, 1, "Orc", by_player);
+		DamageSystem {}.run_now(&game.gs.ecs);
+		game.gs.ecs.read_storage::<Awareness>().get(goblin).map(|a| a.state)
+	};
+
+	assert_eq!(hurt(&mut game, false), Some(AwarenessState::Alert));
+	assert_eq!(hurt(&mut game, true), Some(AwarenessState::Hunting));
+}
+
+// AI
+// =========================================================================
+
+#[test]
+fn fleeing_the_same_danger_reuses_its_flee_map () {
+	let mut game = TestGame::new(SEED);
+	game.clear_monsters();
+	game.spawn("Goblin", 2, 0);
+	let goblin = game.find("Goblin").expect("The goblin never turned up");
+	let start = game.player_pos();
+	let danger = Point::new(start.x + 3, start.y);
+	let mut flee = FleeAI::default();
+	let from_danger = |game: &TestGame| {
+		let pos = game.gs.ecs.read_storage::<Position>().get(goblin).map(|pos| Point::new(pos.x, pos.y))
+			.expect("The goblin's gone");
+		(pos.x - danger.x).abs().max((pos.y - danger.y).abs())
+	};
+	let before = from_danger(&game);
+
+	for _ in 0 .. 2 {
+		let danger_idx = game.gs.ecs.fetch::<Map>().xy_idx(danger.x, danger.y);
+		game.gs.ecs.write_storage::<MyTurn>().insert(goblin, MyTurn {}).expect("The goblin's turn got lost");
+		game.gs.ecs.write_storage::<WantsToFlee>().insert(goblin, WantsToFlee { indices: vec![danger_idx] })
+			.expect("The goblin stood its ground");
+		flee.run_now(&game.gs.ecs);
+	}
+
+	assert!(from_danger(&game) > before);
+	assert_eq!(flee.flee_maps_kept(), 1);
+}
+
+// Turns
+// =========================================================================
+
+#[test]
+fn waiting_lets_the_turn_go_by () {
+	let mut game = TestGame::new(SEED);
+	let player = *game.gs.ecs.fetch::<Entity>();
+	let hunger = |game: &TestGame| game.gs.ecs.read_storage::<HungerClock>().get(player).map(|hc| hc.duration);
+	let before = hunger(&game).expect("The player's never hungry");
+
+	game.play(&[Action::Wait, Action::Wait]);
+
+	assert_eq!(hunger(&game), Some(before - 2));
+}
+
+// Levels
+// =========================================================================
+
+#[test]
+fn descending_goes_deeper_and_patches_the_player_up () {
+	let mut game = TestGame::new(SEED);
+	game.set_player_hp(1);
+	game.stand_on_stairs();
+
+	game.act(&Action::Descend);
+
+	assert_eq!(game.depth(), 2);
+	assert!(game.player_hp() >= game.player_max_hp() / 2);
+	assert!(game.logged("You descend"));
+}
+
+#[test]
+fn descending_off_the_stairs_does_nothing () {
+	let mut game = TestGame::new(SEED);
+
+	game.act(&Action::Descend);
+
+	assert_eq!(game.depth(), 1);
+}
+
+#[test]
+fn the_same_seed_builds_the_same_dungeon () {
+	let a = TestGame::new(SEED);
+	let b = TestGame::new(SEED);
+
+	assert!(a.gs.ecs.fetch::<Map>().tiles == b.gs.ecs.fetch::<Map>().tiles);
+	assert_eq!(a.player_pos(), b.player_pos());
+}
@@ -0,0 +1,329 @@
+mod flows;
+
+use std::sync::Once;
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+use crate::{build_state, CombatStats, InBackpack, LaunchOptions, Monster, Name, Player, Position, State, TileType, TurnState, WantsToPickupItem, WantsToThrowItem, WantsToUseItem};
+use crate::ai::TurnSystem;
+use crate::config::Config;
+use crate::damage_system::DamageSystem;
+use crate::display::DisplaySettings;
+use crate::settings::GameplaySettings;
+use crate::event_counter::EventCounter;
+use crate::gamelog::GameLog;
+use crate::input::{Input, PadPress};
+use crate::keybindings::KeyBindings;
+use crate::map::Map;
+use crate::map_indexing_system::MapIndexingSystem;
+use crate::spawner;
+
+// Harness
+// =========================================================================
+
+/// Something the player does on their turn, at the level of what the keys
+/// would have asked for rather than the keys themselves
+pub enum Action {
+	Move(i32, i32),
+	PickUp,
+	/// Uses the first thing in the backpack going by that name, aimed at the
+	/// target if it needs one
+	Use(&'static str, Option<Point>),
+	/// Throws the first thing in the backpack going by that name at a spot
+	Throw(&'static str, Point),
+	/// Goes down the stairs, if the player's stood on them
+	Descend,
+	Wait,
+}
+
+/// A whole game with no window, started from a fixed seed and played a turn
+/// at a time by the test. Saves are never written, the config is the
+/// defaults whatever's on disk, and every level's built with the simple
+/// builder so there's always a room about the player.
+pub struct TestGame {
+	pub gs : State,
+}
+
+impl TestGame {
+	pub fn new (seed: u64) -> TestGame {
+		let options = LaunchOptions {
+			seed: Some(seed),
+			builder: Some("simple".to_string()),
+			depth: 1,
+			wizard: false,
+			no_save: true,
+		};
+		let mut gs = build_state(DisplaySettings::defaults(), GameplaySettings::defaults(), KeyBindings::defaults(), Config::default(), options);
+		gs.run_systems();
+		*gs.ecs.write_resource::<TurnState>() = TurnState::AwaitingInput;
+
+		TestGame { gs }
+	}
+
+	/// Does each action in turn, with everyone else getting their go after
+	/// each one
+	pub fn play (&mut self, actions: &[Action]) {
+		for action in actions.iter() {
+			self.act(action);
+		}
+	}
+
+	pub fn act (&mut self, action: &Action) {
+		let ecs = &mut self.gs.ecs;
+		let player = *ecs.fetch::<Entity>();
+
+		match action {
+			Action::Move(dx, dy) => crate::player::try_move_player(*dx, *dy, ecs),
+			Action::PickUp => {
+				let here = *ecs.fetch::<Point>();
+				let items : Vec<Entity> = {
+					let entities = ecs.entities();
+					let items = ecs.read_storage::<crate::Item>();
+					let positions = ecs.read_storage::<Position>();
+					(&entities, &items, &positions).join()
+						.filter(|(_, _, pos)| pos.x == here.x && pos.y == here.y)
+						.map(|(entity, _, _)| entity)
+						.collect()
+				};
+				ecs.write_storage::<WantsToPickupItem>()
+					.insert(player, WantsToPickupItem { items, collected_by: player })
+					.expect("Butterfingers");
+			}
+			Action::Use(name, target) => {
+				let item = self.carried(name).expect("Nothing like that in the backpack");
+				self.gs.ecs.write_storage::<WantsToUseItem>()
+					.insert(player, WantsToUseItem { item, target: *target })
+					.expect("Couldn't work out which end to hold");
+			}
+			Action::Throw(name, target) => {
+				let item = self.carried(name).expect("Nothing like that to throw");
+				self.gs.ecs.write_storage::<WantsToThrowItem>()
+					.insert(player, WantsToThrowItem { item, target: *target })
+					.expect("Dropped it on your foot");
+			}
+			Action::Descend => {
+				self.descend();
+				return;
+			}
+			Action::Wait => {}
+		}
+
+		self.take_turn();
+	}
+
+	/// The player's turn and then everyone else's, as the turn handlers run
+	/// them over a few frames
+	fn take_turn (&mut self) {
+		self.gs.ecs.write_resource::<EventCounter>().turns += 1;
+		self.run(TurnState::PlayerTurn);
+		if self.run_over() { return; }
+
+		self.run(TurnState::MonsterTurn);
+		if TurnSystem::anyone_goes_twice(&self.gs.ecs) { self.run(TurnState::HastedTurn); }
+		if self.run_over() { return; }
+
+		*self.gs.ecs.write_resource::<TurnState>() = TurnState::AwaitingInput;
+	}
+
+	fn run (&mut self, turn: TurnState) {
+		*self.gs.ecs.write_resource::<TurnState>() = turn;
+		self.gs.run_systems();
+		DamageSystem::delete_the_dead(&mut self.gs.ecs);
+	}
+
+	fn descend (&mut self) {
+		let on_stairs = {
+			let map = self.gs.ecs.fetch::<Map>();
+			let pos = self.gs.ecs.fetch::<Point>();
+			map.tiles[map.xy_idx(pos.x, pos.y)] == TileType::DownStairs
+		};
+		if !on_stairs { return; }
+
+		self.gs.goto_next_level();
+		let build = self.gs.level_build.take().expect("The stairs went nowhere");
+		self.gs.arrive_on_next_level(build);
+		self.run(TurnState::PreRun);
+		*self.gs.ecs.write_resource::<TurnState>() = TurnState::AwaitingInput;
+	}
+
+	/// Presses a button on a pad and runs frames off it as the game would,
+	/// through whatever menus are open, until the game's waiting on the
+	/// player again. Nothing's drawn anywhere.
+	pub fn press (&mut self, press: PadPress) {
+		let mut ctx = headless_context();
+		let input = Input::read(&mut ctx, &self.gs.ecs.fetch::<KeyBindings>(), Some(press));
+		self.gs.input = input;
+		self.frame(&mut ctx);
+
+		ctx.key = None;
+		self.gs.input = Input::default();
+		while self.gs.ui.is_empty() && self.turn_state() != TurnState::AwaitingInput && !self.run_over() {
+			self.frame(&mut ctx);
+		}
+	}
+
+	fn frame (&mut self, ctx: &mut Rltk) {
+		let transition = self.gs.handler().update(&mut self.gs, ctx);
+		self.gs.apply(transition);
+		DamageSystem::delete_the_dead(&mut self.gs.ecs);
+	}
+
+	// Setting Up
+	// =====================================================================
+
+	/// Puts something from the spawn tables down, `dx` and `dy` from the
+	/// player, and indexes it straight away so it can be bumped into
+	pub fn spawn (&mut self, name: &str, dx: i32, dy: i32) {
+		let pos = *self.gs.ecs.fetch::<Point>();
+		spawner::spawn_named(&mut self.gs.ecs, name, pos.x + dx, pos.y + dy);
+		self.reindex();
+	}
+
+	/// Takes every monster off the level, so nothing wanders in and spoils
+	/// the test
+	pub fn clear_monsters (&mut self) {
+		let monsters : Vec<Entity> = {
+			let entities = self.gs.ecs.entities();
+			let monsters = self.gs.ecs.read_storage::<Monster>();
+			(&entities, &monsters).join().map(|(entity, _)| entity).collect()
+		};
+		self.gs.ecs.delete_entities(&monsters).expect("The monsters won't leave");
+		self.gs.ecs.maintain();
+		self.reindex();
+	}
+
+	/// Moves the player onto the way down
+	pub fn stand_on_stairs (&mut self) {
+		let (x, y) = {
+			let map = self.gs.ecs.fetch::<Map>();
+			let idx = map.tiles.iter().position(|tile| *tile == TileType::DownStairs)
+				.expect("This level has no way down");
+			(idx as i32 % map.width, idx as i32 / map.width)
+		};
+
+		let player = *self.gs.ecs.fetch::<Entity>();
+		self.gs.ecs.write_storage::<Position>().insert(player, Position { x, y })
+			.expect("Couldn't find the stairs");
+		*self.gs.ecs.write_resource::<Point>() = Point::new(x, y);
+		self.reindex();
+	}
+
+	pub fn set_player_hp (&mut self, hp: i32) {
+		let player = *self.gs.ecs.fetch::<Entity>();
+		self.gs.ecs.write_storage::<CombatStats>().get_mut(player).expect("The player's lost their health").hp = hp;
+	}
+
+	fn reindex (&mut self) {
+		MapIndexingSystem {}.run_now(&self.gs.ecs);
+	}
+
+	// Looking
+	// =====================================================================
+
+	pub fn player_hp (&self) -> i32 {
+		let player = *self.gs.ecs.fetch::<Entity>();
+		self.gs.ecs.read_storage::<CombatStats>().get(player).map_or(0, |stats| stats.hp)
+	}
+
+	pub fn player_max_hp (&self) -> i32 {
+		let player = *self.gs.ecs.fetch::<Entity>();
+		self.gs.ecs.read_storage::<CombatStats>().get(player).map_or(0, |stats| stats.max_hp)
+	}
+
+	/// The names of everything in the player's backpack
+	pub fn inventory (&self) -> Vec<String> {
+		let player = *self.gs.ecs.fetch::<Entity>();
+		let backpack = self.gs.ecs.read_storage::<InBackpack>();
+		let names = self.gs.ecs.read_storage::<Name>();
+		(&backpack, &names).join()
+			.filter(|(pack, _)| pack.owner == player)
+			.map(|(_, name)| name.name.to_string())
+			.collect()
+	}
+
+	/// The first thing carried going by `name`
+	fn carried (&self, name: &str) -> Option<Entity> {
+		let player = *self.gs.ecs.fetch::<Entity>();
+		let entities = self.gs.ecs.entities();
+		let backpack = self.gs.ecs.read_storage::<InBackpack>();
+		let names = self.gs.ecs.read_storage::<Name>();
+		(&entities, &backpack, &names).join()
+			.find(|(_, pack, item_name)| pack.owner == player && item_name.name == name)
+			.map(|(entity, _, _)| entity)
+	}
+
+	/// The first thing going by `name` that isn't the player
+	pub fn find (&self, name: &str) -> Option<Entity> {
+		let entities = self.gs.ecs.entities();
+		let names = self.gs.ecs.read_storage::<Name>();
+		let players = self.gs.ecs.read_storage::<Player>();
+		(&entities, &names, !&players).join()
+			.find(|(_, n, _)| n.name == name)
+			.map(|(entity, _, _)| entity)
+	}
+
+	/// The health of the first thing going by `name`, if there's one left
+	pub fn hp_of (&self, name: &str) -> Option<i32> {
+		let names = self.gs.ecs.read_storage::<Name>();
+		let stats = self.gs.ecs.read_storage::<CombatStats>();
+		let players = self.gs.ecs.read_storage::<Player>();
+		(&names, &stats, !&players).join()
+			.find(|(n, _, _)| n.name == name)
+			.map(|(_, stats, _)| stats.hp)
+	}
+
+	pub fn player_pos (&self) -> Point {
+		*self.gs.ecs.fetch::<Point>()
+	}
+
+	pub fn depth (&self) -> i32 {
+		self.gs.ecs.fetch::<Map>().depth
+	}
+
+	pub fn turn_state (&self) -> TurnState {
+		*self.gs.ecs.fetch::<TurnState>()
+	}
+
+	/// Whether anything in the log has `text` in it
+	pub fn logged (&self, text: &str) -> bool {
+		self.gs.ecs.fetch::<GameLog>().entries.iter().any(|entry| entry.text.contains(text))
+	}
+
+	fn run_over (&self) -> bool {
+		matches!(self.turn_state(), TurnState::GameOver | TurnState::Victory)
+	}
+}
+
+/// A context standing in for the window, with a console behind it for the
+/// menus to measure the screen by
+fn headless_context () -> Rltk {
+	static CONSOLE : Once = Once::new();
+	CONSOLE.call_once(|| {
+		rltk::BACKEND_INTERNAL.lock().consoles.push(rltk::DisplayConsole {
+			console: rltk::SimpleConsole::init(80, 50),
+			shader_index: 0,
+			font_index: 0,
+		});
+	});
+
+	Rltk {
+		width_pixels: 640,
+		height_pixels: 400,
+		original_width_pixels: 640,
+		original_height_pixels: 400,
+		fps: 60.,
+		frame_time_ms: 16.,
+		active_console: 0,
+		key: None,
+		mouse_pos: (0, 0),
+		left_click: false,
+		shift: false,
+		control: false,
+		alt: false,
+		web_button: None,
+		quitting: false,
+		post_scanlines: false,
+		post_screenburn: false,
+		screen_burn_color: RGB::named(rltk::BLACK),
+		mouse_visible: false,
+	}
+}
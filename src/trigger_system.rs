@@ -1,9 +1,15 @@
-use rltk::RGB;
+use rltk::Point;
 use specs::prelude::*;
-use crate::{EntityMoved, EntityTrigger, Hidden, InflictsDamage, Name, Position, SingleActivation, SufferDamage};
+use crate::{CombatStats, EmitsNoise, EntityMoved, EntityTrigger, Hidden, InflictsDamage, Name, NameOf, Position, ScriptedEffect, SingleActivation, StatusEffects, SufferDamage};
+use crate::ai::{within_earshot, NoiseBuilder};
+use crate::config::Config;
+use crate::debug_log::OrWarn;
 use crate::gamelog::GameLog;
 use crate::map::Map;
-use crate::particle_system::ParticleBuilder;
+use crate::spatial::SpatialIndex;
+use crate::palette::Palette;
+use crate::particle_system::{Particle, ParticleBuilder};
+use crate::scripting::{apply_commands, Blame, ScriptEngine, ScriptTarget};
 
 pub struct TriggerSystem {}
 
@@ -11,6 +17,7 @@ impl<'a> System<'a> for TriggerSystem {
 	type SystemData = (
 		Entities<'a>,
 		ReadExpect<'a, Map>,
+		ReadExpect<'a, SpatialIndex>,
 		WriteStorage<'a, EntityMoved>,
 		ReadStorage<'a, Position>,
 		ReadStorage<'a, EntityTrigger>,
@@ -19,65 +26,101 @@ impl<'a> System<'a> for TriggerSystem {
 		WriteExpect<'a, GameLog>,
 		ReadStorage<'a, InflictsDamage>,
 		WriteExpect<'a, ParticleBuilder>,
+		ReadExpect<'a, Palette>,
 		WriteStorage<'a, SufferDamage>,
 		ReadStorage<'a, SingleActivation>,
+		ReadStorage<'a, EmitsNoise>,
+		WriteExpect<'a, NoiseBuilder>,
+		ReadExpect<'a, Config>,
+		ReadStorage<'a, ScriptedEffect>,
+		WriteExpect<'a, ScriptEngine>,
+		WriteStorage<'a, CombatStats>,
+		WriteStorage<'a, StatusEffects>,
+		ReadExpect<'a, Point>,
 	);
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
-			entities, map, mut entity_moved, position, entity_trigger,
-			mut hidden, names, mut log, inflicts_damage, mut particles,
-			mut suffer_damage, single_activation,
+			entities, map, spatial, mut entity_moved, position, entity_trigger,
+			mut hidden, names, mut log, inflicts_damage, mut particles, palette,
+			mut suffer_damage, single_activation, emits_noise, mut noise, config,
+			scripted, mut scripts, mut combat_stats, mut statuses, player_pos,
 		) = data;
 
 		let mut remove_entities : Vec<Entity> = Vec::new();
 
 		for (entity, mut _moved, pos) in (&entities, &mut entity_moved, &position).join() {
 			let idx = map.xy_idx(pos.x, pos.y);
-			for entity_id in map.tile_content[idx].iter() {
-				if entity == *entity_id { continue } // don't check self
+			for entity_id in spatial.content(idx) {
+				if entity == entity_id { continue } // don't check self
 
-				let is_trigger = entity_trigger.get(*entity_id);
+				let is_trigger = entity_trigger.get(entity_id);
 				if let Some(_trigger) = is_trigger {
 
-					let damage = inflicts_damage.get(*entity_id);
+					let damage = inflicts_damage.get(entity_id);
 					if let Some(damage) = damage {
 						particles.request(
 							pos.x, pos.y,
-							RGB::named(rltk::RED),
-							RGB::named(rltk::BLACK),
-							rltk::to_cp437('‼'),
-							150.,
+							Particle { fg: palette.damage, bg: palette.background, glyph: rltk::to_cp437('‼'), lifetime: config.particles.hit_ms },
 						);
 
 						SufferDamage::new_damage(
 							&mut suffer_damage,
 							entity,
 							damage.damage,
+							names.name_or(entity_id, "a trap"),
+							false,
 						);
 					}
 
-					let name = names.get(*entity_id);
+					let name = names.get(entity_id);
 					if let Some(name) = name {
-						log.entries.push(format!(
+						log.combat(format!(
 							"{} triggers!",
 							&name.name,
 						));
 					}
 
-					let sa = single_activation.get(*entity_id);
+					if let Some(emits_noise) = emits_noise.get(entity_id) {
+						noise.request(pos.x, pos.y, emits_noise.radius);
+						let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+						if within_earshot(&map, &spatial, pos.x, pos.y, emits_noise.radius, player_idx) {
+							log.combat("A piercing shriek echoes through the dungeon!".to_string());
+						}
+					}
+
+					if let Some(scripted) = scripted.get(entity_id) {
+						let trap_name = names.name_or(entity_id, "a trap");
+						let (target_hp, target_max_hp) = combat_stats.get(entity).map_or((0, 0), |s| (s.hp, s.max_hp));
+						let on = ScriptTarget {
+							user: trap_name,
+							target: names.name_of(entity),
+							target_hp,
+							target_max_hp,
+							depth: map.depth,
+						};
+						match scripts.run(&scripted.script, &on) {
+							Ok(commands) => apply_commands(
+								commands, entity, Blame { cause: trap_name, by_player: false },
+								&mut combat_stats, &mut suffer_damage, &mut statuses, &mut log,
+							),
+							Err(e) => log.system(format!("The {} fizzles. ({})", trap_name, e)),
+						}
+					}
+
+					let sa = single_activation.get(entity_id);
 					if let Some(_sa) = sa {
-						remove_entities.push(*entity_id);
+						remove_entities.push(entity_id);
 					}
 
 					// No longer hidden
-					hidden.remove(*entity_id);
+					hidden.remove(entity_id);
 				}
 			}
 		}
 
 		for trap in remove_entities.iter() {
-			entities.delete(*trap).expect("Failed to de-trap");
+			entities.delete(*trap).or_warn("Failed to de-trap");
 		}
 
 		entity_moved.clear();
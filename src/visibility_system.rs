@@ -3,6 +3,7 @@ use specs::prelude::*;
 use crate::{Hidden, Name, Player};
 use crate::gamelog::GameLog;
 use crate::map::Map;
+use crate::spatial::{LiveMap, SpatialIndex};
 use super::{Viewshed, Position};
 
 pub struct VisibilitySystem {}
@@ -10,6 +11,7 @@ pub struct VisibilitySystem {}
 impl<'a> System<'a> for VisibilitySystem {
 	type SystemData = (
 		WriteExpect<'a, Map>,
+		ReadExpect<'a, SpatialIndex>,
 		Entities<'a>,
 		WriteStorage<'a, Viewshed>,
 		WriteStorage<'a, Position>,
@@ -22,7 +24,7 @@ impl<'a> System<'a> for VisibilitySystem {
 
 	fn run(&mut self, data: Self::SystemData) {
 		let (
-			mut map, entities, mut viewshed, pos, player,
+			mut map, spatial, entities, mut viewshed, pos, player,
 			mut hidden, mut rng, mut log, names,
 		) = data;
 
@@ -34,7 +36,7 @@ impl<'a> System<'a> for VisibilitySystem {
 			viewshed.visible_tiles = field_of_view(
 				Point::new(pos.x, pos.y),
 				viewshed.range,
-				&*map
+				&LiveMap { map: &map, spatial: &spatial, can_open_doors: false },
 			);
 			viewshed.visible_tiles.retain(
 				|p|
@@ -47,25 +49,32 @@ impl<'a> System<'a> for VisibilitySystem {
 			// Reveal what the player can see
 			let p : Option<&Player> = player.get(ent);
 			if let Some(_p) = p {
-				for t in map.visible_tiles.iter_mut() { *t = false };
+				map.hide_all();
 				for vis in viewshed.visible_tiles.iter() {
 					let idx = map.xy_idx(vis.x, vis.y);
-					map.revealed_tiles[idx] = true;
-					map.visible_tiles[idx] = true;
+					map.show(idx);
+
+					// Remember what's here, for examining it later
+					let seen : Vec<String> = spatial.content(idx)
+						.filter(|e| *e != ent && hidden.get(*e).is_none())
+						.filter_map(|e| names.get(e).map(|n| n.name.to_string()))
+						.collect();
+					if seen.is_empty() { map.remembered.remove(&idx); }
+					else { map.remembered.insert(idx, seen); }
 
 					// Chance to reveal hidden things
-					for e in map.tile_content[idx].iter() {
-						let maybe_hidden = hidden.get(*e);
+					for e in spatial.content(idx) {
+						let maybe_hidden = hidden.get(e);
 						if let Some(_hidden) = maybe_hidden {
 							if rng.roll_dice(1, 24) == 1 {
-								let name = names.get(*e);
+								let name = names.get(e);
 								if let Some(name) = name {
-									log.entries.push(format!(
+									log.system(format!(
 										"You've spotted a {}!",
 										&name.name,
 									));
 								}
-								hidden.remove(*e);
+								hidden.remove(e);
 							}
 						}
 					}